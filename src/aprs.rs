@@ -0,0 +1,132 @@
+//! APRS position reports.
+//!
+//! This module formats GPS frames as APRS position reports without timestamp, in the TNC2
+//! monitor format, so that they can be sent to a TNC or KISS modem through a serial connection and
+//! be gated to the APRS-IS network. Reports use the *balloon* symbol (`/O`) and include the
+//! altitude in feet in the comment, as required by the APRS specification:
+//!
+//! ```text
+//! CALLSIGN-SSID>APRS:!DDMM.mmN/DDDMM.mmWO/A=aaaaaa comment
+//! ```
+//!
+//! The callsign, SSID and comment can be set in the `[aprs]` section of the configuration.
+
+use crate::gps::Frame;
+
+/// Destination address (*tocall*) of generic APRS packets.
+const DESTINATION: &str = "APRS";
+/// Symbol table identifier (primary table).
+const SYMBOL_TABLE: char = '/';
+/// Symbol code of a balloon, in the primary table.
+const SYMBOL_CODE: char = 'O';
+/// Feet in a meter.
+const FEET_PER_METER: f32 = 3.280_84;
+
+/// Encodes the position in the GPS frame as an APRS position report.
+///
+/// An SSID of 0 will not be added to the callsign, as it's the default one. The comment will be
+/// added after the altitude, if not empty.
+#[must_use]
+pub fn encode_position(frame: &Frame, callsign: &str, ssid: u8, comment: &str) -> String {
+    let station = if ssid == 0 {
+        callsign.to_uppercase()
+    } else {
+        format!("{}-{ssid}", callsign.to_uppercase())
+    };
+
+    let mut report = format!(
+        "{station}>{DESTINATION}:!{}{SYMBOL_TABLE}{}{SYMBOL_CODE}/A={:06}",
+        format_coordinate(frame.latitude(), 2, ('N', 'S')),
+        format_coordinate(frame.longitude(), 3, ('E', 'W')),
+        altitude_feet(frame.altitude())
+    );
+    if !comment.is_empty() {
+        report.push(' ');
+        report.push_str(comment);
+    }
+
+    report
+}
+
+/// Formats a coordinate in degrees and minutes, with hundredths of minute precision.
+///
+/// The hemisphere will be the first character of the given pair for positive coordinates, and the
+/// second for negative ones.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn format_coordinate(coordinate: f32, degree_digits: usize, hemisphere: (char, char)) -> String {
+    // Rounding to hundredths of minute before splitting avoids printing 60 minutes.
+    let hundredths = (f64::from(coordinate).abs() * 6_000_f64).round() as u32;
+    let degrees = hundredths / 6_000;
+    let minutes = hundredths % 6_000;
+
+    format!(
+        "{:0width$}{:02}.{:02}{}",
+        degrees,
+        minutes / 100,
+        minutes % 100,
+        if coordinate < 0_f32 {
+            hemisphere.1
+        } else {
+            hemisphere.0
+        },
+        width = degree_digits
+    )
+}
+
+/// Converts an altitude in meters to feet, clamped to the six digits allowed by APRS.
+#[allow(clippy::cast_possible_truncation)]
+fn altitude_feet(altitude: f32) -> i32 {
+    ((altitude * FEET_PER_METER).round() as i32).clamp(-99_999, 999_999)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::encode_position;
+    use crate::gps::{FixStatus, Frame};
+
+    /// Creates a frame at the given position.
+    fn frame(latitude: f32, longitude: f32, altitude: f32) -> Frame {
+        Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            FixStatus::Active,
+            9,
+            latitude,
+            longitude,
+            altitude,
+        )
+    }
+
+    /// Checks a position report against the example in the APRS specification.
+    #[test]
+    fn encode_north_west() {
+        assert_eq!(
+            encode_position(
+                &frame(49.058_333, -72.029_167, 376.123_2),
+                "n0call",
+                11,
+                "OpenStratos"
+            ),
+            "N0CALL-11>APRS:!4903.50N/07201.75WO/A=001234 OpenStratos"
+        );
+    }
+
+    /// Checks a position report in the southern and eastern hemispheres, without SSID or comment.
+    #[test]
+    fn encode_south_east() {
+        assert_eq!(
+            encode_position(&frame(-33.868_8, 151.209_3, 30_000_f32), "VK2ABC", 0, ""),
+            "VK2ABC>APRS:!3352.13S/15112.56EO/A=098425"
+        );
+    }
+
+    /// Checks that minutes are never rounded up to 60, and that negative altitudes are encoded.
+    #[test]
+    fn encode_rounding() {
+        assert_eq!(
+            encode_position(&frame(0.999_999, -0.000_001, -10_f32), "EA1ABC", 9, ""),
+            "EA1ABC-9>APRS:!0100.00N/00000.00WO/A=-00033"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Arming of the probe.
+//!
+//! Once the initialization SMS is sent, the probe will not stop until the landing, so an
+//! accidental power on during the assembly can be costly. If `require_arm` is set in the
+//! `[flight]` section of the configuration, the probe holds after acquiring the GPS fix, recording
+//! and reporting its position, but without committing to the launch sequence until it's armed.
+//!
+//! The probe can be armed by setting the `arm_gpio` pin high, for example with a switch or a
+//! remove-before-flight pin, or by sending the authenticated `ARM` command through the telemetry
+//! link (check the [`command`](../telemetry/command/index.html) module). Arming can't be undone.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::{error, info};
+
+use crate::config::CONFIG;
+
+/// Wether the probe was armed with a command.
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Arms the probe.
+pub fn arm() {
+    if !ARMED.swap(true, Ordering::SeqCst) {
+        info!("Probe armed by command.");
+    }
+}
+
+/// Checks if the probe is armed.
+///
+/// It's always armed if arming is not required in the configuration.
+pub fn is_armed() -> bool {
+    let flight = CONFIG.flight();
+    if !flight.require_arm() || ARMED.load(Ordering::SeqCst) {
+        return true;
+    }
+
+    flight.arm_gpio().is_some_and(|pin| match pin.get_value() {
+        Ok(value) => value == 1,
+        Err(e) => {
+            error!(pin = pin.get_pin(), error = %e, "Error reading the arm GPIO pin.");
+            false
+        }
+    })
+}
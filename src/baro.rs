@@ -0,0 +1,377 @@
+//! Barometric pressure sensor.
+//!
+//! The GPS altitude has an error of several meters, and the fix can be lost for a while. A
+//! barometric pressure sensor, such as a Bosch BMP280 connected to the I²C bus of the Raspberry
+//! Pi, gives a much more stable altitude in the short term, but it drifts with the weather and
+//! the temperature of the probe. Both of them are fused with an
+//! [`AltitudeFilter`](struct.AltitudeFilter.html), a simple complementary filter that follows the
+//! barometric altitude changes and slowly converges to the GPS altitude.
+//!
+//! Pressure is converted to altitude with the 1976 U.S. Standard Atmosphere model, in
+//! [`pressure_to_altitude()`](fn.pressure_to_altitude.html), up to 51 km of altitude.
+//!
+//! The sensor is only used if the `[baro]` configuration section is present. The connection is
+//! opened the first time the pressure is read.
+
+#![allow(missing_debug_implementations)]
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    os::unix::io::AsRawFd,
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Error};
+use once_cell::sync::Lazy;
+use tracing::info;
+
+use crate::{config::CONFIG, error};
+
+/// The barometric sensor control structure.
+pub static BAROMETER: Lazy<Mutex<Bmp280>> = Lazy::new(|| Mutex::new(Bmp280::default()));
+
+/// Default I²C bus device of the sensor.
+pub const DEFAULT_I2C_BUS: &str = "/dev/i2c-1";
+/// Default I²C address of the sensor.
+pub const DEFAULT_ADDRESS: u16 = 0x76;
+/// Default weight of the barometric altitude changes in the fused altitude.
+pub const DEFAULT_WEIGHT: f32 = 0.9;
+
+/// `ioctl()` request to set the address of the I²C slave device.
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+/// Ratio between the gas constant and the standard gravity times the molar mass of air, in
+/// *m/K*.
+const GAS_CONSTANT_RATIO: f64 = 8.314_459_8 / (9.806_65 * 0.028_964_4);
+/// Layers of the standard atmosphere, as `(base altitude, base temperature, lapse rate, base
+/// pressure)` tuples, in *m*, *K*, *K/m* and *Pa*.
+const ATMOSPHERE_LAYERS: [(f64, f64, f64, f64); 5] = [
+    (0_f64, 288.15, -0.0065, 101_325_f64),
+    (11_000_f64, 216.65, 0_f64, 22_632.06),
+    (20_000_f64, 216.65, 0.001, 5_474.889),
+    (32_000_f64, 228.65, 0.0028, 868.018_7),
+    (47_000_f64, 270.65, 0_f64, 110.906_3),
+];
+
+/// Converts a pressure, in *Pa*, to the altitude in the standard atmosphere, in *m*.
+#[must_use]
+pub fn pressure_to_altitude(pressure: f32) -> f32 {
+    let pressure = f64::from(pressure);
+    let &(base_altitude, temperature, lapse_rate, base_pressure) = ATMOSPHERE_LAYERS
+        .iter()
+        .rev()
+        .find(|&&(_, _, _, base_pressure)| pressure <= base_pressure)
+        .unwrap_or(&ATMOSPHERE_LAYERS[0]);
+
+    let altitude = if lapse_rate == 0_f64 {
+        base_altitude - GAS_CONSTANT_RATIO * temperature * (pressure / base_pressure).ln()
+    } else {
+        base_altitude
+            + temperature / lapse_rate
+                * ((pressure / base_pressure).powf(-GAS_CONSTANT_RATIO * lapse_rate) - 1_f64)
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let altitude = altitude as f32;
+    altitude
+}
+
+/// Complementary filter fusing the GPS and the barometric altitudes.
+///
+/// Each update, the fused altitude follows the change of the barometric altitude with the given
+/// weight, and the GPS altitude with the rest. If there is no GPS altitude, it only follows the
+/// barometric changes, and if there is no barometric altitude, it's the GPS altitude.
+#[derive(Debug, Clone, Copy)]
+pub struct AltitudeFilter {
+    /// Weight of the barometric altitude changes, between 0 and 1.
+    weight: f32,
+    /// Last fused altitude, in *m*.
+    fused: Option<f32>,
+    /// Last barometric altitude, in *m*.
+    baro: Option<f32>,
+}
+
+impl Default for AltitudeFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_WEIGHT)
+    }
+}
+
+impl AltitudeFilter {
+    /// Creates a new filter, with the given weight of the barometric altitude changes.
+    #[must_use]
+    pub fn new(weight: f32) -> Self {
+        Self {
+            weight: weight.clamp(0_f32, 1_f32),
+            fused: None,
+            baro: None,
+        }
+    }
+
+    /// Updates the filter with new GPS and barometric altitudes, in *m*, returning the fused
+    /// altitude.
+    pub fn update(&mut self, gps: Option<f32>, baro: Option<f32>) -> Option<f32> {
+        let change = match (self.baro, baro) {
+            (Some(previous), Some(current)) => Some(current - previous),
+            _ => None,
+        };
+
+        self.fused = match (self.fused, change, gps) {
+            (Some(fused), Some(change), Some(gps)) => {
+                Some(self.weight * (fused + change) + (1_f32 - self.weight) * gps)
+            }
+            (Some(fused), Some(change), None) => Some(fused + change),
+            (_, _, Some(gps)) => Some(gps),
+            (fused, _, None) => fused,
+        };
+        if baro.is_some() {
+            self.baro = baro;
+        }
+        self.fused
+    }
+
+    /// Gets the last fused altitude, in *m*.
+    #[must_use]
+    pub fn altitude(&self) -> Option<f32> {
+        self.fused
+    }
+}
+
+/// BMP280 barometric sensor control structure.
+#[derive(Default)]
+pub struct Bmp280 {
+    /// I²C device, with the address of the sensor set.
+    device: Option<File>,
+    /// Calibration parameters of the sensor.
+    calibration: Calibration,
+}
+
+impl Bmp280 {
+    /// Initializes the sensor, reading its calibration and setting it in normal mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sensor is not configured, if its I²C device can't be opened, or if
+    /// the sensor can't be set up.
+    pub fn initialize(&mut self) -> Result<(), Error> {
+        info!("Initializing barometric sensor\u{2026}");
+        let config = CONFIG.baro().ok_or(error::Baro::NotConfigured)?;
+        let device = open_i2c(config.i2c_bus(), config.address()).context(error::Baro::Init)?;
+        self.device = Some(device);
+
+        let mut calibration = [0; 24];
+        self.read_registers(0x88, &mut calibration)
+            .context(error::Baro::Init)?;
+        self.calibration = Calibration::from_bytes(&calibration);
+
+        // Temperature oversampling ×2, pressure oversampling ×16, normal mode, 62.5 ms standby and
+        // IIR filter coefficient 16 (the ultra high resolution settings of the datasheet).
+        self.write_register(0xF4, 0b0101_0111)
+            .context(error::Baro::Init)?;
+        self.write_register(0xF5, 0b0011_0000)
+            .context(error::Baro::Init)?;
+
+        info!("Barometric sensor initialized.");
+        Ok(())
+    }
+
+    /// Reads the pressure, in *Pa*.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sensor can't be initialized or read.
+    pub fn pressure(&mut self) -> Result<f32, Error> {
+        if self.device.is_none() {
+            self.initialize()?;
+        }
+
+        let mut data = [0; 6];
+        self.read_registers(0xF7, &mut data)
+            .context(error::Baro::Read)?;
+        let adc_p =
+            (i32::from(data[0]) << 12) | (i32::from(data[1]) << 4) | i32::from(data[2] >> 4);
+        let adc_t =
+            (i32::from(data[3]) << 12) | (i32::from(data[4]) << 4) | i32::from(data[5] >> 4);
+
+        let (_, pressure) = self.calibration.compensate(adc_t, adc_p);
+        #[allow(clippy::cast_possible_truncation)]
+        let pressure = pressure as f32;
+        Ok(pressure)
+    }
+
+    /// Writes a register of the sensor.
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        let device = self.device.as_mut().ok_or(error::Baro::NoDevice)?;
+        device.write_all(&[register, value])?;
+        Ok(())
+    }
+
+    /// Reads consecutive registers of the sensor, starting at the given one.
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let device = self.device.as_mut().ok_or(error::Baro::NoDevice)?;
+        device.write_all(&[register])?;
+        device.read_exact(buffer)?;
+        Ok(())
+    }
+}
+
+/// Opens the given I²C bus device, setting the address of the slave.
+fn open_i2c(bus: &Path, address: u16) -> Result<File, Error> {
+    let device = OpenOptions::new().read(true).write(true).open(bus)?;
+    // Safe because the descriptor is open, and the `I2C_SLAVE` request takes an integer argument.
+    if unsafe { libc::ioctl(device.as_raw_fd(), I2C_SLAVE, libc::c_ulong::from(address)) } < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(device)
+}
+
+/// Factory calibration parameters of a BMP280 sensor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    /// Temperature compensation parameters.
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    /// Pressure compensation parameters.
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+}
+
+impl Calibration {
+    /// Parses the calibration parameters from the little endian `0x88` to `0x9F` registers.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; 24]) -> Self {
+        let unsigned = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let signed = |i: usize| i16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        Self {
+            t1: unsigned(0),
+            t2: signed(2),
+            t3: signed(4),
+            p1: unsigned(6),
+            p2: signed(8),
+            p3: signed(10),
+            p4: signed(12),
+            p5: signed(14),
+            p6: signed(16),
+            p7: signed(18),
+            p8: signed(20),
+            p9: signed(22),
+        }
+    }
+
+    /// Compensates the raw temperature and pressure readings, returning the temperature, in
+    /// *°C*, and the pressure, in *Pa*.
+    ///
+    /// This is the floating point compensation of the BMP280 datasheet.
+    #[must_use]
+    pub fn compensate(&self, adc_t: i32, adc_p: i32) -> (f64, f64) {
+        let (adc_t, adc_p) = (f64::from(adc_t), f64::from(adc_p));
+
+        let var1 = (adc_t / 16_384_f64 - f64::from(self.t1) / 1_024_f64) * f64::from(self.t2);
+        let var2 =
+            (adc_t / 131_072_f64 - f64::from(self.t1) / 8_192_f64).powi(2) * f64::from(self.t3);
+        let t_fine = var1 + var2;
+        let temperature = t_fine / 5_120_f64;
+
+        let var1 = t_fine / 2_f64 - 64_000_f64;
+        let var2 = var1 * var1 * f64::from(self.p6) / 32_768_f64;
+        let var2 = var2 + var1 * f64::from(self.p5) * 2_f64;
+        let var2 = var2 / 4_f64 + f64::from(self.p4) * 65_536_f64;
+        let var1 = (f64::from(self.p3) * var1 * var1 / 524_288_f64 + f64::from(self.p2) * var1)
+            / 524_288_f64;
+        let var1 = (1_f64 + var1 / 32_768_f64) * f64::from(self.p1);
+        if var1 == 0_f64 {
+            // Avoid a division by zero.
+            return (temperature, 0_f64);
+        }
+
+        let pressure = 1_048_576_f64 - adc_p;
+        let pressure = (pressure - var2 / 4_096_f64) * 6_250_f64 / var1;
+        let var1 = f64::from(self.p9) * pressure * pressure / 2_147_483_648_f64;
+        let var2 = pressure * f64::from(self.p8) / 32_768_f64;
+        (
+            temperature,
+            pressure + (var1 + var2 + f64::from(self.p7)) / 16_f64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pressure_to_altitude, AltitudeFilter, Calibration};
+
+    /// Checks the pressure to altitude conversion against the standard atmosphere tables.
+    #[test]
+    fn standard_atmosphere() {
+        let reference = [
+            (107_477.5, -500_f32),
+            (101_325.0, 0_f32),
+            (89_874.57, 1_000_f32),
+            (54_019.9, 5_000_f32),
+            (22_632.06, 11_000_f32),
+            (12_044.6, 15_000_f32),
+            (5_474.89, 20_000_f32),
+            (2_511.02, 25_000_f32),
+            (1_171.87, 30_000_f32),
+            (868.02, 32_000_f32),
+        ];
+
+        for (pressure, altitude) in reference {
+            let converted = pressure_to_altitude(pressure);
+            assert!(
+                (converted - altitude).abs() < 0.5,
+                "{pressure} Pa should be {altitude} m, found {converted} m"
+            );
+        }
+    }
+
+    /// Checks the compensation with the example values of the BMP280 datasheet.
+    #[test]
+    fn datasheet_compensation() {
+        let mut bytes = [0; 24];
+        for (i, value) in [
+            27_504, 26_435, -1_000, 36_477, -10_685, 3_024, 2_855, 140, -7, 15_500, -14_600, 6_000,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let value = (value as u16).to_le_bytes();
+            bytes[i * 2..i * 2 + 2].copy_from_slice(&value);
+        }
+
+        let (temperature, pressure) = Calibration::from_bytes(&bytes).compensate(519_888, 415_148);
+        assert!((temperature - 25.08).abs() < 0.01);
+        assert!((pressure - 100_653.27).abs() < 0.01);
+    }
+
+    /// Checks that the fused altitude follows the barometric changes, converges to the GPS
+    /// altitude and survives GPS dropouts.
+    #[test]
+    fn altitude_fusion() {
+        let mut filter = AltitudeFilter::new(0.9);
+        assert_eq!(filter.update(None, Some(95.0)), None);
+        assert_eq!(filter.update(Some(100.0), Some(95.0)), Some(100.0));
+
+        // A noisy GPS jump is mostly ignored, since the barometer does not change.
+        let fused = filter.update(Some(120.0), Some(95.0)).unwrap();
+        assert!((fused - 102.0).abs() < 1e-3);
+
+        // The barometric change is followed even without GPS.
+        let fused = filter.update(None, Some(105.0)).unwrap();
+        assert!((fused - 112.0).abs() < 1e-3);
+
+        // With a constant offset, the fused altitude converges to the GPS one.
+        for i in 1_u8..=100 {
+            let _ = filter.update(Some(200.0 + f32::from(i)), Some(205.0 + f32::from(i)));
+        }
+        assert!((filter.altitude().unwrap() - 300.0).abs() < 0.1);
+    }
+}
@@ -10,24 +10,90 @@
 //! explained here:
 //!
 //! * **Debug mode** (`debug = bool`): Turns the debug mode on or off, it's off by default. The
-//! debug mode will print all serial communication in logs, and it will add more insightful logs,
-//! that enable debugging system malfunction. This mode will consume more resources than
-//! non-debugging mode, and it's not recommended for normal balloon operation. Also, debug logs will
-//! be full of silly comments that might not provide anything useful in a real flight.
+//!   debug mode will print all serial communication in logs, and it will add more insightful logs,
+//!   that enable debugging system malfunction. This mode will consume more resources than
+//!   non-debugging mode, and it's not recommended for normal balloon operation. Also, debug logs
+//!   will be full of silly comments that might not provide anything useful in a real flight.
 //! * **Camera rotation** (`camera_rotation = 0-359`): Sets the rotation of the camera for videos
-//! and pictures, in degrees. This is useful if the probe, by design, requires the camera to be in a
-//! non-vertical position.
+//!   and pictures, in degrees. This is useful if the probe, by design, requires the camera to be in
+//!   a non-vertical position.
 //! * **Data directory** (`data_dir = "/path/to/data"`): Sets the path to the main data output
-//! directory. Logs, images, videos and current state file will be stored in this path. Make sure
-//! it's a reliable path between reboots.
+//!   directory. Logs, images, videos and current state file will be stored in this path. Make sure
+//!   it's a reliable path between reboots.
+//! * **Flight section** (`[flight]`): Sets the expected flight length and maximum height, the time
+//!   to wait for the GPS position to stabilize after the first fix, and the flight profile
+//!   (`"standard"`, `"fast_ascent"` or `"float"`). The profile sets the default thresholds used to
+//!   detect the launch, a float, the burst and the landing, and the altitudes of the descent SMSs,
+//!   that can then be overridden one by one. It can also require the probe to be armed before
+//!   committing to the launch sequence, with an arming GPIO pin or with the telemetry `ARM`
+//!   command, that needs the telemetry `command_key`.
+//! * **Log section** (`[log]`, optional): Sets the format of the log records. `"text"` logs are
+//!   colored, human readable lines, while `"json"` logs have one JSON object per line, for log
+//!   aggregators (check the [`logger`](../logger/index.html) module). With `file = true`, logs are
+//!   also written to rotated files in the `logs` data subdirectory, keeping at most `max_files`
+//!   files and `max_size_mb` MiB.
 //! * **Picture section** (`[picture]`): Sets the configuration for pictures. Dimensions, quality,
-//! brightness, contrast, ISO, exposure and many more can be configured. Two configuration options
-//! are a bit different from the rest actually. The `exif` parameter sets if GPS data should be
-//! added to images, so that the final image has position metadata, for example. The `raw` option
-//! controls if the raw sensor data should be added to images as JPEG metadata. This will add about
-//! 8MiB of information to the images, at least.
+//!   brightness, contrast, ISO, exposure and many more can be configured. Two configuration options
+//!   are a bit different from the rest actually. The `exif` parameter sets if GPS data should be
+//!   added to images, so that the final image has position metadata, for example. The `raw` option
+//!   controls if the raw sensor data should be added to images as JPEG metadata. This will add
+//!   about 8MiB of information to the images, at least. The `warmup_ms` option sets how long the
+//!   camera runs before capturing, so that the exposure and white balance can settle. An optional
+//!   `annotation` template burns the latest GPS data (time, altitude, position and satellites) into
+//!   the pictures taken with a valid fix. Besides the pictures taken every `interval` seconds, an
+//!   extra one is taken the first time each of the `altitude_triggers` is crossed upwards.
 //! * **Video section** (`[video]`): Sets the configuration for videos. Dimensions, frames per
-//! second, bitrate, and many more, most of them also available for pictures.
+//!   second, bitrate, and many more, most of them also available for pictures. Both pictures and
+//!   videos accept a `metering` mode (`"average"`, `"spot"`, `"backlit"` or `"matrix"`) and a `drc`
+//!   dynamic range compression level (`"off"`, `"low"`, `"med"` or `"high"`), useful with the dark
+//!   sky and the bright ground of near space. The 10 second test recording done at initialization
+//!   can be skipped with `skip_camera_test = true`, in which case only the `raspivid` binary and
+//!   the camera detection are checked.
+//! * **Telemetry section** (`[telemetry]`): Sets the serial port used for telemetry, and the
+//!   telemetry mode. In `"transparent"` mode packets are written as they are, while in `"api"` mode
+//!   they are wrapped in XBee API frames addressed to the configured `destination`. Packets are
+//!   sent at most once every `interval_ms` milliseconds, always sending the latest data. If a
+//!   `command_key` is set, authenticated commands will be accepted from the ground through the same
+//!   serial (check the [`command`](../telemetry/command/index.html) module).
+//! * **Habitat section** (`[habitat]`, optional): Sets the callsign and the fields of UKHAS
+//!   telemetry sentences for Habitat / `SondeHub` trackers (check the
+//!   [`habitat`](../habitat/index.html) module).
+//! * **MQTT section** (`[mqtt]`, optional): Sets the broker, the flight identifier and the
+//!   credentials used to publish telemetry over GPRS (check the [`mqtt`](../mqtt/index.html)
+//!   module).
+//! * **Iridium section** (`[iridium]`, required with the `iridium` feature): Sets the serial of the
+//!   Iridium SBD modem used when there is no GSM connectivity (check the
+//!   [`iridium`](../iridium/index.html) module).
+//! * **LoRa section** (`[lora]`, optional): Sets the SPI device, the frequency, the spreading
+//!   factor and the bandwidth of the LoRa radio that also sends the telemetry (check the
+//!   [`lora`](../lora/index.html) module).
+//! * **Barometer section** (`[baro]`, optional): Sets the I²C bus and address of the barometric
+//!   pressure sensor, and the weight of its altitude in the altitude fused with the GPS one (check
+//!   the [`baro`](../baro/index.html) module).
+//! * **HTTP section** (`[http]`, optional): Sets the address and port of the local status endpoint,
+//!   `127.0.0.1:8080` by default (check the [`http`](../http/index.html) module).
+//! * **Webhook section** (`[webhook]`, optional): Sets the URL that gets notified of every state
+//!   transition, such as a Discord channel webhook (check the [`webhook`](../webhook/index.html)
+//!   module).
+//! * **Geofence sections** (`[[geofence]]`, optional): Set the polygons that the probe should stay
+//!   inside of, or outside of, with an alert being sent if it doesn't (check the
+//!   [`geofence`](../geofence/index.html) module).
+//! * **APRS section** (`[aprs]`, optional): Sets the callsign, SSID and comment used in APRS
+//!   position reports (check the [`aprs`](../aprs/index.html) module).
+//!
+//! ## Live reload
+//!
+//! The configuration is reloaded when the process receives a `SIGHUP` signal
+//! (`kill -HUP <pid>`), for example to tune it during a long pre-launch hold without re-running
+//! the initialization. The new file is validated, and if it's invalid the error is logged and the
+//! current configuration is kept.
+//!
+//! Options read each time they are used take effect on their next use: the debug mode, the flight
+//! and battery options, the picture and video settings, the SMS phone number, the delivery
+//! timeout, the telemetry interval, the Habitat, APRS and webhook sections and the GPS rate
+//! window. Device options (serial ports, baud rates, GPIO pins, the I²C bus, the navigation rate
+//! and reset), the data directory, the log format, the HTTP endpoint, the MQTT broker and the
+//! geofences are only read at startup, and require a restart.
 //!
 //! You can also check the [`Config`](struct.Config.html) structure for further implementation
 //! details.
@@ -36,9 +102,16 @@
 
 use std::{
     fs::File,
-    io::{BufReader, Read},
+    io::{self, BufReader, Read},
+    ops::Deref,
     path::{Path, PathBuf},
     result::Result,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    thread,
+    time::Duration,
     u8,
 };
 
@@ -50,11 +123,16 @@ use std::{ffi::OsStr, i8, u16};
 #[cfg(any(feature = "gps", feature = "fona"))]
 use std::fmt;
 
+// Only required for the HTTP status endpoint
+#[cfg(feature = "http")]
+use std::net::{IpAddr, SocketAddr};
+
 use anyhow::{Context, Error};
 use colored::Colorize;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use toml;
+use tracing::{error, info};
 
 // Only required for GPS, FONA or telemetry
 #[cfg(any(feature = "gps", feature = "fona"))]
@@ -64,19 +142,192 @@ use serde::de::{self, Deserializer, Visitor};
 #[cfg(any(feature = "gps", feature = "fona"))]
 use sysfs_gpio::Pin;
 
-use crate::{error, generate_error_string, CONFIG_FILE};
+// Only required for the barometric sensor
+#[cfg(feature = "baro")]
+use crate::baro::{
+    DEFAULT_ADDRESS as DEFAULT_BARO_ADDRESS, DEFAULT_I2C_BUS, DEFAULT_WEIGHT as DEFAULT_BARO_WEIGHT,
+};
+// Only required for GPS
+#[cfg(feature = "gps")]
+use crate::{
+    geofence::Fence,
+    gps::{ResetKind, DEFAULT_NAV_RATE, DEFAULT_RATE_WINDOW, MAX_NAV_RATE},
+};
+// Only required for GPS
+#[cfg(feature = "gps")]
+use crate::habitat::{Field as HabitatField, DEFAULT_CALLSIGN, DEFAULT_FIELDS};
+// Only required for the HTTP status endpoint
+#[cfg(feature = "http")]
+use crate::http::{DEFAULT_ADDRESS, DEFAULT_PORT as DEFAULT_HTTP_PORT};
+// Only required for Iridium
+#[cfg(feature = "iridium")]
+use crate::iridium::{
+    DEFAULT_BAUD_RATE as DEFAULT_IRIDIUM_BAUD_RATE, DEFAULT_TIMEOUT as DEFAULT_IRIDIUM_TIMEOUT,
+};
+// Only required for LoRa
+#[cfg(feature = "lora")]
+use crate::lora::{
+    bandwidth_bits, DEFAULT_BANDWIDTH, DEFAULT_FREQUENCY, DEFAULT_SPI_DEVICE,
+    DEFAULT_SPREADING_FACTOR, FREQUENCY_RANGE, SPREADING_FACTOR_RANGE,
+};
+// Only required for MQTT
+#[cfg(feature = "mqtt")]
+use crate::mqtt::{DEFAULT_BUFFER, DEFAULT_KEEP_ALIVE, DEFAULT_PORT as DEFAULT_MQTT_PORT};
+// Only required for the camera
+#[cfg(feature = "raspicam")]
+use crate::raspicam::DEFAULT_PICTURE_WARMUP_MS;
+// Only required for picture annotations
+#[cfg(all(feature = "raspicam", feature = "gps"))]
+use crate::raspicam::DEFAULT_ANNOTATION_SIZE;
+// Only required for simulation
+#[cfg(feature = "sim")]
+use crate::sim::{DEFAULT_FLIGHT_FILE, DEFAULT_SPEEDUP};
+// Only required for serial devices
+#[cfg(any(feature = "gps", feature = "fona", feature = "telemetry"))]
+use crate::serial::DEFAULT_TIMEOUT as DEFAULT_SERIAL_TIMEOUT;
+// Only required for telemetry
+#[cfg(feature = "telemetry")]
+use crate::telemetry::xbee::BROADCAST_ADDRESS;
+// Only required for the webhook
+#[cfg(feature = "webhook")]
+use crate::webhook::DEFAULT_TIMEOUT as DEFAULT_WEBHOOK_TIMEOUT;
+use crate::{
+    error, generate_error_string,
+    logger::{DEFAULT_MAX_LOG_FILES, DEFAULT_MAX_LOG_SIZE_MB},
+    logic::DEFAULT_FIX_STABILIZATION_TIME,
+    CONFIG_FILE,
+};
 
 /// Configuration object.
-pub static CONFIG: Lazy<Config> = Lazy::new(|| match Config::from_file(CONFIG_FILE) {
+///
+/// It can be reloaded from the configuration file with [`reload_config()`](fn.reload_config.html).
+pub static CONFIG: Lazy<LiveConfig> = Lazy::new(|| match Config::from_file(CONFIG_FILE) {
     Err(e) => {
         panic!(
             "{}",
             generate_error_string(&e, "error loading configuration").red()
         );
     }
-    Ok(c) => c,
+    Ok(c) => LiveConfig::new(c),
 });
 
+/// Set by the `SIGHUP` signal handler, to reload the configuration.
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Interval between the checks for a configuration reload request.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Configuration that can be replaced at runtime.
+///
+/// It dereferences to the current configuration. Replaced configurations are leaked on purpose:
+/// references to them could still be in use, and reloads are rare.
+#[derive(Debug)]
+pub struct LiveConfig {
+    /// Current configuration.
+    current: RwLock<&'static Config>,
+}
+
+impl LiveConfig {
+    /// Creates a live configuration with the given initial configuration.
+    #[must_use]
+    pub fn new(config: Config) -> Self {
+        Self {
+            current: RwLock::new(Box::leak(Box::new(config))),
+        }
+    }
+
+    /// Reloads the configuration from the given file.
+    ///
+    /// If the file can't be read or the configuration is invalid, the current configuration is
+    /// kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, or if the configuration in it is invalid.
+    pub fn reload_from<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let config = Config::from_file(path)?;
+        let mut current = match self.current.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                error!("The configuration lock was poisoned.");
+                poisoned.into_inner()
+            }
+        };
+        *current = Box::leak(Box::new(config));
+        Ok(())
+    }
+}
+
+impl Deref for LiveConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        match self.current.read() {
+            Ok(guard) => *guard,
+            Err(poisoned) => {
+                error!("The configuration lock was poisoned.");
+                *poisoned.into_inner()
+            }
+        }
+    }
+}
+
+/// Reloads the configuration file, returning wether the new configuration was applied.
+///
+/// Errors are logged, and the current configuration is kept.
+pub fn reload_config() -> bool {
+    match CONFIG.reload_from(CONFIG_FILE) {
+        Ok(()) => {
+            info!("Configuration reloaded.");
+            true
+        }
+        Err(e) => {
+            error!(
+                "{}",
+                generate_error_string(
+                    &e,
+                    "Error reloading the configuration, keeping the current one"
+                )
+            );
+            false
+        }
+    }
+}
+
+/// Reloads the configuration each time the process receives a `SIGHUP` signal.
+///
+/// The signal handler only flags the request, the configuration is reloaded in a background
+/// thread.
+///
+/// # Errors
+///
+/// Returns an error if the signal handler can't be installed, or if the reload thread can't be
+/// spawned.
+pub fn reload_on_sighup() -> Result<(), Error> {
+    /// Flags the reload request, it's async-signal-safe.
+    extern "C" fn request_reload(_signal: libc::c_int) {
+        RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    let handler: extern "C" fn(libc::c_int) = request_reload;
+    // Safe because the handler only stores an atomic flag.
+    if unsafe { libc::signal(libc::SIGHUP, handler as libc::sighandler_t) } == libc::SIG_ERR {
+        return Err(io::Error::last_os_error()).context(error::Config::Signal);
+    }
+
+    let _ = thread::Builder::new()
+        .name("config".to_owned())
+        .spawn(|| loop {
+            thread::sleep(RELOAD_CHECK_INTERVAL);
+            if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+                info!("SIGHUP received, reloading the configuration\u{2026}");
+                let _ = reload_config();
+            }
+        })
+        .context(error::Config::Signal)?;
+    Ok(())
+}
+
 /// Configuration object.
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -86,6 +337,9 @@ pub struct Config {
     data_dir: PathBuf,
     /// Flight configuration.
     flight: Flight,
+    /// Logging configuration.
+    #[serde(default)]
+    log: Log,
     /// Battery configuration.
     #[cfg(feature = "fona")]
     battery: Battery,
@@ -98,12 +352,46 @@ pub struct Config {
     /// GPS configuration.
     #[cfg(feature = "gps")]
     gps: Gps,
+    /// APRS configuration.
+    #[cfg(feature = "gps")]
+    aprs: Option<Aprs>,
+    /// UKHAS / Habitat sentence configuration.
+    #[cfg(feature = "gps")]
+    #[serde(default)]
+    habitat: Habitat,
+    /// Geofences.
+    #[cfg(feature = "gps")]
+    #[serde(default)]
+    geofence: Vec<Fence>,
     /// FONA module configuration.
     #[cfg(feature = "fona")]
     fona: Fona,
+    /// Iridium SBD modem configuration.
+    #[cfg(feature = "iridium")]
+    iridium: Iridium,
+    /// Barometric sensor configuration.
+    #[cfg(feature = "baro")]
+    baro: Option<Baro>,
+    /// MQTT telemetry configuration.
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<Mqtt>,
+    /// LoRa telemetry configuration.
+    #[cfg(feature = "lora")]
+    lora: Option<Lora>,
+    /// HTTP status endpoint configuration.
+    #[cfg(feature = "http")]
+    #[serde(default)]
+    http: Http,
+    /// State transition webhook configuration.
+    #[cfg(feature = "webhook")]
+    webhook: Option<Webhook>,
     ///Telemetry configuration.
     #[cfg(feature = "telemetry")]
     telemetry: Telemetry,
+    /// Flight simulation configuration.
+    #[cfg(feature = "sim")]
+    #[serde(default)]
+    simulation: Simulation,
 }
 
 impl Config {
@@ -135,12 +423,25 @@ impl Config {
     /// Verify the correctness of the configuration, and return a list of errors if invalid.
     #[allow(clippy::too_many_lines)]
     fn verify(&self) -> (bool, String) {
-        // Only required for Raspicam
-        #[cfg(feature = "raspicam")]
         let mut errors = String::new();
-        #[cfg(feature = "raspicam")]
         let mut ok = true;
 
+        if self.log.max_files() == 0 || self.log.max_size() == 0 {
+            ok = false;
+            errors.push_str(&format!(
+                "log maximum files and size must be positive, found {} files and {} MiB\n",
+                self.log.max_files(),
+                self.log.max_size() / 1024 / 1024
+            ));
+        }
+
+        // Check that the flight detection thresholds are consistent.
+        let flight_errors = self.flight.verify();
+        if !flight_errors.is_empty() {
+            ok = false;
+            errors.push_str(&flight_errors);
+        }
+
         #[cfg(feature = "raspicam")]
         {
             // Check for picture configuration errors.
@@ -230,6 +531,15 @@ impl Config {
                 }
             }
 
+            #[cfg(feature = "gps")]
+            if !(6..=160).contains(&self.picture.annotation_size()) {
+                ok = false;
+                errors.push_str(&format!(
+                    "picture annotation size must be between 6 and 160, found {}\n",
+                    self.picture.annotation_size()
+                ));
+            }
+
             // Check for video configuration errors.
             if self.video.width > 2592 {
                 ok = false;
@@ -318,7 +628,8 @@ impl Config {
                 }
             }
 
-            // Video modes.
+            // Video modes of the camera module. 640×480 can go up to 90fps thanks to the 2×2
+            // binned and skipped sensor modes.
             match (self.video.width, self.video.height, self.video.fps) {
                 (2592, 1944, 1..=15)
                 | (1920, 1080, 1..=30)
@@ -336,18 +647,145 @@ impl Config {
             }
         }
 
-        // TODO check GPS configuration
-
-        // Only required for Raspicam
-        #[cfg(feature = "raspicam")]
+        // Check that GPIO pins are not shared between devices. The FONA uses two pins, and the GPS
+        // can share its pin with a backup GPS or with the arm switch, so there can be collisions
+        // even without the FONA.
+        #[cfg(any(feature = "fona", feature = "gps"))]
         {
-            (ok, errors)
+            use std::fmt::Write;
+
+            let mut pins = Vec::new();
+            #[cfg(feature = "gps")]
+            {
+                pins.push(("GPS power", self.gps.power_gpio.get_pin()));
+                if let Some(backup) = &self.gps.backup {
+                    pins.push(("Backup GPS power", backup.power_gpio.get_pin()));
+                }
+                if let Some(arm) = self.flight.arm_gpio {
+                    pins.push(("Arm", arm.get_pin()));
+                }
+            }
+            #[cfg(feature = "fona")]
+            {
+                pins.push(("FONA power", self.fona.power_gpio.get_pin()));
+                pins.push(("FONA status", self.fona.status_gpio.get_pin()));
+            }
+
+            for (i, &(name, pin)) in pins.iter().enumerate() {
+                for &(other_name, other_pin) in &pins[i + 1..] {
+                    if pin == other_pin {
+                        ok = false;
+                        // Writing to a `String` never fails.
+                        let _ = writeln!(
+                            errors,
+                            "{name} GPIO pin and {other_name} GPIO pin must be different, both \
+                             are {pin}",
+                        );
+                    }
+                }
+            }
+        }
+
+        // Check that the MQTT flight identifier can be used as a topic level.
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = &self.mqtt {
+            if mqtt.flight_id.is_empty() || mqtt.flight_id.contains(['/', '+', '#']) {
+                ok = false;
+                errors.push_str(
+                    "MQTT flight ID must not be empty nor contain '/', '+' or '#' characters\n",
+                );
+            }
+        }
+
+        // Check that the LoRa radio supports the modulation settings.
+        #[cfg(feature = "lora")]
+        if let Some(lora) = &self.lora {
+            if !FREQUENCY_RANGE.contains(&lora.frequency()) {
+                ok = false;
+                errors.push_str(&format!(
+                    "LoRa frequency must be between {} and {} Hz, found {} Hz\n",
+                    FREQUENCY_RANGE.start(),
+                    FREQUENCY_RANGE.end(),
+                    lora.frequency()
+                ));
+            }
+            if !SPREADING_FACTOR_RANGE.contains(&lora.spreading_factor()) {
+                ok = false;
+                errors.push_str(&format!(
+                    "LoRa spreading factor must be between {} and {}, found {}\n",
+                    SPREADING_FACTOR_RANGE.start(),
+                    SPREADING_FACTOR_RANGE.end(),
+                    lora.spreading_factor()
+                ));
+            }
+            if bandwidth_bits(lora.bandwidth()).is_none() {
+                ok = false;
+                errors.push_str(&format!(
+                    "LoRa bandwidth is not supported by the radio, found {} Hz\n",
+                    lora.bandwidth()
+                ));
+            }
+        }
+
+        // Check that the geofences are valid polygons.
+        #[cfg(feature = "gps")]
+        for fence in &self.geofence {
+            use std::fmt::Write;
+
+            if fence.vertices().len() < 3 {
+                ok = false;
+                // Writing to a `String` never fails.
+                let _ = writeln!(
+                    errors,
+                    "geofence '{}' must have at least 3 vertices",
+                    fence.name()
+                );
+            }
+            if fence.vertices().iter().any(|&[lat, lon]| {
+                !(-90_f64..=90_f64).contains(&lat) || !(-180_f64..=180_f64).contains(&lon)
+            }) {
+                ok = false;
+                let _ = writeln!(
+                    errors,
+                    "geofence '{}' vertices must be valid [latitude, longitude] pairs",
+                    fence.name()
+                );
+            }
+        }
+
+        // Check that the probe can be armed if it's required, with the arm pin or with the
+        // authenticated `ARM` command, so that it does not wait forever before the launch.
+        #[cfg(feature = "gps")]
+        if self.flight.require_arm() && self.flight.arm_gpio().is_none() {
+            #[cfg(feature = "telemetry")]
+            let arm_command = self.telemetry.command_key().is_some();
+            #[cfg(not(feature = "telemetry"))]
+            let arm_command = false;
+            if !arm_command {
+                ok = false;
+                errors.push_str(
+                    "flight arming is required, but there is no arm GPIO pin nor telemetry \
+                     command key\n",
+                );
+            }
         }
 
-        #[cfg(not(feature = "raspicam"))]
+        // Check that the GPS supports the navigation rate.
+        #[cfg(feature = "gps")]
         {
-            (true, String::new())
+            let nav_rate = self.gps.nav_rate_hz();
+            if nav_rate == 0 || nav_rate > MAX_NAV_RATE {
+                ok = false;
+                errors.push_str(&format!(
+                    "GPS navigation rate must be between 1 and {MAX_NAV_RATE} Hz, found \
+                     {nav_rate} Hz\n",
+                ));
+            }
         }
+
+        // TODO check GPS configuration
+
+        (ok, errors)
     }
 
     /// Gets wether OpenStratos should run in debug mode.
@@ -358,8 +796,8 @@ impl Config {
 
     /// Gets the flight information.
     #[must_use]
-    pub fn flight(&self) -> Flight {
-        self.flight
+    pub fn flight(&self) -> &Flight {
+        &self.flight
     }
 
     /// Gets battery configuration
@@ -397,6 +835,69 @@ impl Config {
         &self.fona
     }
 
+    /// Gets the Iridium SBD modem configuration.
+    #[cfg(feature = "iridium")]
+    #[must_use]
+    pub fn iridium(&self) -> &Iridium {
+        &self.iridium
+    }
+
+    /// Gets the barometric sensor configuration, if any.
+    #[cfg(feature = "baro")]
+    #[must_use]
+    pub fn baro(&self) -> Option<&Baro> {
+        self.baro.as_ref()
+    }
+
+    /// Gets the MQTT telemetry configuration, if any.
+    #[cfg(feature = "mqtt")]
+    #[must_use]
+    pub fn mqtt(&self) -> Option<&Mqtt> {
+        self.mqtt.as_ref()
+    }
+
+    /// Gets the LoRa telemetry configuration, if any.
+    #[cfg(feature = "lora")]
+    #[must_use]
+    pub fn lora(&self) -> Option<&Lora> {
+        self.lora.as_ref()
+    }
+
+    /// Gets the HTTP status endpoint configuration.
+    #[cfg(feature = "http")]
+    #[must_use]
+    pub fn http(&self) -> Http {
+        self.http
+    }
+
+    /// Gets the state transition webhook configuration, if any.
+    #[cfg(feature = "webhook")]
+    #[must_use]
+    pub fn webhook(&self) -> Option<&Webhook> {
+        self.webhook.as_ref()
+    }
+
+    /// Gets the APRS configuration, if any.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn aprs(&self) -> Option<&Aprs> {
+        self.aprs.as_ref()
+    }
+
+    /// Gets the UKHAS / Habitat sentence configuration.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn habitat(&self) -> &Habitat {
+        &self.habitat
+    }
+
+    /// Gets the geofences.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn geofence(&self) -> &[Fence] {
+        &self.geofence
+    }
+
     /// Gets the telemetry configuration.
     #[cfg(feature = "telemetry")]
     #[must_use]
@@ -404,6 +905,19 @@ impl Config {
         &self.telemetry
     }
 
+    /// Gets the flight simulation configuration.
+    #[cfg(feature = "sim")]
+    #[must_use]
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// Gets the logging configuration.
+    #[must_use]
+    pub fn log(&self) -> Log {
+        self.log
+    }
+
     /// Gets the configured data directory.
     #[must_use]
     pub fn data_dir(&self) -> &Path {
@@ -411,129 +925,508 @@ impl Config {
     }
 }
 
+/// Logging configuration structure.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct Log {
+    /// Format of the log records.
+    format: Option<LogFormat>,
+    /// Wether to write the logs to files in the data directory too.
+    file: Option<bool>,
+    /// Maximum number of log files kept.
+    max_files: Option<usize>,
+    /// Maximum total size of the log files, in MiB.
+    max_size_mb: Option<u64>,
+}
+
+impl Log {
+    /// Gets the format of the log records, text by default.
+    #[must_use]
+    pub fn format(self) -> LogFormat {
+        self.format.unwrap_or(LogFormat::Text)
+    }
+
+    /// Gets wether the logs should be written to files in the data directory too.
+    #[must_use]
+    pub fn file(self) -> bool {
+        self.file == Some(true)
+    }
+
+    /// Gets the maximum number of log files kept, including the current one, `10` by default.
+    #[must_use]
+    pub fn max_files(self) -> usize {
+        self.max_files.unwrap_or(DEFAULT_MAX_LOG_FILES)
+    }
+
+    /// Gets the maximum total size of the log files, in bytes, 100 MiB by default.
+    #[must_use]
+    pub fn max_size(self) -> u64 {
+        self.max_size_mb.unwrap_or(DEFAULT_MAX_LOG_SIZE_MB) * 1024 * 1024
+    }
+}
+
+/// Format of the log records.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable text, colored if the output supports it.
+    Text,
+    /// One JSON object per line, for log aggregators.
+    Json,
+}
+
 /// Flight configuration structure.
-#[derive(Debug, Clone, Copy, Deserialize)]
+///
+/// The detection thresholds default to the ones of the flight profile, and can be overridden one
+/// by one.
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(not(feature = "fona"), derive(Copy))]
 pub struct Flight {
     /// Approximate expected flight length, in minutes.
     length: u32,
     /// Approximate expected maximum height, in meters.
     expected_max_height: u32,
+    /// Time to wait after acquiring the fix, so that the GPS position stabilizes, in seconds.
+    fix_stabilization_secs: Option<u64>,
+    /// Flight profile, that sets the default detection thresholds.
+    profile: Option<Profile>,
+    /// Altitude over the launch altitude at which the launch is detected, in *m*.
+    launch_altitude_threshold: Option<f32>,
+    /// Smoothed ascent rate at which the launch is detected, in *m/s*.
+    launch_ascent_rate: Option<f32>,
+    /// Altitude lost from the maximum altitude at which the burst is detected, in *m*.
+    burst_altitude_loss: Option<f32>,
+    /// Smoothed descent rate at which the burst is detected, in *m/s*.
+    burst_descent_rate: Option<f32>,
+    /// Maximum altitude variation while landed, in *m*.
+    landing_altitude_tolerance: Option<f32>,
+    /// Time during which the altitude must stay within the tolerance to detect the landing, in
+    /// seconds.
+    landing_stable_secs: Option<u32>,
+    /// Maximum smoothed vertical rate while floating, in *m/s*.
+    float_ascent_rate: Option<f32>,
+    /// Minimum altitude at which a float can be detected, in *m*.
+    float_min_altitude: Option<f32>,
+    /// Time during which the probe must stay level to detect a float, in seconds.
+    float_detection_secs: Option<u32>,
+    /// Altitudes at which SMSs are sent during the descent, in *m*.
+    #[cfg(feature = "fona")]
+    descent_sms_altitudes: Option<Vec<f32>>,
+    /// Wether the probe must be armed before committing to the launch sequence.
+    require_arm: Option<bool>,
+    /// GPIO pin that arms the probe when it reads high.
+    #[cfg(feature = "gps")]
+    #[serde(default, deserialize_with = "deserialize_optional_pin")]
+    arm_gpio: Option<Pin>,
 }
 
 impl Flight {
     /// Gets the approximate expected flight length, in minutes.
     #[must_use]
-    pub fn length(self) -> u32 {
+    pub fn length(&self) -> u32 {
         self.length
     }
 
     /// Gets the approximate expected maximum height, in meters.
     #[must_use]
-    pub fn expected_max_height(self) -> u32 {
+    pub fn expected_max_height(&self) -> u32 {
         self.expected_max_height
     }
-}
-
-/// Battery configuration structure.
-#[cfg(feature = "fona")]
-#[derive(Debug, Clone, Copy, Deserialize)]
-pub struct Battery {
-    /// Minimum voltage for the main battery when empty, at 0%, in volts (`V`).
-    main_min: f32,
-    /// Maximum voltage for the main battery when full, at 100%, in volts (`V`).
-    main_max: f32,
-    /// Minimum voltage for the FONA battery when empty, at 0%, in volts (`V`).
-    fona_min: f32,
-    /// Maximum voltage for the FONA battery when full, at 100%, in volts (`V`).
-    fona_max: f32,
-    /// Minimum admissible percentage for main battery for the launch.
-    main_min_percent: f32,
-    /// Minimum admissible percentage for FONA battery for the launch.
-    fona_min_percent: f32,
-}
 
-#[cfg(feature = "fona")]
-impl Battery {
-    /// Gets the minimum voltage for the main battery when empty, at 0%, in volts (`V`).
+    /// Gets the time to wait after acquiring the fix, so that the GPS position stabilizes, 10
+    /// seconds by default.
     #[must_use]
-    pub fn main_min(self) -> f32 {
-        self.main_min
+    pub fn fix_stabilization_time(&self) -> Duration {
+        self.fix_stabilization_secs
+            .map_or(DEFAULT_FIX_STABILIZATION_TIME, Duration::from_secs)
     }
 
-    /// Gets the maximum voltage for the main battery when full, at 100%, in volts (`V`).
+    /// Gets wether the probe must be armed before committing to the launch sequence.
     #[must_use]
-    pub fn main_max(self) -> f32 {
-        self.main_max
+    pub fn require_arm(&self) -> bool {
+        self.require_arm == Some(true)
     }
 
-    /// Gets the minimum voltage for the FONA battery when empty, at 0%, in volts (`V`).
+    /// Gets the GPIO pin that arms the probe when it reads high, if any.
+    #[cfg(feature = "gps")]
     #[must_use]
-    pub fn fona_min(self) -> f32 {
-        self.fona_min
+    pub fn arm_gpio(&self) -> Option<Pin> {
+        self.arm_gpio
     }
 
-    /// Gets the maximum voltage for the FONA battery when full, at 0%, in volts (`V`).
+    /// Gets the flight profile, `"standard"` by default.
     #[must_use]
-    pub fn fona_max(self) -> f32 {
-        self.fona_max
+    pub fn profile(&self) -> Profile {
+        self.profile.unwrap_or(Profile::Standard)
     }
 
-    /// Gets the minimum admissible percentage for main battery for the launch.
+    /// Gets the altitude over the launch altitude at which the launch is detected, in *m*.
     #[must_use]
-    pub fn main_min_percent(self) -> f32 {
-        self.main_min_percent
+    pub fn launch_altitude_threshold(&self) -> f32 {
+        self.launch_altitude_threshold
+            .unwrap_or_else(|| self.profile().launch_altitude_threshold())
     }
 
-    /// Gets the minimum admissible percentage for FONA battery for the launch.
+    /// Gets the smoothed ascent rate at which the launch is detected, in *m/s*.
     #[must_use]
-    pub fn fona_min_percent(self) -> f32 {
-        self.fona_min_percent
+    pub fn launch_ascent_rate(&self) -> f32 {
+        self.launch_ascent_rate
+            .unwrap_or_else(|| self.profile().launch_ascent_rate())
     }
-}
-
-/// Video configuration structure.
-#[cfg(feature = "raspicam")]
-#[derive(Debug, Clone, Copy, Deserialize)]
-pub struct Video {
-    /// Height of the video, in px.
-    height: u16,
-    /// Width of the video, in px.
-    width: u16,
-    /// Rotation of the camera, in degrees (°).
-    rotation: Option<u16>,
-    /// Frames per second (FPS) for the video.
-    fps: u8,
-    /// Bit rate for the video, in bps (bits per second).
-    bitrate: u32,
-    /// Exposure configuration.
-    exposure: Option<Exposure>,
-    /// Brightness correction.
-    brightness: Option<u8>,
-    /// Contrast correction.
-    contrast: Option<i8>,
-    /// Sharpness configuration.
-    sharpness: Option<i8>,
-    /// Saturation configuration.
-    saturation: Option<i8>,
-    /// ISO for the image.
-    iso: Option<u16>,
-    /// Video stabilization.
-    stabilization: Option<bool>,
-    /// EV correction configuration.
-    ev: Option<i8>,
-    /// White balance configuration.
-    white_balance: Option<WhiteBalance>,
-}
 
-#[cfg(feature = "raspicam")]
-impl Video {
-    /// Gets the configured video height for the camera, in pixels.
+    /// Gets the altitude lost from the maximum altitude at which the burst is detected, in *m*.
     #[must_use]
-    pub fn height(self) -> u16 {
-        self.height
+    pub fn burst_altitude_loss(&self) -> f32 {
+        self.burst_altitude_loss
+            .unwrap_or_else(|| self.profile().burst_altitude_loss())
     }
 
-    /// Gets the configured video width for the camera, in pixels.
+    /// Gets the smoothed descent rate at which the burst is detected, in *m/s*.
+    #[must_use]
+    pub fn burst_descent_rate(&self) -> f32 {
+        self.burst_descent_rate
+            .unwrap_or_else(|| self.profile().burst_descent_rate())
+    }
+
+    /// Gets the maximum altitude variation while landed, in *m*.
+    #[must_use]
+    pub fn landing_altitude_tolerance(&self) -> f32 {
+        self.landing_altitude_tolerance
+            .unwrap_or_else(|| self.profile().landing_altitude_tolerance())
+    }
+
+    /// Gets the time during which the altitude must stay within the tolerance to detect the
+    /// landing, in seconds.
+    #[must_use]
+    pub fn landing_stable_secs(&self) -> u32 {
+        self.landing_stable_secs
+            .unwrap_or_else(|| self.profile().landing_stable_secs())
+    }
+
+    /// Gets the maximum smoothed vertical rate, up or down, while floating, in *m/s*.
+    #[must_use]
+    pub fn float_ascent_rate(&self) -> f32 {
+        self.float_ascent_rate
+            .unwrap_or_else(|| self.profile().float_ascent_rate())
+    }
+
+    /// Gets the minimum altitude at which a float can be detected, in *m*.
+    #[must_use]
+    pub fn float_min_altitude(&self) -> f32 {
+        self.float_min_altitude
+            .unwrap_or_else(|| self.profile().float_min_altitude())
+    }
+
+    /// Gets the time during which the probe must stay level to detect a float, in seconds.
+    #[must_use]
+    pub fn float_detection_secs(&self) -> u32 {
+        self.float_detection_secs
+            .unwrap_or_else(|| self.profile().float_detection_secs())
+    }
+
+    /// Gets the altitudes at which SMSs are sent during the descent, in *m*.
+    #[cfg(feature = "fona")]
+    #[must_use]
+    pub fn descent_sms_altitudes(&self) -> &[f32] {
+        self.descent_sms_altitudes
+            .as_deref()
+            .unwrap_or_else(|| self.profile().descent_sms_altitudes())
+    }
+
+    /// Verifies that the detection thresholds are consistent, returning the errors found.
+    fn verify(&self) -> String {
+        use std::fmt::Write;
+
+        // Writing to a `String` never fails, so the results of `writeln!` are ignored.
+        let mut errors = String::new();
+
+        for (name, value) in [
+            (
+                "launch altitude threshold",
+                self.launch_altitude_threshold(),
+            ),
+            ("launch ascent rate", self.launch_ascent_rate()),
+            ("burst altitude loss", self.burst_altitude_loss()),
+            ("burst descent rate", self.burst_descent_rate()),
+            (
+                "landing altitude tolerance",
+                self.landing_altitude_tolerance(),
+            ),
+            ("float ascent rate", self.float_ascent_rate()),
+            ("float minimum altitude", self.float_min_altitude()),
+        ] {
+            if value.is_nan() || value <= 0_f32 {
+                let _ = writeln!(errors, "flight {name} must be positive, found {value}");
+            }
+        }
+        if self.landing_stable_secs() == 0 {
+            errors.push_str("flight landing stable time must be positive, found 0 s\n");
+        }
+        if self.float_detection_secs() == 0 {
+            errors.push_str("flight float detection time must be positive, found 0 s\n");
+        }
+        if self.float_ascent_rate() >= self.burst_descent_rate() {
+            let _ = writeln!(
+                errors,
+                "flight float ascent rate must be lower than the burst descent rate, found {} m/s \
+                 and {} m/s",
+                self.float_ascent_rate(),
+                self.burst_descent_rate()
+            );
+        }
+        if self.landing_altitude_tolerance() >= self.burst_altitude_loss() {
+            let _ = writeln!(
+                errors,
+                "flight landing altitude tolerance must be lower than the burst altitude loss, \
+                 found {} m and {} m",
+                self.landing_altitude_tolerance(),
+                self.burst_altitude_loss()
+            );
+        }
+        #[allow(clippy::cast_precision_loss)]
+        if self.burst_altitude_loss() >= self.expected_max_height as f32 {
+            let _ = writeln!(
+                errors,
+                "flight burst altitude loss must be lower than the expected maximum height, \
+                 found {} m and {} m",
+                self.burst_altitude_loss(),
+                self.expected_max_height
+            );
+        }
+        #[cfg(feature = "fona")]
+        {
+            let altitudes = self.descent_sms_altitudes();
+            if altitudes
+                .iter()
+                .any(|&altitude| altitude.is_nan() || altitude <= 0_f32)
+                || altitudes.windows(2).any(|pair| pair[0] <= pair[1])
+            {
+                let _ = writeln!(
+                    errors,
+                    "flight descent SMS altitudes must be positive and strictly decreasing, \
+                     found {altitudes:?}"
+                );
+            }
+        }
+
+        errors
+    }
+}
+
+/// Flight profile, that sets the default launch, float, burst and landing detection thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    /// Standard latex balloon, ascending at about 5 *m/s* and bursting at the maximum height.
+    Standard,
+    /// Fast ascent, with an overfilled balloon ascending at 7 *m/s* or more, and a fast descent.
+    FastAscent,
+    /// Zero-pressure or float balloon, ascending slowly and oscillating around its float height.
+    Float,
+}
+
+impl Profile {
+    /// Gets the altitude over the launch altitude at which the launch is detected, in *m*.
+    #[must_use]
+    pub fn launch_altitude_threshold(self) -> f32 {
+        match self {
+            Profile::Standard | Profile::Float => 100_f32,
+            Profile::FastAscent => 200_f32,
+        }
+    }
+
+    /// Gets the smoothed ascent rate at which the launch is detected, in *m/s*.
+    #[must_use]
+    pub fn launch_ascent_rate(self) -> f32 {
+        match self {
+            Profile::Standard => 3_f32,
+            Profile::FastAscent => 5_f32,
+            Profile::Float => 1.5_f32,
+        }
+    }
+
+    /// Gets the altitude lost from the maximum altitude at which the burst is detected, in *m*.
+    #[must_use]
+    pub fn burst_altitude_loss(self) -> f32 {
+        match self {
+            Profile::Standard => 1_000_f32,
+            Profile::FastAscent => 1_500_f32,
+            Profile::Float => 2_000_f32,
+        }
+    }
+
+    /// Gets the smoothed descent rate at which the burst is detected, in *m/s*.
+    #[must_use]
+    pub fn burst_descent_rate(self) -> f32 {
+        match self {
+            Profile::Standard => 15_f32,
+            Profile::FastAscent => 20_f32,
+            Profile::Float => 10_f32,
+        }
+    }
+
+    /// Gets the maximum altitude variation while landed, in *m*.
+    ///
+    /// It's above the usual vertical error of the GPS, so that its noise is not mistaken for a
+    /// descent.
+    #[must_use]
+    pub fn landing_altitude_tolerance(self) -> f32 {
+        20_f32
+    }
+
+    /// Gets the time during which the altitude must stay within the tolerance to detect the
+    /// landing, in seconds.
+    #[must_use]
+    pub fn landing_stable_secs(self) -> u32 {
+        match self {
+            Profile::Standard | Profile::FastAscent => 60,
+            Profile::Float => 120,
+        }
+    }
+
+    /// Gets the maximum smoothed vertical rate, up or down, while floating, in *m/s*.
+    #[must_use]
+    pub fn float_ascent_rate(self) -> f32 {
+        match self {
+            Profile::Standard | Profile::FastAscent => 0.5_f32,
+            Profile::Float => 1_f32,
+        }
+    }
+
+    /// Gets the minimum altitude at which a float can be detected, in *m*.
+    #[must_use]
+    pub fn float_min_altitude(self) -> f32 {
+        match self {
+            Profile::Standard | Profile::FastAscent => 10_000_f32,
+            Profile::Float => 5_000_f32,
+        }
+    }
+
+    /// Gets the time during which the probe must stay level to detect a float, in seconds.
+    #[must_use]
+    pub fn float_detection_secs(self) -> u32 {
+        match self {
+            Profile::Standard | Profile::FastAscent => 600,
+            Profile::Float => 300,
+        }
+    }
+
+    /// Gets the altitudes at which SMSs are sent during the descent, in *m*.
+    #[cfg(feature = "fona")]
+    #[must_use]
+    pub fn descent_sms_altitudes(self) -> &'static [f32] {
+        match self {
+            Profile::Standard | Profile::Float => &[2_500_f32, 1_500_f32, 500_f32],
+            Profile::FastAscent => &[3_000_f32, 2_000_f32, 1_000_f32, 500_f32],
+        }
+    }
+}
+
+/// Battery configuration structure.
+#[cfg(feature = "fona")]
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Battery {
+    /// Minimum voltage for the main battery when empty, at 0%, in volts (`V`).
+    main_min: f32,
+    /// Maximum voltage for the main battery when full, at 100%, in volts (`V`).
+    main_max: f32,
+    /// Minimum voltage for the FONA battery when empty, at 0%, in volts (`V`).
+    fona_min: f32,
+    /// Maximum voltage for the FONA battery when full, at 100%, in volts (`V`).
+    fona_max: f32,
+    /// Minimum admissible percentage for main battery for the launch.
+    main_min_percent: f32,
+    /// Minimum admissible percentage for FONA battery for the launch.
+    fona_min_percent: f32,
+}
+
+#[cfg(feature = "fona")]
+impl Battery {
+    /// Gets the minimum voltage for the main battery when empty, at 0%, in volts (`V`).
+    #[must_use]
+    pub fn main_min(self) -> f32 {
+        self.main_min
+    }
+
+    /// Gets the maximum voltage for the main battery when full, at 100%, in volts (`V`).
+    #[must_use]
+    pub fn main_max(self) -> f32 {
+        self.main_max
+    }
+
+    /// Gets the minimum voltage for the FONA battery when empty, at 0%, in volts (`V`).
+    #[must_use]
+    pub fn fona_min(self) -> f32 {
+        self.fona_min
+    }
+
+    /// Gets the maximum voltage for the FONA battery when full, at 0%, in volts (`V`).
+    #[must_use]
+    pub fn fona_max(self) -> f32 {
+        self.fona_max
+    }
+
+    /// Gets the minimum admissible percentage for main battery for the launch, from 0 to 1.
+    #[must_use]
+    pub fn main_min_percent(self) -> f32 {
+        self.main_min_percent
+    }
+
+    /// Gets the minimum admissible percentage for FONA battery for the launch, from 0 to 1.
+    #[must_use]
+    pub fn fona_min_percent(self) -> f32 {
+        self.fona_min_percent
+    }
+}
+
+/// Video configuration structure.
+#[cfg(feature = "raspicam")]
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Video {
+    /// Height of the video, in px.
+    height: u16,
+    /// Width of the video, in px.
+    width: u16,
+    /// Rotation of the camera, in degrees (°).
+    rotation: Option<u16>,
+    /// Frames per second (FPS) for the video.
+    fps: u8,
+    /// Bit rate for the video, in bps (bits per second).
+    bitrate: u32,
+    /// Exposure configuration.
+    exposure: Option<Exposure>,
+    /// Brightness correction.
+    brightness: Option<u8>,
+    /// Contrast correction.
+    contrast: Option<i8>,
+    /// Sharpness configuration.
+    sharpness: Option<i8>,
+    /// Saturation configuration.
+    saturation: Option<i8>,
+    /// ISO for the image.
+    iso: Option<u16>,
+    /// Video stabilization.
+    stabilization: Option<bool>,
+    /// EV correction configuration.
+    ev: Option<i8>,
+    /// White balance configuration.
+    white_balance: Option<WhiteBalance>,
+    /// Metering mode.
+    metering: Option<Metering>,
+    /// Dynamic range compression.
+    drc: Option<Drc>,
+    /// Wether to skip the test recording during initialization.
+    skip_camera_test: Option<bool>,
+}
+
+#[cfg(feature = "raspicam")]
+impl Video {
+    /// Gets the configured video height for the camera, in pixels.
+    #[must_use]
+    pub fn height(self) -> u16 {
+        self.height
+    }
+
+    /// Gets the configured video width for the camera, in pixels.
     #[must_use]
     pub fn width(self) -> u16 {
         self.width
@@ -610,11 +1503,32 @@ impl Video {
     pub fn white_balance(self) -> Option<WhiteBalance> {
         self.white_balance
     }
+
+    /// Gets the configured metering mode for videos.
+    #[must_use]
+    pub fn metering(self) -> Option<Metering> {
+        self.metering
+    }
+
+    /// Gets the configured dynamic range compression for videos.
+    #[must_use]
+    pub fn drc(self) -> Option<Drc> {
+        self.drc
+    }
+
+    /// Gets wether the test recording should be skipped during initialization.
+    ///
+    /// If skipped, only the `raspivid` binary and the camera detection will be checked.
+    #[must_use]
+    pub fn skip_camera_test(self) -> bool {
+        self.skip_camera_test == Some(true)
+    }
 }
 
 /// Picture configuration structure.
 #[cfg(feature = "raspicam")]
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(not(feature = "gps"), derive(Copy))]
 pub struct Picture {
     /// Height of the picture, in px.
     height: u16,
@@ -645,104 +1559,135 @@ pub struct Picture {
     ev: Option<i8>,
     /// White balance configuration.
     white_balance: Option<WhiteBalance>,
+    /// Metering mode.
+    metering: Option<Metering>,
+    /// Dynamic range compression.
+    drc: Option<Drc>,
     /// Interval between pictures during flight.
     interval: u32,
     /// Repeat each picture after these seconds (for issues with probe movement).
     repeat: Option<u32>,
     /// Timeout for first picture after launch, in seconds.
     first_timeout: u32,
+    /// Camera warm-up time before taking the picture, in milliseconds.
+    warmup_ms: Option<u32>,
+    /// Altitudes that trigger an extra picture when crossed upwards, in *m*.
+    #[cfg(feature = "gps")]
+    #[serde(default)]
+    altitude_triggers: Vec<u32>,
+    /// Template of the text annotated in pictures, with the latest GPS data.
+    #[cfg(feature = "gps")]
+    annotation: Option<String>,
+    /// Position of the annotation in the picture.
+    #[cfg(feature = "gps")]
+    annotation_position: Option<AnnotationPosition>,
+    /// Size of the annotation text.
+    #[cfg(feature = "gps")]
+    annotation_size: Option<u8>,
 }
 
 #[cfg(feature = "raspicam")]
 impl Picture {
     /// Gets the configured picture height for the camera, in pixels.
     #[must_use]
-    pub fn height(self) -> u16 {
+    pub fn height(&self) -> u16 {
         self.height
     }
 
     /// Gets the configured picture width for the camera, in pixels.
     #[must_use]
-    pub fn width(self) -> u16 {
+    pub fn width(&self) -> u16 {
         self.width
     }
 
     /// Gets the configured picture rotation for the camera, in degrees (°).
     #[must_use]
-    pub fn rotation(self) -> Option<u16> {
+    pub fn rotation(&self) -> Option<u16> {
         self.rotation
     }
 
     /// Gets the configured picture quality for the camera.
     #[must_use]
-    pub fn quality(self) -> u8 {
+    pub fn quality(&self) -> u8 {
         self.quality
     }
 
     /// Gets wether the camera should add available EXIF information to pictures.
     #[cfg(feature = "gps")]
     #[must_use]
-    pub fn exif(self) -> bool {
+    pub fn exif(&self) -> bool {
         self.exif == Some(true)
     }
 
     /// Gets wether the camera should add raw sensor data to pictures as JPEG metadata.
     #[must_use]
-    pub fn raw(self) -> bool {
+    pub fn raw(&self) -> bool {
         self.raw == Some(true)
     }
 
     /// Gets the configured exposure for pictures.
     #[must_use]
-    pub fn exposure(self) -> Option<Exposure> {
+    pub fn exposure(&self) -> Option<Exposure> {
         self.exposure
     }
 
     /// Gets the configured brightness for pictures.
     #[must_use]
-    pub fn brightness(self) -> Option<u8> {
+    pub fn brightness(&self) -> Option<u8> {
         self.brightness
     }
 
     /// Gets the configured contrast for pictures.
     #[must_use]
-    pub fn contrast(self) -> Option<i8> {
+    pub fn contrast(&self) -> Option<i8> {
         self.contrast
     }
 
     /// Gets the configured sharpness for pictures.
     #[must_use]
-    pub fn sharpness(self) -> Option<i8> {
+    pub fn sharpness(&self) -> Option<i8> {
         self.sharpness
     }
 
     /// Gets the configured saturation for pictures.
     #[must_use]
-    pub fn saturation(self) -> Option<i8> {
+    pub fn saturation(&self) -> Option<i8> {
         self.saturation
     }
 
     /// Gets the configured ISO for pictures.
     #[must_use]
-    pub fn iso(self) -> Option<u16> {
+    pub fn iso(&self) -> Option<u16> {
         self.iso
     }
 
     /// Gets the configured EV compensation for pictures.
     #[must_use]
-    pub fn ev(self) -> Option<i8> {
+    pub fn ev(&self) -> Option<i8> {
         self.ev
     }
 
     /// Gets the configured automatic white balance for pictures.
     #[must_use]
-    pub fn white_balance(self) -> Option<WhiteBalance> {
+    pub fn white_balance(&self) -> Option<WhiteBalance> {
         self.white_balance
     }
 
+    /// Gets the configured metering mode for pictures.
+    #[must_use]
+    pub fn metering(&self) -> Option<Metering> {
+        self.metering
+    }
+
+    /// Gets the configured dynamic range compression for pictures.
+    #[must_use]
+    pub fn drc(&self) -> Option<Drc> {
+        self.drc
+    }
+
     /// Gets the interval between pictures during flight.
     #[must_use]
-    pub fn interval(self) -> u32 {
+    pub fn interval(&self) -> u32 {
         self.interval
     }
 
@@ -750,15 +1695,64 @@ impl Picture {
     ///
     /// Repeat each picture after these seconds (for issues with probe movement).
     #[must_use]
-    pub fn repeat(self) -> Option<u32> {
+    pub fn repeat(&self) -> Option<u32> {
         self.repeat
     }
 
     /// Gets the timeout for first picture after launch, in seconds.
     #[must_use]
-    pub fn first_timeout(self) -> u32 {
+    pub fn first_timeout(&self) -> u32 {
         self.first_timeout
     }
+
+    /// Gets the camera warm-up time before taking a picture, in milliseconds, `200` by default.
+    #[must_use]
+    pub fn warmup_ms(&self) -> u32 {
+        self.warmup_ms.unwrap_or(DEFAULT_PICTURE_WARMUP_MS)
+    }
+
+    /// Gets the altitudes that trigger an extra picture the first time they are crossed upwards,
+    /// in *m*.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn altitude_triggers(&self) -> &[u32] {
+        &self.altitude_triggers
+    }
+
+    /// Gets the template of the text annotated in pictures, if any.
+    ///
+    /// The `{date}`, `{time}`, `{altitude}`, `{latitude}`, `{longitude}` and `{satellites}`
+    /// placeholders are replaced with the latest GPS data.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn annotation(&self) -> Option<&str> {
+        self.annotation.as_deref()
+    }
+
+    /// Gets the position of the annotation in the picture, at the top by default.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn annotation_position(&self) -> AnnotationPosition {
+        self.annotation_position.unwrap_or(AnnotationPosition::Top)
+    }
+
+    /// Gets the size of the annotation text, `32` by default.
+    #[cfg(feature = "gps")]
+    #[must_use]
+    pub fn annotation_size(&self) -> u8 {
+        self.annotation_size.unwrap_or(DEFAULT_ANNOTATION_SIZE)
+    }
+}
+
+/// Position of the picture annotation.
+#[cfg(all(feature = "raspicam", feature = "gps"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationPosition {
+    /// At the top of the picture.
+    Top,
+    /// At the bottom of the picture.
+    Bottom,
 }
 
 /// Exposure setting.
@@ -840,54 +1834,533 @@ pub enum WhiteBalance {
     Horizon,
 }
 
-#[cfg(feature = "raspicam")]
-impl AsRef<OsStr> for WhiteBalance {
-    fn as_ref(&self) -> &OsStr {
-        OsStr::new(match *self {
-            WhiteBalance::Off => "off",
-            WhiteBalance::Auto => "auto",
-            WhiteBalance::Sun => "sun",
-            WhiteBalance::CloudShade => "cloudshade",
-            WhiteBalance::Tungsten => "tungsten",
-            WhiteBalance::Fluorescent => "fluorescent",
-            WhiteBalance::Incandescent => "incandescent",
-            WhiteBalance::Flash => "flash",
-            WhiteBalance::Horizon => "horizon",
-        })
+#[cfg(feature = "raspicam")]
+impl AsRef<OsStr> for WhiteBalance {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(match *self {
+            WhiteBalance::Off => "off",
+            WhiteBalance::Auto => "auto",
+            WhiteBalance::Sun => "sun",
+            WhiteBalance::CloudShade => "cloudshade",
+            WhiteBalance::Tungsten => "tungsten",
+            WhiteBalance::Fluorescent => "fluorescent",
+            WhiteBalance::Incandescent => "incandescent",
+            WhiteBalance::Flash => "flash",
+            WhiteBalance::Horizon => "horizon",
+        })
+    }
+}
+
+/// Metering mode setting.
+#[cfg(feature = "raspicam")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+pub enum Metering {
+    /// Average the whole frame for metering (default).
+    Average,
+    /// Spot metering, in the center of the frame.
+    Spot,
+    /// Assume a backlit image.
+    Backlit,
+    /// Matrix metering.
+    Matrix,
+}
+
+#[cfg(feature = "raspicam")]
+impl AsRef<OsStr> for Metering {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(match *self {
+            Metering::Average => "average",
+            Metering::Spot => "spot",
+            Metering::Backlit => "backlit",
+            Metering::Matrix => "matrix",
+        })
+    }
+}
+
+/// Dynamic range compression setting.
+#[cfg(feature = "raspicam")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+pub enum Drc {
+    /// No dynamic range compression (default).
+    Off,
+    /// Low dynamic range compression.
+    Low,
+    /// Medium dynamic range compression.
+    Med,
+    /// High dynamic range compression.
+    High,
+}
+
+#[cfg(feature = "raspicam")]
+impl AsRef<OsStr> for Drc {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(match *self {
+            Drc::Off => "off",
+            Drc::Low => "low",
+            Drc::Med => "med",
+            Drc::High => "high",
+        })
+    }
+}
+
+/// GPS configuration structure.
+#[cfg(feature = "gps")]
+#[derive(Debug, Deserialize)]
+pub struct Gps {
+    /// UART serial console path.
+    uart: PathBuf,
+    /// Serial console baud rate.
+    baud_rate: u32,
+    /// Serial read/write timeout, in milliseconds.
+    timeout_ms: Option<u64>,
+    /// Power GPIO pin.
+    #[serde(deserialize_with = "deserialize_pin")]
+    power_gpio: Pin,
+    /// Number of frames averaged in the smoothed ascent rate.
+    rate_window: Option<usize>,
+    /// Navigation rate, in *Hz*.
+    nav_rate_hz: Option<u8>,
+    /// Reset sent to the GPS when initializing it.
+    reset_on_init: Option<ResetKind>,
+    /// Backup GPS configuration.
+    backup: Option<GpsBackup>,
+}
+
+#[cfg(feature = "gps")]
+impl Gps {
+    /// Gets the UART serial console path.
+    #[must_use]
+    pub fn uart(&self) -> &Path {
+        &self.uart
+    }
+
+    /// Gets the serial console baud rate.
+    #[must_use]
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Gets the serial read/write timeout, 5 seconds by default.
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_SERIAL_TIMEOUT, Duration::from_millis)
+    }
+
+    /// Gets the power GPIO pin.
+    #[must_use]
+    pub fn power_gpio(&self) -> Pin {
+        self.power_gpio
+    }
+
+    /// Gets the number of frames averaged in the smoothed ascent rate, 5 by default.
+    #[must_use]
+    pub fn rate_window(&self) -> usize {
+        self.rate_window.unwrap_or(DEFAULT_RATE_WINDOW)
+    }
+
+    /// Gets the navigation rate, in *Hz*, 10 Hz by default.
+    #[must_use]
+    pub fn nav_rate_hz(&self) -> u8 {
+        self.nav_rate_hz.unwrap_or(DEFAULT_NAV_RATE)
+    }
+
+    /// Gets the reset sent to the GPS when initializing it, if any.
+    #[must_use]
+    pub fn reset_on_init(&self) -> Option<ResetKind> {
+        self.reset_on_init
+    }
+
+    /// Gets the backup GPS configuration, if any.
+    #[must_use]
+    pub fn backup(&self) -> Option<&GpsBackup> {
+        self.backup.as_ref()
+    }
+}
+
+/// Backup GPS configuration structure.
+#[cfg(feature = "gps")]
+#[derive(Debug, Deserialize)]
+pub struct GpsBackup {
+    /// UART serial console path.
+    uart: PathBuf,
+    /// Serial console baud rate.
+    baud_rate: u32,
+    /// Serial read/write timeout, in milliseconds.
+    timeout_ms: Option<u64>,
+    /// Power GPIO pin.
+    #[serde(deserialize_with = "deserialize_pin")]
+    power_gpio: Pin,
+}
+
+#[cfg(feature = "gps")]
+impl GpsBackup {
+    /// Gets the UART serial console path.
+    #[must_use]
+    pub fn uart(&self) -> &Path {
+        &self.uart
+    }
+
+    /// Gets the serial console baud rate.
+    #[must_use]
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Gets the serial read/write timeout, 5 seconds by default.
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_SERIAL_TIMEOUT, Duration::from_millis)
+    }
+
+    /// Gets the power GPIO pin.
+    #[must_use]
+    pub fn power_gpio(&self) -> Pin {
+        self.power_gpio
+    }
+}
+
+/// APRS configuration structure.
+#[cfg(feature = "gps")]
+#[derive(Debug, Deserialize)]
+pub struct Aprs {
+    /// Callsign of the station.
+    callsign: String,
+    /// Secondary station identifier, 0 by default.
+    ssid: Option<u8>,
+    /// Comment to add to position reports.
+    comment: Option<String>,
+}
+
+#[cfg(feature = "gps")]
+impl Aprs {
+    /// Gets the callsign of the station.
+    #[must_use]
+    pub fn callsign(&self) -> &str {
+        &self.callsign
+    }
+
+    /// Gets the secondary station identifier, 0 by default.
+    #[must_use]
+    pub fn ssid(&self) -> u8 {
+        self.ssid.unwrap_or_default()
+    }
+
+    /// Gets the comment to add to position reports, empty by default.
+    #[must_use]
+    pub fn comment(&self) -> &str {
+        self.comment.as_deref().unwrap_or_default()
+    }
+}
+
+/// MQTT telemetry configuration structure.
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Deserialize)]
+pub struct Mqtt {
+    /// Host name or IP address of the broker.
+    host: String,
+    /// Port of the broker, 1883 by default.
+    port: Option<u16>,
+    /// Identifier of the flight, used in the topic.
+    flight_id: String,
+    /// Client identifier, `openstratos-<flight_id>` by default.
+    client_id: Option<String>,
+    /// User name, if the broker requires authentication.
+    username: Option<String>,
+    /// Password, if the broker requires authentication.
+    password: Option<String>,
+    /// Keep alive interval, in seconds, 60 by default.
+    keep_alive: Option<u16>,
+    /// Maximum number of messages buffered while offline, 100 by default.
+    buffer: Option<usize>,
+}
+
+#[cfg(feature = "mqtt")]
+impl Mqtt {
+    /// Gets the host name or IP address of the broker.
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Gets the port of the broker, 1883 by default.
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_MQTT_PORT)
+    }
+
+    /// Gets the identifier of the flight.
+    #[must_use]
+    pub fn flight_id(&self) -> &str {
+        &self.flight_id
+    }
+
+    /// Gets the client identifier, `openstratos-<flight_id>` by default.
+    #[must_use]
+    pub fn client_id(&self) -> String {
+        self.client_id
+            .clone()
+            .unwrap_or_else(|| format!("openstratos-{}", self.flight_id))
+    }
+
+    /// Gets the user name, if any.
+    #[must_use]
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    /// Gets the password, if any.
+    #[must_use]
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// Gets the keep alive interval, in seconds, 60 by default.
+    #[must_use]
+    pub fn keep_alive(&self) -> u16 {
+        self.keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE)
+    }
+
+    /// Gets the maximum number of messages buffered while offline, 100 by default.
+    #[must_use]
+    pub fn buffer(&self) -> usize {
+        self.buffer.unwrap_or(DEFAULT_BUFFER)
+    }
+}
+
+/// UKHAS / Habitat sentence configuration structure.
+#[cfg(feature = "gps")]
+#[derive(Debug, Default, Deserialize)]
+pub struct Habitat {
+    /// Callsign of the payload.
+    callsign: Option<String>,
+    /// Fields of the sentence, after the sentence ID.
+    fields: Option<Vec<HabitatField>>,
+}
+
+#[cfg(feature = "gps")]
+impl Habitat {
+    /// Gets the callsign of the payload, `OPENSTRATOS` by default.
+    #[must_use]
+    pub fn callsign(&self) -> &str {
+        self.callsign.as_deref().unwrap_or(DEFAULT_CALLSIGN)
+    }
+
+    /// Gets the fields of the sentence, after the sentence ID.
+    ///
+    /// By default, the time, latitude, longitude, altitude and number of satellites.
+    #[must_use]
+    pub fn fields(&self) -> &[HabitatField] {
+        self.fields.as_deref().unwrap_or(&DEFAULT_FIELDS)
+    }
+}
+
+/// Iridium SBD modem configuration structure.
+#[cfg(feature = "iridium")]
+#[derive(Debug, Deserialize)]
+pub struct Iridium {
+    /// UART serial console path.
+    uart: PathBuf,
+    /// Serial console baud rate.
+    baud_rate: Option<u32>,
+    /// Serial read/write timeout, in milliseconds.
+    timeout_ms: Option<u64>,
+}
+
+#[cfg(feature = "iridium")]
+impl Iridium {
+    /// Gets the UART serial console path.
+    #[must_use]
+    pub fn uart(&self) -> &Path {
+        &self.uart
+    }
+
+    /// Gets the serial console baud rate, 19200 by default.
+    #[must_use]
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate.unwrap_or(DEFAULT_IRIDIUM_BAUD_RATE)
+    }
+
+    /// Gets the serial read/write timeout, 60 seconds by default.
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_IRIDIUM_TIMEOUT, Duration::from_millis)
+    }
+}
+
+/// Barometric sensor configuration structure.
+#[cfg(feature = "baro")]
+#[derive(Debug, Deserialize)]
+pub struct Baro {
+    /// I²C bus device.
+    i2c_bus: Option<PathBuf>,
+    /// I²C address of the sensor.
+    address: Option<u16>,
+    /// Weight of the barometric altitude changes in the fused altitude.
+    weight: Option<f32>,
+}
+
+#[cfg(feature = "baro")]
+impl Baro {
+    /// Gets the I²C bus device, `/dev/i2c-1` by default.
+    #[must_use]
+    pub fn i2c_bus(&self) -> &Path {
+        self.i2c_bus
+            .as_deref()
+            .unwrap_or_else(|| Path::new(DEFAULT_I2C_BUS))
+    }
+
+    /// Gets the I²C address of the sensor, `0x76` by default.
+    #[must_use]
+    pub fn address(&self) -> u16 {
+        self.address.unwrap_or(DEFAULT_BARO_ADDRESS)
+    }
+
+    /// Gets the weight of the barometric altitude changes in the fused altitude, 0.9 by default.
+    #[must_use]
+    pub fn weight(&self) -> f32 {
+        self.weight.unwrap_or(DEFAULT_BARO_WEIGHT)
+    }
+}
+
+/// LoRa telemetry configuration structure.
+#[cfg(feature = "lora")]
+#[derive(Debug, Deserialize)]
+pub struct Lora {
+    /// SPI device of the radio.
+    spi_device: Option<PathBuf>,
+    /// Carrier frequency, in *Hz*.
+    frequency: Option<u32>,
+    /// Spreading factor.
+    spreading_factor: Option<u8>,
+    /// Bandwidth, in *Hz*.
+    bandwidth: Option<u32>,
+}
+
+#[cfg(feature = "lora")]
+impl Lora {
+    /// Gets the SPI device of the radio, `/dev/spidev0.0` by default.
+    #[must_use]
+    pub fn spi_device(&self) -> &Path {
+        self.spi_device
+            .as_deref()
+            .unwrap_or_else(|| Path::new(DEFAULT_SPI_DEVICE))
+    }
+
+    /// Gets the carrier frequency, in *Hz*, 434.25 MHz by default.
+    #[must_use]
+    pub fn frequency(&self) -> u32 {
+        self.frequency.unwrap_or(DEFAULT_FREQUENCY)
+    }
+
+    /// Gets the spreading factor, 11 by default.
+    #[must_use]
+    pub fn spreading_factor(&self) -> u8 {
+        self.spreading_factor.unwrap_or(DEFAULT_SPREADING_FACTOR)
+    }
+
+    /// Gets the bandwidth, in *Hz*, 125 kHz by default.
+    #[must_use]
+    pub fn bandwidth(&self) -> u32 {
+        self.bandwidth.unwrap_or(DEFAULT_BANDWIDTH)
+    }
+}
+
+/// HTTP status endpoint configuration structure.
+#[cfg(feature = "http")]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct Http {
+    /// Address to bind the server to.
+    address: Option<IpAddr>,
+    /// Port to listen on.
+    port: Option<u16>,
+}
+
+#[cfg(feature = "http")]
+impl Http {
+    /// Gets the address to bind the server to, `127.0.0.1` by default.
+    #[must_use]
+    pub fn address(&self) -> IpAddr {
+        self.address.unwrap_or(DEFAULT_ADDRESS)
+    }
+
+    /// Gets the port to listen on, 8080 by default.
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_HTTP_PORT)
+    }
+
+    /// Gets the socket address to bind the server to.
+    #[must_use]
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.address(), self.port())
     }
 }
 
-/// GPS configuration structure.
-#[cfg(feature = "gps")]
+/// State transition webhook configuration structure.
+#[cfg(feature = "webhook")]
 #[derive(Debug, Deserialize)]
-pub struct Gps {
-    /// UART serial console path.
-    uart: PathBuf,
-    /// Serial console baud rate.
-    baud_rate: u32,
-    /// Power GPIO pin.
-    #[serde(deserialize_with = "deserialize_pin")]
-    power_gpio: Pin,
+pub struct Webhook {
+    /// URL of the webhook.
+    url: String,
+    /// User name shown in the notifications.
+    username: Option<String>,
+    /// Timeout of the requests, in milliseconds.
+    timeout_ms: Option<u64>,
 }
 
-#[cfg(feature = "gps")]
-impl Gps {
-    /// Gets the UART serial console path.
+#[cfg(feature = "webhook")]
+impl Webhook {
+    /// Gets the URL of the webhook.
     #[must_use]
-    pub fn uart(&self) -> &Path {
-        &self.uart
+    pub fn url(&self) -> &str {
+        &self.url
     }
 
-    /// Gets the serial console baud rate.
+    /// Gets the user name shown in the notifications, if any.
     #[must_use]
-    pub fn baud_rate(&self) -> u32 {
-        self.baud_rate
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
     }
 
-    /// Gets the power GPIO pin.
+    /// Gets the timeout of the requests, 10 seconds by default.
     #[must_use]
-    pub fn power_gpio(&self) -> Pin {
-        self.power_gpio
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_WEBHOOK_TIMEOUT, Duration::from_millis)
+    }
+}
+
+/// Flight simulation configuration structure.
+#[cfg(feature = "sim")]
+#[derive(Debug, Default, Deserialize)]
+pub struct Simulation {
+    /// Recorded flight file, in CSV or GPX format.
+    flight_file: Option<PathBuf>,
+    /// Speed-up factor of the simulated time.
+    speedup: Option<u32>,
+}
+
+#[cfg(feature = "sim")]
+impl Simulation {
+    /// Gets the recorded flight file, `sim/flight.csv` by default.
+    #[must_use]
+    pub fn flight_file(&self) -> &Path {
+        self.flight_file
+            .as_deref()
+            .unwrap_or_else(|| Path::new(DEFAULT_FLIGHT_FILE))
+    }
+
+    /// Gets the speed-up factor of the simulated time, 60 by default.
+    ///
+    /// A factor of 0 runs the simulation as fast as possible.
+    #[must_use]
+    pub fn speedup(&self) -> u32 {
+        self.speedup.unwrap_or(DEFAULT_SPEEDUP)
     }
 }
 
@@ -899,6 +2372,8 @@ pub struct Fona {
     uart: PathBuf,
     /// Serial console baud rate.
     baud_rate: u32,
+    /// Serial read/write timeout, in milliseconds.
+    timeout_ms: Option<u64>,
     /// Power control GPIO pin.
     #[serde(deserialize_with = "deserialize_pin")]
     power_gpio: Pin,
@@ -909,6 +2384,12 @@ pub struct Fona {
     sms_phone: PhoneNumber,
     /// Operator GSM location service domain.
     location_service: String,
+    /// Time to wait for the delivery report of the landed SMSs, in milliseconds.
+    delivery_timeout_ms: Option<u64>,
+    /// Preferred SMS storage.
+    sms_storage: Option<SmsStorage>,
+    /// Wether to delete the stored SMSs when initializing the module.
+    clear_sms: Option<bool>,
 }
 
 #[cfg(feature = "fona")]
@@ -925,6 +2406,13 @@ impl Fona {
         self.baud_rate
     }
 
+    /// Gets the serial read/write timeout, 5 seconds by default.
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_SERIAL_TIMEOUT, Duration::from_millis)
+    }
+
     /// Gets the power GPIO pin.
     #[must_use]
     pub fn power_gpio(&self) -> Pin {
@@ -948,6 +2436,51 @@ impl Fona {
     pub fn location_service(&self) -> &str {
         &self.location_service
     }
+
+    /// Gets the time to wait for the delivery report of the landed SMSs, if they must be
+    /// confirmed.
+    #[must_use]
+    pub fn delivery_timeout(&self) -> Option<Duration> {
+        self.delivery_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Gets the preferred SMS storage, if it must be set when initializing the module.
+    #[must_use]
+    pub fn sms_storage(&self) -> Option<SmsStorage> {
+        self.sms_storage
+    }
+
+    /// Gets wether the stored SMSs should be deleted when initializing the module.
+    #[must_use]
+    pub fn clear_sms(&self) -> bool {
+        self.clear_sms == Some(true)
+    }
+}
+
+/// SMS storage of the FONA module.
+#[cfg(feature = "fona")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsStorage {
+    /// SIM card storage.
+    Sim,
+    /// Module (phone) storage.
+    Phone,
+    /// Both the SIM card and the module storages.
+    All,
+}
+
+#[cfg(feature = "fona")]
+impl SmsStorage {
+    /// Gets the code of the storage in AT commands.
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            SmsStorage::Sim => "SM",
+            SmsStorage::Phone => "ME",
+            SmsStorage::All => "MT",
+        }
+    }
 }
 
 /// Phone number representation.
@@ -1001,6 +2534,18 @@ pub struct Telemetry {
     uart: PathBuf,
     /// Serial console baud rate.
     baud_rate: u32,
+    /// Serial read/write timeout, in milliseconds.
+    timeout_ms: Option<u64>,
+    /// Telemetry mode.
+    mode: Option<TelemetryMode>,
+    /// 64-bit address of the destination XBee module, in API mode.
+    destination: Option<u64>,
+    /// Wether special bytes should be escaped in API mode (XBee `AP=2`).
+    escaped: Option<bool>,
+    /// Shared secret used to authenticate uplink commands.
+    command_key: Option<String>,
+    /// Minimum interval between telemetry packets, in milliseconds.
+    interval_ms: Option<u64>,
 }
 
 #[cfg(feature = "telemetry")]
@@ -1016,8 +2561,67 @@ impl Telemetry {
     pub fn baud_rate(&self) -> u32 {
         self.baud_rate
     }
+
+    /// Gets the serial read/write timeout, 5 seconds by default.
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_SERIAL_TIMEOUT, Duration::from_millis)
+    }
+
+    /// Gets the telemetry mode, transparent by default.
+    #[must_use]
+    pub fn mode(&self) -> TelemetryMode {
+        self.mode.unwrap_or(TelemetryMode::Transparent)
+    }
+
+    /// Gets the 64-bit address of the destination XBee module, in API mode.
+    ///
+    /// By default, packets will be broadcast to all modules in the network.
+    #[must_use]
+    pub fn destination(&self) -> u64 {
+        self.destination.unwrap_or(BROADCAST_ADDRESS)
+    }
+
+    /// Gets wether special bytes should be escaped in API mode (XBee `AP=2`).
+    #[must_use]
+    pub fn escaped(&self) -> bool {
+        self.escaped == Some(true)
+    }
+
+    /// Gets the minimum interval between telemetry packets, 1 second by default.
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.unwrap_or(1_000))
+    }
+
+    /// Gets the shared secret used to authenticate uplink commands.
+    ///
+    /// If no key is configured, uplink commands are disabled.
+    #[must_use]
+    pub fn command_key(&self) -> Option<&str> {
+        self.command_key.as_deref()
+    }
 }
 
+/// Telemetry mode.
+#[cfg(feature = "telemetry")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryMode {
+    /// Transparent mode: packets are written to the serial as they are.
+    Transparent,
+    /// API mode: packets are wrapped in XBee API transmit request frames.
+    Api,
+}
+
+/// Lowest GPIO pin number of the Raspberry Pi.
+#[cfg(any(feature = "gps", feature = "fona"))]
+const MIN_GPIO_PIN: u64 = 2;
+/// Highest GPIO pin number of the Raspberry Pi.
+#[cfg(any(feature = "gps", feature = "fona"))]
+const MAX_GPIO_PIN: u64 = 28;
+
 /// Deserializes a Raspberry Pi pin number into a `Pin` structure.
 ///
 /// Note: it will make sure it deserializes a Pin between 2 and 28 (pin numbers for Raspberry Pi).
@@ -1026,53 +2630,100 @@ fn deserialize_pin<'de, D>(deserializer: D) -> Result<Pin, D::Error>
 where
     D: Deserializer<'de>,
 {
-    /// Visitor for u32 (comparing to usize).
+    /// Visitor for Raspberry Pi pin numbers.
     struct PinVisitor;
-    impl<'dev> Visitor<'dev> for PinVisitor {
+
+    impl PinVisitor {
+        /// Creates the pin, if the number is a valid Raspberry Pi GPIO pin.
+        fn pin<E>(value: u64) -> Result<Pin, E>
+        where
+            E: de::Error,
+        {
+            if (MIN_GPIO_PIN..=MAX_GPIO_PIN).contains(&value) {
+                Ok(Pin::new(value))
+            } else {
+                Err(E::custom(format!(
+                    "invalid GPIO pin {value}, it must be between {MIN_GPIO_PIN} and \
+                     {MAX_GPIO_PIN}"
+                )))
+            }
+        }
+    }
+
+    impl Visitor<'_> for PinVisitor {
         type Value = Pin;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("an integer between 2 and 28")
+            write!(
+                formatter,
+                "an integer between {MIN_GPIO_PIN} and {MAX_GPIO_PIN}"
+            )
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Pin, E>
+        where
+            E: de::Error,
+        {
+            Self::pin(value)
         }
 
-        #[allow(clippy::absurd_extreme_comparisons)]
         fn visit_i64<E>(self, value: i64) -> Result<Pin, E>
         where
             E: de::Error,
         {
-            if (2..=28).contains(&value) {
-                #[allow(clippy::cast_sign_loss)]
-                {
-                    Ok(Pin::new(value as u64))
-                }
-            } else {
-                Err(E::custom(format!("pin out of range: {value}")))
+            match u64::try_from(value) {
+                Ok(value) => Self::pin(value),
+                Err(_) => Err(E::custom(format!(
+                    "invalid GPIO pin {value}, it must be between {MIN_GPIO_PIN} and \
+                     {MAX_GPIO_PIN}"
+                ))),
             }
         }
     }
 
-    deserializer.deserialize_u8(PinVisitor)
+    deserializer.deserialize_u64(PinVisitor)
+}
+
+/// Deserializes an optional Raspberry Pi GPIO pin, with the same checks as
+/// [`deserialize_pin()`](fn.deserialize_pin.html).
+#[cfg(feature = "gps")]
+fn deserialize_optional_pin<'de, D>(deserializer: D) -> Result<Option<Pin>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_pin(deserializer).map(Some)
 }
 
 #[cfg(test)]
 mod tests {
-    #[cfg(all(feature = "gps", feature = "raspicam"))]
-    use super::Gps;
+    #[cfg(feature = "gps")]
+    use super::GpsBackup;
+    #[cfg(all(feature = "http", feature = "raspicam"))]
+    use super::Http;
+    #[cfg(all(feature = "iridium", feature = "raspicam"))]
+    use super::Iridium;
+    #[cfg(all(feature = "sim", feature = "raspicam"))]
+    use super::Simulation;
     #[cfg(all(feature = "raspicam", feature = "telemetry"))]
     use super::Telemetry;
     #[cfg(all(feature = "raspicam", feature = "fona"))]
     use super::{Battery, Fona, PhoneNumber};
-    use super::{Config, CONFIG};
+    use super::{Config, LiveConfig, LogFormat, Profile, CONFIG};
     #[cfg(feature = "raspicam")]
-    use super::{Exposure, Flight, Picture, Video, WhiteBalance};
+    use super::{Drc, Exposure, Flight, Log, Metering, Picture, Video, WhiteBalance};
+    #[cfg(all(feature = "gps", feature = "raspicam"))]
+    use super::{Gps, Habitat};
 
-    #[cfg(all(feature = "raspicam", any(feature = "gps", feature = "fona")))]
+    #[cfg(any(feature = "gps", feature = "fona"))]
+    use serde::Deserialize;
+    #[cfg(any(feature = "gps", feature = "fona"))]
     use sysfs_gpio::Pin;
 
     #[cfg(feature = "gps")]
     use std::path::Path;
-    #[cfg(feature = "raspicam")]
+    #[cfg(any(feature = "gps", feature = "raspicam"))]
     use std::path::PathBuf;
+    use std::{env, fs, process};
 
     /// Loads the default configuration and checks it.
     #[test]
@@ -1080,6 +2731,11 @@ mod tests {
         let config = Config::from_file("config.toml").unwrap();
 
         assert!(config.debug());
+        assert_eq!(config.log().format(), LogFormat::Text);
+        #[cfg(feature = "http")]
+        assert_eq!(config.http().port(), 8080);
+        #[cfg(feature = "iridium")]
+        assert_eq!(config.iridium().baud_rate(), 19_200);
         #[cfg(feature = "raspicam")]
         {
             assert_eq!(config.picture().height(), 2464);
@@ -1098,6 +2754,9 @@ mod tests {
             assert_eq!(config.gps().uart(), Path::new("/dev/ttyAMA0"));
             assert_eq!(config.gps().baud_rate(), 9_600);
             assert_eq!(config.gps().power_gpio().get_pin(), 3);
+            assert_eq!(config.gps().rate_window(), 5);
+            assert_eq!(config.gps().nav_rate_hz(), 10);
+            assert_eq!(config.gps().reset_on_init(), None);
         }
     }
 
@@ -1109,6 +2768,22 @@ mod tests {
         let flight = Flight {
             length: 300,
             expected_max_height: 35000,
+            fix_stabilization_secs: None,
+            profile: None,
+            launch_altitude_threshold: None,
+            launch_ascent_rate: None,
+            burst_altitude_loss: None,
+            burst_descent_rate: None,
+            landing_altitude_tolerance: None,
+            landing_stable_secs: None,
+            float_ascent_rate: None,
+            float_min_altitude: None,
+            float_detection_secs: None,
+            #[cfg(feature = "fona")]
+            descent_sms_altitudes: None,
+            require_arm: None,
+            #[cfg(feature = "gps")]
+            arm_gpio: None,
         };
 
         #[cfg(feature = "gps")]
@@ -1127,9 +2802,16 @@ mod tests {
             iso: None,
             ev: None,
             white_balance: Some(WhiteBalance::Horizon),
+            metering: None,
+            drc: None,
             first_timeout: 120,
             interval: 300,
             repeat: Some(30),
+            warmup_ms: None,
+            altitude_triggers: Vec::new(),
+            annotation: None,
+            annotation_position: None,
+            annotation_size: None,
         };
 
         #[cfg(not(feature = "gps"))]
@@ -1147,9 +2829,12 @@ mod tests {
             iso: None,
             ev: None,
             white_balance: Some(WhiteBalance::Horizon),
+            metering: None,
+            drc: None,
             first_timeout: 120,
             interval: 300,
             repeat: Some(30),
+            warmup_ms: None,
         };
 
         let video = Video {
@@ -1167,16 +2852,23 @@ mod tests {
             stabilization: Some(true),
             ev: None,
             white_balance: Some(WhiteBalance::Horizon),
+            metering: None,
+            drc: None,
+            skip_camera_test: None,
         };
 
         #[cfg(feature = "fona")]
         let fona = Fona {
             uart: PathBuf::from("/dev/ttyUSB0"),
             baud_rate: 9_600,
+            timeout_ms: None,
             power_gpio: Pin::new(7),
             status_gpio: Pin::new(21),
             sms_phone: PhoneNumber(String::new()),
             location_service: "gprs-service.com".to_owned(),
+            delivery_timeout_ms: None,
+            sms_storage: None,
+            clear_sms: None,
         };
 
         #[cfg(feature = "fona")]
@@ -1193,25 +2885,62 @@ mod tests {
         let telemetry = Telemetry {
             uart: PathBuf::from("/dev/ttyUSB0"),
             baud_rate: 230_400,
+            timeout_ms: None,
+            mode: None,
+            destination: None,
+            escaped: None,
+            command_key: None,
+            interval_ms: None,
         };
 
         #[cfg(feature = "gps")]
         let gps = Gps {
             uart: PathBuf::from("/dev/ttyAMA0"),
             baud_rate: 9_600,
+            timeout_ms: None,
             power_gpio: Pin::new(3),
+            rate_window: None,
+            nav_rate_hz: None,
+            reset_on_init: None,
+            backup: None,
+        };
+
+        #[cfg(feature = "iridium")]
+        let iridium = Iridium {
+            uart: PathBuf::from("/dev/ttyUSB2"),
+            baud_rate: None,
+            timeout_ms: None,
         };
 
         #[cfg(all(feature = "gps", feature = "fona", feature = "telemetry"))]
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             battery,
             data_dir: PathBuf::from("data"),
             picture,
             video,
             gps,
+            aprs: None,
+            habitat: Habitat::default(),
+            #[cfg(feature = "sim")]
+            simulation: Simulation::default(),
             fona,
+            #[cfg(feature = "iridium")]
+            iridium,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            #[cfg(feature = "lora")]
+            lora: None,
             telemetry,
         };
 
@@ -1219,22 +2948,52 @@ mod tests {
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             battery,
             data_dir: PathBuf::from("data"),
             picture,
             video,
             gps,
+            aprs: None,
+            habitat: Habitat::default(),
+            #[cfg(feature = "sim")]
+            simulation: Simulation::default(),
             fona,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            #[cfg(feature = "lora")]
+            lora: None,
         };
 
         #[cfg(all(feature = "gps", not(feature = "fona"), feature = "telemetry"))]
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             data_dir: PathBuf::from("data"),
             picture,
             video,
             gps,
+            aprs: None,
+            habitat: Habitat::default(),
+            #[cfg(feature = "sim")]
+            simulation: Simulation::default(),
             telemetry,
         };
 
@@ -1242,21 +3001,47 @@ mod tests {
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             data_dir: PathBuf::from("data"),
             picture,
             video,
             gps,
+            aprs: None,
+            habitat: Habitat::default(),
+            #[cfg(feature = "sim")]
+            simulation: Simulation::default(),
         };
 
         #[cfg(all(not(feature = "gps"), feature = "fona", feature = "telemetry"))]
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             battery,
             data_dir: PathBuf::from("data"),
             picture,
             video,
             fona,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            #[cfg(feature = "lora")]
+            lora: None,
             telemetry,
         };
 
@@ -1264,17 +3049,39 @@ mod tests {
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             battery,
             data_dir: PathBuf::from("data"),
             picture,
             video,
             fona,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            #[cfg(feature = "lora")]
+            lora: None,
         };
 
         #[cfg(all(not(feature = "gps"), not(feature = "fona"), feature = "telemetry"))]
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             data_dir: PathBuf::from("data"),
             picture,
             video,
@@ -1289,6 +3096,15 @@ mod tests {
         let config = Config {
             debug: None,
             flight,
+            log: Log::default(),
+            #[cfg(feature = "http")]
+            http: Http::default(),
+            #[cfg(feature = "baro")]
+            baro: None,
+            #[cfg(feature = "gps")]
+            geofence: Vec::new(),
+            #[cfg(feature = "webhook")]
+            webhook: None,
             data_dir: PathBuf::from("data"),
             picture,
             video,
@@ -1304,11 +3120,296 @@ mod tests {
              equal to 2592px, found 5648px\nvideo height must be below or equal to 1944px, \
              found 12546px\nvideo framerate must be below or equal to 90fps, found 92fps\n\
              video mode must be one of 2592\u{d7}1944 1-15fps, 1920\u{d7}1080 1-30fps, \
-             1296\u{d7}972 1-42fps, 1296\u{d7}730 1-49fps, 640\u{d7}480 1-60fps, found 5648x12546 \
+             1296\u{d7}972 1-42fps, 1296\u{d7}730 1-49fps, 640\u{d7}480 1-90fps, found 5648x12546 \
              92fps\n"
         );
     }
 
+    /// Checks that 640×480 video is accepted up to 90fps, and rejected above.
+    #[test]
+    #[cfg(feature = "raspicam")]
+    fn video_mode_640x480() {
+        let mut config = Config::from_file("config.toml").unwrap();
+        config.video.width = 640;
+        config.video.height = 480;
+
+        for fps in [1, 60, 90] {
+            config.video.fps = fps;
+            assert_eq!(config.verify(), (true, String::new()));
+        }
+
+        config.video.fps = 91;
+        let (verify, errors) = config.verify();
+        assert!(!verify);
+        assert!(errors.ends_with("640\u{d7}480 1-90fps, found 640x480 91fps\n"));
+    }
+
+    /// Checks that the metering and dynamic range compression modes are parsed, and that invalid
+    /// modes are rejected when loading the configuration.
+    #[test]
+    #[cfg(feature = "raspicam")]
+    fn metering_drc() {
+        let contents = fs::read_to_string("config.toml").unwrap();
+        let config: Config = toml::from_str(
+            &contents
+                .replace("#metering = \"average\"", "metering = \"spot\"")
+                .replace("#drc = \"off\"", "drc = \"med\""),
+        )
+        .unwrap();
+        assert_eq!(config.picture().metering(), Some(Metering::Spot));
+        assert_eq!(config.picture().drc(), Some(Drc::Med));
+        assert_eq!(config.video().metering(), Some(Metering::Spot));
+        assert_eq!(config.video().drc(), Some(Drc::Med));
+
+        assert!(toml::from_str::<Config>(
+            &contents.replace("#metering = \"average\"", "metering = \"center\"")
+        )
+        .is_err());
+        assert!(
+            toml::from_str::<Config>(&contents.replace("#drc = \"off\"", "drc = \"medium\""))
+                .is_err()
+        );
+    }
+
+    /// Checks that GPS navigation rates are accepted up to 10 Hz, and rejected above.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn gps_nav_rate() {
+        let mut config = Config::from_file("config.toml").unwrap();
+
+        for nav_rate in [1, 5, 10] {
+            config.gps.nav_rate_hz = Some(nav_rate);
+            assert_eq!(config.verify(), (true, String::new()));
+        }
+
+        for nav_rate in [0, 11] {
+            config.gps.nav_rate_hz = Some(nav_rate);
+            assert_eq!(
+                config.verify(),
+                (
+                    false,
+                    format!(
+                        "GPS navigation rate must be between 1 and 10 Hz, found {nav_rate} Hz\n"
+                    )
+                )
+            );
+        }
+    }
+
+    /// Checks the thresholds of the flight profiles, and that explicit values override them.
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn flight_profile() {
+        let mut config = Config::from_file("config.toml").unwrap();
+        assert_eq!(config.flight().profile(), Profile::Standard);
+        assert_eq!(config.flight().launch_ascent_rate(), 3_f32);
+        assert_eq!(config.flight().burst_altitude_loss(), 1_000_f32);
+        #[cfg(feature = "fona")]
+        assert_eq!(
+            config.flight().descent_sms_altitudes(),
+            &[2_500_f32, 1_500_f32, 500_f32]
+        );
+
+        config.flight.profile = Some(Profile::FastAscent);
+        assert_eq!(config.flight().launch_altitude_threshold(), 200_f32);
+        assert_eq!(config.flight().launch_ascent_rate(), 5_f32);
+        assert_eq!(config.flight().burst_descent_rate(), 20_f32);
+        #[cfg(feature = "fona")]
+        assert_eq!(
+            config.flight().descent_sms_altitudes(),
+            &[3_000_f32, 2_000_f32, 1_000_f32, 500_f32]
+        );
+
+        config.flight.launch_ascent_rate = Some(8_f32);
+        config.flight.landing_stable_secs = Some(90);
+        assert_eq!(config.flight().launch_ascent_rate(), 8_f32);
+        assert_eq!(config.flight().landing_stable_secs(), 90);
+        assert_eq!(config.flight().launch_altitude_threshold(), 200_f32);
+        assert_eq!(config.verify(), (true, String::new()));
+
+        config.flight.landing_altitude_tolerance = Some(2_000_f32);
+        assert_eq!(
+            config.verify(),
+            (
+                false,
+                String::from(
+                    "flight landing altitude tolerance must be lower than the burst altitude \
+                     loss, found 2000 m and 1500 m\n"
+                )
+            )
+        );
+
+        config.flight.landing_altitude_tolerance = Some(-5_f32);
+        config.flight.landing_stable_secs = Some(0);
+        assert_eq!(
+            config.verify(),
+            (
+                false,
+                String::from(
+                    "flight landing altitude tolerance must be positive, found -5\n\
+                     flight landing stable time must be positive, found 0 s\n"
+                )
+            )
+        );
+    }
+
+    /// Checks that the flight profile is deserialized from its snake case name.
+    #[test]
+    fn deserialize_profile() {
+        let contents = fs::read_to_string("config.toml").unwrap();
+        let config: Config = toml::from_str(&contents.replace(
+            "profile = \"standard\"",
+            "profile = \"float\"\nburst_altitude_loss = 500",
+        ))
+        .unwrap();
+
+        assert_eq!(config.flight().profile(), Profile::Float);
+        assert_eq!(config.flight().landing_stable_secs(), 120);
+        assert!((config.flight().burst_altitude_loss() - 500_f32).abs() < f32::EPSILON);
+    }
+
+    /// Checks that valid GPIO pins are deserialized, and out of range ones are rejected.
+    #[test]
+    #[cfg(any(feature = "gps", feature = "fona"))]
+    fn deserialize_pin() {
+        /// Structure with a single pin, for testing.
+        #[derive(Debug, Deserialize)]
+        struct PinTest {
+            /// The pin.
+            #[serde(deserialize_with = "super::deserialize_pin")]
+            pin: Pin,
+        }
+
+        for pin in [2, 17, 28] {
+            let test: PinTest = toml::from_str(&format!("pin = {pin}")).unwrap();
+            assert_eq!(test.pin.get_pin(), pin);
+        }
+
+        for pin in ["0", "1", "29", "300", "-3"] {
+            let error = toml::from_str::<PinTest>(&format!("pin = {pin}")).unwrap_err();
+            assert!(
+                error.to_string().contains(&format!(
+                    "invalid GPIO pin {pin}, it must be between 2 and 28"
+                )),
+                "unexpected error: {error}"
+            );
+        }
+
+        assert!(toml::from_str::<PinTest>("pin = \"seven\"").is_err());
+    }
+
+    /// Checks that devices sharing a GPIO pin are rejected.
+    #[test]
+    #[cfg(feature = "fona")]
+    fn pin_collision() {
+        let mut config = Config::from_file("config.toml").unwrap();
+        assert_eq!(config.verify(), (true, String::new()));
+
+        config.fona.status_gpio = Pin::new(config.fona.power_gpio.get_pin());
+        let (verify, errors) = config.verify();
+        assert!(!verify);
+        assert_eq!(
+            errors,
+            "FONA power GPIO pin and FONA status GPIO pin must be different, both are 7\n"
+        );
+
+        #[cfg(feature = "gps")]
+        {
+            config.fona.status_gpio = Pin::new(21);
+            config.fona.power_gpio = Pin::new(config.gps.power_gpio.get_pin());
+            let (verify, errors) = config.verify();
+            assert!(!verify);
+            assert_eq!(
+                errors,
+                "GPS power GPIO pin and FONA power GPIO pin must be different, both are 3\n"
+            );
+        }
+    }
+
+    /// Checks that a backup GPS sharing the GPIO pin of the main GPS is rejected, even without
+    /// the FONA.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn backup_gps_pin_collision() {
+        let mut config = Config::from_file("config.toml").unwrap();
+        config.gps.backup = Some(GpsBackup {
+            uart: PathBuf::from("/dev/ttyUSB3"),
+            baud_rate: 9_600,
+            timeout_ms: None,
+            power_gpio: Pin::new(config.gps.power_gpio.get_pin()),
+        });
+
+        let (verify, errors) = config.verify();
+        assert!(!verify);
+        assert_eq!(
+            errors,
+            "GPS power GPIO pin and Backup GPS power GPIO pin must be different, both are 3\n"
+        );
+    }
+
+    /// Checks that an arm switch sharing the GPIO pin of the GPS is rejected, even without the
+    /// FONA.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn arm_pin_collision() {
+        let mut config = Config::from_file("config.toml").unwrap();
+        config.flight.arm_gpio = Some(Pin::new(config.gps.power_gpio.get_pin()));
+
+        let (verify, errors) = config.verify();
+        assert!(!verify);
+        assert_eq!(
+            errors,
+            "GPS power GPIO pin and Arm GPIO pin must be different, both are 3\n"
+        );
+    }
+
+    /// Checks that arming can only be required if the probe can be armed, with the arm pin or
+    /// with the telemetry command.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn arm_required() {
+        let mut config = Config::from_file("config.toml").unwrap();
+        config.flight.require_arm = Some(true);
+
+        let (verify, errors) = config.verify();
+        assert!(!verify);
+        assert_eq!(
+            errors,
+            "flight arming is required, but there is no arm GPIO pin nor telemetry command key\n"
+        );
+
+        #[cfg(feature = "telemetry")]
+        {
+            config.telemetry.command_key = Some("secret".to_owned());
+            assert_eq!(config.verify(), (true, String::new()));
+            config.telemetry.command_key = None;
+        }
+
+        config.flight.arm_gpio = Some(Pin::new(26));
+        assert_eq!(config.verify(), (true, String::new()));
+    }
+
+    /// Checks that a reload swaps in the new values, and that invalid files are rejected.
+    #[test]
+    fn live_reload() {
+        let contents = fs::read_to_string("config.toml").unwrap();
+        let path = env::temp_dir().join(format!("os_balloon_config_{}.toml", process::id()));
+        let config = LiveConfig::new(Config::from_file("config.toml").unwrap());
+        assert!(config.debug());
+
+        fs::write(&path, contents.replace("debug =  true", "debug = false")).unwrap();
+        config.reload_from(&path).unwrap();
+        assert!(!config.debug());
+
+        fs::write(&path, "debug = \"maybe\"").unwrap();
+        assert!(config.reload_from(&path).is_err());
+        assert!(!config.debug());
+
+        fs::remove_file(&path).unwrap();
+        assert!(config.reload_from(&path).is_err());
+        assert!(!config.debug());
+    }
+
     /// Tests the default configuration and its loading using the static `CONFIG` constant.
     #[test]
     fn config_static() {
@@ -0,0 +1,33 @@
+//! Checksum module.
+
+/// Computes the CRC16-CCITT checksum of the given data.
+///
+/// This is the *CRC-16/CCITT-FALSE* variant (polynomial `0x1021`, initial value `0xFFFF`, no
+/// reflection and no final XOR), used both in telemetry packets and UKHAS sentences.
+#[must_use]
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::crc16_ccitt;
+
+    /// Checks the CRC16-CCITT checksum against the standard check value.
+    #[test]
+    fn crc16_ccitt_check() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(crc16_ccitt(&[]), 0xFFFF);
+    }
+}
@@ -0,0 +1,87 @@
+//! Data directory.
+//!
+//! Everything the probe records during the flight is stored in the `data` directory, set in the
+//! `data_dir` configuration option, with a subdirectory per kind of data. The baseline
+//! subdirectories in [`SUBDIRS`](constant.SUBDIRS.html) are created when the data filesystem is
+//! initialized, and any other subdirectory is created the first time it's requested with
+//! [`ensure_subdir()`](fn.ensure_subdir.html).
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+
+use crate::{config::CONFIG, error};
+
+/// Video directory inside data directory.
+pub const VIDEO_DIR: &str = "video";
+/// Image directory inside data directory.
+pub const IMG_DIR: &str = "img";
+/// Log directory inside data directory, only created if logs are written to files.
+pub const LOG_DIR: &str = "logs";
+
+/// Subdirectories created when initializing the data filesystem.
+pub const SUBDIRS: [&str; 2] = [VIDEO_DIR, IMG_DIR];
+
+/// Gets the path of the given subdirectory of the data directory, creating it if needed.
+///
+/// Requesting an existing subdirectory is not an error.
+///
+/// # Errors
+///
+/// Returns an error if the subdirectory can't be created.
+pub fn ensure_subdir(name: &str) -> Result<PathBuf, Error> {
+    ensure_subdir_in(CONFIG.data_dir(), name)
+}
+
+/// Gets the path of the given subdirectory of the given data directory, creating it if needed.
+fn ensure_subdir_in(data_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    let path = data_dir.join(name);
+    fs::create_dir_all(&path).context(error::Fs::DirectoryCreation { path: path.clone() })?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::ensure_subdir_in;
+    use crate::error;
+
+    /// Checks that requesting a subdirectory creates it, idempotently.
+    #[test]
+    fn subdir_creation() {
+        let data_dir = env::temp_dir().join(format!("os_balloon_subdir_{}", process::id()));
+        let expected = data_dir.join("test");
+        let _ = fs::remove_dir_all(&data_dir);
+
+        assert_eq!(ensure_subdir_in(&data_dir, "test").unwrap(), expected);
+        assert!(expected.is_dir());
+        fs::write(expected.join("file"), "data").unwrap();
+
+        // Requesting it again keeps the contents.
+        assert_eq!(ensure_subdir_in(&data_dir, "test").unwrap(), expected);
+        assert_eq!(fs::read_to_string(expected.join("file")).unwrap(), "data");
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    /// Checks that the error of a failed creation includes the path.
+    #[test]
+    fn subdir_creation_error() {
+        let data_dir = env::temp_dir().join(format!("os_balloon_subdir_file_{}", process::id()));
+        let file = data_dir.join("test");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(&file, "not a directory").unwrap();
+
+        let error = ensure_subdir_in(&data_dir, "test/nested").unwrap_err();
+        match error.downcast_ref::<error::Fs>() {
+            Some(error::Fs::DirectoryCreation { path }) => assert_eq!(path, &file.join("nested")),
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,502 @@
+//! `SQLite` flight database.
+//!
+//! If the `sqlite` feature is enabled, every GPS frame, battery reading and state transition can
+//! be stored in an `SQLite` database in the `data` directory, for post-flight analysis. The schema
+//! is created or migrated when the database is opened, using `SQLite`'s `user_version` pragma to
+//! track the current schema version.
+//!
+//! Inserting in the database should not slow down the main logic, so the
+//! [`Logger`](struct.Logger.html) sends records to a background thread, that inserts them in
+//! batches, in a single transaction per batch.
+
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use tracing::error;
+
+use crate::{
+    config::CONFIG,
+    error,
+    gps::{FixStatus, Frame},
+    logic::State,
+};
+
+/// Database file, in the `data` directory.
+pub const DB_FILE: &str = "flight.sqlite";
+/// Maximum number of records inserted in a single transaction.
+pub const BATCH_SIZE: usize = 64;
+
+/// Schema migrations, in order. The schema version is the number of applied migrations.
+const MIGRATIONS: [&str; 1] = ["CREATE TABLE frames (
+        id INTEGER PRIMARY KEY,
+        fix_time TEXT NOT NULL,
+        status TEXT NOT NULL,
+        satellites INTEGER NOT NULL,
+        latitude REAL NOT NULL,
+        longitude REAL NOT NULL,
+        altitude REAL NOT NULL,
+        pdop REAL NOT NULL,
+        hdop REAL NOT NULL,
+        vdop REAL NOT NULL,
+        speed REAL NOT NULL,
+        course REAL NOT NULL
+    );
+    CREATE INDEX frames_fix_time ON frames (fix_time);
+    CREATE TABLE states (
+        id INTEGER PRIMARY KEY,
+        time TEXT NOT NULL,
+        state TEXT NOT NULL
+    );
+    CREATE INDEX states_time ON states (time);
+    CREATE TABLE batteries (
+        id INTEGER PRIMARY KEY,
+        time TEXT NOT NULL,
+        main REAL,
+        fona REAL
+    );
+    CREATE INDEX batteries_time ON batteries (time);"];
+
+/// Flight database.
+#[derive(Debug)]
+pub struct Database {
+    /// Connection to the `SQLite` database.
+    connection: Connection,
+}
+
+impl Database {
+    /// Opens the database at the given path, creating or migrating its schema if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened, or if its schema can't be migrated.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let connection = Connection::open(path.as_ref()).context(error::Db::Open {
+            path: path.as_ref().to_owned(),
+        })?;
+        Self::with_connection(connection)
+    }
+
+    /// Opens the database in the `data` directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened, or if its schema can't be migrated.
+    pub fn open_in_data_dir() -> Result<Self, Error> {
+        Self::open(CONFIG.data_dir().join(DB_FILE))
+    }
+
+    /// Opens a new in-memory database, mostly for testing purposes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be created.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        let connection = Connection::open_in_memory().context(error::Db::Open {
+            path: ":memory:".into(),
+        })?;
+        Self::with_connection(connection)
+    }
+
+    /// Creates the database from a connection, migrating its schema.
+    fn with_connection(connection: Connection) -> Result<Self, Error> {
+        let mut db = Self { connection };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// Applies the migrations that have not been applied yet.
+    fn migrate(&mut self) -> Result<(), Error> {
+        let version: usize = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .context(error::Db::Migration)?;
+
+        let transaction = self
+            .connection
+            .transaction()
+            .context(error::Db::Migration)?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            transaction
+                .execute_batch(migration)
+                .context(error::Db::Migration)?;
+            transaction
+                .pragma_update(None, "user_version", i + 1)
+                .context(error::Db::Migration)?;
+        }
+        transaction.commit().context(error::Db::Migration)?;
+
+        Ok(())
+    }
+
+    /// Gets the version of the database schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be queried.
+    pub fn schema_version(&self) -> Result<usize, Error> {
+        self.connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .context(error::Db::Query)
+    }
+
+    /// Inserts a GPS frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame can't be inserted.
+    pub fn insert_frame(&self, frame: &Frame) -> Result<(), Error> {
+        insert_frame(&self.connection, frame)
+    }
+
+    /// Inserts a state transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transition can't be inserted.
+    pub fn insert_state(&self, time: DateTime<Utc>, state: State) -> Result<(), Error> {
+        insert_state(&self.connection, time, state)
+    }
+
+    /// Inserts a battery reading, as charge from 0 to 1, if known.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reading can't be inserted.
+    pub fn insert_battery(
+        &self,
+        time: DateTime<Utc>,
+        main: Option<f32>,
+        fona: Option<f32>,
+    ) -> Result<(), Error> {
+        insert_battery(&self.connection, time, main, fona)
+    }
+
+    /// Gets the stored GPS frames with a fix time in the given range, ordered by fix time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be queried.
+    pub fn frames(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Frame>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached(
+                "SELECT fix_time, status, satellites, latitude, longitude, altitude, pdop, hdop, \
+                 vdop, speed, course FROM frames WHERE fix_time BETWEEN ?1 AND ?2 \
+                 ORDER BY fix_time",
+            )
+            .context(error::Db::Query)?;
+        let rows = statement
+            .query_map(params![from, to], |row| {
+                let status: String = row.get(1)?;
+                Ok(Frame::new(
+                    row.get(0)?,
+                    status.parse().unwrap_or(FixStatus::Void),
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                )
+                .with_dop(row.get(6)?, row.get(7)?, row.get(8)?)
+                .with_velocity(row.get(9)?, row.get(10)?))
+            })
+            .context(error::Db::Query)?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .context(error::Db::Query)
+    }
+
+    /// Gets the stored state transitions, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be queried.
+    pub fn states(&self) -> Result<Vec<(DateTime<Utc>, String)>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT time, state FROM states ORDER BY time, id")
+            .context(error::Db::Query)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context(error::Db::Query)?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .context(error::Db::Query)
+    }
+
+    /// Inserts the given records in a single transaction.
+    fn insert_batch(&mut self, records: &[Record]) -> Result<(), Error> {
+        let transaction = self.connection.transaction().context(error::Db::Insert)?;
+        for record in records {
+            match *record {
+                Record::Frame(ref frame) => insert_frame(&transaction, frame)?,
+                Record::State(time, state) => insert_state(&transaction, time, state)?,
+                Record::Battery(time, main, fona) => {
+                    insert_battery(&transaction, time, main, fona)?;
+                }
+            }
+        }
+        transaction.commit().context(error::Db::Insert)?;
+        Ok(())
+    }
+}
+
+/// Record to insert in the database.
+#[derive(Debug, Clone, Copy)]
+enum Record {
+    /// GPS frame.
+    Frame(Frame),
+    /// State transition.
+    State(DateTime<Utc>, State),
+    /// Battery reading.
+    Battery(DateTime<Utc>, Option<f32>, Option<f32>),
+}
+
+/// Background database logger.
+///
+/// Records are sent to a background thread, that inserts them in batches of up to
+/// [`BATCH_SIZE`](constant.BATCH_SIZE.html) records. Insertion errors are logged, but they do not
+/// stop the logger. The thread finishes once the logger is dropped, after inserting all the
+/// pending records.
+#[derive(Debug)]
+pub struct Logger {
+    /// Channel to send the records to the background thread.
+    sender: Option<Sender<Record>>,
+    /// Background thread handle.
+    thread: Option<JoinHandle<Database>>,
+}
+
+impl Logger {
+    /// Starts the background logger for the given database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the logger thread can't be spawned.
+    pub fn spawn(db: Database) -> Result<Self, Error> {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("database logger".to_owned())
+            .spawn(move || Self::run(db, &receiver))
+            .context(error::Db::Logger)?;
+
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Logs a GPS frame.
+    pub fn log_frame(&self, frame: &Frame) {
+        self.send(Record::Frame(*frame));
+    }
+
+    /// Logs a state transition.
+    pub fn log_state(&self, time: DateTime<Utc>, state: State) {
+        self.send(Record::State(time, state));
+    }
+
+    /// Logs a battery reading, as charge from 0 to 1, if known.
+    pub fn log_battery(&self, time: DateTime<Utc>, main: Option<f32>, fona: Option<f32>) {
+        self.send(Record::Battery(time, main, fona));
+    }
+
+    /// Stops the logger, waiting for all the pending records to be inserted.
+    ///
+    /// Returns the database, so that it can be queried.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the logger thread panicked.
+    pub fn finish(mut self) -> Result<Database, Error> {
+        drop(self.sender.take());
+        self.thread
+            .take()
+            .ok_or(error::Db::Logger)?
+            .join()
+            .map_err(|_| error::Db::Logger.into())
+    }
+
+    /// Sends a record to the background thread.
+    fn send(&self, record: Record) {
+        if let Some(ref sender) = self.sender {
+            if sender.send(record).is_err() {
+                error!("The database logger thread has stopped, record lost.");
+            }
+        }
+    }
+
+    /// Inserts the received records in batches, until the channel is closed.
+    fn run(mut db: Database, receiver: &Receiver<Record>) -> Database {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        while let Ok(record) = receiver.recv() {
+            batch.push(record);
+            while batch.len() < BATCH_SIZE {
+                match receiver.try_recv() {
+                    Ok(record) => batch.push(record),
+                    Err(_) => break,
+                }
+            }
+
+            if let Err(e) = db.insert_batch(&batch) {
+                error!(
+                    "Error inserting {} records in the database: {}",
+                    batch.len(),
+                    e
+                );
+            }
+            batch.clear();
+        }
+        db
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("The database logger thread panicked.");
+            }
+        }
+    }
+}
+
+/// Inserts a GPS frame using the given connection.
+fn insert_frame(connection: &Connection, frame: &Frame) -> Result<(), Error> {
+    let _ = connection
+        .prepare_cached(
+            "INSERT INTO frames (fix_time, status, satellites, latitude, longitude, altitude, \
+             pdop, hdop, vdop, speed, course) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )
+        .and_then(|mut statement| {
+            statement.execute(params![
+                frame.fix_time(),
+                frame.status().to_string(),
+                frame.satellites(),
+                frame.latitude(),
+                frame.longitude(),
+                frame.altitude(),
+                frame.pdop(),
+                frame.hdop(),
+                frame.vdop(),
+                frame.speed(),
+                frame.course(),
+            ])
+        })
+        .context(error::Db::Insert)?;
+    Ok(())
+}
+
+/// Inserts a state transition using the given connection.
+fn insert_state(connection: &Connection, time: DateTime<Utc>, state: State) -> Result<(), Error> {
+    let _ = connection
+        .prepare_cached("INSERT INTO states (time, state) VALUES (?1, ?2)")
+        .and_then(|mut statement| statement.execute(params![time, state.as_str()]))
+        .context(error::Db::Insert)?;
+    Ok(())
+}
+
+/// Inserts a battery reading using the given connection.
+fn insert_battery(
+    connection: &Connection,
+    time: DateTime<Utc>,
+    main: Option<f32>,
+    fona: Option<f32>,
+) -> Result<(), Error> {
+    let _ = connection
+        .prepare_cached("INSERT INTO batteries (time, main, fona) VALUES (?1, ?2, ?3)")
+        .and_then(|mut statement| statement.execute(params![time, main, fona]))
+        .context(error::Db::Insert)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{Database, Logger, MIGRATIONS};
+    use crate::{
+        gps::{FixStatus, Frame},
+        logic::State,
+    };
+
+    /// Creates a test frame, some seconds after the start of the flight.
+    fn frame(seconds: i64) -> Frame {
+        Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap() + Duration::seconds(seconds),
+            FixStatus::Active,
+            9,
+            42.5,
+            -2.75,
+            650_f32 + seconds as f32 * 5_f32,
+        )
+        .with_dop(1.5, 0.75, 1.25)
+        .with_velocity(3.5, 90_f32)
+    }
+
+    /// Checks that the schema is created when opening a new database.
+    #[test]
+    fn migration() {
+        let db = Database::open_in_memory().unwrap();
+        assert_eq!(db.schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    /// Checks that inserted frames can be queried back, in order and filtered by time.
+    #[test]
+    fn insert_and_query_frames() {
+        let db = Database::open_in_memory().unwrap();
+        for seconds in [2, 0, 1, 3] {
+            db.insert_frame(&frame(seconds)).unwrap();
+        }
+
+        let frames = db.frames(frame(1).fix_time(), frame(2).fix_time()).unwrap();
+        assert_eq!(frames.len(), 2);
+        for (stored, expected) in frames.iter().zip([frame(1), frame(2)]) {
+            assert_eq!(stored.fix_time(), expected.fix_time());
+            assert_eq!(stored.status(), expected.status());
+            assert_eq!(stored.satellites(), expected.satellites());
+            assert_eq!(stored.latitude(), expected.latitude());
+            assert_eq!(stored.longitude(), expected.longitude());
+            assert_eq!(stored.altitude(), expected.altitude());
+            assert_eq!(stored.hdop(), expected.hdop());
+            assert_eq!(stored.course(), expected.course());
+        }
+    }
+
+    /// Checks that the background logger inserts all the records.
+    #[test]
+    fn background_logger() {
+        let logger = Logger::spawn(Database::open_in_memory().unwrap()).unwrap();
+        let start = frame(0).fix_time();
+        logger.log_state(start, State::Init);
+        for seconds in 0..200 {
+            logger.log_frame(&frame(seconds));
+        }
+        logger.log_battery(start, Some(0.9), None);
+        logger.log_state(start + Duration::seconds(200), State::ShutDown);
+
+        let db = logger.finish().unwrap();
+        assert_eq!(
+            db.frames(start, start + Duration::seconds(200))
+                .unwrap()
+                .len(),
+            200
+        );
+        assert_eq!(
+            db.states().unwrap(),
+            vec![
+                (start, "INITIALIZING".to_owned()),
+                (start + Duration::seconds(200), "SHUT_DOWN".to_owned()),
+            ]
+        );
+    }
+}
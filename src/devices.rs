@@ -0,0 +1,773 @@
+//! Hardware abstraction.
+//!
+//! The state machine logic does not use the GPS, GSM, camera, satellite modem and telemetry
+//! singletons directly. Instead, it uses them through the [`GpsDevice`](trait.GpsDevice.html),
+//! [`GsmDevice`](trait.GsmDevice.html), [`CameraDevice`](trait.CameraDevice.html),
+//! [`SatelliteDevice`](trait.SatelliteDevice.html),
+//! [`BarometerDevice`](trait.BarometerDevice.html) and
+//! [`TelemetryDevice`](trait.TelemetryDevice.html) traits,
+//! that are implemented by the real hardware structures and by the shared singletons. This makes
+//! it possible to run the logic with fake devices, in tests or in simulations.
+
+// Only required for GPS, FONA, Raspicam or telemetry
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
+use std::sync::{Mutex, MutexGuard};
+
+// Only required for FONA or Raspicam
+#[cfg(any(feature = "fona", feature = "raspicam"))]
+use std::time::Duration;
+// Only required for Raspicam
+#[cfg(feature = "raspicam")]
+use std::path::Path;
+
+// Only required for GPS, FONA, Raspicam or telemetry
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
+use anyhow::Error;
+// Only required for GPS, FONA, Raspicam or telemetry
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
+use tracing::error;
+
+#[cfg(feature = "baro")]
+use crate::baro::Bmp280;
+#[cfg(feature = "fona")]
+use crate::fona::{Delivery, Fona, Location};
+#[cfg(feature = "gps")]
+use crate::gps::{Frame, FrameHistory, Gps};
+#[cfg(feature = "iridium")]
+use crate::iridium::{Iridium, Sbdix};
+#[cfg(feature = "raspicam")]
+use crate::raspicam::Camera;
+#[cfg(feature = "telemetry")]
+use crate::telemetry::{Packet, Telemetry};
+
+/// GPS device.
+#[cfg(feature = "gps")]
+pub trait GpsDevice {
+    /// Initializes the GPS.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the GPS can't be turned on or configured.
+    fn initialize(&mut self) -> Result<(), Error>;
+
+    /// Turns the GPS off.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the GPS can't be turned off.
+    fn turn_off(&mut self) -> Result<(), Error>;
+
+    /// Gets the latest GPS data.
+    fn latest_data(&self) -> Option<Frame>;
+
+    /// Gets the launch position, the first valid GPS data.
+    fn launch_position(&self) -> Option<Frame>;
+
+    /// Gets the ascent rate averaged over the latest valid GPS data, in *m/s*.
+    ///
+    /// It's negative while descending.
+    fn smoothed_ascent_rate(&self) -> Option<f32>;
+
+    /// Gets the history of the latest valid GPS data.
+    fn history(&self) -> FrameHistory;
+}
+
+/// GSM device.
+#[cfg(feature = "fona")]
+pub trait GsmDevice {
+    /// Initializes the GSM module.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module can't be turned on or set up.
+    fn initialize(&mut self) -> Result<(), Error>;
+
+    /// Turns the GSM module off.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module can't be turned off.
+    fn turn_off(&mut self) -> Result<(), Error>;
+
+    /// Sends an SMS with the given text to the configured phone number.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SMS can't be sent.
+    fn send_sms(&mut self, message: &str) -> Result<(), Error>;
+
+    /// Sends an SMS with the given text to the configured phone number, and waits for its delivery
+    /// report for the given time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SMS can't be sent. A missing delivery report is not an error.
+    fn send_sms_confirmed(&mut self, message: &str, timeout: Duration) -> Result<Delivery, Error>;
+
+    /// Gets the GSM location of the module.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the location can't be obtained from the GSM network.
+    fn location(&mut self) -> Result<Location, Error>;
+
+    /// Gets the remaining battery of the GSM module, from 0 to 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the battery can't be read.
+    fn battery_percent(&mut self) -> Result<f32, Error>;
+
+    /// Gets the voltage of the analog-digital converter of the module, in *V*.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the voltage can't be read.
+    fn adc_voltage(&mut self) -> Result<f32, Error>;
+
+    /// Checks if the module has GSM connectivity.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module can't be queried.
+    fn has_connectivity(&mut self) -> Result<bool, Error>;
+}
+
+/// Camera device.
+#[cfg(feature = "raspicam")]
+pub trait CameraDevice {
+    /// Starts recording video, for the given time if any, to the given file if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the camera is already recording, or if the recording can't be started.
+    fn record(&mut self, time: Option<Duration>, file_name: Option<&Path>) -> Result<(), Error>;
+
+    /// Stops the video recording.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the recording can't be stopped.
+    fn stop_recording(&mut self) -> Result<(), Error>;
+
+    /// Checks if the camera is recording.
+    fn is_recording(&self) -> bool;
+
+    /// Takes a picture, to the given file if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the camera is recording, or if the picture can't be taken.
+    fn take_picture(&mut self, file_name: Option<&Path>) -> Result<(), Error>;
+
+    /// Checks wether the camera is detected, without recording.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the camera can't be queried.
+    fn detect(&self) -> Result<bool, Error>;
+}
+
+/// Satellite modem.
+#[cfg(feature = "iridium")]
+pub trait SatelliteDevice {
+    /// Sends a binary message, returning the result of the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the modem can't be reached. A failed session is not an error, it's
+    /// reported in the result.
+    fn send_message(&mut self, message: &[u8]) -> Result<Sbdix, Error>;
+}
+
+/// Barometric pressure sensor.
+#[cfg(feature = "baro")]
+pub trait BarometerDevice {
+    /// Reads the pressure, in *Pa*.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sensor can't be read.
+    fn pressure(&mut self) -> Result<f32, Error>;
+}
+
+/// Telemetry link.
+#[cfg(feature = "telemetry")]
+pub trait TelemetryDevice {
+    /// Offers a packet, to be sent respecting the configured interval.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet, or a waiting one, is due and can't be sent.
+    fn offer(&mut self, packet: Packet) -> Result<(), Error>;
+
+    /// Sends the waiting packet right away, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is a waiting packet and it can't be sent.
+    fn flush(&mut self) -> Result<(), Error>;
+
+    /// Sends a packet right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet can't be sent.
+    fn send(&mut self, packet: &Packet) -> Result<(), Error>;
+}
+
+#[cfg(feature = "gps")]
+impl GpsDevice for Gps {
+    fn initialize(&mut self) -> Result<(), Error> {
+        Gps::initialize(self)
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        Gps::turn_off(self)
+    }
+
+    fn latest_data(&self) -> Option<Frame> {
+        Gps::latest_data(self)
+    }
+
+    fn launch_position(&self) -> Option<Frame> {
+        Gps::launch_position(self)
+    }
+
+    fn smoothed_ascent_rate(&self) -> Option<f32> {
+        Gps::smoothed_ascent_rate(self)
+    }
+
+    fn history(&self) -> FrameHistory {
+        Gps::history(self).clone()
+    }
+}
+
+#[cfg(feature = "gps")]
+impl GpsDevice for &'static Mutex<Gps> {
+    fn initialize(&mut self) -> Result<(), Error> {
+        lock(self, "GPS").initialize()
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        lock(self, "GPS").turn_off()
+    }
+
+    fn latest_data(&self) -> Option<Frame> {
+        lock(self, "GPS").latest_data()
+    }
+
+    fn launch_position(&self) -> Option<Frame> {
+        lock(self, "GPS").launch_position()
+    }
+
+    fn smoothed_ascent_rate(&self) -> Option<f32> {
+        lock(self, "GPS").smoothed_ascent_rate()
+    }
+
+    fn history(&self) -> FrameHistory {
+        lock(self, "GPS").history().clone()
+    }
+}
+
+#[cfg(feature = "fona")]
+impl GsmDevice for Fona {
+    fn initialize(&mut self) -> Result<(), Error> {
+        Fona::initialize(self)
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        Fona::turn_off(self)
+    }
+
+    fn send_sms(&mut self, message: &str) -> Result<(), Error> {
+        Fona::send_sms(self, message)
+    }
+
+    fn send_sms_confirmed(&mut self, message: &str, timeout: Duration) -> Result<Delivery, Error> {
+        Fona::send_sms_confirmed(self, message, timeout)
+    }
+
+    fn location(&mut self) -> Result<Location, Error> {
+        Fona::location(self)
+    }
+
+    fn battery_percent(&mut self) -> Result<f32, Error> {
+        Fona::battery_percent(self)
+    }
+
+    fn adc_voltage(&mut self) -> Result<f32, Error> {
+        Fona::adc_voltage(self)
+    }
+
+    fn has_connectivity(&mut self) -> Result<bool, Error> {
+        Fona::has_connectivity(self)
+    }
+}
+
+#[cfg(feature = "fona")]
+impl GsmDevice for &'static Mutex<Fona> {
+    fn initialize(&mut self) -> Result<(), Error> {
+        lock(self, "FONA").initialize()
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        lock(self, "FONA").turn_off()
+    }
+
+    fn send_sms(&mut self, message: &str) -> Result<(), Error> {
+        lock(self, "FONA").send_sms(message)
+    }
+
+    fn send_sms_confirmed(&mut self, message: &str, timeout: Duration) -> Result<Delivery, Error> {
+        lock(self, "FONA").send_sms_confirmed(message, timeout)
+    }
+
+    fn location(&mut self) -> Result<Location, Error> {
+        lock(self, "FONA").location()
+    }
+
+    fn battery_percent(&mut self) -> Result<f32, Error> {
+        lock(self, "FONA").battery_percent()
+    }
+
+    fn adc_voltage(&mut self) -> Result<f32, Error> {
+        lock(self, "FONA").adc_voltage()
+    }
+
+    fn has_connectivity(&mut self) -> Result<bool, Error> {
+        lock(self, "FONA").has_connectivity()
+    }
+}
+
+#[cfg(feature = "raspicam")]
+impl CameraDevice for Camera {
+    fn record(&mut self, time: Option<Duration>, file_name: Option<&Path>) -> Result<(), Error> {
+        Camera::record::<_, &Path, _>(self, time, file_name)
+    }
+
+    fn stop_recording(&mut self) -> Result<(), Error> {
+        Ok(Camera::stop_recording(self)?)
+    }
+
+    fn is_recording(&self) -> bool {
+        Camera::is_recording(self)
+    }
+
+    fn take_picture(&mut self, file_name: Option<&Path>) -> Result<(), Error> {
+        Camera::take_picture::<&Path, _>(self, file_name)
+    }
+
+    fn detect(&self) -> Result<bool, Error> {
+        Camera::detect()
+    }
+}
+
+#[cfg(feature = "raspicam")]
+impl CameraDevice for &'static Mutex<Camera> {
+    fn record(&mut self, time: Option<Duration>, file_name: Option<&Path>) -> Result<(), Error> {
+        CameraDevice::record(&mut *lock(self, "CAMERA"), time, file_name)
+    }
+
+    fn stop_recording(&mut self) -> Result<(), Error> {
+        CameraDevice::stop_recording(&mut *lock(self, "CAMERA"))
+    }
+
+    fn is_recording(&self) -> bool {
+        lock(self, "CAMERA").is_recording()
+    }
+
+    fn take_picture(&mut self, file_name: Option<&Path>) -> Result<(), Error> {
+        CameraDevice::take_picture(&mut *lock(self, "CAMERA"), file_name)
+    }
+
+    fn detect(&self) -> Result<bool, Error> {
+        Camera::detect()
+    }
+}
+
+#[cfg(feature = "iridium")]
+impl SatelliteDevice for Iridium {
+    fn send_message(&mut self, message: &[u8]) -> Result<Sbdix, Error> {
+        Iridium::send_message(self, message)
+    }
+}
+
+#[cfg(feature = "iridium")]
+impl SatelliteDevice for &'static Mutex<Iridium> {
+    fn send_message(&mut self, message: &[u8]) -> Result<Sbdix, Error> {
+        lock(self, "IRIDIUM").send_message(message)
+    }
+}
+
+#[cfg(feature = "baro")]
+impl BarometerDevice for Bmp280 {
+    fn pressure(&mut self) -> Result<f32, Error> {
+        Bmp280::pressure(self)
+    }
+}
+
+#[cfg(feature = "baro")]
+impl BarometerDevice for &'static Mutex<Bmp280> {
+    fn pressure(&mut self) -> Result<f32, Error> {
+        lock(self, "BAROMETER").pressure()
+    }
+}
+
+#[cfg(feature = "telemetry")]
+impl TelemetryDevice for Telemetry {
+    fn offer(&mut self, packet: Packet) -> Result<(), Error> {
+        Telemetry::offer(self, packet)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Telemetry::flush(self)
+    }
+
+    fn send(&mut self, packet: &Packet) -> Result<(), Error> {
+        Telemetry::send(self, packet)
+    }
+}
+
+#[cfg(feature = "telemetry")]
+impl TelemetryDevice for &'static Mutex<Telemetry> {
+    fn offer(&mut self, packet: Packet) -> Result<(), Error> {
+        lock(self, "TELEMETRY").offer(packet)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        lock(self, "TELEMETRY").flush()
+    }
+
+    fn send(&mut self, packet: &Packet) -> Result<(), Error> {
+        lock(self, "TELEMETRY").send(packet)
+    }
+}
+
+/// Locks the mutex of a shared device, even if it was poisoned.
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
+fn lock<'m, D>(mutex: &'m Mutex<D>, name: &str) -> MutexGuard<'m, D> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The {} mutex was poisoned.", name);
+            poisoned.into_inner()
+        }
+    }
+}
+
+/// Fake devices, for testing the logic without hardware.
+#[cfg(test)]
+// Only the GPS dependent states are tested with fake devices.
+#[cfg_attr(not(feature = "gps"), allow(dead_code))]
+pub(crate) mod fake {
+    // Only required for GPS
+    #[cfg(feature = "gps")]
+    use std::cell::{Cell, RefCell};
+    // Only required for Raspicam
+    #[cfg(feature = "raspicam")]
+    use std::path::Path;
+    use std::time::Duration;
+
+    // Only required for GPS, FONA, Raspicam or telemetry
+    #[cfg(any(
+        feature = "gps",
+        feature = "fona",
+        feature = "raspicam",
+        feature = "telemetry"
+    ))]
+    use anyhow::Error;
+    // Only required for FONA
+    #[cfg(feature = "fona")]
+    use anyhow::bail;
+    // Only required for GPS and FONA
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    use chrono::Utc;
+
+    #[cfg(feature = "raspicam")]
+    use super::CameraDevice;
+    #[cfg(feature = "gps")]
+    use super::GpsDevice;
+    #[cfg(feature = "fona")]
+    use super::GsmDevice;
+    #[cfg(feature = "iridium")]
+    use super::SatelliteDevice;
+    #[cfg(feature = "telemetry")]
+    use super::TelemetryDevice;
+    #[cfg(feature = "baro")]
+    use crate::baro::AltitudeFilter;
+    #[cfg(feature = "fona")]
+    use crate::fona::{Delivery, Location};
+    #[cfg(feature = "iridium")]
+    use crate::iridium::Sbdix;
+    use crate::logic::Context;
+    #[cfg(feature = "telemetry")]
+    use crate::telemetry::Packet;
+    #[cfg(feature = "gps")]
+    use crate::{config::CONFIG, logic::FlightStats};
+    #[cfg(feature = "gps")]
+    use crate::{
+        geofence::Monitor,
+        gps::{self, Frame, FrameHistory, Gps},
+    };
+
+    /// Creates a context with fake devices and the given sleep function.
+    ///
+    /// The fake GPS will never have a fix.
+    pub(crate) fn context<S>(sleep: S) -> Context
+    where
+        S: Fn(Duration) + 'static,
+    {
+        Context {
+            #[cfg(feature = "gps")]
+            gps: Box::new(FakeGps::new(Vec::new())),
+            #[cfg(feature = "fona")]
+            gsm: Box::new(FakeGsm::default()),
+            #[cfg(feature = "raspicam")]
+            camera: Box::new(FakeCamera::default()),
+            #[cfg(feature = "iridium")]
+            satellite: Box::new(FakeSatellite::default()),
+            #[cfg(feature = "telemetry")]
+            telemetry: Box::new(FakeTelemetry),
+            sleep: Box::new(sleep),
+            shutdown_requested: Box::new(|| false),
+            #[cfg(all(feature = "gps", feature = "fona"))]
+            clock: Box::new(Utc::now),
+            #[cfg(feature = "gps")]
+            armed: Box::new(|| true),
+            #[cfg(feature = "gps")]
+            save_stats: Box::new(|_| Ok(())),
+            #[cfg(feature = "gps")]
+            stats: FlightStats::default(),
+            #[cfg(feature = "gps")]
+            geofence: Monitor::new(CONFIG.geofence()),
+            #[cfg(feature = "gps")]
+            track: None,
+            #[cfg(feature = "gps")]
+            gpx: None,
+            #[cfg(feature = "sqlite")]
+            db: None,
+            #[cfg(all(feature = "gps", feature = "raspicam"))]
+            pictures: None,
+            #[cfg(feature = "baro")]
+            barometer: None,
+            #[cfg(feature = "baro")]
+            altitude: AltitudeFilter::default(),
+        }
+    }
+
+    /// Fake GPS, that returns the given data in order, one item per call to `latest_data()`, and
+    /// then keeps returning the last one.
+    #[cfg(feature = "gps")]
+    #[derive(Debug)]
+    pub(crate) struct FakeGps {
+        /// Data to return.
+        data: Vec<Option<Frame>>,
+        /// Index of the next data to return.
+        next: Cell<usize>,
+        /// GPS data updated with the returned data, that keeps the ascent rate and the history.
+        gps: RefCell<Gps>,
+    }
+
+    #[cfg(feature = "gps")]
+    impl FakeGps {
+        /// Creates a fake GPS that will return the given data.
+        pub(crate) fn new(data: Vec<Option<Frame>>) -> Self {
+            Self {
+                data,
+                next: Cell::new(0),
+                gps: RefCell::new(Gps::new(
+                    CONFIG.gps().rate_window(),
+                    gps::history_capacity(),
+                )),
+            }
+        }
+    }
+
+    #[cfg(feature = "gps")]
+    impl GpsDevice for FakeGps {
+        fn initialize(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn turn_off(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn latest_data(&self) -> Option<Frame> {
+            let next = self.next.get();
+            self.next.set(next + 1);
+            if let Some(&Some(frame)) = self.data.get(next) {
+                self.gps.borrow_mut().update(frame);
+            }
+            self.data
+                .get(next)
+                .or_else(|| self.data.last())
+                .copied()
+                .flatten()
+        }
+
+        fn launch_position(&self) -> Option<Frame> {
+            let returned = self.next.get().min(self.data.len());
+            self.data[..returned]
+                .iter()
+                .flatten()
+                .find(|frame| frame.is_valid())
+                .copied()
+        }
+
+        fn smoothed_ascent_rate(&self) -> Option<f32> {
+            self.gps.borrow().smoothed_ascent_rate()
+        }
+
+        fn history(&self) -> FrameHistory {
+            self.gps.borrow().history().clone()
+        }
+    }
+
+    /// Fake GSM module, always connected and with full batteries.
+    #[cfg(feature = "fona")]
+    #[derive(Debug, Default, Clone, Copy)]
+    pub(crate) struct FakeGsm {
+        /// GSM location, if the module can get it.
+        pub(crate) location: Option<Location>,
+    }
+
+    #[cfg(feature = "fona")]
+    impl GsmDevice for FakeGsm {
+        fn initialize(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn turn_off(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn send_sms(&mut self, _message: &str) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn send_sms_confirmed(
+            &mut self,
+            _message: &str,
+            _timeout: Duration,
+        ) -> Result<Delivery, Error> {
+            Ok(Delivery::Delivered)
+        }
+
+        fn location(&mut self) -> Result<Location, Error> {
+            match self.location {
+                Some(location) => Ok(location),
+                None => bail!("the fake GSM module has no location"),
+            }
+        }
+
+        fn battery_percent(&mut self) -> Result<f32, Error> {
+            Ok(1_f32)
+        }
+
+        fn adc_voltage(&mut self) -> Result<f32, Error> {
+            Ok(4.2)
+        }
+
+        fn has_connectivity(&mut self) -> Result<bool, Error> {
+            Ok(true)
+        }
+    }
+
+    /// Fake camera, that only keeps track of the recording status.
+    #[cfg(feature = "raspicam")]
+    #[derive(Debug, Default, Clone, Copy)]
+    pub(crate) struct FakeCamera {
+        /// Wether the camera is recording.
+        recording: bool,
+    }
+
+    #[cfg(feature = "raspicam")]
+    impl CameraDevice for FakeCamera {
+        fn record(
+            &mut self,
+            time: Option<Duration>,
+            _file_name: Option<&Path>,
+        ) -> Result<(), Error> {
+            self.recording = time.is_none();
+            Ok(())
+        }
+
+        fn stop_recording(&mut self) -> Result<(), Error> {
+            self.recording = false;
+            Ok(())
+        }
+
+        fn is_recording(&self) -> bool {
+            self.recording
+        }
+
+        fn take_picture(&mut self, _file_name: Option<&Path>) -> Result<(), Error> {
+            self.recording = false;
+            Ok(())
+        }
+
+        fn detect(&self) -> Result<bool, Error> {
+            Ok(true)
+        }
+    }
+
+    /// Fake satellite modem, that keeps the sent messages.
+    #[cfg(feature = "iridium")]
+    #[derive(Debug, Default, Clone)]
+    pub(crate) struct FakeSatellite {
+        /// Messages sent.
+        pub(crate) sent: Vec<Vec<u8>>,
+    }
+
+    #[cfg(feature = "iridium")]
+    impl SatelliteDevice for FakeSatellite {
+        fn send_message(&mut self, message: &[u8]) -> Result<Sbdix, Error> {
+            self.sent.push(message.to_vec());
+            #[allow(clippy::cast_possible_truncation)]
+            let momsn = self.sent.len() as u16;
+            Ok(format!("+SBDIX: 0, {momsn}, 0, 0, 0, 0").parse()?)
+        }
+    }
+
+    /// Fake telemetry link, that drops every packet.
+    #[cfg(feature = "telemetry")]
+    #[derive(Debug, Default, Clone, Copy)]
+    pub(crate) struct FakeTelemetry;
+
+    #[cfg(feature = "telemetry")]
+    impl TelemetryDevice for FakeTelemetry {
+        fn offer(&mut self, _packet: Packet) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn send(&mut self, _packet: &Packet) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+}
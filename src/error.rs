@@ -1,6 +1,10 @@
 //! Error module.
 
 use std::{fmt, path::PathBuf};
+
+// Only required for the HTTP status endpoint
+#[cfg(feature = "http")]
+use std::net::SocketAddr;
 use thiserror::Error;
 
 use crate::STATE_FILE;
@@ -11,6 +15,12 @@ pub enum Logic {
     /// Initialization error.
     #[error("there was an error during the initialization")]
     Init,
+    /// A shutdown was requested while the logic was running.
+    #[error("a shutdown was requested")]
+    ShutdownRequested,
+    /// Error installing the termination signal handlers.
+    #[error("could not install the termination signal handlers")]
+    Signal,
 }
 
 /// GPS errors.
@@ -23,6 +33,15 @@ pub enum Gps {
     /// The GPS was already initialized when trying to initialize it.
     #[error("the GPS was already initialized when OpenStratos tried to initialize it")]
     AlreadyInitialized,
+    /// Error resetting the GPS module.
+    #[error("an error occurred trying to reset the GPS module")]
+    Reset,
+    /// Invalid NMEA sentence.
+    #[error("invalid NMEA sentence: '{}'", sentence)]
+    InvalidSentence {
+        /// The invalid sentence that was received.
+        sentence: String,
+    },
     /// Invalid GPS status code.
     #[error("invalid GPS status: '{}'", status)]
     InvalidStatus {
@@ -54,6 +73,8 @@ pub enum Config {
         /// The list of errors in the configuration.
         errors: String,
     },
+    /// Error setting up the reload of the configuration on `SIGHUP`.
+    Signal,
 }
 
 impl fmt::Display for Config {
@@ -75,6 +96,7 @@ impl fmt::Display for Config {
                 path.display()
             ),
             Config::Invalid { errors } => write!(f, "the configuration is invalid:\n{}", errors),
+            Config::Signal => write!(f, "error setting up the configuration reload on SIGHUP"),
         }
     }
 }
@@ -116,6 +138,24 @@ pub enum Log {
     Build,
 }
 
+/// Errors related to the persisted flight statistics.
+#[cfg(feature = "gps")]
+#[derive(Debug, Clone, Copy, Error)]
+pub enum Stats {
+    /// Error writing the flight statistics file.
+    #[error("error writing the flight statistics file")]
+    FileWrite,
+    /// Error reading the flight statistics file.
+    #[error("error reading the flight statistics file")]
+    FileRead,
+    /// Invalid flight statistics file.
+    #[error("the flight statistics file is invalid")]
+    Invalid,
+    /// Error reading the flight statistics of the last run.
+    #[error("error reading the flight statistics of the last run")]
+    Read,
+}
+
 /// Errors related to reading and modifying the last known state.
 #[derive(Debug, Clone, Error)]
 pub enum LastState {
@@ -188,6 +228,12 @@ pub enum Fona {
     /// No OK received after sending SMS.
     #[error("no OK received after sending SMS")]
     SmsOk,
+    /// Error enabling the SMS status reports.
+    #[error("error enabling SMS status reports with `AT+CSMP` and `AT+CNMI`")]
+    SmsStatusReports,
+    /// No status report received for the SMS in time.
+    #[error("no status report received for the SMS in time")]
+    SmsDeliveryTimeout,
     /// Error getting location on `AT+CMGF=1` response.
     #[error("error getting location on `AT+CMGF=1` response")]
     LocAtCmgf,
@@ -235,6 +281,24 @@ pub enum Fona {
     /// Invalid response to AT+CADC? (read ADC) command.
     #[error("FONA returned an invalid response to AT+CADC?")]
     CADCInvalidResponse,
+    /// Invalid response to AT+CPMS (preferred SMS storage) command.
+    #[error("FONA returned an invalid response to AT+CPMS")]
+    CPMSInvalidResponse,
+    /// Error deleting the SMSs stored in the module with AT+CMGD.
+    #[error("error deleting the SMSs stored in the FONA module")]
+    DeleteSms,
+    /// Error opening a TCP connection through GPRS.
+    #[cfg(feature = "mqtt")]
+    #[error("error opening a TCP connection through GPRS")]
+    TcpConnect,
+    /// Error sending data through the TCP connection.
+    #[cfg(feature = "mqtt")]
+    #[error("error sending data through the TCP connection")]
+    TcpSend,
+    /// Error closing the TCP connection.
+    #[cfg(feature = "mqtt")]
+    #[error("error closing the TCP connection")]
+    TcpClose,
 }
 
 /// Errors related to the Raspicam camera.
@@ -250,6 +314,8 @@ pub enum Raspicam {
     },
     /// Camera testing error.
     Test,
+    /// Camera detection error.
+    Detect,
     /// Error removing camera test file.
     TestRemove {
         /// Output file for the test.
@@ -268,6 +334,7 @@ impl fmt::Display for Raspicam {
                 file.display()
             ),
             Raspicam::Test => write!(f, "an error occurred when trying to test the camera"),
+            Raspicam::Detect => write!(f, "an error occurred when trying to detect the camera"),
             Raspicam::TestRemove { test_file } => write!(
                 f,
                 "there was an error trying to remove the camera test file {}",
@@ -277,8 +344,311 @@ impl fmt::Display for Raspicam {
     }
 }
 
+/// Errors related to telemetry.
+#[cfg(feature = "telemetry")]
+#[derive(Debug, Clone, Copy, Error)]
+pub enum Telemetry {
+    /// Error initializing the telemetry serial.
+    #[error("there was an error during the initialization of the telemetry serial")]
+    Init,
+    /// There was no open serial connection when trying to send telemetry.
+    #[error("there was no open serial connection when trying to send telemetry")]
+    NoSerial,
+    /// Error sending a telemetry packet.
+    #[error("there was a I/O error when trying to send a telemetry packet")]
+    Send,
+    /// The telemetry packet did not have the expected length.
+    #[error(
+        "invalid telemetry packet length: expected {} bytes, found {}",
+        expected,
+        found
+    )]
+    InvalidLength {
+        /// The expected length of the packet, in bytes.
+        expected: usize,
+        /// The length of the received packet, in bytes.
+        found: usize,
+    },
+    /// The CRC of the telemetry packet did not match its payload.
+    #[error(
+        "invalid telemetry packet CRC: expected {:#06X}, found {:#06X}",
+        expected,
+        found
+    )]
+    InvalidCrc {
+        /// The CRC computed over the payload of the packet.
+        expected: u16,
+        /// The CRC found in the packet.
+        found: u16,
+    },
+    /// The telemetry packet protocol version is not supported.
+    #[error("unsupported telemetry protocol version {}", version)]
+    UnsupportedVersion {
+        /// The version found in the packet.
+        version: u8,
+    },
+    /// The telemetry packet contained an invalid state code.
+    #[error("invalid state code {} in telemetry packet", code)]
+    InvalidState {
+        /// The invalid state code.
+        code: u8,
+    },
+    /// The telemetry packet contained an invalid timestamp.
+    #[error("invalid timestamp {} in telemetry packet", timestamp)]
+    InvalidTimestamp {
+        /// The invalid timestamp, in milliseconds since the UNIX epoch.
+        timestamp: i64,
+    },
+}
+
+/// Errors related to commands received through the telemetry link.
+#[cfg(feature = "telemetry")]
+#[derive(Debug, Clone, Error)]
+pub enum Command {
+    /// The command line did not follow the command format.
+    #[error("malformed command")]
+    Malformed,
+    /// The HMAC of the command did not match its contents.
+    #[error("invalid command HMAC")]
+    InvalidMac,
+    /// The command counter was not greater than the last accepted one.
+    #[error(
+        "replayed command: counter {} is not greater than {}",
+        counter,
+        last_counter
+    )]
+    Replayed {
+        /// The counter of the command.
+        counter: u64,
+        /// The last accepted counter.
+        last_counter: u64,
+    },
+    /// Unknown command.
+    #[error("unknown command `{}`", command)]
+    Unknown {
+        /// The unknown command name.
+        command: String,
+    },
+    /// The command did not have the expected argument.
+    #[error("invalid argument for command `{}`", command)]
+    InvalidArgument {
+        /// The command name.
+        command: String,
+    },
+}
+
+/// Errors writing flight track files.
+#[cfg(feature = "gps")]
+#[derive(Debug, Error)]
+pub enum Track {
+    /// Error writing the track file.
+    Write {
+        /// Path of the track file.
+        path: PathBuf,
+    },
+}
+
+#[cfg(feature = "gps")]
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Track::Write { path } => {
+                write!(f, "error writing the track file at '{}'", path.display())
+            }
+        }
+    }
+}
+
+/// Errors related to the flight database.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Error)]
+pub enum Db {
+    /// Error opening the database.
+    Open {
+        /// Path of the database.
+        path: PathBuf,
+    },
+    /// Error migrating the database schema.
+    Migration,
+    /// Error inserting in the database.
+    Insert,
+    /// Error querying the database.
+    Query,
+    /// Error in the background database logger.
+    Logger,
+}
+
+#[cfg(feature = "sqlite")]
+impl fmt::Display for Db {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Db::Open { path } => write!(f, "error opening the database at '{}'", path.display()),
+            Db::Migration => write!(f, "error migrating the database schema"),
+            Db::Insert => write!(f, "error inserting in the database"),
+            Db::Query => write!(f, "error querying the database"),
+            Db::Logger => write!(f, "error in the background database logger"),
+        }
+    }
+}
+
+/// Errors related to the flight simulation.
+#[cfg(feature = "sim")]
+#[derive(Debug, Clone, Error)]
+pub enum Sim {
+    /// Error reading the flight file.
+    #[error("error reading the flight file '{}'", path.display())]
+    Read {
+        /// Path of the flight file.
+        path: PathBuf,
+    },
+    /// Invalid CSV flight data.
+    #[error("invalid flight data in line {}", line)]
+    InvalidCsv {
+        /// Line of the invalid data, starting at 1.
+        line: usize,
+    },
+    /// Invalid GPX track point.
+    #[error("invalid GPX track point number {}", point)]
+    InvalidGpx {
+        /// Number of the invalid track point, starting at 1.
+        point: usize,
+    },
+    /// The flight file has no frames.
+    #[error("the flight file has no frames")]
+    Empty,
+}
+
+/// Errors related to MQTT telemetry.
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Clone, Copy, Error)]
+pub enum Mqtt {
+    /// Error connecting to the broker.
+    #[error("error connecting to the MQTT broker")]
+    Connect,
+    /// Error publishing a message.
+    #[error("error publishing an MQTT message")]
+    Publish,
+    /// A reconnection was attempted too recently.
+    #[error("waiting to reconnect to the MQTT broker")]
+    Backoff,
+}
+
+/// Errors related to LoRa telemetry.
+#[cfg(feature = "lora")]
+#[derive(Debug, Clone, Copy, Error)]
+pub enum Lora {
+    /// Error initializing the radio.
+    #[error("error initializing the LoRa radio")]
+    Init,
+    /// The radio is not a supported SX1276 family radio.
+    #[error("unsupported LoRa radio silicon version 0x{:02X}", version)]
+    Version {
+        /// Silicon version reported by the radio.
+        version: u8,
+    },
+    /// The bandwidth is not supported by the radio.
+    #[error("unsupported LoRa bandwidth of {} Hz", bandwidth)]
+    InvalidBandwidth {
+        /// Bandwidth, in Hz.
+        bandwidth: u32,
+    },
+    /// The payload does not fit in a LoRa packet.
+    #[error("LoRa payload of {} bytes is too long", length)]
+    PayloadTooLong {
+        /// Length of the payload, in bytes.
+        length: usize,
+    },
+    /// Error sending a packet.
+    #[error("error sending a LoRa packet")]
+    Send,
+    /// The transmission did not end in time.
+    #[error("timeout waiting for the end of the LoRa transmission")]
+    TxTimeout,
+}
+
+/// Errors related to the Iridium SBD modem.
+#[cfg(feature = "iridium")]
+#[derive(Debug, Clone, Error)]
+pub enum Iridium {
+    /// Error opening the modem serial.
+    #[error("error opening the Iridium modem serial")]
+    Init,
+    /// There was no open serial connection when trying to use the modem.
+    #[error("there was no open serial connection when trying to use the Iridium modem")]
+    NoSerial,
+    /// `EOF` was found in the modem serial.
+    #[error("EOF was found when reading the Iridium modem serial")]
+    SerialEnd,
+    /// Error writing to the modem serial.
+    #[error("there was a I/O error when trying to write to the Iridium modem")]
+    Command,
+    /// The modem returned an unexpected response.
+    #[error("unexpected Iridium modem response: `{}`", response)]
+    Response {
+        /// Contents of the response.
+        response: String,
+    },
+    /// The message does not fit in an SBD message.
+    #[error("invalid SBD message length: {} bytes (maximum 340)", length)]
+    Length {
+        /// Length of the message.
+        length: usize,
+    },
+    /// Error writing the message to the modem buffer.
+    #[error("error writing the SBD message to the modem, status: {}", status)]
+    Write {
+        /// Status returned by `AT+SBDWB`.
+        status: String,
+    },
+    /// Invalid `+SBDIX` response.
+    #[error("invalid `+SBDIX` response: `{}`", response)]
+    InvalidSbdix {
+        /// Contents of the response.
+        response: String,
+    },
+    /// The SBD session failed.
+    #[error("the SBD session failed with status {}", status)]
+    Session {
+        /// Mobile originated status of the session.
+        status: u8,
+    },
+}
+
+/// Errors related to the HTTP status endpoint.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Copy, Error)]
+pub enum Http {
+    /// Error binding the server to its address.
+    #[error("error binding the HTTP server to {}", address)]
+    Bind {
+        /// Address of the server.
+        address: SocketAddr,
+    },
+    /// Error starting the server thread.
+    #[error("error starting the HTTP server thread")]
+    Thread,
+}
+
+/// Errors related to the barometric sensor.
+#[cfg(feature = "baro")]
+#[derive(Debug, Clone, Copy, Error)]
+pub enum Baro {
+    /// The sensor is not configured.
+    #[error("the barometric sensor is not configured")]
+    NotConfigured,
+    /// Error initializing the sensor.
+    #[error("error initializing the barometric sensor")]
+    Init,
+    /// No I²C device when trying to communicate with the sensor.
+    #[error("no I²C device when trying to communicate with the barometric sensor")]
+    NoDevice,
+    /// Error reading the pressure.
+    #[error("error reading the pressure from the barometric sensor")]
+    Read,
+}
+
 /// Errors related to logic initialization.
-#[cfg(any(feature = "fona", feature = "gps"))]
+#[cfg(any(feature = "fona", feature = "gps", feature = "telemetry"))]
 #[derive(Debug, Clone, Copy, Error)]
 pub enum Init {
     /// Error initializing GPS module.
@@ -301,4 +671,8 @@ pub enum Init {
     #[cfg(feature = "fona")]
     #[error("not enough battery for the flight")]
     NotEnoughBattery,
+    /// Error initializing telemetry.
+    #[cfg(feature = "telemetry")]
+    #[error("error initializing telemetry")]
+    Telemetry,
 }
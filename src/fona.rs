@@ -2,16 +2,24 @@
 
 #![allow(missing_debug_implementations)]
 
-// TODO: timeouts
-
 use std::{fmt, io::Write, sync::Mutex, thread, time::Duration};
 
+// Only required when sending SMSs
+#[cfg(not(feature = "no_sms"))]
+use std::time::Instant;
+
 use anyhow::{bail, Context, Error};
 use once_cell::sync::Lazy;
 use tokio_serial::SerialPort;
 use tracing::{debug, error, info, warn};
 
-use crate::{config::CONFIG, error, generate_error_string};
+use crate::{
+    config::{SmsStorage, CONFIG},
+    error, generate_error_string, serial,
+};
+
+/// Flag of the `AT+CMGD` command that deletes all the messages in the preferred storage.
+const DELETE_ALL_FLAG: u8 = 4;
 
 /// The FONA module control structure.
 pub static FONA: Lazy<Mutex<Fona>> = Lazy::new(|| Mutex::new(Fona { serial: None }));
@@ -53,12 +61,11 @@ impl Fona {
 
         info!("Starting serial connection.");
 
-        let serial = tokio_serial::new(
-            CONFIG.fona().uart().to_string_lossy(),
+        let serial = serial::open(
+            CONFIG.fona().uart(),
             CONFIG.fona().baud_rate(),
-        )
-        //.timeout(Duration::from_secs(5))
-        .open()?;
+            CONFIG.fona().timeout(),
+        )?;
         self.serial = Some(serial);
         info!("Serial connection started.");
 
@@ -78,11 +85,17 @@ impl Fona {
             let _ = self.send_command_read("ATE0")?;
             thread::sleep(Duration::from_millis(100));
 
-            if self.send_command_read("ATE0")? == "OK" {
-                Ok(())
-            } else {
-                Err(error::Fona::EchoOff.into())
+            if self.send_command_read("ATE0")? != "OK" {
+                return Err(error::Fona::EchoOff.into());
+            }
+
+            if let Some(storage) = CONFIG.fona().sms_storage() {
+                let _ = self.set_sms_storage(storage)?;
+            }
+            if CONFIG.fona().clear_sms() {
+                self.delete_all_sms()?;
             }
+            Ok(())
         } else {
             error!("Initialization error.");
             Err(error::Fona::Init.into())
@@ -139,10 +152,108 @@ impl Fona {
     where
         M: AsRef<str>,
     {
-        let character_count = message.as_ref().chars().count();
+        let _ = self.submit_sms(message.as_ref())?;
+        Ok(())
+    }
+
+    /// Sends an SMS with the given text to the given phone number, and waits for its delivery.
+    ///
+    /// Delivery reports are requested for this SMS only, and the `+CDS` status report matching the
+    /// message reference is awaited for the given time. Temporary errors keep waiting for the
+    /// final report.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delivery reports can't be requested or the SMS can't be sent. A
+    /// missing or failed delivery report is not an error, it's reported in the result.
+    pub fn send_sms_confirmed<M>(
+        &mut self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<Delivery, Error>
+    where
+        M: AsRef<str>,
+    {
+        #[cfg(not(feature = "no_sms"))]
+        {
+            // Request a status report (SRR bit of the first octet), routed as `+CDS` result code.
+            if self.send_command_read("AT+CSMP=49,167,0,0")? != "OK"
+                || self.send_command_read("AT+CNMI=2,1,0,1,0")? != "OK"
+            {
+                error!("Error enabling SMS status reports.");
+                return Err(error::Fona::SmsStatusReports.into());
+            }
+
+            let reference = self.submit_sms(message.as_ref());
+            // Restore the default first octet, without status report.
+            if !matches!(self.send_command_read("AT+CSMP=17,167,0,0"), Ok(ref ok) if ok == "OK") {
+                warn!("Could not disable the SMS status reports.");
+            }
+            let reference = reference?;
+
+            info!(
+                "Waiting {} seconds for the delivery report of SMS {}\u{2026}",
+                timeout.as_secs(),
+                reference
+            );
+            let start = Instant::now();
+            while start.elapsed() < timeout {
+                let line = match self.read_line() {
+                    Ok(line) => line,
+                    Err(e)
+                        if matches!(
+                            e.downcast_ref::<error::Fona>(),
+                            Some(error::Fona::PartialResponse { .. })
+                        ) =>
+                    {
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                match StatusReport::parse(&line) {
+                    Some(report) if report.reference() == reference => {
+                        if let Some(delivery) = report.delivery() {
+                            info!(
+                                status = report.status(),
+                                "SMS {}: {:?}.", reference, delivery
+                            );
+                            return Ok(delivery);
+                        }
+                        debug!(
+                            status = report.status(),
+                            "Temporary delivery error for SMS {}, still trying.", reference
+                        );
+                    }
+                    Some(report) => {
+                        debug!("Ignoring the status report of SMS {}.", report.reference());
+                    }
+                    None => {
+                        if !line.is_empty() {
+                            debug!("Ignoring `{}` while waiting for the delivery report.", line);
+                        }
+                    }
+                }
+            }
+
+            error!("No delivery report received for SMS {}.", reference);
+            Err(error::Fona::SmsDeliveryTimeout.into())
+        }
+
+        #[cfg(feature = "no_sms")]
+        {
+            let _ = timeout;
+            self.send_sms(message)?;
+            Ok(Delivery::Delivered)
+        }
+    }
+
+    /// Submits an SMS with the given text to the network, returning its message reference.
+    fn submit_sms(&mut self, message: &str) -> Result<u8, Error> {
+        let character_count = message.chars().count();
         info!(
             "Sending SMS: `{}` ({} characters) to number {}",
-            message.as_ref(),
+            message,
             character_count,
             CONFIG.fona().sms_phone().as_str(),
         );
@@ -169,20 +280,17 @@ impl Fona {
 
                 // Write message
                 serial
-                    .write_all(message.as_ref().as_bytes())
+                    .write_all(message.as_bytes())
                     .context(error::Fona::Command)?;
 
-                debug!("Sent: `{}`", message.as_ref());
+                debug!("Sent: `{}`", message);
 
                 // Write Ctrl+Z
                 serial.write_all(&[0x1A]).context(error::Fona::Command)?;
 
                 debug!("Sent Ctrl+Z");
             } else {
-                error!(
-                    "No serial when trying to send message `{}`",
-                    message.as_ref()
-                );
+                error!("No serial when trying to send message `{}`", message);
                 return Err(error::Fona::NoSerial.into());
             }
 
@@ -195,13 +303,16 @@ impl Fona {
             }
 
             let response = self.read_line()?;
-            if !response.starts_with("+CMGS: ") {
+            let Some(reference) = response
+                .strip_prefix("+CMGS: ")
+                .and_then(|reference| reference.trim().parse().ok())
+            else {
                 error!(
                     "Error reading +CMGS response to the message, read `{}`",
                     response
                 );
                 return Err(error::Fona::SmsCmgs.into());
-            }
+            };
 
             let new_line = self.read_line()?;
             if !new_line.is_empty() {
@@ -220,13 +331,13 @@ impl Fona {
             }
 
             info!("SMS Sent.");
-            Ok(())
+            Ok(reference)
         }
 
         #[cfg(feature = "no_sms")]
         {
             thread::sleep(Duration::from_secs(5));
-            Ok(())
+            Ok(0)
         }
     }
 
@@ -330,6 +441,98 @@ impl Fona {
         })
     }
 
+    /// Opens a TCP connection to the given host and port through GPRS.
+    ///
+    /// Any previous connection is closed first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the GPRS context can't be brought up, or if the connection can't be
+    /// opened.
+    #[cfg(feature = "mqtt")]
+    pub fn tcp_connect(&mut self, host: &str, port: u16) -> Result<(), Error> {
+        info!("Opening TCP connection to {}:{}\u{2026}", host, port);
+
+        // Closes any previous connection, responding `SHUT OK`.
+        let _ = self.send_command_read("AT+CIPSHUT")?;
+
+        let apn_command = format!(r#"AT+CSTT="{}""#, CONFIG.fona().location_service());
+        for command in ["AT+CGATT=1", apn_command.as_str(), "AT+CIICR"] {
+            if self.send_command_read(command)? != "OK" {
+                error!("Error opening TCP connection on `{}` response.", command);
+                return Err(error::Fona::TcpConnect.into());
+            }
+        }
+
+        // The response is the IP address of the module.
+        let address = self.send_command_read("AT+CIFSR")?;
+        debug!("GPRS address: {}", address);
+
+        let start_command = format!(r#"AT+CIPSTART="TCP","{host}","{port}""#);
+        if self.send_command_read(&start_command)? != "OK" {
+            error!(
+                "Error opening TCP connection on `{}` response.",
+                start_command
+            );
+            return Err(error::Fona::TcpConnect.into());
+        }
+        let result = self.read_non_empty_line()?;
+        if result != "CONNECT OK" && result != "ALREADY CONNECT" {
+            error!("Error opening TCP connection, received `{}`.", result);
+            return Err(error::Fona::TcpConnect.into());
+        }
+
+        info!("TCP connection open.");
+        Ok(())
+    }
+
+    /// Sends the given data through the open TCP connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no serial, or if the module does not accept the data.
+    #[cfg(feature = "mqtt")]
+    pub fn tcp_send(&mut self, data: &[u8]) -> Result<(), Error> {
+        if self.send_command_read_limit(format!("AT+CIPSEND={}", data.len()), 2)? != "> " {
+            error!("Error sending TCP data on `AT+CIPSEND` response.");
+            return Err(error::Fona::TcpSend.into());
+        }
+
+        if let Some(ref mut serial) = self.serial {
+            serial.write_all(data).context(error::Fona::TcpSend)?;
+            debug!("Sent {} bytes through TCP.", data.len());
+        } else {
+            error!("No serial when trying to send TCP data");
+            return Err(error::Fona::NoSerial.into());
+        }
+
+        let result = self.read_non_empty_line()?;
+        if result == "SEND OK" {
+            Ok(())
+        } else {
+            error!("Error sending TCP data, received `{}`.", result);
+            Err(error::Fona::TcpSend.into())
+        }
+    }
+
+    /// Closes the TCP connection and the GPRS context.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the GPRS context can't be shut down.
+    #[cfg(feature = "mqtt")]
+    pub fn tcp_close(&mut self) -> Result<(), Error> {
+        // The connection might have already been closed by the server.
+        let _ = self.send_command_read("AT+CIPCLOSE")?;
+        if self.send_command_read("AT+CIPSHUT")? == "SHUT OK" {
+            info!("TCP connection closed.");
+            Ok(())
+        } else {
+            error!("Error closing the TCP connection on `AT+CIPSHUT` response.");
+            Err(error::Fona::TcpClose.into())
+        }
+    }
+
     /// Checks the FONA battery level, in percentage.
     pub fn battery_percent(&mut self) -> Result<f32, Error> {
         let bat_voltage = self.battery_voltage()?;
@@ -374,6 +577,46 @@ impl Fona {
         }
     }
 
+    /// Sets the preferred storage for reading, writing and receiving SMSs, returning its usage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module can't be queried or its response is invalid.
+    pub fn set_sms_storage(&mut self, storage: SmsStorage) -> Result<StorageUsage, Error> {
+        info!("Setting the {} SMS storage\u{2026}", storage.code());
+        let response = self.send_command_read(storage_command(storage))?;
+        let usage = StorageUsage::parse(&response).ok_or(error::Fona::CPMSInvalidResponse)?;
+        if self.read_non_empty_line()? != "OK" {
+            return Err(error::Fona::CPMSInvalidResponse.into());
+        }
+
+        info!(
+            "SMS storage set, {} of {} messages used.",
+            usage.used(),
+            usage.total()
+        );
+        if usage.is_full() {
+            warn!("The SMS storage is full.");
+        }
+        Ok(usage)
+    }
+
+    /// Deletes all the SMSs in the preferred storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module can't be queried or the SMSs can't be deleted.
+    pub fn delete_all_sms(&mut self) -> Result<(), Error> {
+        info!("Deleting the stored SMSs\u{2026}");
+        if self.send_command_read(delete_all_command())? == "OK" {
+            info!("Stored SMSs deleted.");
+            Ok(())
+        } else {
+            error!("Error deleting the stored SMSs.");
+            Err(error::Fona::DeleteSms.into())
+        }
+    }
+
     /// Checks if the FONA module has GSM connectivity.
     pub fn has_connectivity(&mut self) -> Result<bool, Error> {
         let response = self.send_command_read("AT+CREG?")?;
@@ -405,7 +648,7 @@ impl Fona {
 
                 // We read enough bytes.
                 if response.len() == count {
-                    let res = String::from_utf8(response)?;
+                    let res = decode_response(&response);
                     debug!(
                         "Received: `{}`",
                         res.replace('\r', "\\r").replace('\n', "\\n")
@@ -463,37 +706,20 @@ impl Fona {
         }
     }
 
+    /// Reads lines from the serial until a non-empty one is found.
+    fn read_non_empty_line(&mut self) -> Result<String, Error> {
+        loop {
+            let line = self.read_line()?;
+            if !line.is_empty() {
+                return Ok(line);
+            }
+        }
+    }
+
     /// Reads a line from the serial.
     fn read_line(&mut self) -> Result<String, Error> {
-        use std::io::{ErrorKind, Read};
-
         if let Some(ref mut serial) = self.serial {
-            let mut response = Vec::new();
-            for res in serial.bytes() {
-                match res {
-                    Ok(b'\r') => {}
-                    Ok(b'\n') => {
-                        let res = String::from_utf8(response)?;
-                        debug!("Received: `{}\r\n`", res);
-                        return Ok(res);
-                    }
-                    Ok(b) => {
-                        response.push(b);
-                    }
-                    Err(e) => {
-                        return Err(match e.kind() {
-                            ErrorKind::TimedOut => {
-                                let partial = String::from_utf8(response)?;
-                                debug!("Received (partial): `{}`", partial);
-                                error::Fona::PartialResponse { response: partial }.into()
-                            }
-                            _ => e.into(),
-                        });
-                    }
-                }
-            }
-
-            Err(error::Fona::SerialEnd.into())
+            read_line(serial.as_mut())
         } else {
             error!("No serial when trying to read response");
             Err(error::Fona::NoSerial.into())
@@ -501,6 +727,62 @@ impl Fona {
     }
 }
 
+/// Reads a line from the given reader, without the line ending.
+fn read_line<R>(reader: R) -> Result<String, Error>
+where
+    R: std::io::Read,
+{
+    use std::io::ErrorKind;
+
+    let mut response = Vec::new();
+    for res in reader.bytes() {
+        match res {
+            Ok(b'\r') => {}
+            Ok(b'\n') => {
+                let res = decode_response(&response);
+                debug!("Received: `{}\r\n`", res);
+                return Ok(res);
+            }
+            Ok(b) => {
+                response.push(b);
+            }
+            Err(e) => {
+                return Err(match e.kind() {
+                    ErrorKind::TimedOut => {
+                        let partial = decode_response(&response);
+                        debug!("Received (partial): `{}`", partial);
+                        error::Fona::PartialResponse { response: partial }.into()
+                    }
+                    _ => e.into(),
+                });
+            }
+        }
+    }
+
+    Err(error::Fona::SerialEnd.into())
+}
+
+/// Decodes a response of the FONA module.
+///
+/// There is usually some noise in the serial line during power transitions, so NUL bytes, other
+/// control characters and invalid UTF-8 sequences are dropped, instead of failing the whole
+/// command. Line endings and tabs are kept.
+fn decode_response(bytes: &[u8]) -> String {
+    let response: String = String::from_utf8_lossy(bytes)
+        .chars()
+        .filter(|&c| {
+            c != char::REPLACEMENT_CHARACTER && (!c.is_control() || matches!(c, '\r' | '\n' | '\t'))
+        })
+        .collect();
+    if response.len() != bytes.len() {
+        warn!(
+            raw = %bytes.escape_ascii(),
+            "Dropped noise bytes from the FONA response."
+        );
+    }
+    response
+}
+
 impl Drop for Fona {
     fn drop(&mut self) {
         match self.is_on() {
@@ -525,6 +807,137 @@ impl Drop for Fona {
     }
 }
 
+/// Delivery result of an SMS, as reported by the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The SMS was delivered to the receiver.
+    Delivered,
+    /// The SMS could not be delivered.
+    Failed,
+}
+
+/// SMS status report, received as a `+CDS` unsolicited result code in text mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusReport {
+    /// Reference of the reported message.
+    reference: u8,
+    /// Status of the message, as defined in GSM 03.40.
+    status: u8,
+}
+
+impl StatusReport {
+    /// Parses a `+CDS: <fo>,<mr>,[<ra>],[<tora>],<scts>,<dt>,<st>` status report line.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields = split_fields(line.strip_prefix("+CDS: ")?);
+        if fields.len() != 7 {
+            return None;
+        }
+
+        Some(Self {
+            reference: fields[1].trim().parse().ok()?,
+            status: fields[6].trim().parse().ok()?,
+        })
+    }
+
+    /// Gets the reference of the reported message.
+    #[must_use]
+    pub fn reference(self) -> u8 {
+        self.reference
+    }
+
+    /// Gets the status of the message, as defined in GSM 03.40.
+    #[must_use]
+    pub fn status(self) -> u8 {
+        self.status
+    }
+
+    /// Gets the delivery result, or `None` if the network is still trying to deliver the message.
+    #[must_use]
+    pub fn delivery(self) -> Option<Delivery> {
+        match self.status {
+            0x00..=0x1F => Some(Delivery::Delivered),
+            0x20..=0x3F => None,
+            _ => Some(Delivery::Failed),
+        }
+    }
+}
+
+/// Usage of an SMS storage, as reported by the `+CPMS` result code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Number of messages stored.
+    used: u16,
+    /// Capacity of the storage, in messages.
+    total: u16,
+}
+
+impl StorageUsage {
+    /// Parses the usage of the first storage of a `+CPMS` line.
+    ///
+    /// Both the `+CPMS: <used>,<total>,\u{2026}` response to setting the storage and the
+    /// `+CPMS: <mem>,<used>,<total>,\u{2026}` response to querying it are accepted.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = split_fields(line.strip_prefix("+CPMS: ")?)
+            .into_iter()
+            .map(str::trim)
+            .filter(|field| !field.starts_with('"'));
+
+        let used = fields.next()?.parse().ok()?;
+        let total = fields.next()?.parse().ok()?;
+        (used <= total).then_some(Self { used, total })
+    }
+
+    /// Gets the number of messages stored.
+    #[must_use]
+    pub fn used(self) -> u16 {
+        self.used
+    }
+
+    /// Gets the capacity of the storage, in messages.
+    #[must_use]
+    pub fn total(self) -> u16 {
+        self.total
+    }
+
+    /// Checks if the storage is full, so that no more messages can be received.
+    #[must_use]
+    pub fn is_full(self) -> bool {
+        self.used == self.total
+    }
+}
+
+/// Generates the `AT+CPMS` command that uses the given storage for every SMS operation.
+fn storage_command(storage: SmsStorage) -> String {
+    let code = storage.code();
+    format!(r#"AT+CPMS="{code}","{code}","{code}""#)
+}
+
+/// Generates the `AT+CMGD` command that deletes all the messages in the preferred storage.
+fn delete_all_command() -> String {
+    format!("AT+CMGD=1,{DELETE_ALL_FLAG}")
+}
+
+/// Splits the comma separated fields of a result code, keeping the quoted commas.
+fn split_fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&line[start..]);
+    fields
+}
+
 /// Structure representing the location of the probe as obtained by the FONA module.
 #[derive(Debug, Clone, Copy)]
 pub struct Location {
@@ -535,6 +948,15 @@ pub struct Location {
 }
 
 impl Location {
+    /// Creates a new location, with the given latitude and longitude, in degrees (°).
+    #[must_use]
+    pub fn new(latitude: f32, longitude: f32) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
     /// Gets the latitude of the location, in degrees (°).
     #[must_use]
     pub fn latitude(self) -> f32 {
@@ -550,7 +972,104 @@ impl Location {
 
 #[cfg(test)]
 mod tests {
-    use super::FONA;
+    use std::io::{self, Cursor, Read};
+
+    use super::{
+        decode_response, delete_all_command, read_line, storage_command, Delivery, StatusReport,
+        StorageUsage, FONA,
+    };
+    use crate::config::SmsStorage;
+
+    /// Checks that noise bytes in a response are dropped instead of failing the command.
+    #[test]
+    fn response_noise() {
+        let mut serial = Cursor::new(b"\0O\xFFK\r\n+CSQ: 1\x805,0\r\n\xC3\xB1\r\nAT".to_vec());
+        assert_eq!(read_line(&mut serial).unwrap(), "OK");
+        assert_eq!(read_line(&mut serial).unwrap(), "+CSQ: 15,0");
+        // Valid UTF-8 is kept.
+        assert_eq!(read_line(&mut serial).unwrap(), "\u{f1}");
+        // The serial ended without a line ending.
+        assert!(read_line(&mut serial).is_err());
+
+        assert_eq!(decode_response(b"\r\nOK\r\n"), "\r\nOK\r\n");
+        assert_eq!(decode_response(b"\x1B\tOK"), "\tOK");
+
+        /// Reader that times out after its data.
+        struct Timeout(Cursor<&'static [u8]>);
+
+        impl Read for Timeout {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.read(buf)? {
+                    0 => Err(io::ErrorKind::TimedOut.into()),
+                    read => Ok(read),
+                }
+            }
+        }
+
+        let error = read_line(Timeout(Cursor::new(b"+CMG\xFE"))).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "FONA returned a partial response: `+CMG`"
+        );
+    }
+
+    /// Checks the parsing of `+CDS` status reports, and their delivery results.
+    #[test]
+    fn status_report() {
+        let report = StatusReport::parse(
+            "+CDS: 6,46,\"+34600000000\",145,\"17/05/13,13:42:07+08\",\"17/05/13,13:42:10+08\",0",
+        )
+        .unwrap();
+        assert_eq!(report.reference(), 46);
+        assert_eq!(report.status(), 0);
+        assert_eq!(report.delivery(), Some(Delivery::Delivered));
+
+        // Temporary error, the network keeps trying.
+        let report = StatusReport::parse(
+            "+CDS: 6,47,,,\"17/05/13,13:42:07+08\",\"17/05/13,13:42:10+08\",48",
+        )
+        .unwrap();
+        assert_eq!(report.reference(), 47);
+        assert_eq!(report.delivery(), None);
+
+        // Permanent error.
+        let report = StatusReport::parse(
+            "+CDS: 6,48,\"+34600000000\",145,\"17/05/13,13:42:07+08\",\"17/05/13,13:42:10+08\",65",
+        )
+        .unwrap();
+        assert_eq!(report.delivery(), Some(Delivery::Failed));
+
+        assert!(StatusReport::parse("+CMGS: 46").is_none());
+        assert!(StatusReport::parse("+CDS: 6,46").is_none());
+        assert!(StatusReport::parse("OK").is_none());
+    }
+
+    /// Checks the parsing of `+CPMS` responses, and the storage commands.
+    #[test]
+    fn sms_storage() {
+        let usage = StorageUsage::parse("+CPMS: 3,30,3,30,3,30").unwrap();
+        assert_eq!(usage.used(), 3);
+        assert_eq!(usage.total(), 30);
+        assert!(!usage.is_full());
+
+        let usage = StorageUsage::parse(r#"+CPMS: "SM",30,30,"SM",30,30,"SM",30,30"#).unwrap();
+        assert_eq!(usage.used(), 30);
+        assert!(usage.is_full());
+
+        assert!(StorageUsage::parse("+CPMS: 31,30").is_none());
+        assert!(StorageUsage::parse("+CPMS: 3").is_none());
+        assert!(StorageUsage::parse("+CMS ERROR: 302").is_none());
+
+        assert_eq!(
+            storage_command(SmsStorage::Sim),
+            r#"AT+CPMS="SM","SM","SM""#
+        );
+        assert_eq!(
+            storage_command(SmsStorage::All),
+            r#"AT+CPMS="MT","MT","MT""#
+        );
+        assert_eq!(delete_all_command(), "AT+CMGD=1,4");
+    }
 
     /// Tests FONA initialization.
     #[test]
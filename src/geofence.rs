@@ -0,0 +1,322 @@
+//! Geofence alerts.
+//!
+//! The flight can be restricted to a permitted area, such as the authorized airspace, or kept
+//! away from areas such as borders or the ocean. Fences are configured as polygons in the
+//! `[[geofence]]` configuration sections, each of them with a list of `[latitude, longitude]`
+//! vertices, in *°*, and a `kind`:
+//!
+//! * `allowed`: the probe should stay inside the polygon. An alert is fired the first time a frame
+//!   is outside of it.
+//! * `forbidden`: the probe should stay outside of the polygon. An alert is fired the first time a
+//!   frame is inside of it.
+//!
+//! Every new GPS frame of the flight logic is checked with a [`Monitor`](struct.Monitor.html),
+//! and each fence fires its alert only once, by SMS and telemetry. Points in the boundary of a
+//! polygon are considered inside of it.
+//!
+//! Polygons can span the antimeridian: longitudes are unwrapped along the edges, so a polygon from
+//! 170° to -170° covers the 20° around the antimeridian, not the 340° around the Greenwich
+//! meridian. Polygons must not span more than 180° of longitude between consecutive vertices.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::gps::Frame;
+
+/// Kind of a geofence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// The probe should stay inside the fence.
+    Allowed,
+    /// The probe should stay outside the fence.
+    Forbidden,
+}
+
+/// Geofence configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Fence {
+    /// Name of the fence, used in the alerts.
+    name: String,
+    /// Kind of the fence.
+    kind: Kind,
+    /// Vertices of the polygon, as `[latitude, longitude]` pairs in *°*.
+    vertices: Vec<[f64; 2]>,
+}
+
+impl Fence {
+    /// Creates a new fence, with the vertices as `[latitude, longitude]` pairs in *°*.
+    #[must_use]
+    pub fn new<N>(name: N, kind: Kind, vertices: Vec<[f64; 2]>) -> Self
+    where
+        N: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            kind,
+            vertices,
+        }
+    }
+
+    /// Gets the name of the fence.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the kind of the fence.
+    #[must_use]
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Gets the vertices of the polygon, as `[latitude, longitude]` pairs in *°*.
+    #[must_use]
+    pub fn vertices(&self) -> &[[f64; 2]] {
+        &self.vertices
+    }
+
+    /// Checks if the given point is inside the polygon of the fence, or in its boundary.
+    #[must_use]
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        let polygon = unwrap_longitudes(&self.vertices);
+        [0_f64, 360_f64, -360_f64]
+            .iter()
+            .any(|offset| contains(&polygon, latitude, longitude + offset))
+    }
+
+    /// Checks if the probe is breaching the fence at the given point.
+    #[must_use]
+    pub fn is_breached(&self, latitude: f64, longitude: f64) -> bool {
+        match self.kind {
+            Kind::Allowed => !self.contains(latitude, longitude),
+            Kind::Forbidden => self.contains(latitude, longitude),
+        }
+    }
+}
+
+/// Geofence alert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    /// Name of the breached fence.
+    name: String,
+    /// Kind of the breached fence.
+    kind: Kind,
+}
+
+impl Alert {
+    /// Gets the name of the breached fence.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the kind of the breached fence.
+    #[must_use]
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Allowed => write!(f, "Left allowed area: {}.", self.name),
+            Kind::Forbidden => write!(f, "Entered forbidden area: {}.", self.name),
+        }
+    }
+}
+
+/// Geofence monitor, that fires the alert of each fence only once.
+#[derive(Debug, Clone, Default)]
+pub struct Monitor {
+    /// Fences, with wether their alert has already been fired.
+    fences: Vec<(Fence, bool)>,
+}
+
+impl Monitor {
+    /// Creates a monitor for the given fences.
+    #[must_use]
+    pub fn new(fences: &[Fence]) -> Self {
+        Self {
+            fences: fences.iter().cloned().map(|fence| (fence, false)).collect(),
+        }
+    }
+
+    /// Checks a new frame, returning the alerts of the fences breached for the first time.
+    pub fn check(&mut self, frame: &Frame) -> Vec<Alert> {
+        let (latitude, longitude) = (f64::from(frame.latitude()), f64::from(frame.longitude()));
+
+        let mut alerts = Vec::new();
+        for (fence, fired) in &mut self.fences {
+            if !*fired && fence.is_breached(latitude, longitude) {
+                *fired = true;
+                alerts.push(Alert {
+                    name: fence.name.clone(),
+                    kind: fence.kind,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+/// Unwraps the longitudes of the polygon, so that there are no jumps of more than 180° between
+/// consecutive vertices. The points are returned as `(longitude, latitude)` pairs.
+fn unwrap_longitudes(vertices: &[[f64; 2]]) -> Vec<(f64, f64)> {
+    let mut polygon: Vec<(f64, f64)> = Vec::with_capacity(vertices.len());
+    for &[latitude, longitude] in vertices {
+        let longitude = match polygon.last() {
+            Some(&(previous, _)) => previous + normalize(longitude - previous),
+            None => longitude,
+        };
+        polygon.push((longitude, latitude));
+    }
+    polygon
+}
+
+/// Normalizes a longitude difference to the `[-180, 180)` range.
+fn normalize(difference: f64) -> f64 {
+    (difference + 180_f64).rem_euclid(360_f64) - 180_f64
+}
+
+/// Checks if the point is inside the polygon, or in its boundary, using the even-odd rule.
+///
+/// The polygon is given as `(x, y)` pairs.
+fn contains(polygon: &[(f64, f64)], y: f64, x: f64) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for &current in polygon {
+        let ((x1, y1), (x2, y2)) = (previous, current);
+
+        // Check the boundary first, so that points on an edge are always inside.
+        let cross = (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1);
+        if cross.abs() < 1e-9
+            && x >= x1.min(x2)
+            && x <= x1.max(x2)
+            && y >= y1.min(y2)
+            && y <= y1.max(y2)
+        {
+            return true;
+        }
+
+        if (y1 > y) != (y2 > y) && x < x1 + (y - y1) * (x2 - x1) / (y2 - y1) {
+            inside = !inside;
+        }
+        previous = current;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{Fence, Kind, Monitor};
+    use crate::gps::{FixStatus, Frame};
+
+    /// Creates a U-shaped, concave fence, open to the north.
+    fn u_shape(kind: Kind) -> Fence {
+        Fence::new(
+            "U",
+            kind,
+            vec![
+                [0.0, 0.0],
+                [0.0, 3.0],
+                [3.0, 3.0],
+                [3.0, 2.0],
+                [1.0, 2.0],
+                [1.0, 1.0],
+                [3.0, 1.0],
+                [3.0, 0.0],
+            ],
+        )
+    }
+
+    /// Checks points inside and outside a concave polygon.
+    #[test]
+    fn concave_polygon() {
+        let fence = u_shape(Kind::Allowed);
+
+        // The arms and the base of the U.
+        assert!(fence.contains(2.5, 0.5));
+        assert!(fence.contains(2.5, 2.5));
+        assert!(fence.contains(0.5, 1.5));
+        // The gap between the arms.
+        assert!(!fence.contains(2.0, 1.5));
+        assert!(!fence.contains(2.9, 1.5));
+        // Outside of the bounding box.
+        assert!(!fence.contains(-0.5, 1.5));
+        assert!(!fence.contains(1.5, 3.5));
+    }
+
+    /// Checks that points in the boundary of a polygon are inside of it.
+    #[test]
+    fn boundary() {
+        let fence = u_shape(Kind::Allowed);
+
+        // Vertices.
+        assert!(fence.contains(0.0, 0.0));
+        assert!(fence.contains(1.0, 1.0));
+        // Edges, including the inner ones of the gap.
+        assert!(fence.contains(0.0, 1.5));
+        assert!(fence.contains(2.0, 1.0));
+        assert!(fence.contains(1.0, 1.5));
+        // Just outside of the inner edge.
+        assert!(!fence.contains(1.000_001, 1.5));
+    }
+
+    /// Checks a polygon spanning the antimeridian.
+    #[test]
+    fn antimeridian() {
+        let fence = Fence::new(
+            "Pacific",
+            Kind::Forbidden,
+            vec![
+                [-10.0, 170.0],
+                [10.0, 170.0],
+                [10.0, -170.0],
+                [-10.0, -170.0],
+            ],
+        );
+
+        assert!(fence.contains(0.0, 180.0));
+        assert!(fence.contains(0.0, -180.0));
+        assert!(fence.contains(5.0, 175.0));
+        assert!(fence.contains(5.0, -175.0));
+        assert!(!fence.contains(0.0, 0.0));
+        assert!(!fence.contains(0.0, 160.0));
+        assert!(!fence.contains(0.0, -160.0));
+    }
+
+    /// Checks that each fence fires its alert only once.
+    #[test]
+    fn alerts_fire_once() {
+        let time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let frame =
+            |latitude, longitude| Frame::new(time, FixStatus::Active, 8, latitude, longitude, 0.0);
+        let mut monitor = Monitor::new(&[
+            u_shape(Kind::Allowed),
+            Fence::new(
+                "Gap",
+                Kind::Forbidden,
+                vec![[2.0, 1.0], [2.0, 2.0], [3.0, 2.0], [3.0, 1.0]],
+            ),
+        ]);
+
+        assert!(monitor.check(&frame(0.5, 0.5)).is_empty());
+
+        let alerts = monitor.check(&frame(2.5, 1.5));
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].to_string(), "Left allowed area: U.");
+        assert_eq!(alerts[1].to_string(), "Entered forbidden area: Gap.");
+
+        assert!(monitor.check(&frame(2.5, 1.5)).is_empty());
+        assert!(monitor.check(&frame(5.0, 5.0)).is_empty());
+    }
+}
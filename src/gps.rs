@@ -2,66 +2,167 @@
 
 #![allow(missing_debug_implementations)]
 
-use crate::{config::CONFIG, error};
+use crate::{config::CONFIG, error, serial};
 use anyhow::{Context, Error};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use std::{
+    cell::Cell,
+    collections::VecDeque,
     fmt,
-    io::{self, Read, Write},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
     str::FromStr,
     sync::Mutex,
     thread,
     time::{Duration, Instant},
 };
-use sysfs_gpio::Direction;
-use tracing::{info, warn};
+use sysfs_gpio::{Direction, Pin};
+use tokio_serial::SerialPort;
+use tracing::{error, info, warn};
 
 /// GPS data for concurrent check.
-pub static GPS: Lazy<Mutex<Gps>> = Lazy::new(|| Mutex::new(Gps::default()));
+pub static GPS: Lazy<Mutex<Gps>> =
+    Lazy::new(|| Mutex::new(Gps::new(CONFIG.gps().rate_window(), history_capacity())));
+
+/// Default number of samples averaged in the smoothed ascent rate.
+pub const DEFAULT_RATE_WINDOW: usize = 5;
+
+/// Default navigation rate of the GPS, in *Hz*.
+pub const DEFAULT_NAV_RATE: u8 = 10;
+/// Maximum navigation rate supported by the uBLOX MAX-M8Q module, in *Hz*.
+pub const MAX_NAV_RATE: u8 = 10;
+
+/// Maximum time to wait for the GPS to come back after a reset.
+pub const RESET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time after which the fix of a GPS is considered stale, in seconds.
+pub const STALE_FIX_TIME: i64 = 5;
+
+/// Default number of frames kept in the frame history.
+pub const FRAME_HISTORY_CAPACITY: usize = 60;
+
+/// Mean radius of the Earth, in *m*.
+pub const EARTH_RADIUS: f64 = 6_371_000_f64;
 
 /// GPS information structure.
 #[derive(Debug, Default)]
 pub struct Gps {
     latest_data: Option<Frame>,
+    backup_data: Option<Frame>,
+    launch_position: Option<Frame>,
+    source: Cell<Receiver>,
+    ascent_rate: AscentRate,
+    history: FrameHistory,
 }
 
 impl Gps {
-    /// Initializes the GPS.
+    /// Creates the GPS data, averaging the given number of frames in the smoothed ascent rate and
+    /// keeping the given number of frames in the history.
+    #[must_use]
+    pub fn new(rate_window: usize, history_capacity: usize) -> Self {
+        Self {
+            latest_data: None,
+            backup_data: None,
+            launch_position: None,
+            source: Cell::default(),
+            ascent_rate: AscentRate::new(rate_window),
+            history: FrameHistory::new(history_capacity),
+        }
+    }
+
+    /// Initializes the GPS, and the backup GPS if it's configured.
+    ///
+    /// The frames of each GPS are read in a background thread, that stores them as the latest data
+    /// of that GPS.
+    ///
+    /// An error initializing the backup GPS is only logged. An error initializing the primary GPS
+    /// is only logged too if the backup GPS was initialized, so that the flight can continue with
+    /// it.
     pub fn initialize(&mut self) -> Result<(), Error> {
         info!("Initializing GPS\u{2026}");
-        CONFIG
-            .gps()
-            .power_gpio()
+        let gps = CONFIG.gps();
+        let primary = Self::initialize_receiver(
+            Receiver::Primary,
+            gps.uart(),
+            gps.baud_rate(),
+            gps.timeout(),
+            gps.power_gpio(),
+        )
+        .and_then(|serial| spawn_reader(Receiver::Primary, serial));
+
+        if let Some(backup) = gps.backup() {
+            info!("Initializing backup GPS\u{2026}");
+            let result = Self::initialize_receiver(
+                Receiver::Backup,
+                backup.uart(),
+                backup.baud_rate(),
+                backup.timeout(),
+                backup.power_gpio(),
+            )
+            .and_then(|serial| spawn_reader(Receiver::Backup, serial));
+            match (primary, result) {
+                (Err(e), Ok(())) => {
+                    error!(error = ?e, "Error initializing the primary GPS, using the backup GPS.");
+                    self.source.set(Receiver::Backup);
+                    return Ok(());
+                }
+                (primary, Err(e)) => {
+                    error!(error = ?e, "Error initializing the backup GPS.");
+                    return primary;
+                }
+                (primary, Ok(())) => return primary,
+            }
+        }
+
+        primary
+    }
+
+    /// Initializes one of the GPS receivers, returning its serial connection.
+    fn initialize_receiver(
+        receiver: Receiver,
+        uart: &Path,
+        baud_rate: u32,
+        timeout: Duration,
+        power_gpio: Pin,
+    ) -> Result<Box<dyn SerialPort>, Error> {
+        power_gpio
             .set_direction(Direction::Out)
             .context(error::Gps::Init)?;
 
-        if self.is_on().context(error::Gps::Init)? {
-            info!("GPS is on, turning off for 2 seconds for stability");
-            self.turn_off().context(error::Gps::Init)?;
+        if is_on(power_gpio).context(error::Gps::Init)? {
+            info!(
+                "The {} GPS is on, turning off for 2 seconds for stability",
+                receiver
+            );
+            turn_off(power_gpio).context(error::Gps::Init)?;
             thread::sleep(Duration::from_secs(2))
         }
 
-        info!("Turning GPS on\u{2026}");
-        self.turn_on().context(error::Gps::Init)?;
-        info!("GPS on.");
+        info!("Turning the {} GPS on\u{2026}", receiver);
+        turn_on(power_gpio).context(error::Gps::Init)?;
+        info!("The {} GPS is on.", receiver);
 
         info!("Starting serial connection\u{2026}");
-        let mut serial = tokio_serial::new(
-            CONFIG.gps().uart().to_string_lossy(),
-            CONFIG.gps().baud_rate(),
-        )
-        .open()
-        .context(error::Gps::Init)?;
+        let mut serial = serial::open(uart, baud_rate, timeout).context(error::Gps::Init)?;
         // serial.set_exclusive(false).context(error::Gps::Init)?;
         info!("Serial connection started.");
 
+        if let Some(kind) = CONFIG.gps().reset_on_init() {
+            info!(
+                "Sending a {} start reset to the {} GPS\u{2026}",
+                kind, receiver
+            );
+            reset_receiver(&mut serial, kind).context(error::Gps::Init)?;
+            info!("The {} GPS is back after the reset.", receiver);
+        }
+
+        // GSA sentences are kept enabled, since they carry the dilutions of precision.
         info!("Sending configuration frames\u{2026}");
         let messages = [
             // Set refresh
-            vec![
-                0xB5, 0x62, 0x06, 0x08, 0x06, 0x00, 0x64, 0x00, 0x01, 0x00, 0x01, 0x00, 0x7A, 0x12,
-            ],
+            cfg_rate(CONFIG.gps().nav_rate_hz()).to_vec(),
             // Disable GSV:
             vec![0xB5, 0x62, 0x05, 0x01, 0x02, 0x00, 0x06, 0x01, 0x0F, 0x38],
             // Disable VTG:
@@ -93,48 +194,48 @@ impl Gps {
             warn!("GPS failed to enter airborne (<1g) mode");
         }
 
-        // TODO: select appropriate maximum length
-        // let (_writer, reader) = LinesCodec::new_with_max_length(250)
-        //     .framed(serial)
-        //     .then(Self::parse_frame)
-        //     .split();
+        Ok(serial)
+    }
 
-        // let processor = reader
-        //     .for_each(|frame| {
-        //         GPS.lock().unwrap().latest_data = if frame.is_valid() { Some(frame) } else { None };
-        //         Ok(())
-        //     })
-        //     .map_err(|e| warn!("Error processing frame: {}", e));
-        // tokio::run(processor);
+    /// Resets the primary GPS, and waits for it to come back.
+    ///
+    /// The latest data is discarded, since it could come from a module in a bad state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reset can't be sent to the primary GPS, or if it does not come back
+    /// in time.
+    pub fn reset(&mut self, kind: ResetKind) -> Result<(), Error> {
+        info!("Sending a {} start reset to the primary GPS\u{2026}", kind);
+        let gps = CONFIG.gps();
+        let mut serial =
+            serial::open(gps.uart(), gps.baud_rate(), gps.timeout()).context(error::Gps::Reset)?;
+        reset_receiver(&mut serial, kind)?;
 
+        self.latest_data = None;
+        self.source.set(Receiver::Primary);
+        info!("The primary GPS is back after the reset.");
         Ok(())
     }
 
-    /// Checks if the GPS is on.
+    /// Checks if the primary GPS is on.
     pub fn is_on(&self) -> Result<bool, Error> {
-        Ok(CONFIG.gps().power_gpio().get_value()? == 1)
+        is_on(CONFIG.gps().power_gpio())
     }
 
-    /// Turns the GPS on.
+    /// Turns the primary GPS on.
     pub fn turn_on(&self) -> Result<(), Error> {
-        if self.is_on()? {
-            warn!("Turning on the GPS but it was already on.");
-        } else {
-            CONFIG.gps().power_gpio().set_value(1)?
-        }
-
-        Ok(())
+        turn_on(CONFIG.gps().power_gpio())
     }
 
-    /// Turns the GPS off.
+    /// Turns the GPS off, and the backup GPS if it's configured.
     pub fn turn_off(&self) -> Result<(), Error> {
-        if self.is_on()? {
-            CONFIG.gps().power_gpio().set_value(0)?
-        } else {
-            warn!("Turning off the GPS but it was already off.");
+        if let Some(backup) = CONFIG.gps().backup() {
+            if let Err(e) = turn_off(backup.power_gpio()) {
+                error!(error = ?e, "Error turning the backup GPS off.");
+            }
         }
-
-        Ok(())
+        turn_off(CONFIG.gps().power_gpio())
     }
 
     /// Enters airborne (<1g) GPS mode.
@@ -191,33 +292,119 @@ impl Gps {
     }
 
     /// Gets the latest GPS data.
+    ///
+    /// The data of the primary GPS is preferred, but if its fix is void or older than
+    /// [`STALE_FIX_TIME`](constant.STALE_FIX_TIME.html) seconds, the data of the backup GPS is
+    /// used, if it's valid and recent.
     pub fn latest_data(&self) -> Option<Frame> {
-        self.latest_data
+        self.select(Utc::now())
+    }
+
+    /// Gets the GPS receiver that provided the latest data.
+    #[must_use]
+    pub fn source(&self) -> Receiver {
+        self.source.get()
+    }
+
+    /// Selects the data of the primary or the backup GPS, for the given current time.
+    fn select(&self, now: DateTime<Utc>) -> Option<Frame> {
+        let fresh = |frame: &Frame| frame.is_fresh(now);
+
+        let (receiver, frame) = match (self.latest_data, self.backup_data) {
+            (Some(primary), _) if fresh(&primary) => (Receiver::Primary, primary),
+            (_, Some(backup)) if fresh(&backup) => (Receiver::Backup, backup),
+            (Some(primary), _) => (Receiver::Primary, primary),
+            (None, Some(backup)) => (Receiver::Backup, backup),
+            (None, None) => return None,
+        };
+
+        if self.source.replace(receiver) != receiver {
+            match receiver {
+                Receiver::Primary => info!("The primary GPS is providing the fix again."),
+                Receiver::Backup => warn!(
+                    "The primary GPS fix is stale or void, the backup GPS is providing the fix."
+                ),
+            }
+        }
+        Some(frame)
+    }
+
+    /// Gets the instantaneous ascent rate between the two latest valid frames, in *m/s*.
+    ///
+    /// It's negative while descending.
+    #[must_use]
+    pub fn ascent_rate(&self) -> Option<f32> {
+        self.ascent_rate.instantaneous()
+    }
+
+    /// Gets the ascent rate averaged over the latest valid frames, in *m/s*.
+    ///
+    /// It's negative while descending.
+    #[must_use]
+    pub fn smoothed_ascent_rate(&self) -> Option<f32> {
+        self.ascent_rate.smoothed()
+    }
+
+    /// Gets the launch position, the first valid frame selected as the latest data.
+    #[must_use]
+    pub fn launch_position(&self) -> Option<Frame> {
+        self.launch_position
+    }
+
+    /// Gets the great circle distance from the launch position to the latest position, in *m*.
+    ///
+    /// It's `None` if the launch position is not known yet or if there is no latest data.
+    #[must_use]
+    pub fn distance_from_launch(&self) -> Option<f32> {
+        let launch = self.launch_position?;
+        self.latest_data().map(|frame| launch.distance_to(&frame))
+    }
+
+    /// Gets the bearing from the launch position to the latest position, in *°* (degrees).
+    ///
+    /// It's `None` if the launch position is not known yet or if there is no latest data.
+    #[must_use]
+    pub fn bearing_from_launch(&self) -> Option<f32> {
+        let launch = self.launch_position?;
+        self.latest_data().map(|frame| launch.bearing_to(&frame))
+    }
+
+    /// Gets the history of the latest valid frames.
+    #[must_use]
+    pub fn history(&self) -> &FrameHistory {
+        &self.history
     }
 
-    /// Parses a GPS frame.
-    fn parse_frame(line: Result<String, io::Error>) -> Result<Frame, Error> {
-        let _line_str = line?; //                 if (bytes_ordered > 9)
-                               // 		{
-                               // 			return true;
-                               // 		}
+    /// Stores a new frame received from the primary GPS.
+    pub fn update(&mut self, frame: Frame) {
+        self.latest_data = Some(frame);
+        self.process(frame);
+    }
 
-        // 		gettimeofday(&time_now, NULL);
-        // 		ms_now = (long)((time_now.tv_sec)*1000 + (time_now.tv_usec)/1000);
+    /// Stores a new frame received from the backup GPS.
+    pub fn update_backup(&mut self, frame: Frame) {
+        self.backup_data = Some(frame);
+        self.process(frame);
+    }
 
-        // 		if (this->serial->available())
-        // 		{
-        // 			byte = this->serial->read_byte();
-        // 			if (byte == ack_packet[bytes_ordered])
-        // 			{
-        // 				bytes_ordered++;
-        // 			}
-        // 			else
-        // 			{
-        // 				bytes_ordered = 0;
-        // 			}
-        // }
-        unimplemented!()
+    /// Updates the ascent rate and the history with the new frame, if it's the selected one and
+    /// valid.
+    ///
+    /// The first of these frames is recorded as the launch position.
+    fn process(&mut self, frame: Frame) {
+        if self.select(frame.fix_time()) == Some(frame) && frame.is_valid() {
+            if self.launch_position.is_none() {
+                info!(
+                    latitude = frame.latitude(),
+                    longitude = frame.longitude(),
+                    altitude = frame.altitude(),
+                    "Launch position recorded."
+                );
+                self.launch_position = Some(frame);
+            }
+            self.ascent_rate.update(&frame);
+            self.history.push(frame);
+        }
     }
 }
 
@@ -228,7 +415,11 @@ impl Drop for Gps {
 }
 
 /// This structure represents a GPS frame.
-#[derive(Debug, Clone, Copy)]
+///
+/// It can be serialized, for example to JSON, with the same field names as its getters. The fix
+/// time is serialized as an RFC 3339 string, and the status as `"A"` or `"V"`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Frame {
     /// Time of the current fix.
     fix_time: DateTime<Utc>,
@@ -255,6 +446,61 @@ pub struct Frame {
 }
 
 impl Frame {
+    /// Creates a new GPS frame.
+    ///
+    /// Dilutions of precision and velocity will be zero, they can be set with
+    /// [`with_dop()`](#method.with_dop) and [`with_velocity()`](#method.with_velocity).
+    #[must_use]
+    pub fn new(
+        fix_time: DateTime<Utc>,
+        status: FixStatus,
+        satellites: u8,
+        latitude: f32,
+        longitude: f32,
+        altitude: f32,
+    ) -> Self {
+        Self {
+            fix_time,
+            status,
+            satellites,
+            latitude,
+            longitude,
+            altitude,
+            pdop: 0_f32,
+            hdop: 0_f32,
+            vdop: 0_f32,
+            speed: 0_f32,
+            course: 0_f32,
+        }
+    }
+
+    /// Sets the position, horizontal and vertical dilutions of precision of the frame.
+    #[must_use]
+    pub fn with_dop(self, pdop: f32, hdop: f32, vdop: f32) -> Self {
+        Self {
+            pdop,
+            hdop,
+            vdop,
+            ..self
+        }
+    }
+
+    /// Sets the dilutions of precision of the frame from a GSA sentence of the same fix.
+    #[must_use]
+    pub fn with_gsa(self, gsa: &Gsa) -> Self {
+        self.with_dop(gsa.pdop, gsa.hdop, gsa.vdop)
+    }
+
+    /// Sets the speed, in *m/s*, and course, in *°* (degrees), of the frame.
+    #[must_use]
+    pub fn with_velocity(self, speed: f32, course: f32) -> Self {
+        Self {
+            speed,
+            course,
+            ..self
+        }
+    }
+
     /// Gets the time of the current fix.
     pub fn fix_time(&self) -> DateTime<Utc> {
         self.fix_time
@@ -270,6 +516,12 @@ impl Frame {
         self.status == FixStatus::Active
     }
 
+    /// Checks if the frame is from a valid fix that is not stale at the given time.
+    #[must_use]
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.is_valid() && now - self.fix_time <= chrono::Duration::seconds(STALE_FIX_TIME)
+    }
+
     /// Gets the number of satellites connected.
     pub fn satellites(&self) -> u8 {
         self.satellites
@@ -314,14 +566,495 @@ impl Frame {
     pub fn course(&self) -> f32 {
         self.course
     }
+
+    /// Gets the great circle distance to the position of another frame, in *m*.
+    ///
+    /// The altitude is not taken into account.
+    #[must_use]
+    pub fn distance_to(&self, other: &Self) -> f32 {
+        let (latitude_1, latitude_2) = (
+            f64::from(self.latitude).to_radians(),
+            f64::from(other.latitude).to_radians(),
+        );
+        let latitude_delta = latitude_2 - latitude_1;
+        let longitude_delta = f64::from(other.longitude - self.longitude).to_radians();
+
+        let a = (latitude_delta / 2_f64).sin().powi(2)
+            + latitude_1.cos() * latitude_2.cos() * (longitude_delta / 2_f64).sin().powi(2);
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            (2_f64 * EARTH_RADIUS * a.sqrt().asin()) as f32
+        }
+    }
+
+    /// Gets the initial bearing of the great circle path to the position of another frame, in
+    /// *°* (degrees), between 0 and 360, clockwise from the north.
+    #[must_use]
+    pub fn bearing_to(&self, other: &Self) -> f32 {
+        let (latitude_1, latitude_2) = (
+            f64::from(self.latitude).to_radians(),
+            f64::from(other.latitude).to_radians(),
+        );
+        let longitude_delta = f64::from(other.longitude - self.longitude).to_radians();
+
+        let y = longitude_delta.sin() * latitude_2.cos();
+        let x = latitude_1.cos() * latitude_2.sin()
+            - latitude_1.sin() * latitude_2.cos() * longitude_delta.cos();
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            y.atan2(x).to_degrees().rem_euclid(360_f64) as f32
+        }
+    }
+}
+
+/// GPS receiver.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Receiver {
+    /// Primary GPS.
+    #[default]
+    Primary,
+    /// Backup GPS.
+    Backup,
+}
+
+impl fmt::Display for Receiver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Receiver::Primary => "primary",
+                Receiver::Backup => "backup",
+            }
+        )
+    }
+}
+
+/// Kind of GPS reset, that determines the navigation data cleared before restarting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetKind {
+    /// Hot start, keeping all the navigation data.
+    Hot,
+    /// Warm start, clearing the ephemeris.
+    Warm,
+    /// Cold start, clearing all the navigation data.
+    Cold,
+}
+
+impl ResetKind {
+    /// Gets the mask of the battery backed RAM sections to clear in the reset.
+    fn nav_bbr_mask(self) -> u16 {
+        match self {
+            ResetKind::Hot => 0x0000,
+            ResetKind::Warm => 0x0001,
+            ResetKind::Cold => 0xFFFF,
+        }
+    }
+}
+
+impl fmt::Display for ResetKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                ResetKind::Hot => "hot",
+                ResetKind::Warm => "warm",
+                ResetKind::Cold => "cold",
+            }
+        )
+    }
+}
+
+/// Generates the UBX CFG-RST message for the given kind of reset.
+///
+/// Only the GNSS subsystem is restarted, with a controlled software reset, so that the serial
+/// connection is kept.
+#[must_use]
+pub fn cfg_rst(kind: ResetKind) -> [u8; 12] {
+    let [mask_low, mask_high] = kind.nav_bbr_mask().to_le_bytes();
+    let mut msg = [
+        // Header, class, ID, Length
+        0xB5, 0x62, 0x06, 0x04, 0x04, 0x00,
+        // Payload:
+        // BBR sections to clear, reset mode (GNSS only), reserved
+        mask_low, mask_high, 0x02, 0x00, // Checksum
+        0x00, 0x00,
+    ];
+    let (ck_a, ck_b) = ubx_checksum(&msg[2..10]);
+    msg[10] = ck_a;
+    msg[11] = ck_b;
+    msg
+}
+
+/// Sends the reset message to the GPS, and waits until it outputs NMEA sentences again.
+fn reset_receiver<S>(serial: &mut S, kind: ResetKind) -> Result<(), Error>
+where
+    S: Write + Read,
+{
+    serial
+        .write_all(&cfg_rst(kind))
+        .context(error::Gps::Reset)?;
+    serial.flush().context(error::Gps::Reset)?;
+
+    // Give the module some time to stop the output before the reset.
+    thread::sleep(Duration::from_millis(100));
+    let start = Instant::now();
+    while start.elapsed() < RESET_TIMEOUT {
+        let mut byte = [0];
+        match serial.read(&mut byte) {
+            Ok(1) if byte[0] == b'$' => return Ok(()),
+            Ok(0) => thread::sleep(Duration::from_millis(10)),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(Error::new(e).context(error::Gps::Reset)),
+        }
+    }
+
+    Err(Error::msg(format!(
+        "no output in {} seconds after the reset",
+        RESET_TIMEOUT.as_secs()
+    ))
+    .context(error::Gps::Reset))
+}
+
+/// Generates the UBX CFG-RATE message setting the given navigation rate, in *Hz*.
+///
+/// The measurement period is rounded down to whole milliseconds, with one navigation solution per
+/// measurement, aligned to GPS time.
+#[must_use]
+pub fn cfg_rate(nav_rate_hz: u8) -> [u8; 14] {
+    let [period_low, period_high] = (1_000 / u16::from(nav_rate_hz.max(1))).to_le_bytes();
+    let mut msg = [
+        // Header, class, ID, Length
+        0xB5,
+        0x62,
+        0x06,
+        0x08,
+        0x06,
+        0x00,
+        // Payload:
+        // Measurement period (ms), navigation rate (cycles), time reference (GPS)
+        period_low,
+        period_high,
+        0x01,
+        0x00,
+        0x01,
+        0x00, // Checksum
+        0x00,
+        0x00,
+    ];
+    let (ck_a, ck_b) = ubx_checksum(&msg[2..12]);
+    msg[12] = ck_a;
+    msg[13] = ck_b;
+    msg
+}
+
+/// Computes the 8-bit Fletcher checksum of an UBX message, from its class to its payload.
+fn ubx_checksum(bytes: &[u8]) -> (u8, u8) {
+    bytes.iter().fold((0_u8, 0_u8), |(ck_a, ck_b), &byte| {
+        let ck_a = ck_a.wrapping_add(byte);
+        (ck_a, ck_b.wrapping_add(ck_a))
+    })
+}
+
+/// Checks if the GPS with the given power pin is on.
+fn is_on(power_gpio: Pin) -> Result<bool, Error> {
+    Ok(power_gpio.get_value()? == 1)
+}
+
+/// Turns the GPS with the given power pin on.
+fn turn_on(power_gpio: Pin) -> Result<(), Error> {
+    if is_on(power_gpio)? {
+        warn!("Turning on the GPS but it was already on.");
+    } else {
+        power_gpio.set_value(1)?
+    }
+
+    Ok(())
+}
+
+/// Turns the GPS with the given power pin off.
+fn turn_off(power_gpio: Pin) -> Result<(), Error> {
+    if is_on(power_gpio)? {
+        power_gpio.set_value(0)?
+    } else {
+        warn!("Turning off the GPS but it was already off.");
+    }
+
+    Ok(())
+}
+
+/// Starts reading the frames of the given GPS receiver in a background thread, storing them in
+/// [`GPS`](static.GPS.html).
+fn spawn_reader(receiver: Receiver, serial: Box<dyn SerialPort>) -> Result<(), Error> {
+    let _ = thread::Builder::new()
+        .name(format!("{receiver} GPS"))
+        .spawn(move || {
+            let mut store = |frame| {
+                let mut gps = match GPS.lock() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => {
+                        error!("The GPS mutex was poisoned.");
+                        poisoned.into_inner()
+                    }
+                };
+                match receiver {
+                    Receiver::Primary => gps.update(frame),
+                    Receiver::Backup => gps.update_backup(frame),
+                }
+            };
+            if let Err(e) = read_frames(BufReader::new(serial), &mut store) {
+                error!(error = %e, "Error reading the {} GPS frames.", receiver);
+            }
+        })
+        .context(error::Gps::Init)?;
+    info!("Reading the {} GPS frames.", receiver);
+
+    Ok(())
+}
+
+/// Reads NMEA sentences from the given reader until `EOF`, storing the assembled frames.
+///
+/// Invalid sentences are logged and ignored. Read timeouts are ignored too, so that this can be
+/// used with a serial connection: the part of the sentence read before the timeout is kept, and
+/// the rest of it is read in the next iteration.
+///
+/// # Errors
+///
+/// Returns an error if the reader fails, except on timeouts.
+fn read_frames<R, F>(mut reader: R, store: &mut F) -> Result<(), io::Error>
+where
+    R: BufRead,
+    F: FnMut(Frame) + ?Sized,
+{
+    let mut assembler = FrameAssembler::default();
+    let mut line = Vec::new();
+    loop {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e),
+        }
+
+        // The UBX acknowledgements are binary, so they can come before the sentence.
+        let text = String::from_utf8_lossy(&line);
+        if let Some(start) = text.find('$') {
+            match assembler.push(&text[start..]) {
+                Ok(Some(frame)) => store(frame),
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Invalid GPS sentence."),
+            }
+        }
+        line.clear();
+    }
+}
+
+/// Gets the number of frames kept in the history of the GPS data.
+///
+/// It's at least [`FRAME_HISTORY_CAPACITY`], and enough frames at the configured navigation rate
+/// for the landing detection.
+#[must_use]
+pub fn history_capacity() -> usize {
+    let landing_frames = (u64::from(CONFIG.flight().landing_stable_secs()) + 2)
+        * u64::from(CONFIG.gps().nav_rate_hz());
+    FRAME_HISTORY_CAPACITY.max(usize::try_from(landing_frames).unwrap_or(usize::MAX))
+}
+
+/// Ascent rate filter.
+///
+/// The ascent rate between two consecutive frames is very jittery, since the GPS altitude has an
+/// error of several meters. This filter keeps the instantaneous rate, and its moving average over
+/// the last `window` frames, that should be used to detect the flight events.
+#[derive(Debug, Clone)]
+pub struct AscentRate {
+    /// Number of instantaneous rates averaged.
+    window: usize,
+    /// Time and altitude of the previous frame.
+    previous: Option<(DateTime<Utc>, f32)>,
+    /// Latest instantaneous rates, in *m/s*.
+    rates: VecDeque<f32>,
+}
+
+impl Default for AscentRate {
+    fn default() -> Self {
+        Self::new(DEFAULT_RATE_WINDOW)
+    }
+}
+
+impl AscentRate {
+    /// Creates a new filter averaging the given number of samples.
+    ///
+    /// A window of 0 is treated as 1, that is, no smoothing.
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            previous: None,
+            rates: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Adds a new frame to the filter.
+    ///
+    /// Frames that are not newer than the previous one are ignored.
+    pub fn update(&mut self, frame: &Frame) {
+        let (time, altitude) = (frame.fix_time(), frame.altitude());
+        if let Some((previous_time, previous_altitude)) = self.previous {
+            #[allow(clippy::cast_precision_loss)]
+            let elapsed = (time - previous_time).num_milliseconds() as f32 / 1_000_f32;
+            if elapsed <= 0_f32 {
+                return;
+            }
+
+            if self.rates.len() == self.window {
+                let _ = self.rates.pop_front();
+            }
+            self.rates
+                .push_back((altitude - previous_altitude) / elapsed);
+        }
+        self.previous = Some((time, altitude));
+    }
+
+    /// Gets the instantaneous ascent rate between the two latest frames, in *m/s*.
+    #[must_use]
+    pub fn instantaneous(&self) -> Option<f32> {
+        self.rates.back().copied()
+    }
+
+    /// Gets the average of the latest instantaneous ascent rates, in *m/s*.
+    #[must_use]
+    pub fn smoothed(&self) -> Option<f32> {
+        if self.rates.is_empty() {
+            None
+        } else {
+            #[allow(clippy::cast_precision_loss)]
+            Some(self.rates.iter().sum::<f32>() / self.rates.len() as f32)
+        }
+    }
+}
+
+/// History of the latest valid GPS frames.
+///
+/// It keeps the last [`FRAME_HISTORY_CAPACITY`] frames, so that the features that need the recent
+/// trajectory of the probe can share it.
+#[derive(Debug, Clone)]
+pub struct FrameHistory {
+    /// Maximum number of frames kept.
+    capacity: usize,
+    /// Latest frames, from older to newer.
+    frames: Vec<Frame>,
+}
+
+impl Default for FrameHistory {
+    fn default() -> Self {
+        Self::new(FRAME_HISTORY_CAPACITY)
+    }
+}
+
+impl FrameHistory {
+    /// Creates a new history keeping the given number of frames.
+    ///
+    /// A capacity of 0 is treated as 1, that is, only the latest frame.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            frames: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds a new frame to the history, evicting the oldest one if it's full.
+    pub fn push(&mut self, frame: Frame) {
+        if self.frames.len() == self.capacity {
+            let _ = self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+
+    /// Gets the frames in the history, from older to newer.
+    #[must_use]
+    pub fn recent(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Gets the latest frames spanning at least the given time, from older to newer.
+    ///
+    /// The window starts at the newest frame that is at least `span` older than the latest one.
+    /// It's `None` if the history does not cover that span yet.
+    #[must_use]
+    pub fn window(&self, span: chrono::Duration) -> Option<&[Frame]> {
+        let latest = self.frames.last()?.fix_time();
+        let start = self
+            .frames
+            .iter()
+            .rposition(|frame| latest - frame.fix_time() >= span)?;
+        Some(&self.frames[start..])
+    }
+
+    /// Gets the horizontal distance traveled along the frames in the history, in *m*.
+    #[must_use]
+    pub fn distance_traveled(&self) -> f32 {
+        self.frames
+            .windows(2)
+            .map(|pair| pair[0].distance_to(&pair[1]))
+            .sum()
+    }
+
+    /// Gets the altitude trend of the frames in the history, in *m/s*.
+    ///
+    /// It's the slope of the least squares line fitting the altitudes over time, much less
+    /// sensitive to the altitude error of single frames than the ascent rate between two of them.
+    /// It's `None` if there are not at least two frames with different times.
+    #[must_use]
+    pub fn altitude_trend(&self) -> Option<f32> {
+        let first = self.frames.first()?.fix_time();
+        #[allow(clippy::cast_precision_loss)]
+        let points: Vec<_> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                (
+                    (frame.fix_time() - first).num_milliseconds() as f32 / 1_000_f32,
+                    frame.altitude(),
+                )
+            })
+            .collect();
+
+        #[allow(clippy::cast_precision_loss)]
+        let count = points.len() as f32;
+        let mean_time = points.iter().map(|&(time, _)| time).sum::<f32>() / count;
+        let mean_altitude = points.iter().map(|&(_, altitude)| altitude).sum::<f32>() / count;
+        let (covariance, variance) = points.iter().fold(
+            (0_f32, 0_f32),
+            |(covariance, variance), &(time, altitude)| {
+                (
+                    covariance + (time - mean_time) * (altitude - mean_altitude),
+                    variance + (time - mean_time).powi(2),
+                )
+            },
+        );
+
+        if variance > 0_f32 {
+            Some(covariance / variance)
+        } else {
+            None
+        }
+    }
 }
 
 /// GPS fix status.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FixStatus {
     /// GPS fix active.
+    #[serde(rename = "A")]
     Active,
     /// GPS fix not valid.
+    #[serde(rename = "V")]
     Void,
 }
 
@@ -352,9 +1085,601 @@ impl fmt::Display for FixStatus {
     }
 }
 
+/// Fix mode, as reported in the GSA sentences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixMode {
+    /// No fix available.
+    NoFix,
+    /// 2D fix, without altitude.
+    Fix2D,
+    /// 3D fix.
+    Fix3D,
+}
+
+/// GSA sentence, with the dilutions of precision and the satellites used in the fix.
+///
+/// The RMC and GGA sentences don't carry the vertical dilution of precision, so it's read from
+/// the GSA sentence of the same fix, that is kept enabled when initializing the GPS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gsa {
+    /// Fix mode.
+    mode: FixMode,
+    /// Number of satellites used in the fix.
+    satellites: u8,
+    /// Position dilution of precision (3D).
+    pdop: f32,
+    /// Horizontal dilution of precision (2D).
+    hdop: f32,
+    /// Vertical dilution of precision (1D).
+    vdop: f32,
+}
+
+impl Gsa {
+    /// Gets the fix mode.
+    #[must_use]
+    pub fn mode(&self) -> FixMode {
+        self.mode
+    }
+
+    /// Gets the number of satellites used in the fix.
+    #[must_use]
+    pub fn satellites(&self) -> u8 {
+        self.satellites
+    }
+
+    /// Gets the position dilution of precision (3D).
+    #[must_use]
+    pub fn pdop(&self) -> f32 {
+        self.pdop
+    }
+
+    /// Gets the horizontal dilution of precision (2D).
+    #[must_use]
+    pub fn hdop(&self) -> f32 {
+        self.hdop
+    }
+
+    /// Gets the vertical dilution of precision (1D).
+    #[must_use]
+    pub fn vdop(&self) -> f32 {
+        self.vdop
+    }
+}
+
+impl FromStr for Gsa {
+    type Err = error::Gps;
+
+    /// Parses a `$--GSA` sentence from any talker, checking its checksum.
+    fn from_str(s: &str) -> Result<Gsa, Self::Err> {
+        let invalid = || error::Gps::InvalidSentence {
+            sentence: s.to_owned(),
+        };
+
+        let fields = nmea_fields(s).ok_or_else(invalid)?;
+        // NMEA 4.1 adds the GNSS system ID at the end.
+        if !fields[0].ends_with("GSA") || !(fields.len() == 18 || fields.len() == 19) {
+            return Err(invalid());
+        }
+
+        let mode = match fields[2] {
+            "1" => FixMode::NoFix,
+            "2" => FixMode::Fix2D,
+            "3" => FixMode::Fix3D,
+            _ => return Err(invalid()),
+        };
+        let dop = |field: &str| -> Result<f32, Self::Err> {
+            if field.is_empty() {
+                Ok(0_f32)
+            } else {
+                field.parse().map_err(|_| invalid())
+            }
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        let satellites = fields[3..15]
+            .iter()
+            .filter(|field| !field.is_empty())
+            .count() as u8;
+        Ok(Gsa {
+            mode,
+            satellites,
+            pdop: dop(fields[15])?,
+            hdop: dop(fields[16])?,
+            vdop: dop(fields[17])?,
+        })
+    }
+}
+
+/// Speed of one knot, in *m/s*.
+const KNOT: f32 = 0.514_444;
+
+/// RMC sentence, with the date, the status, the position and the velocity of a fix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rmc {
+    /// Time of the fix, if the GPS knows it.
+    time: Option<NaiveTime>,
+    /// Date of the fix, if the GPS knows it.
+    date: Option<NaiveDate>,
+    /// GPS fix status.
+    status: FixStatus,
+    /// Latitude, in *°* (degrees).
+    latitude: f32,
+    /// Longitude, in *°* (degrees).
+    longitude: f32,
+    /// Speed over ground, in *m/s*.
+    speed: f32,
+    /// Course over ground, in *°* (degrees).
+    course: f32,
+}
+
+impl FromStr for Rmc {
+    type Err = error::Gps;
+
+    /// Parses a `$--RMC` sentence from any talker, checking its checksum.
+    fn from_str(s: &str) -> Result<Rmc, Self::Err> {
+        let invalid = || error::Gps::InvalidSentence {
+            sentence: s.to_owned(),
+        };
+
+        let fields = nmea_fields(s).ok_or_else(invalid)?;
+        // NMEA 2.3 adds the mode indicator, and NMEA 4.1 the navigational status.
+        if !fields[0].ends_with("RMC") || !(12..=14).contains(&fields.len()) {
+            return Err(invalid());
+        }
+
+        Ok(Rmc {
+            time: nmea_time(fields[1]).map_err(|_| invalid())?,
+            date: if fields[9].is_empty() {
+                None
+            } else {
+                Some(NaiveDate::parse_from_str(fields[9], "%d%m%y").map_err(|_| invalid())?)
+            },
+            status: fields[2].parse()?,
+            latitude: nmea_coordinate(fields[3], fields[4]).ok_or_else(invalid)?,
+            longitude: nmea_coordinate(fields[5], fields[6]).ok_or_else(invalid)?,
+            speed: nmea_number::<f32>(fields[7]).ok_or_else(invalid)? * KNOT,
+            course: nmea_number(fields[8]).ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// GGA sentence, with the satellites, the horizontal dilution of precision and the altitude of a
+/// fix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Gga {
+    /// Time of the fix, if the GPS knows it.
+    time: Option<NaiveTime>,
+    /// Number of satellites used in the fix.
+    satellites: u8,
+    /// Horizontal dilution of precision (2D).
+    hdop: f32,
+    /// Altitude from sea level, in *m*.
+    altitude: f32,
+}
+
+impl FromStr for Gga {
+    type Err = error::Gps;
+
+    /// Parses a `$--GGA` sentence from any talker, checking its checksum.
+    fn from_str(s: &str) -> Result<Gga, Self::Err> {
+        let invalid = || error::Gps::InvalidSentence {
+            sentence: s.to_owned(),
+        };
+
+        let fields = nmea_fields(s).ok_or_else(invalid)?;
+        if !fields[0].ends_with("GGA") || fields.len() != 15 {
+            return Err(invalid());
+        }
+
+        Ok(Gga {
+            time: nmea_time(fields[1]).map_err(|_| invalid())?,
+            satellites: nmea_number(fields[7]).ok_or_else(invalid)?,
+            hdop: nmea_number(fields[8]).ok_or_else(invalid)?,
+            altitude: nmea_number(fields[9]).ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// Assembler of GPS frames from the NMEA sentences of each navigation solution.
+///
+/// The uBLOX module outputs the RMC, GGA and GSA sentences of each solution in that order. The RMC
+/// sentence carries the date, the status and the velocity, the GGA sentence the satellites and
+/// the altitude, and the GSA sentence the dilutions of precision, so a frame is completed with the
+/// first GSA sentence after the RMC and GGA sentences of the same fix. If there is no GSA
+/// sentence, the frame is completed without it when the RMC sentence of the next solution arrives.
+#[derive(Debug, Default)]
+struct FrameAssembler {
+    /// RMC sentence of the current solution.
+    rmc: Option<Rmc>,
+    /// GGA sentence of the current solution.
+    gga: Option<Gga>,
+}
+
+impl FrameAssembler {
+    /// Adds an NMEA sentence, returning the frame it completes, if any.
+    ///
+    /// Sentences other than RMC, GGA and GSA are ignored.
+    fn push(&mut self, sentence: &str) -> Result<Option<Frame>, error::Gps> {
+        match sentence.get(3..6) {
+            Some("RMC") => {
+                let rmc = sentence.parse()?;
+                let previous = self.take();
+                self.rmc = Some(rmc);
+                Ok(previous)
+            }
+            Some("GGA") => {
+                let gga: Gga = sentence.parse()?;
+                if gga.time.is_some() && self.rmc.is_some_and(|rmc| rmc.time == gga.time) {
+                    self.gga = Some(gga);
+                }
+                Ok(None)
+            }
+            Some("GSA") => {
+                let gsa: Gsa = sentence.parse()?;
+                Ok(self.take().map(|frame| frame.with_gsa(&gsa)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Takes the frame of the current solution, if its RMC and GGA sentences were received and the
+    /// GPS knows the time of the fix.
+    fn take(&mut self) -> Option<Frame> {
+        let (rmc, gga) = (self.rmc.take()?, self.gga.take()?);
+        let fix_time = rmc.date?.and_time(rmc.time?).and_utc();
+        Some(
+            Frame::new(
+                fix_time,
+                rmc.status,
+                gga.satellites,
+                rmc.latitude,
+                rmc.longitude,
+                gga.altitude,
+            )
+            .with_dop(0_f32, gga.hdop, 0_f32)
+            .with_velocity(rmc.speed, rmc.course),
+        )
+    }
+}
+
+/// Parses a numeric field of an NMEA sentence, that is zero if it's empty.
+fn nmea_number<T>(field: &str) -> Option<T>
+where
+    T: FromStr + Default,
+{
+    if field.is_empty() {
+        Some(T::default())
+    } else {
+        field.parse().ok()
+    }
+}
+
+/// Parses the `hhmmss.ss` time field of an NMEA sentence, that is `None` if it's empty.
+fn nmea_time(field: &str) -> Result<Option<NaiveTime>, chrono::ParseError> {
+    if field.is_empty() {
+        Ok(None)
+    } else {
+        NaiveTime::parse_from_str(field, "%H%M%S%.f").map(Some)
+    }
+}
+
+/// Parses a `(d)ddmm.mmmm` coordinate of an NMEA sentence, with its hemisphere, in *°* (degrees).
+///
+/// Southern latitudes and western longitudes are negative, and empty coordinates are zero.
+fn nmea_coordinate(value: &str, hemisphere: &str) -> Option<f32> {
+    let raw: f64 = nmea_number(value)?;
+    let degrees = (raw / 100_f64).trunc();
+    let coordinate = degrees + (raw - degrees * 100_f64) / 60_f64;
+    #[allow(clippy::cast_possible_truncation)]
+    match hemisphere {
+        "N" | "E" | "" => Some(coordinate as f32),
+        "S" | "W" => Some(-coordinate as f32),
+        _ => None,
+    }
+}
+
+/// Splits an NMEA sentence in its fields, if its checksum is valid.
+///
+/// The first field is the address, with the talker and the sentence type.
+fn nmea_fields(sentence: &str) -> Option<Vec<&str>> {
+    let (data, checksum) = sentence.trim_end().strip_prefix('$')?.split_once('*')?;
+    let checksum = u8::from_str_radix(checksum, 16).ok()?;
+    if data.bytes().fold(0, |sum, byte| sum ^ byte) == checksum {
+        Some(data.split(',').collect())
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{FixStatus, GPS};
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{
+        cfg_rate, cfg_rst, read_frames, AscentRate, FixMode, FixStatus, Frame, FrameHistory, Gps,
+        Gsa, Receiver, ResetKind, GPS,
+    };
+
+    /// Checks the parsing of a GSA sentence with a 3D fix, and its merge into a frame.
+    #[test]
+    fn gsa_3d_fix() {
+        let gsa: Gsa = "$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39\r\n"
+            .parse()
+            .unwrap();
+        assert_eq!(gsa.mode(), FixMode::Fix3D);
+        assert_eq!(gsa.satellites(), 5);
+        assert!((gsa.pdop() - 2.5).abs() < f32::EPSILON);
+        assert!((gsa.hdop() - 1.3).abs() < f32::EPSILON);
+        assert!((gsa.vdop() - 2.1).abs() < f32::EPSILON);
+
+        let time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let frame = Frame::new(time, FixStatus::Active, 5, 42.5, -2.75, 1_000.0).with_gsa(&gsa);
+        assert!((frame.pdop() - 2.5).abs() < f32::EPSILON);
+        assert!((frame.hdop() - 1.3).abs() < f32::EPSILON);
+        assert!((frame.vdop() - 2.1).abs() < f32::EPSILON);
+    }
+
+    /// Checks other GSA sentences, and invalid ones.
+    #[test]
+    fn gsa_sentences() {
+        // NMEA 4.1, with the GNSS system ID, and no fix.
+        let gsa: Gsa = "$GNGSA,A,1,,,,,,,,,,,,,,,,1*1D".parse().unwrap();
+        assert_eq!(gsa.mode(), FixMode::NoFix);
+        assert_eq!(gsa.satellites(), 0);
+
+        let gsa: Gsa = "$GPGSA,A,2,04,05,09,,,,,,,,,,3.1,2.4,*11".parse().unwrap();
+        assert_eq!(gsa.mode(), FixMode::Fix2D);
+        assert_eq!(gsa.satellites(), 3);
+        assert!(gsa.vdop().abs() < f32::EPSILON);
+
+        // Wrong checksum, missing checksum, other sentences.
+        assert!("$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*38"
+            .parse::<Gsa>()
+            .is_err());
+        assert!("$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1"
+            .parse::<Gsa>()
+            .is_err());
+        assert!("$GPGGA,,,,,,0,00,,,M,,M,,*66".parse::<Gsa>().is_err());
+    }
+
+    /// Checks that the frames are assembled from the RMC, GGA and GSA sentences of each fix.
+    #[test]
+    fn frame_assembly() {
+        let output = "\u{b5}b\u{5}\u{1}$GPRMC,140000.00,A,4230.00000,N,00245.00000,W,10.000,90.00,\
+                      250317,,,A*75\r\n\
+                      $GPGGA,140000.00,4230.00000,N,00245.00000,W,1,08,1.30,1000.0,M,50.0,M,,*73\r\n\
+                      $GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39\r\n\
+                      $GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*38\r\n\
+                      $GPRMC,140000.10,A,4230.00000,N,00245.00000,W,10.000,90.00,250317,,,A*74\r\n\
+                      $GPGGA,140000.10,4230.00000,N,00245.00000,W,1,08,1.30,1000.5,M,50.0,M,,*77\r\n\
+                      $GPRMC,,V,,,,,,,,,,N*53\r\n\
+                      $GPGGA,,,,,,0,00,99.99,,,,,,*48\r\n\
+                      $GPGSA,A,1,,,,,,,,,,,,,99.99,99.99,99.99*30\r\n\
+                      $GPRMC,140001.00,V,,,,,,,250317,,,N*7B\r\n\
+                      $GPGGA,140001.00,,,,,0,00,99.99,,,,,,*62\r\n\
+                      $GPGSA,A,1,,,,,,,,,,,,,99.99,99.99,99.99*30\r\n";
+        let mut frames = Vec::new();
+        read_frames(output.as_bytes(), &mut |frame| frames.push(frame)).unwrap();
+
+        let time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        assert_eq!(frames.len(), 3);
+        let frame = frames[0];
+        assert_eq!(frame.fix_time(), time);
+        assert_eq!(frame.status(), FixStatus::Active);
+        assert_eq!(frame.satellites(), 8);
+        assert!((frame.latitude() - 42.5).abs() < f32::EPSILON);
+        assert!((frame.longitude() + 2.75).abs() < f32::EPSILON);
+        assert!((frame.altitude() - 1_000.0).abs() < f32::EPSILON);
+        assert!((frame.pdop() - 2.5).abs() < f32::EPSILON);
+        assert!((frame.hdop() - 1.3).abs() < f32::EPSILON);
+        assert!((frame.vdop() - 2.1).abs() < f32::EPSILON);
+        assert!((frame.speed() - 5.144_44).abs() < 1e-4);
+        assert!((frame.course() - 90.0).abs() < f32::EPSILON);
+
+        // Without a GSA sentence, the frame is completed by the next RMC sentence, with the
+        // horizontal dilution of precision of the GGA sentence.
+        let frame = frames[1];
+        assert_eq!(frame.fix_time(), time + Duration::milliseconds(100));
+        assert!((frame.altitude() - 1_000.5).abs() < f32::EPSILON);
+        assert!(frame.pdop().abs() < f32::EPSILON);
+        assert!((frame.hdop() - 1.3).abs() < f32::EPSILON);
+
+        // Void fixes are only assembled if the GPS knows the time.
+        let frame = frames[2];
+        assert_eq!(frame.fix_time(), time + Duration::seconds(1));
+        assert_eq!(frame.status(), FixStatus::Void);
+        assert_eq!(frame.satellites(), 0);
+        assert!((frame.vdop() - 99.99).abs() < f32::EPSILON);
+    }
+
+    /// Checks the generated CFG-RATE messages and their checksums.
+    #[test]
+    fn cfg_rate_message() {
+        assert_eq!(
+            cfg_rate(1),
+            [0xB5, 0x62, 0x06, 0x08, 0x06, 0x00, 0xE8, 0x03, 0x01, 0x00, 0x01, 0x00, 0x01, 0x39]
+        );
+        assert_eq!(
+            cfg_rate(5),
+            [0xB5, 0x62, 0x06, 0x08, 0x06, 0x00, 0xC8, 0x00, 0x01, 0x00, 0x01, 0x00, 0xDE, 0x6A]
+        );
+        // The original hard-coded 10 Hz message.
+        assert_eq!(
+            cfg_rate(10),
+            [0xB5, 0x62, 0x06, 0x08, 0x06, 0x00, 0x64, 0x00, 0x01, 0x00, 0x01, 0x00, 0x7A, 0x12]
+        );
+    }
+
+    /// Checks the generated CFG-RST messages and their checksums.
+    #[test]
+    fn cfg_rst_message() {
+        assert_eq!(
+            cfg_rst(ResetKind::Hot),
+            [0xB5, 0x62, 0x06, 0x04, 0x04, 0x00, 0x00, 0x00, 0x02, 0x00, 0x10, 0x68]
+        );
+        assert_eq!(
+            cfg_rst(ResetKind::Warm),
+            [0xB5, 0x62, 0x06, 0x04, 0x04, 0x00, 0x01, 0x00, 0x02, 0x00, 0x11, 0x6C]
+        );
+        assert_eq!(
+            cfg_rst(ResetKind::Cold),
+            [0xB5, 0x62, 0x06, 0x04, 0x04, 0x00, 0xFF, 0xFF, 0x02, 0x00, 0x0E, 0x61]
+        );
+    }
+
+    /// Checks that the reset waits for the output of the GPS.
+    #[test]
+    fn reset_wait() {
+        use std::io::{self, Cursor, Read, Write};
+
+        use super::reset_receiver;
+
+        /// Fake serial port, recording the written bytes.
+        struct Serial {
+            /// Bytes to read.
+            output: Cursor<Vec<u8>>,
+            /// Written bytes.
+            input: Vec<u8>,
+        }
+
+        impl Read for Serial {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.output.read(buf)
+            }
+        }
+
+        impl Write for Serial {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.input.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut serial = Serial {
+            output: Cursor::new(b"\x00\xFF$GPGGA,".to_vec()),
+            input: Vec::new(),
+        };
+        reset_receiver(&mut serial, ResetKind::Cold).unwrap();
+        assert_eq!(serial.input, cfg_rst(ResetKind::Cold));
+        assert_eq!(serial.output.position(), 3);
+    }
+
+    /// Checks that the frame history keeps only the latest frames, and the windows over them.
+    #[test]
+    fn frame_history_capacity() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let mut history = FrameHistory::new(3);
+        assert!(history.recent().is_empty());
+        assert_eq!(history.altitude_trend(), None);
+
+        let frames: Vec<_> = (0..5)
+            .map(|second| {
+                Frame::new(
+                    start + Duration::seconds(second),
+                    FixStatus::Active,
+                    8,
+                    42.5,
+                    -2.75,
+                    1_000.0,
+                )
+            })
+            .collect();
+        for &frame in &frames {
+            history.push(frame);
+        }
+        assert_eq!(history.recent(), &frames[2..]);
+        assert_eq!(history.window(Duration::seconds(1)), Some(&frames[3..]));
+        assert_eq!(history.window(Duration::seconds(2)), Some(&frames[2..]));
+        assert_eq!(history.window(Duration::seconds(3)), None);
+
+        // Only the valid frames of the selected GPS are kept in the history of the GPS.
+        let mut gps = Gps::default();
+        gps.update(frames[0]);
+        gps.update(Frame::new(start, FixStatus::Void, 0, 0.0, 0.0, 0.0));
+        assert_eq!(gps.history().recent(), &frames[..1]);
+    }
+
+    /// Checks the distance traveled and the altitude trend of a synthetic ascent.
+    #[test]
+    fn frame_history_helpers() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let mut history = FrameHistory::default();
+
+        // 0.001° of latitude every 10 seconds, with a 5 m/s ascent and ±4 m of altitude error.
+        for step in 0..=30_i16 {
+            let error = if step % 2 == 0 { 4_f32 } else { -4_f32 };
+            history.push(Frame::new(
+                start + Duration::seconds(10 * i64::from(step)),
+                FixStatus::Active,
+                8,
+                42.5 + f32::from(step) * 0.001,
+                -2.75,
+                1_000_f32 + 50_f32 * f32::from(step) + error,
+            ));
+        }
+
+        // 0.03° of latitude are about 3.3 km.
+        assert!((history.distance_traveled() - 3_336_f32).abs() < 5_f32);
+        assert!((history.altitude_trend().unwrap() - 5_f32).abs() < 0.05);
+
+        // A single frame has no trend, nor distance traveled.
+        let mut history = FrameHistory::new(1);
+        history.push(Frame::new(
+            start,
+            FixStatus::Active,
+            8,
+            42.5,
+            -2.75,
+            1_000.0,
+        ));
+        assert_eq!(history.distance_traveled(), 0_f32);
+        assert_eq!(history.altitude_trend(), None);
+    }
+
+    /// Checks the distance and the bearing from the launch position.
+    #[test]
+    fn distance_from_launch() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let mut gps = Gps::default();
+
+        // No launch position is known before the first valid frame.
+        gps.update(Frame::new(start, FixStatus::Void, 0, 0.0, 0.0, 0.0));
+        assert_eq!(gps.launch_position(), None);
+        assert_eq!(gps.distance_from_launch(), None);
+        assert_eq!(gps.bearing_from_launch(), None);
+
+        let launch = Frame::new(
+            start + Duration::seconds(1),
+            FixStatus::Active,
+            8,
+            42.5,
+            -2.75,
+            1_000.0,
+        );
+        gps.update(launch);
+        assert_eq!(gps.launch_position(), Some(launch));
+        assert_eq!(gps.distance_from_launch(), Some(0_f32));
+
+        // 0.01° of latitude to the north and 0.01° of longitude to the east, about 1.4 km away.
+        gps.update(Frame::new(
+            start + Duration::seconds(60),
+            FixStatus::Active,
+            8,
+            42.51,
+            -2.74,
+            1_300.0,
+        ));
+        assert_eq!(gps.launch_position(), Some(launch));
+        assert!((gps.distance_from_launch().unwrap() - 1_381_f32).abs() < 5_f32);
+        assert!((gps.bearing_from_launch().unwrap() - 36.4).abs() < 0.5);
+
+        // Bearings are always between 0 and 360 degrees.
+        let west = Frame::new(start, FixStatus::Active, 8, 42.5, -2.76, 1_000.0);
+        assert!((launch.bearing_to(&west) - 270_f32).abs() < 0.1);
+    }
 
     /// Checks the GPS status from string conversion.
     #[test]
@@ -376,6 +1701,161 @@ mod tests {
         assert_eq!(format!("{}", FixStatus::Void), "V");
     }
 
+    /// Checks that the smoothed ascent rate follows a noisy synthetic ascent much more closely than
+    /// the raw one.
+    #[test]
+    fn ascent_rate_smoothing() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        // 5 m/s ascent, with a ±8 m altitude error alternating between frames.
+        let noise = [8_f32, -8_f32, 6_f32, -7_f32, 8_f32, -6_f32, 7_f32, -8_f32];
+        let mut filter = AscentRate::new(5);
+        assert_eq!(filter.smoothed(), None);
+
+        let mut max_raw_error = 0_f32;
+        let mut max_smoothed_error = 0_f32;
+        for (i, error) in (0_u8..40).zip(noise.iter().cycle()) {
+            let altitude = 500_f32 + 5_f32 * f32::from(i) + error;
+            filter.update(&Frame::new(
+                start + Duration::seconds(i64::from(i)),
+                FixStatus::Active,
+                8,
+                40.0,
+                3.0,
+                altitude,
+            ));
+            if i >= 5 {
+                max_raw_error = max_raw_error.max((filter.instantaneous().unwrap() - 5_f32).abs());
+                max_smoothed_error =
+                    max_smoothed_error.max((filter.smoothed().unwrap() - 5_f32).abs());
+            }
+        }
+
+        // The raw rate would trigger a 15 m/s burst detection.
+        assert!(max_raw_error > 10_f32);
+        assert!(max_smoothed_error < 4_f32);
+    }
+
+    /// Checks that repeated frames do not change the ascent rate.
+    #[test]
+    fn ascent_rate_repeated_frame() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let mut filter = AscentRate::new(0);
+        let first = Frame::new(start, FixStatus::Active, 8, 40.0, 3.0, 100.0);
+        let second = Frame::new(
+            start + Duration::seconds(2),
+            FixStatus::Active,
+            8,
+            40.0,
+            3.0,
+            90.0,
+        );
+
+        filter.update(&first);
+        filter.update(&second);
+        filter.update(&second);
+        assert_eq!(filter.instantaneous(), Some(-5_f32));
+        assert_eq!(filter.smoothed(), Some(-5_f32));
+    }
+
+    /// Checks that the backup GPS data is used when the primary GPS fix goes stale.
+    #[test]
+    fn backup_fallback() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let primary = Frame::new(start, FixStatus::Active, 8, 40.0, 3.0, 1_000.0);
+        let backup = Frame::new(
+            start + Duration::seconds(10),
+            FixStatus::Active,
+            6,
+            40.001,
+            3.001,
+            1_050.0,
+        );
+
+        let mut gps = Gps::default();
+        gps.update(primary);
+        gps.update_backup(backup);
+
+        // The primary fix is still recent.
+        let frame = gps.select(start + Duration::seconds(3)).unwrap();
+        assert_eq!(frame.satellites(), 8);
+        assert_eq!(gps.source(), Receiver::Primary);
+
+        // The primary fix is stale, the backup one is used.
+        let frame = gps.select(start + Duration::seconds(12)).unwrap();
+        assert_eq!(frame.satellites(), 6);
+        assert!((frame.altitude() - 1_050.0).abs() < f32::EPSILON);
+        assert_eq!(gps.source(), Receiver::Backup);
+
+        // The primary GPS recovers.
+        gps.update(Frame::new(
+            start + Duration::seconds(12),
+            FixStatus::Active,
+            8,
+            40.0,
+            3.0,
+            1_060.0,
+        ));
+        assert_eq!(
+            gps.select(start + Duration::seconds(13))
+                .unwrap()
+                .satellites(),
+            8
+        );
+        assert_eq!(gps.source(), Receiver::Primary);
+
+        // A void primary fix is never preferred over a valid backup fix.
+        gps.update(Frame::new(
+            start + Duration::seconds(13),
+            FixStatus::Void,
+            0,
+            0.0,
+            0.0,
+            0.0,
+        ));
+        gps.update_backup(Frame::new(
+            start + Duration::seconds(13),
+            FixStatus::Active,
+            6,
+            40.0,
+            3.0,
+            1_070.0,
+        ));
+        assert_eq!(
+            gps.select(start + Duration::seconds(13))
+                .unwrap()
+                .satellites(),
+            6
+        );
+    }
+
+    /// Checks that a fully populated frame round-trips through JSON.
+    #[test]
+    fn frame_json() {
+        let frame = Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 7).unwrap(),
+            FixStatus::Active,
+            9,
+            40.4168,
+            -3.7038,
+            23_456.7,
+        )
+        .with_dop(1.5, 0.9, 1.2)
+        .with_velocity(12.5, 271.3);
+
+        let json = serde_json::to_value(frame).unwrap();
+        assert_eq!(json["fix_time"], "2017-03-25T14:00:07Z");
+        assert_eq!(json["status"], "A");
+        assert_eq!(json["satellites"], 9);
+        assert_eq!(serde_json::from_value::<Frame>(json).unwrap(), frame);
+
+        assert_eq!(serde_json::to_string(&FixStatus::Void).unwrap(), "\"V\"");
+        assert_eq!(
+            serde_json::from_str::<FixStatus>("\"A\"").unwrap(),
+            FixStatus::Active
+        );
+        assert!(serde_json::from_str::<FixStatus>("\"Active\"").is_err());
+    }
+
     /// Checks the GPS initialization.
     #[test]
     #[ignore]
@@ -0,0 +1,249 @@
+//! GPX track log.
+//!
+//! Every valid GPS frame of the flight is recorded as a `<trkpt>` in a GPX 1.1 file in the `data`
+//! directory, named after the start time of the flight (`flight-<start>.gpx`). Points are written
+//! in batches, and the closing tags of the document are rewritten after each batch, so that the
+//! file is always a valid GPX document, even if the flight ends abruptly.
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{config::CONFIG, error, gps::Frame};
+
+/// Number of points buffered before writing them to the file.
+pub const FLUSH_EVERY: usize = 10;
+
+/// GPX track log writer.
+#[derive(Debug)]
+pub struct Gpx {
+    /// Path of the GPX file.
+    path: PathBuf,
+    /// The GPX file.
+    file: File,
+    /// Position of the closing tags in the file.
+    tail_offset: u64,
+    /// Points waiting to be written.
+    pending: Vec<Frame>,
+    /// Wether the current track segment is open.
+    segment_open: bool,
+}
+
+impl Gpx {
+    /// Creates the GPX file of a flight starting at the given time, in the given directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be created or written.
+    pub fn create<P>(dir: P, start: DateTime<Utc>) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = dir
+            .as_ref()
+            .join(format!("flight-{}.gpx", start.format("%Y%m%dT%H%M%SZ")));
+        let file = File::create(&path).context(error::Track::Write { path: path.clone() })?;
+
+        let mut gpx = Self {
+            path,
+            file,
+            tail_offset: 0,
+            pending: Vec::new(),
+            segment_open: false,
+        };
+        let header = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <gpx version=\"1.1\" creator=\"OpenStratos\" \
+             xmlns=\"http://www.topografix.com/GPX/1/1\">\n\
+             <metadata>\n<time>{}</time>\n</metadata>\n<trk>\n<name>OpenStratos flight</name>\n",
+            format_time(start)
+        );
+        gpx.write_batch(&header)?;
+
+        Ok(gpx)
+    }
+
+    /// Creates the GPX file of a flight starting at the given time, in the `data` directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be created or written.
+    pub fn in_data_dir(start: DateTime<Utc>) -> Result<Self, Error> {
+        Self::create(CONFIG.data_dir(), start)
+    }
+
+    /// Gets the path of the GPX file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records the frame as a track point, if it's valid.
+    ///
+    /// Points are written to the file every [`FLUSH_EVERY`](constant.FLUSH_EVERY.html) frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the points are due to be written and the file can't be written.
+    pub fn push(&mut self, frame: &Frame) -> Result<(), Error> {
+        if frame.is_valid() {
+            self.pending.push(*frame);
+            if self.pending.len() >= FLUSH_EVERY {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the waiting points to the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = String::new();
+        if !self.segment_open {
+            batch.push_str("<trkseg>\n");
+            self.segment_open = true;
+        }
+        for frame in self.pending.drain(..) {
+            // Writing to a `String` never fails.
+            let _ = write!(
+                batch,
+                "<trkpt lat=\"{:.6}\" lon=\"{:.6}\">\n<ele>{:.1}</ele>\n<time>{}</time>\n\
+                 <sat>{}</sat>\n</trkpt>\n",
+                frame.latitude(),
+                frame.longitude(),
+                frame.altitude(),
+                format_time(frame.fix_time()),
+                frame.satellites()
+            );
+        }
+        self.write_batch(&batch)
+    }
+
+    /// Writes the waiting points and closes the current track segment, on landing.
+    ///
+    /// New points will be recorded in a new segment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn end_segment(&mut self) -> Result<(), Error> {
+        self.flush()?;
+        if self.segment_open {
+            self.segment_open = false;
+            self.write_batch("</trkseg>\n")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the given contents in place of the closing tags, and then rewrites the closing tags.
+    fn write_batch(&mut self, contents: &str) -> Result<(), Error> {
+        let tail = if self.segment_open {
+            "</trkseg>\n</trk>\n</gpx>\n"
+        } else {
+            "</trk>\n</gpx>\n"
+        };
+
+        let _ = self
+            .file
+            .seek(SeekFrom::Start(self.tail_offset))
+            .context(error::Track::Write {
+                path: self.path.clone(),
+            })?;
+        self.file
+            .write_all(contents.as_bytes())
+            .and_then(|()| self.file.write_all(tail.as_bytes()))
+            .and_then(|()| self.file.flush())
+            .context(error::Track::Write {
+                path: self.path.clone(),
+            })?;
+        self.tail_offset += contents.len() as u64;
+
+        Ok(())
+    }
+}
+
+/// Formats a time in ISO-8601 / RFC 3339 format, as required by GPX.
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::Gpx;
+    use crate::gps::{FixStatus, Frame};
+
+    /// Checks that all the given tags are balanced in the document.
+    fn assert_balanced(gpx: &str) {
+        for tag in ["gpx", "metadata", "trk", "trkseg", "trkpt", "ele", "time"] {
+            let opening =
+                gpx.matches(&format!("<{tag}>")).count() + gpx.matches(&format!("<{tag} ")).count();
+            assert_eq!(
+                opening,
+                gpx.matches(&format!("</{tag}>")).count(),
+                "unbalanced <{tag}>"
+            );
+        }
+        assert!(gpx.ends_with("</trk>\n</gpx>\n"));
+    }
+
+    /// Creates a frame at the given time.
+    fn frame(time: DateTime<Utc>, status: FixStatus, altitude: f32) -> Frame {
+        Frame::new(time, status, 8, 42.5, -2.75, altitude)
+    }
+
+    /// Checks the structure of a small track, and that it's valid before and after landing.
+    #[test]
+    fn small_track() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap();
+        let dir = env::temp_dir().join(format!("os_balloon_gpx_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut gpx = Gpx::create(&dir, start).unwrap();
+        assert_eq!(gpx.path(), dir.join("flight-20170325T121146Z.gpx"));
+        assert_balanced(&fs::read_to_string(gpx.path()).unwrap());
+
+        gpx.push(&frame(start, FixStatus::Active, 650_f32)).unwrap();
+        gpx.push(&frame(start + Duration::seconds(1), FixStatus::Void, 0_f32))
+            .unwrap();
+        gpx.push(&frame(
+            start + Duration::seconds(2),
+            FixStatus::Active,
+            655.5,
+        ))
+        .unwrap();
+        gpx.flush().unwrap();
+
+        let contents = fs::read_to_string(gpx.path()).unwrap();
+        assert_balanced(&contents);
+        assert_eq!(contents.matches("<trkpt ").count(), 2);
+        assert!(contents.contains("<time>2017-03-25T12:11:46Z</time>\n</metadata>"));
+        assert!(contents.contains(
+            "<trkpt lat=\"42.500000\" lon=\"-2.750000\">\n<ele>655.5</ele>\n\
+             <time>2017-03-25T12:11:48Z</time>\n<sat>8</sat>\n</trkpt>\n</trkseg>\n"
+        ));
+
+        gpx.end_segment().unwrap();
+        let contents = fs::read_to_string(gpx.path()).unwrap();
+        assert_balanced(&contents);
+        assert_eq!(contents.matches("<trkseg>").count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+//! UKHAS telemetry sentences.
+//!
+//! This module formats GPS frames as UKHAS telemetry sentences, understood by Habitat and
+//! `SondeHub` trackers:
+//!
+//! ```text
+//! $$CALLSIGN,sentence_id,field,field,…*CRC
+//! ```
+//!
+//! The `CRC` is the CRC16-CCITT of everything between the `$$` and the `*`, in 4 uppercase
+//! hexadecimal digits, and the sentence is terminated with a new line. The callsign and the
+//! fields after the sentence ID can be set in the `[habitat]` section of the configuration. By
+//! default, the time, latitude, longitude, altitude and number of satellites will be sent.
+
+use serde::Deserialize;
+
+use crate::{config::CONFIG, crc::crc16_ccitt, gps::Frame};
+
+/// Default callsign, if none is configured.
+pub const DEFAULT_CALLSIGN: &str = "OPENSTRATOS";
+/// Default fields of a sentence, after the sentence ID.
+pub const DEFAULT_FIELDS: [Field; 5] = [
+    Field::Time,
+    Field::Latitude,
+    Field::Longitude,
+    Field::Altitude,
+    Field::Satellites,
+];
+
+/// Fields that can be sent in a sentence.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    /// Time of the fix, as `HH:MM:SS`.
+    Time,
+    /// Latitude, in degrees, with 6 decimals.
+    Latitude,
+    /// Longitude, in degrees, with 6 decimals.
+    Longitude,
+    /// Altitude, in meters.
+    Altitude,
+    /// Number of satellites.
+    Satellites,
+    /// Speed, in *m/s*, with 1 decimal.
+    Speed,
+    /// Course, in degrees.
+    Course,
+}
+
+impl Field {
+    /// Formats the field for the given frame.
+    fn format(self, frame: &Frame) -> String {
+        match self {
+            Field::Time => frame.fix_time().format("%H:%M:%S").to_string(),
+            Field::Latitude => format!("{:.6}", frame.latitude()),
+            Field::Longitude => format!("{:.6}", frame.longitude()),
+            Field::Altitude => format!("{:.0}", frame.altitude()),
+            Field::Satellites => frame.satellites().to_string(),
+            Field::Speed => format!("{:.1}", frame.speed()),
+            Field::Course => format!("{:.0}", frame.course()),
+        }
+    }
+}
+
+/// Encodes the GPS frame as a UKHAS sentence, using the configured callsign and fields.
+#[must_use]
+pub fn encode(frame: &Frame, sentence_id: u32) -> String {
+    encode_with(
+        frame,
+        sentence_id,
+        CONFIG.habitat().callsign(),
+        CONFIG.habitat().fields(),
+    )
+}
+
+/// Encodes the GPS frame as a UKHAS sentence, with the given callsign and fields.
+#[must_use]
+pub fn encode_with(frame: &Frame, sentence_id: u32, callsign: &str, fields: &[Field]) -> String {
+    let mut payload = format!("{callsign},{sentence_id}");
+    for field in fields {
+        payload.push(',');
+        payload.push_str(&field.format(frame));
+    }
+
+    format!("$${payload}*{:04X}\n", crc16_ccitt(payload.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{encode_with, Field, DEFAULT_FIELDS};
+    use crate::{
+        crc::crc16_ccitt,
+        gps::{FixStatus, Frame},
+    };
+
+    /// Creates a test frame.
+    fn frame() -> Frame {
+        Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            FixStatus::Active,
+            9,
+            51.5,
+            -0.125,
+            12_345_f32,
+        )
+        .with_velocity(5.5, 270_f32)
+    }
+
+    /// Checks a sentence with the default fields against a reference sentence.
+    #[test]
+    fn encode_default_fields() {
+        assert_eq!(
+            encode_with(&frame(), 42, "OPENSTRATOS", &DEFAULT_FIELDS),
+            "$$OPENSTRATOS,42,12:11:46,51.500000,-0.125000,12345,9*14F2\n"
+        );
+    }
+
+    /// Checks a sentence with a custom field order.
+    #[test]
+    fn encode_custom_fields() {
+        let sentence = encode_with(
+            &frame(),
+            7,
+            "STRATOS1",
+            &[Field::Altitude, Field::Speed, Field::Course, Field::Time],
+        );
+        assert_eq!(sentence, "$$STRATOS1,7,12345,5.5,270,12:11:46*D56A\n");
+
+        let (payload, crc) = sentence
+            .trim_start_matches("$$")
+            .trim_end()
+            .split_once('*')
+            .unwrap();
+        assert_eq!(
+            u16::from_str_radix(crc, 16).unwrap(),
+            crc16_ccitt(payload.as_bytes())
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Local HTTP status endpoint.
+//!
+//! During the pre-launch checkout, the probe can be queried over Wi-Fi or Ethernet with any HTTP
+//! client, such as `curl`. The server is started by [`start()`](fn.start.html) in a background
+//! thread, and binds to the address and port of the `[http]` configuration section,
+//! `127.0.0.1:8080` by default. Only `GET` requests are accepted, in these paths:
+//!
+//! * `/status`: a JSON object with the current state, the last GPS frame, the battery charges, the
+//!   available disk space, in bytes, and wether the camera is recording. Data that is not available
+//!   in the current build or can't be read is `null`.
+//! * `/healthz`: a liveness check, that always returns `{"status":"ok"}`.
+//!
+//! Requests are served one at a time, and the server never writes to the devices.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{IpAddr, Ipv4Addr, TcpListener, TcpStream},
+    thread,
+};
+
+use anyhow::{Context, Error};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+// Only required for GPS, FONA or Raspicam
+#[cfg(any(feature = "gps", feature = "fona", feature = "raspicam"))]
+use tracing::error;
+
+#[cfg(feature = "fona")]
+use crate::fona::FONA;
+#[cfg(feature = "gps")]
+use crate::gps::{Frame, GPS};
+#[cfg(feature = "raspicam")]
+use crate::raspicam::CAMERA;
+use crate::{
+    config::CONFIG,
+    error,
+    logic::{current_state, get_available_disk_space, State},
+};
+
+/// Default address to bind the server to.
+pub const DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+/// Default port of the server.
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Starts the HTTP server in a background thread.
+///
+/// # Errors
+///
+/// Returns an error if the server can't bind to the configured address, or if its thread can't be
+/// spawned.
+pub fn start() -> Result<(), Error> {
+    let address = CONFIG.http().socket_addr();
+    let listener = TcpListener::bind(address).context(error::Http::Bind { address })?;
+
+    let _ = thread::Builder::new()
+        .name("http".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(serve);
+                if let Err(e) = result {
+                    warn!(error = %e, "Error serving HTTP request.");
+                }
+            }
+        })
+        .context(error::Http::Thread)?;
+
+    info!("HTTP status endpoint listening on {}.", address);
+    Ok(())
+}
+
+/// Serves a request from the given connection.
+fn serve(mut stream: TcpStream) -> Result<(), io::Error> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    let _ = reader.read_line(&mut request_line)?;
+
+    // Headers are not used, but they are read so that the client does not get a reset connection.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    debug!("HTTP request: {} {}", method, path);
+
+    let response = handle(method, path);
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status(),
+        response.reason(),
+        response.body().len(),
+        response.body()
+    )?;
+    stream.flush()
+}
+
+/// Handles a request with the given method and path.
+#[must_use]
+pub fn handle(method: &str, path: &str) -> Response {
+    if method != "GET" {
+        return Response::new(405, &json!({ "error": "method not allowed" }));
+    }
+
+    // The query string, if any, is ignored.
+    match path.split('?').next().unwrap_or_default() {
+        "/status" => Response::new(200, &Status::current().to_json()),
+        "/healthz" => Response::new(200, &json!({ "status": "ok" })),
+        _ => Response::new(404, &json!({ "error": "not found" })),
+    }
+}
+
+/// HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// Status code.
+    status: u16,
+    /// JSON body.
+    body: String,
+}
+
+impl Response {
+    /// Creates a new response with the given status code and JSON body.
+    fn new(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            body: body.to_string(),
+        }
+    }
+
+    /// Gets the status code of the response.
+    #[must_use]
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Gets the reason phrase of the status code.
+    #[must_use]
+    pub fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// Gets the JSON body of the response.
+    #[must_use]
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+/// Live status of the probe.
+#[derive(Debug, Clone, Copy)]
+pub struct Status {
+    /// Current state.
+    state: State,
+    /// Last GPS frame.
+    #[cfg(feature = "gps")]
+    frame: Option<Frame>,
+    /// Charge of the main battery, from 0 to 1.
+    main_battery: Option<f32>,
+    /// Charge of the FONA battery, from 0 to 1.
+    fona_battery: Option<f32>,
+    /// Available disk space, in bytes.
+    disk_free: Option<u64>,
+    /// Wether the camera is recording video.
+    recording: bool,
+}
+
+impl Status {
+    /// Gets the current status of the probe.
+    #[must_use]
+    pub fn current() -> Self {
+        #[cfg(feature = "gps")]
+        let frame = match GPS.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                error!("The GPS mutex was poisoned.");
+                poisoned.into_inner()
+            }
+        }
+        .latest_data();
+
+        #[cfg(feature = "fona")]
+        let (main_battery, fona_battery) = {
+            let mut fona = match FONA.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    error!("The FONA mutex was poisoned.");
+                    poisoned.into_inner()
+                }
+            };
+            let main_battery = fona.adc_voltage().ok().map(|adc_voltage| {
+                (adc_voltage - CONFIG.battery().main_min())
+                    / (CONFIG.battery().main_max() - CONFIG.battery().main_min())
+            });
+            (main_battery, fona.battery_percent().ok())
+        };
+        #[cfg(not(feature = "fona"))]
+        let (main_battery, fona_battery) = (None, None);
+
+        #[cfg(feature = "raspicam")]
+        let recording = match CAMERA.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                error!("The CAMERA mutex was poisoned.");
+                poisoned.into_inner()
+            }
+        }
+        .is_recording();
+        #[cfg(not(feature = "raspicam"))]
+        let recording = false;
+
+        Self {
+            state: current_state(),
+            #[cfg(feature = "gps")]
+            frame,
+            main_battery,
+            fona_battery,
+            disk_free: get_available_disk_space().ok(),
+            recording,
+        }
+    }
+
+    /// Serializes the status as a JSON object.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        #[cfg(feature = "gps")]
+        let gps = self.frame.map(|frame| {
+            json!({
+                "time": frame.fix_time().to_rfc3339(),
+                "valid": frame.is_valid(),
+                "latitude": frame.latitude(),
+                "longitude": frame.longitude(),
+                "altitude": frame.altitude(),
+                "satellites": frame.satellites(),
+                "pdop": frame.pdop(),
+                "speed": frame.speed(),
+                "course": frame.course(),
+            })
+        });
+        #[cfg(not(feature = "gps"))]
+        let gps: Option<Value> = None;
+
+        json!({
+            "state": self.state.as_str(),
+            "gps": gps,
+            "battery": {
+                "main": self.main_battery,
+                "fona": self.fona_battery,
+            },
+            "disk_free": self.disk_free,
+            "recording": self.recording,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::handle;
+
+    /// Checks the JSON shape of the status response.
+    #[test]
+    fn status() {
+        let response = handle("GET", "/status");
+        assert_eq!(response.status(), 200);
+
+        let json: Value = serde_json::from_str(response.body()).unwrap();
+        assert!(json["state"].is_string());
+        assert!(json["gps"].is_object() || json["gps"].is_null());
+        assert!(json["battery"].get("main").is_some());
+        assert!(json["battery"].get("fona").is_some());
+        assert!(json["disk_free"].is_u64() || json["disk_free"].is_null());
+        assert_eq!(json["recording"], false);
+    }
+
+    /// Checks the liveness response.
+    #[test]
+    fn healthz() {
+        let response = handle("GET", "/healthz?verbose");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body(), r#"{"status":"ok"}"#);
+    }
+
+    /// Checks the error responses.
+    #[test]
+    fn errors() {
+        assert_eq!(handle("GET", "/").status(), 404);
+        assert_eq!(handle("POST", "/status").status(), 405);
+        assert_eq!(handle("POST", "/status").reason(), "Method Not Allowed");
+    }
+}
@@ -0,0 +1,356 @@
+//! Iridium SBD satellite modem.
+//!
+//! GSM coverage is only available near the ground, and not at all in the ocean or in remote
+//! areas. If the probe has no GSM connectivity when it should send a descent or landed SMS, the
+//! position is sent over the Iridium network instead, using the Short Burst Data (SBD) service of
+//! a RockBLOCK-style serial modem.
+//!
+//! Messages are the same binary [`Packet`](../telemetry/struct.Packet.html) used in the
+//! telemetry, written to the modem buffer with `AT+SBDWB` and sent in an SBD session started with
+//! `AT+SBDIX`. Each message must fit in the 340 byte limit of mobile originated SBD messages.
+//!
+//! The serial connection is opened the first time a message is sent.
+
+#![allow(missing_debug_implementations)]
+
+use std::{
+    fmt,
+    io::{ErrorKind, Read, Write},
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{Context, Error};
+use once_cell::sync::Lazy;
+use tokio_serial::SerialPort;
+use tracing::{debug, error, info};
+
+use crate::{config::CONFIG, error, serial};
+
+/// The Iridium modem control structure.
+pub static IRIDIUM: Lazy<Mutex<Iridium>> = Lazy::new(|| Mutex::new(Iridium { serial: None }));
+
+/// Maximum length of a mobile originated SBD message, in bytes.
+pub const MAX_MESSAGE_LENGTH: usize = 340;
+/// Default baud rate of the modem serial.
+pub const DEFAULT_BAUD_RATE: u32 = 19_200;
+/// Default read/write timeout of the modem serial, long enough for an SBD session.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_mins(1);
+
+/// Iridium SBD modem control structure.
+pub struct Iridium {
+    serial: Option<Box<dyn SerialPort>>,
+}
+
+impl fmt::Debug for Iridium {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Iridium {{ serial: {:?} }}",
+            if let Some(ref serial) = &self.serial {
+                serial.name()
+            } else {
+                None
+            }
+        )
+    }
+}
+
+impl Iridium {
+    /// Initializes the serial connection with the modem.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the serial can't be opened, or if the modem does not respond.
+    pub fn initialize(&mut self) -> Result<(), Error> {
+        info!("Starting Iridium serial connection.");
+        let serial = serial::open(
+            CONFIG.iridium().uart(),
+            CONFIG.iridium().baud_rate(),
+            CONFIG.iridium().timeout(),
+        )
+        .context(error::Iridium::Init)?;
+        self.serial = Some(serial);
+
+        // Turn echo and flow control off, since RockBLOCK modems only wire RX and TX.
+        for command in ["ATE0", "AT&K0"] {
+            self.send_command(command.as_bytes())?;
+            self.expect(command, "OK")?;
+        }
+
+        info!("Iridium modem initialized.");
+        Ok(())
+    }
+
+    /// Sends a message in an SBD session, and returns the result of the session.
+    ///
+    /// The message is only sent if the mobile originated status of the result is successful.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is empty or too long, or if the modem can't be reached. A
+    /// failed session is not an error, it's reported in the result.
+    pub fn send_message(&mut self, message: &[u8]) -> Result<Sbdix, Error> {
+        if self.serial.is_none() {
+            self.initialize()?;
+        }
+        let frame = sbdwb_frame(message)?;
+
+        let command = format!("AT+SBDWB={}", message.len());
+        self.send_command(command.as_bytes())?;
+        self.expect(&command, "READY")?;
+
+        self.write(&frame)?;
+        let status = self.read_non_empty_line()?;
+        if status != "0" {
+            error!("Error writing the SBD message: `{}`", status);
+            return Err(error::Iridium::Write { status }.into());
+        }
+        self.expect(&command, "OK")?;
+
+        self.send_command(b"AT+SBDIX")?;
+        let sbdix = self.read_non_empty_line()?.parse::<Sbdix>()?;
+        self.expect("AT+SBDIX", "OK")?;
+
+        if sbdix.is_success() {
+            info!(momsn = sbdix.momsn(), "SBD message sent.");
+            Ok(sbdix)
+        } else {
+            Err(error::Iridium::Session {
+                status: sbdix.mo_status(),
+            }
+            .into())
+        }
+    }
+
+    /// Sends a command to the modem, ending with a carriage return.
+    fn send_command(&mut self, command: &[u8]) -> Result<(), Error> {
+        debug!("Sent command: `{}\\r`", String::from_utf8_lossy(command));
+        self.write(command)?;
+        self.write(b"\r")
+    }
+
+    /// Writes raw data to the modem serial.
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        if let Some(ref mut serial) = self.serial {
+            serial.write_all(data).context(error::Iridium::Command)
+        } else {
+            error!("No serial when trying to write to the Iridium modem");
+            Err(error::Iridium::NoSerial.into())
+        }
+    }
+
+    /// Reads a response line, and checks that it's the expected one.
+    fn expect(&mut self, command: &str, expected: &str) -> Result<(), Error> {
+        let response = self.read_non_empty_line()?;
+        if response == expected {
+            Ok(())
+        } else {
+            error!(
+                "Unexpected response to `{}`: `{}`, expected `{}`",
+                command, response, expected
+            );
+            Err(error::Iridium::Response { response }.into())
+        }
+    }
+
+    /// Reads lines from the serial until a non-empty one is found.
+    fn read_non_empty_line(&mut self) -> Result<String, Error> {
+        let Some(ref mut serial) = self.serial else {
+            error!("No serial when trying to read response");
+            return Err(error::Iridium::NoSerial.into());
+        };
+
+        let mut response = Vec::new();
+        for res in serial.bytes() {
+            match res {
+                Ok(b'\r') => {}
+                Ok(b'\n') if response.is_empty() => {}
+                Ok(b'\n') => {
+                    let res = String::from_utf8(response)?;
+                    debug!("Received: `{}\r\n`", res);
+                    return Ok(res);
+                }
+                Ok(b) => response.push(b),
+                Err(e) if e.kind() == ErrorKind::TimedOut => {
+                    let partial = String::from_utf8_lossy(&response).into_owned();
+                    return Err(error::Iridium::Response { response: partial }.into());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(error::Iridium::SerialEnd.into())
+    }
+}
+
+/// Creates the binary data sent after the `AT+SBDWB` command: the message followed by its
+/// checksum.
+///
+/// The checksum is the least significant 2 bytes of the sum of all the bytes of the message, in
+/// big endian order.
+///
+/// # Errors
+///
+/// Returns an error if the message is empty or longer than
+/// [`MAX_MESSAGE_LENGTH`](constant.MAX_MESSAGE_LENGTH.html).
+pub fn sbdwb_frame(message: &[u8]) -> Result<Vec<u8>, error::Iridium> {
+    if message.is_empty() || message.len() > MAX_MESSAGE_LENGTH {
+        return Err(error::Iridium::Length {
+            length: message.len(),
+        });
+    }
+
+    let checksum = message
+        .iter()
+        .fold(0_u16, |sum, &byte| sum.wrapping_add(u16::from(byte)));
+
+    let mut frame = Vec::with_capacity(message.len() + 2);
+    frame.extend_from_slice(message);
+    frame.extend_from_slice(&checksum.to_be_bytes());
+    Ok(frame)
+}
+
+/// Result of an SBD session, as returned in the `+SBDIX` response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sbdix {
+    /// Mobile originated status.
+    mo_status: u8,
+    /// Mobile originated message sequence number.
+    momsn: u16,
+    /// Mobile terminated status.
+    mt_status: u8,
+    /// Mobile terminated message sequence number.
+    mtmsn: u16,
+    /// Length of the received mobile terminated message, in bytes.
+    mt_length: u16,
+    /// Number of mobile terminated messages waiting in the gateway.
+    mt_queued: u16,
+}
+
+impl Sbdix {
+    /// Checks if the mobile originated message was sent.
+    ///
+    /// Statuses 0 to 4 mean that the message was transferred successfully.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.mo_status <= 4
+    }
+
+    /// Gets the mobile originated status.
+    #[must_use]
+    pub fn mo_status(&self) -> u8 {
+        self.mo_status
+    }
+
+    /// Gets the mobile originated message sequence number.
+    #[must_use]
+    pub fn momsn(&self) -> u16 {
+        self.momsn
+    }
+
+    /// Gets the mobile terminated status.
+    #[must_use]
+    pub fn mt_status(&self) -> u8 {
+        self.mt_status
+    }
+
+    /// Gets the mobile terminated message sequence number.
+    #[must_use]
+    pub fn mtmsn(&self) -> u16 {
+        self.mtmsn
+    }
+
+    /// Gets the length of the received mobile terminated message, in bytes.
+    #[must_use]
+    pub fn mt_length(&self) -> u16 {
+        self.mt_length
+    }
+
+    /// Gets the number of mobile terminated messages waiting in the gateway.
+    #[must_use]
+    pub fn mt_queued(&self) -> u16 {
+        self.mt_queued
+    }
+}
+
+impl FromStr for Sbdix {
+    type Err = error::Iridium;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || error::Iridium::InvalidSbdix {
+            response: s.to_owned(),
+        };
+
+        let values = s.trim().strip_prefix("+SBDIX:").ok_or_else(invalid)?;
+        let mut values = values.split(',').map(str::trim);
+        let mut next = || values.next().ok_or_else(invalid);
+
+        let sbdix = Self {
+            mo_status: next()?.parse().map_err(|_| invalid())?,
+            momsn: next()?.parse().map_err(|_| invalid())?,
+            mt_status: next()?.parse().map_err(|_| invalid())?,
+            mtmsn: next()?.parse().map_err(|_| invalid())?,
+            mt_length: next()?.parse().map_err(|_| invalid())?,
+            mt_queued: next()?.parse().map_err(|_| invalid())?,
+        };
+
+        if values.next().is_some() {
+            Err(invalid())
+        } else {
+            Ok(sbdix)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sbdwb_frame, Sbdix, MAX_MESSAGE_LENGTH};
+    use crate::telemetry::PACKET_LENGTH;
+
+    /// Checks the checksum appended to the `AT+SBDWB` binary data.
+    #[test]
+    fn sbdwb_framing() {
+        assert_eq!(sbdwb_frame(b"hello").unwrap(), b"hello\x02\x14");
+        assert_eq!(
+            sbdwb_frame(&[0xFF; 3]).unwrap(),
+            [0xFF, 0xFF, 0xFF, 0x02, 0xFD]
+        );
+
+        // The checksum only keeps the 2 least significant bytes of the sum (86 700).
+        let frame = sbdwb_frame(&[0xFF; MAX_MESSAGE_LENGTH]).unwrap();
+        assert_eq!(frame.len(), MAX_MESSAGE_LENGTH + 2);
+        assert_eq!(frame[MAX_MESSAGE_LENGTH..], 21_164_u16.to_be_bytes());
+    }
+
+    /// Checks that messages over the SBD limit are rejected.
+    #[test]
+    fn sbdwb_length() {
+        assert!(sbdwb_frame(&[]).is_err());
+        assert!(sbdwb_frame(&[0; MAX_MESSAGE_LENGTH + 1]).is_err());
+        assert!(PACKET_LENGTH <= MAX_MESSAGE_LENGTH);
+    }
+
+    /// Checks the parsing of `+SBDIX` responses.
+    #[test]
+    fn sbdix_parser() {
+        let sbdix: Sbdix = "+SBDIX: 0, 23, 1, 5, 12, 2".parse().unwrap();
+        assert!(sbdix.is_success());
+        assert_eq!(sbdix.mo_status(), 0);
+        assert_eq!(sbdix.momsn(), 23);
+        assert_eq!(sbdix.mt_status(), 1);
+        assert_eq!(sbdix.mtmsn(), 5);
+        assert_eq!(sbdix.mt_length(), 12);
+        assert_eq!(sbdix.mt_queued(), 2);
+
+        let failed: Sbdix = "+SBDIX:32,24,0,0,0,0\r".parse().unwrap();
+        assert!(!failed.is_success());
+        assert_eq!(failed.mo_status(), 32);
+
+        assert!("+SBDIX: 0, 23, 1, 5, 12".parse::<Sbdix>().is_err());
+        assert!("+SBDIX: 0, 23, 1, 5, 12, 2, 0".parse::<Sbdix>().is_err());
+        assert!("+SBDI: 0, 23, 1, 5, 12, 2".parse::<Sbdix>().is_err());
+        assert!("+SBDIX: 0, -1, 1, 5, 12, 2".parse::<Sbdix>().is_err());
+    }
+}
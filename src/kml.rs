@@ -0,0 +1,246 @@
+//! Live KML track.
+//!
+//! The track of the flight is written as a KML file in the `data` directory, that can be loaded
+//! in Google Earth or similar software. It contains the path of the probe as a `<LineString>`,
+//! with the altitude of each point, so that it can be displayed in 3D, and a `<Placemark>` for
+//! the launch, burst and landing events, once they happen.
+//!
+//! The file is rewritten atomically with every new frame, by writing a temporary file and
+//! renaming it, so that it can be reloaded live without ever finding a partially written file.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+
+use crate::{config::CONFIG, error, gps::Frame};
+
+/// KML track file, in the `data` directory.
+pub const TRACK_FILE: &str = "track.kml";
+
+/// Flight events that are marked in the track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The balloon was launched.
+    Launch,
+    /// The balloon burst.
+    Burst,
+    /// The probe landed.
+    Landing,
+}
+
+impl Event {
+    /// Gets the name of the event, as shown in the placemark.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Event::Launch => "Launch",
+            Event::Burst => "Burst",
+            Event::Landing => "Landing",
+        }
+    }
+}
+
+/// Point of the track, in *°* (degrees) and *m*.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Point {
+    /// Longitude of the point.
+    longitude: f32,
+    /// Latitude of the point.
+    latitude: f32,
+    /// Altitude of the point.
+    altitude: f32,
+}
+
+impl From<&Frame> for Point {
+    fn from(frame: &Frame) -> Self {
+        Self {
+            longitude: frame.longitude(),
+            latitude: frame.latitude(),
+            altitude: frame.altitude(),
+        }
+    }
+}
+
+/// Live KML track writer.
+#[derive(Debug)]
+pub struct Track {
+    /// Path of the KML file.
+    path: PathBuf,
+    /// Points of the track, in order.
+    points: Vec<Point>,
+    /// Marked events, with their position.
+    events: Vec<(Event, Point)>,
+}
+
+impl Track {
+    /// Creates a new track, that will be written to the given path.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            path: path.as_ref().to_owned(),
+            points: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Creates a new track, that will be written to the track file in the `data` directory.
+    #[must_use]
+    pub fn in_data_dir() -> Self {
+        Self::new(CONFIG.data_dir().join(TRACK_FILE))
+    }
+
+    /// Gets the path of the KML file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds the position in the frame to the track, and rewrites the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn push(&mut self, frame: &Frame) -> Result<(), Error> {
+        self.points.push(frame.into());
+        self.write()
+    }
+
+    /// Marks a flight event in the position of the frame, and rewrites the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn mark(&mut self, event: Event, frame: &Frame) -> Result<(), Error> {
+        self.events.push((event, frame.into()));
+        self.write()
+    }
+
+    /// Generates the KML document of the track.
+    #[must_use]
+    pub fn to_kml(&self) -> String {
+        let mut kml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+             <Document>\n\
+             <name>OpenStratos flight</name>\n",
+        );
+
+        for (event, point) in &self.events {
+            // Writing to a `String` never fails.
+            let _ = write!(
+                kml,
+                "<Placemark>\n<name>{}</name>\n<Point>\n<altitudeMode>absolute</altitudeMode>\n\
+                 <coordinates>{}</coordinates>\n</Point>\n</Placemark>\n",
+                event.as_str(),
+                format_point(*point)
+            );
+        }
+
+        kml.push_str(
+            "<Placemark>\n<name>Track</name>\n<LineString>\n\
+             <altitudeMode>absolute</altitudeMode>\n<coordinates>\n",
+        );
+        for point in &self.points {
+            kml.push_str(&format_point(*point));
+            kml.push('\n');
+        }
+        kml.push_str("</coordinates>\n</LineString>\n</Placemark>\n</Document>\n</kml>\n");
+
+        kml
+    }
+
+    /// Writes the KML file atomically.
+    fn write(&self) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("kml.tmp");
+        fs::write(&tmp_path, self.to_kml()).context(error::Track::Write {
+            path: tmp_path.clone(),
+        })?;
+        fs::rename(&tmp_path, &self.path).context(error::Track::Write {
+            path: self.path.clone(),
+        })?;
+        Ok(())
+    }
+}
+
+/// Formats a point as a KML coordinate tuple (`lon,lat,alt`).
+fn format_point(point: Point) -> String {
+    format!(
+        "{:.6},{:.6},{:.1}",
+        point.longitude, point.latitude, point.altitude
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{Event, Track};
+    use crate::gps::{FixStatus, Frame};
+
+    /// Creates a frame at the given position.
+    fn frame(latitude: f32, longitude: f32, altitude: f32) -> Frame {
+        Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            FixStatus::Active,
+            9,
+            latitude,
+            longitude,
+            altitude,
+        )
+    }
+
+    /// Checks that several frames produce a well-formed KML file, in `lon,lat,alt` order.
+    #[test]
+    fn track_kml() {
+        let path = env::temp_dir().join(format!("os_balloon_track_{}.kml", std::process::id()));
+        let mut track = Track::new(&path);
+
+        let frames = [
+            frame(40.5, -3.25, 650_f32),
+            frame(40.625, -3.125, 1_200_f32),
+            frame(40.75, -3.0, 2_400.5),
+        ];
+        track.mark(Event::Launch, &frames[0]).unwrap();
+        for frame in &frames {
+            track.push(frame).unwrap();
+        }
+
+        let kml = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!path.with_extension("kml.tmp").exists());
+        assert_eq!(kml, track.to_kml());
+
+        assert!(kml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml "));
+        assert!(kml.ends_with("</Document>\n</kml>\n"));
+        for tag in [
+            "Document",
+            "Placemark",
+            "Point",
+            "LineString",
+            "coordinates",
+        ] {
+            assert_eq!(
+                kml.matches(&format!("<{tag}>")).count(),
+                kml.matches(&format!("</{tag}>")).count(),
+                "unbalanced <{tag}>"
+            );
+        }
+
+        assert!(kml.contains(
+            "<name>Launch</name>\n<Point>\n<altitudeMode>absolute</altitudeMode>\n\
+             <coordinates>-3.250000,40.500000,650.0</coordinates>"
+        ));
+        assert!(kml.contains(
+            "<coordinates>\n-3.250000,40.500000,650.0\n-3.125000,40.625000,1200.0\n\
+             -3.000000,40.750000,2400.5\n</coordinates>"
+        ));
+    }
+}
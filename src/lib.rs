@@ -34,7 +34,17 @@
 //!
 //! ## Simulation mode
 //!
-//! *In development…*
+//! The launch, burst and landing detection can be tested without any hardware by building with the
+//! `sim` feature:
+//!
+//! ```text
+//! cargo run --no-default-features --features="sim fona raspicam"
+//! ```
+//!
+//! In this mode, the initialization is skipped and GPS frames are read from a recorded flight file
+//! (`sim/flight.csv` by default), while SMSs and camera operations are only logged. Time is sped up
+//! by the factor given in the `[simulation]` section of the configuration file. Please refer to the
+//! [`sim`](sim/index.html) module for further information.
 
 #![deny(clippy::all)]
 #![forbid(anonymous_parameters)]
@@ -60,59 +70,233 @@
 pub const CONFIG_FILE: &str = "config.toml";
 /// Last state file, in the `data` directory.
 pub const STATE_FILE: &str = "last_state";
+/// Flight statistics file, in the `data` directory.
+pub const STATS_FILE: &str = "last_stats.toml";
 
+#[cfg(feature = "gps")]
+pub mod aprs;
+#[cfg(feature = "gps")]
+pub mod arm;
+#[cfg(feature = "baro")]
+pub mod baro;
 pub mod config;
+pub mod crc;
+pub mod data;
+#[cfg(feature = "sqlite")]
+pub mod db;
+pub mod devices;
 pub mod error;
 #[cfg(feature = "fona")]
 pub mod fona;
 #[cfg(feature = "gps")]
+pub mod geofence;
+#[cfg(feature = "gps")]
 pub mod gps;
+#[cfg(feature = "gps")]
+pub mod gpx;
+#[cfg(feature = "gps")]
+pub mod habitat;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "iridium")]
+pub mod iridium;
+#[cfg(feature = "gps")]
+pub mod kml;
+pub mod logger;
 pub mod logic;
+#[cfg(feature = "lora")]
+pub mod lora;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(all(feature = "gps", feature = "fona"))]
+pub mod position;
+#[cfg(feature = "gps")]
+pub mod predict;
 #[cfg(feature = "raspicam")]
 pub mod raspicam;
+pub mod recovery;
+#[cfg(any(feature = "gps", feature = "fona", feature = "telemetry"))]
+pub mod serial;
+#[cfg(feature = "sim")]
+pub mod sim;
 #[cfg(feature = "telemetry")]
 pub mod telemetry;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
 use anyhow::{Context, Error};
 
 pub use crate::config::CONFIG;
-use crate::logic::{MainLogic, State};
-use std::fs;
+use crate::logic::MainLogic;
+// Only required when not simulating
+#[cfg(not(feature = "sim"))]
+use crate::logic::State;
+use std::fmt::Write as _;
 
 /// The main logic of the program.
+#[cfg(not(feature = "sim"))]
 pub fn run() -> Result<(), Error> {
     initialize_data_filesystem().context(error::Fs::DataInit)?;
 
-    if let Some(_state) = State::get_last().context(error::LastState::Read)? {
-        // TODO recover from last state and continue
-        unimplemented!()
+    let mut context = logic::Context::hardware();
+    #[cfg(feature = "gps")]
+    context.start_flight_logs();
+    if let Some(state) = State::get_last().context(error::LastState::Read)? {
+        logic::resume(state, &mut context)
     } else {
-        logic::init().context(error::Logic::Init)?.main_logic()
+        logic::init()
+            .context(error::Logic::Init)?
+            .main_logic(&mut context)
     }
 }
 
-/// Initializes the data file system for videos and images.
-pub fn initialize_data_filesystem() -> Result<(), Error> {
-    let video_path = CONFIG.data_dir().join("video");
-    fs::create_dir_all(&video_path).context(error::Fs::DirectoryCreation { path: video_path })?;
+/// The main logic of the program, in simulation mode.
+///
+/// It skips the initialization, and runs the flight logic from the GPS fix acquisition, with the
+/// simulated devices.
+#[cfg(feature = "sim")]
+pub fn run() -> Result<(), Error> {
+    initialize_data_filesystem().context(error::Fs::DataInit)?;
+
+    let mut context = sim::configured_context()?;
+    context.start_flight_logs();
+    logic::simulation().main_logic(&mut context)
+}
+
+/// Cargo features that change the behaviour of the software, and wether they are compiled in.
+pub const FEATURES: [(&str, bool); 14] = [
+    ("gps", cfg!(feature = "gps")),
+    ("raspicam", cfg!(feature = "raspicam")),
+    ("fona", cfg!(feature = "fona")),
+    ("telemetry", cfg!(feature = "telemetry")),
+    ("no_sms", cfg!(feature = "no_sms")),
+    ("no_power_off", cfg!(feature = "no_power_off")),
+    ("sqlite", cfg!(feature = "sqlite")),
+    ("sim", cfg!(feature = "sim")),
+    ("http", cfg!(feature = "http")),
+    ("iridium", cfg!(feature = "iridium")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("baro", cfg!(feature = "baro")),
+    ("webhook", cfg!(feature = "webhook")),
+    ("lora", cfg!(feature = "lora")),
+];
+
+/// Generates the build information of the binary: its version and its compiled features.
+#[must_use]
+pub fn build_info() -> String {
+    feature_summary(env!("CARGO_PKG_VERSION"), &FEATURES)
+}
 
-    let img_path = CONFIG.data_dir().join("img");
-    fs::create_dir_all(&img_path).context(error::Fs::DirectoryCreation { path: img_path })?;
+/// Generates the summary of the given version and features.
+fn feature_summary(version: &str, features: &[(&str, bool)]) -> String {
+    /// Joins the names of the features with the given state, or `none` if there are none.
+    fn join(features: &[(&str, bool)], enabled: bool) -> String {
+        let names: Vec<_> = features
+            .iter()
+            .filter(|&&(_, state)| state == enabled)
+            .map(|&(name, _)| name)
+            .collect();
+        if names.is_empty() {
+            String::from("none")
+        } else {
+            names.join(", ")
+        }
+    }
+
+    format!(
+        "OpenStratos {version}
+Enabled features: {}
+Disabled features: {}
+",
+        join(features, true),
+        join(features, false)
+    )
+}
+
+/// Initializes the data file system, creating the baseline subdirectories.
+pub fn initialize_data_filesystem() -> Result<(), Error> {
+    for name in data::SUBDIRS {
+        let _ = data::ensure_subdir(name)?;
+    }
 
     Ok(())
 }
 
 /// Generates a stack trace string of an error.
-#[allow(clippy::use_debug)]
+///
+/// Each cause of the error is added in its own indented line, numbered from the outermost to the
+/// root cause. If the `RUST_BACKTRACE` environment variable is set, the backtrace of the error is
+/// added at the end.
 pub fn generate_error_string<S>(error: &Error, main_error: S) -> String
 where
     S: AsRef<str>,
 {
-    let mut result = format!("{}:\n{}\n", main_error.as_ref(), error);
+    let mut result = format!("{}:\n{error}\n", main_error.as_ref());
+
+    for (i, cause) in error.chain().skip(1).enumerate() {
+        // Writing to a `String` never fails.
+        let _ = writeln!(result, "\t{}: {cause}", i + 1);
+    }
 
-    while let Some(error) = error.source() {
-        result.push_str(&format!("\tcaused by: {}\n", error));
+    // `anyhow` only captures the backtrace if `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` are set,
+    // and only shows it in the debug representation of the error, after the causes.
+    let debug = format!("{error:?}");
+    if let Some(start) = debug.find("Stack backtrace:") {
+        result.push_str(&debug[start..]);
+        result.push('\n');
     }
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, Error};
+
+    use super::{feature_summary, generate_error_string, FEATURES};
+
+    /// Checks that every level of a three level error chain appears once, numbered.
+    #[test]
+    fn error_string_chain() {
+        let error = Err::<(), _>(Error::msg("root cause"))
+            .context("middle error")
+            .context("top error")
+            .unwrap_err();
+
+        let string = generate_error_string(&error, "Error running OpenStratos");
+        assert!(string.starts_with(
+            "Error running OpenStratos:\ntop error\n\t1: middle error\n\t2: root cause\n"
+        ));
+        for level in ["top error", "middle error", "root cause"] {
+            assert_eq!(string.matches(level).count(), 1, "{string}");
+        }
+    }
+
+    /// Checks the summary of the version and the compiled features.
+    #[test]
+    fn feature_summary_lists() {
+        assert_eq!(
+            feature_summary(
+                "2.0.0",
+                &[
+                    ("gps", true),
+                    ("fona", false),
+                    ("raspicam", true),
+                    ("no_sms", false)
+                ]
+            ),
+            "OpenStratos 2.0.0\nEnabled features: gps, raspicam\nDisabled features: fona, no_sms\n"
+        );
+        assert_eq!(
+            feature_summary("2.0.0", &[("gps", false)]),
+            "OpenStratos 2.0.0\nEnabled features: none\nDisabled features: gps\n"
+        );
+
+        // The summary of the build matches its configuration.
+        let summary = feature_summary("2.0.0", &FEATURES);
+        assert_eq!(
+            summary.contains("Enabled features: gps"),
+            cfg!(feature = "gps")
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Logging module.
+//!
+//! Logs are written to the standard output, in the format selected in the `[log]` section of the
+//! configuration file:
+//!
+//! * **Text** (`format = "text"`, default): human readable lines, colored if the output supports
+//!   it.
+//! * **JSON** (`format = "json"`): one JSON object per line, with the `timestamp`, `level`,
+//!   `target` and `message` keys, plus the structured fields of the event, if any. This format is
+//!   meant to be ingested by log aggregators.
+//!
+//! Debug logs are only written in debug mode.
+//!
+//! If `file = true` is set in the `[log]` section, logs are also written, without colors, to files
+//! in the `logs` subdirectory of the data directory. Every run starts a new file, and the current
+//! file is rolled once it reaches its share of the maximum total size. The oldest files are deleted
+//! so that at most `max_files` files and `max_size_mb` MiB are kept, but the file being written is
+//! never deleted.
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Error};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::fmt::{writer::MakeWriterExt, MakeWriter};
+
+use crate::{
+    config::{LogFormat, CONFIG},
+    data::{self, LOG_DIR},
+    error,
+};
+
+/// Default maximum number of log files kept.
+pub const DEFAULT_MAX_LOG_FILES: usize = 10;
+/// Default maximum total size of the log files, in MiB.
+pub const DEFAULT_MAX_LOG_SIZE_MB: u64 = 100;
+/// Prefix of the log file names, followed by their sequence number.
+const LOG_FILE_PREFIX: &str = "openstratos-";
+/// Extension of the log files.
+const LOG_FILE_EXTENSION: &str = "log";
+
+/// Initializes the loggers, as configured.
+///
+/// It must only be called once.
+///
+/// # Errors
+///
+/// Returns an error if the log file can't be created, or if the loggers were already initialized.
+pub fn init_loggers() -> Result<(), Error> {
+    let level = if CONFIG.debug() {
+        Level::DEBUG
+    } else {
+        Level::INFO
+    };
+
+    let log = CONFIG.log();
+    let subscriber = if log.file() {
+        let file = RotatingFile::new(
+            data::ensure_subdir(LOG_DIR)?,
+            log.max_files(),
+            log.max_size(),
+        )
+        .context(error::Log::Appender { name: "file" })?;
+        subscriber(log.format(), level, false, io::stdout.and(Mutex::new(file)))
+    } else {
+        subscriber(log.format(), level, true, io::stdout)
+    };
+
+    tracing::subscriber::set_global_default(subscriber).context(error::Log::Build)
+}
+
+/// Creates a log subscriber with the given format and maximum level, that writes to the given
+/// writer, colored if `ansi` is set.
+fn subscriber<W>(
+    format: LogFormat,
+    level: Level,
+    ansi: bool,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(ansi)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(false)
+                .finish(),
+        ),
+    }
+}
+
+/// Log file writer, that rotates the log files in a directory.
+#[derive(Debug)]
+struct RotatingFile {
+    /// Directory of the log files.
+    dir: PathBuf,
+    /// Maximum number of log files kept.
+    max_files: usize,
+    /// Maximum total size of the log files, in bytes.
+    max_size: u64,
+    /// Sequence number of the current file.
+    sequence: u64,
+    /// Current file.
+    file: File,
+    /// Bytes written to the current file.
+    written: u64,
+}
+
+impl RotatingFile {
+    /// Starts a new log file in the given directory, deleting the oldest files beyond the limits.
+    fn new(dir: PathBuf, max_files: usize, max_size: u64) -> io::Result<Self> {
+        let sequence = log_files(&dir)?
+            .last()
+            .map_or(0, |&(sequence, _)| sequence + 1);
+        let file = open_log_file(&dir, sequence)?;
+        let _ = prune(&dir, sequence, max_files, max_size)?;
+
+        Ok(Self {
+            dir,
+            max_files,
+            max_size,
+            sequence,
+            file,
+            written: 0,
+        })
+    }
+
+    /// Gets the size at which the current file is rolled, its share of the maximum total size.
+    fn file_limit(&self) -> u64 {
+        (self.max_size / self.max_files.max(1) as u64).max(1)
+    }
+
+    /// Closes the current file and starts the next one, deleting the oldest files beyond the
+    /// limits.
+    fn roll(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file = open_log_file(&self.dir, self.sequence + 1)?;
+        self.sequence += 1;
+        self.written = 0;
+        let _ = prune(&self.dir, self.sequence, self.max_files, self.max_size)?;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.file_limit() {
+            self.roll()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Creates the log file with the given sequence number in the given directory.
+fn open_log_file(dir: &Path, sequence: u64) -> io::Result<File> {
+    OpenOptions::new()
+        .append(true)
+        .create_new(true)
+        .open(dir.join(format!(
+            "{LOG_FILE_PREFIX}{sequence:06}.{LOG_FILE_EXTENSION}"
+        )))
+}
+
+/// Lists the log files in the given directory with their sequence numbers, from older to newer.
+fn log_files(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension() != Some(OsStr::new(LOG_FILE_EXTENSION)) {
+            continue;
+        }
+        let sequence = path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .and_then(|stem| stem.strip_prefix(LOG_FILE_PREFIX))
+            .and_then(|sequence| sequence.parse().ok());
+        if let Some(sequence) = sequence {
+            files.push((sequence, path));
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// Deletes the oldest log files in the given directory, so that at most `max_files` files and
+/// `max_size` bytes are kept, returning the deleted files.
+///
+/// The current file, with the given sequence number, is never deleted.
+fn prune(dir: &Path, current: u64, max_files: usize, max_size: u64) -> io::Result<Vec<PathBuf>> {
+    let files = log_files(dir)?;
+    let mut count = files.len();
+    let mut sizes = Vec::with_capacity(count);
+    for (_, path) in &files {
+        sizes.push(fs::metadata(path)?.len());
+    }
+    let mut total: u64 = sizes.iter().sum();
+
+    let mut deleted = Vec::new();
+    for ((sequence, path), size) in files.into_iter().zip(sizes) {
+        if count <= max_files && total <= max_size {
+            break;
+        }
+        if sequence == current {
+            continue;
+        }
+        fs::remove_file(&path)?;
+        count -= 1;
+        total -= size;
+        deleted.push(path);
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{self, Write},
+        process,
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::Value;
+    use tracing::{debug, warn, Level};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::{log_files, subscriber, RotatingFile};
+    use crate::config::LogFormat;
+
+    /// Log writer that stores the log output in memory.
+    #[derive(Debug, Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&'w self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Checks that JSON logs have one valid JSON object per line, with the expected keys.
+    #[test]
+    fn json_log() {
+        let buffer = Buffer::default();
+        tracing::subscriber::with_default(
+            subscriber(LogFormat::Json, Level::INFO, true, buffer.clone()),
+            || {
+                warn!(altitude = 1_500_f32, "Could not send the SMS.");
+                debug!("Not logged.");
+            },
+        );
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+
+        let record: Value = serde_json::from_str(lines[0]).unwrap();
+        assert!(record["timestamp"].is_string());
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["target"], "os_balloon::logger::tests");
+        assert_eq!(record["message"], "Could not send the SMS.");
+        assert_eq!(record["altitude"], 1_500_f64);
+    }
+
+    /// Checks that the oldest log files are deleted when the file count limit is exceeded, but
+    /// never the current one.
+    #[test]
+    fn log_rotation() {
+        let dir = env::temp_dir().join(format!("openstratos_logs_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for sequence in 0..5 {
+            fs::write(dir.join(format!("openstratos-{sequence:06}.log")), "old\n").unwrap();
+        }
+        fs::write(dir.join("other.log"), "not a log file").unwrap();
+
+        let sequences = || {
+            log_files(&dir)
+                .unwrap()
+                .into_iter()
+                .map(|(sequence, _)| sequence)
+                .collect::<Vec<_>>()
+        };
+
+        // A new run starts the sixth file, so the three oldest are deleted.
+        let mut file = RotatingFile::new(dir.clone(), 3, 1_024).unwrap();
+        assert_eq!(sequences(), [3, 4, 5]);
+
+        // Filling the current file rolls it, deleting the next oldest one.
+        file.write_all(&[b'a'; 300]).unwrap();
+        assert_eq!(sequences(), [3, 4, 5]);
+        file.write_all(&[b'b'; 300]).unwrap();
+        assert_eq!(sequences(), [4, 5, 6]);
+        assert!(dir.join("other.log").exists());
+
+        // If the size limit is exceeded, even a single big file is kept while it's being written.
+        let mut file = RotatingFile::new(dir.clone(), 3, 10).unwrap();
+        assert_eq!(sequences(), [7]);
+        file.write_all(&[b'c'; 300]).unwrap();
+        assert_eq!(sequences(), [7]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,6 +2,7 @@
 
 #[cfg(feature = "gps")]
 mod acquiring_fix;
+pub mod diagram;
 #[cfg(not(feature = "gps"))]
 mod eternal_loop;
 #[cfg(feature = "gps")]
@@ -13,13 +14,18 @@ mod going_up;
 mod init;
 #[cfg(feature = "gps")]
 mod landed;
+pub mod preflight;
 mod safe_mode;
 mod shut_down;
 #[cfg(feature = "gps")]
 mod waiting_launch;
 
-use crate::{config::CONFIG, error, STATE_FILE};
-use anyhow::{Context, Error};
+// Only required for the HTTP status endpoint
+#[cfg(feature = "http")]
+pub(crate) use self::init::get_available_disk_space;
+
+use crate::{config::CONFIG, error, recovery, STATE_FILE};
+use anyhow::{Context as _, Error};
 use once_cell::sync::Lazy;
 use std::{
     fmt,
@@ -27,18 +33,721 @@ use std::{
     io::{Read, Write},
     str::FromStr,
     sync::Mutex,
+    thread,
+    time::Duration,
+};
+use tracing::{error, info};
+
+// Only required for GPS
+#[cfg(feature = "gps")]
+use chrono::{DateTime, Utc};
+// Only required for GPS
+#[cfg(feature = "gps")]
+use serde::{Deserialize, Serialize};
+// Only required for GPS
+#[cfg(feature = "gps")]
+use std::{fs, path::Path};
+// Only required for GPS
+#[cfg(feature = "gps")]
+use tracing::warn;
+
+// Only required for GPS
+#[cfg(feature = "gps")]
+use crate::{
+    arm,
+    devices::GpsDevice,
+    geofence::Monitor,
+    gps::{Frame, GPS},
+    gpx::Gpx,
+    kml::{Event, Track},
+    predict::{predict_landing, DescentModel},
+    STATS_FILE,
+};
+// Only required for GPS and FONA
+#[cfg(all(feature = "gps", feature = "fona"))]
+use std::fmt::Write as _;
+
+// Only required for SQLite
+#[cfg(feature = "sqlite")]
+use crate::db::{Database, Logger};
+// Only required for the barometric sensor
+#[cfg(feature = "baro")]
+use crate::{
+    baro::{pressure_to_altitude, AltitudeFilter, BAROMETER, DEFAULT_WEIGHT},
+    config::Baro,
+    devices::BarometerDevice,
 };
-use tracing::error;
+// Only required for FONA
+#[cfg(feature = "fona")]
+use crate::{devices::GsmDevice, fona::FONA};
+// Only required for GPS and FONA
+#[cfg(all(feature = "gps", feature = "fona"))]
+use crate::{
+    fona::Delivery,
+    position::{self, Fix},
+};
+// Only required for Iridium
+#[cfg(feature = "iridium")]
+use crate::{devices::SatelliteDevice, iridium::IRIDIUM, telemetry::SEQUENCE};
+// Only required for telemetry
+#[cfg(feature = "telemetry")]
+use crate::{devices::TelemetryDevice, telemetry::TELEMETRY};
+// Only required for GPS and telemetry
+#[cfg(all(feature = "gps", feature = "telemetry"))]
+use crate::telemetry::{Packet, Position};
+// Only required for Raspicam
+#[cfg(feature = "raspicam")]
+use crate::{devices::CameraDevice, raspicam::CAMERA};
+// Only required for GPS and Raspicam
+#[cfg(all(feature = "gps", feature = "raspicam"))]
+use crate::raspicam::PictureSchedule;
+
+// Only required when powering off
+#[cfg(not(feature = "no_power_off"))]
+use std::io;
 
 static CURRENT_STATE: Lazy<Mutex<State>> = Lazy::new(|| Mutex::new(State::Init));
 
+/// Function that saves the flight statistics.
+#[cfg(feature = "gps")]
+pub type SaveStats = dyn Fn(&FlightStats) -> Result<(), Error>;
+
+/// Context of the state machine.
+///
+/// It contains the devices used by the logic, so that they can be replaced by fake devices in
+/// tests or simulations.
+#[allow(missing_debug_implementations, missing_copy_implementations)]
+pub struct Context {
+    /// GPS device.
+    #[cfg(feature = "gps")]
+    pub gps: Box<dyn GpsDevice>,
+    /// GSM device.
+    #[cfg(feature = "fona")]
+    pub gsm: Box<dyn GsmDevice>,
+    /// Camera device.
+    #[cfg(feature = "raspicam")]
+    pub camera: Box<dyn CameraDevice>,
+    /// Satellite modem.
+    #[cfg(feature = "iridium")]
+    pub satellite: Box<dyn SatelliteDevice>,
+    /// Telemetry link.
+    #[cfg(feature = "telemetry")]
+    pub telemetry: Box<dyn TelemetryDevice>,
+    /// Function used to wait, so that time can be accelerated in tests or simulations.
+    pub sleep: Box<dyn Fn(Duration)>,
+    /// Function that checks if a shutdown was requested, to interrupt long waits.
+    pub shutdown_requested: Box<dyn Fn() -> bool>,
+    /// Function that gets the current time, so that the freshness of the GPS data can be checked
+    /// in tests or simulations.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    pub clock: Box<dyn Fn() -> DateTime<Utc>>,
+    /// Function that checks if the probe is armed, so that it can commit to the launch sequence.
+    #[cfg(feature = "gps")]
+    pub armed: Box<dyn Fn() -> bool>,
+    /// Function that saves the flight statistics, so that tests don't write the statistics file.
+    #[cfg(feature = "gps")]
+    pub save_stats: Box<SaveStats>,
+    /// Statistics of the current flight.
+    #[cfg(feature = "gps")]
+    pub stats: FlightStats,
+    /// Geofence monitor, that checks every frame returned by `next_frame()`.
+    #[cfg(feature = "gps")]
+    pub geofence: Monitor,
+    /// KML track of the flight, with every frame returned by `next_frame()`, once the flight logs
+    /// are started.
+    #[cfg(feature = "gps")]
+    pub track: Option<Track>,
+    /// GPX log of the flight, with every frame returned by `next_frame()`, once the flight logs
+    /// are started.
+    #[cfg(feature = "gps")]
+    pub gpx: Option<Gpx>,
+    /// Flight database logger, with every frame returned by `next_frame()`, the battery readings
+    /// and the state transitions, once the flight logs are started.
+    #[cfg(feature = "sqlite")]
+    pub db: Option<Logger>,
+    /// Schedule of the flight pictures, started at the launch.
+    #[cfg(all(feature = "gps", feature = "raspicam"))]
+    pub pictures: Option<PictureSchedule>,
+    /// Barometric sensor, if it's configured.
+    #[cfg(feature = "baro")]
+    pub barometer: Option<Box<dyn BarometerDevice>>,
+    /// Filter fusing the GPS and the barometric altitudes.
+    #[cfg(feature = "baro")]
+    pub altitude: AltitudeFilter,
+}
+
+impl Context {
+    /// Creates the context for the real hardware, using the shared device singletons.
+    #[must_use]
+    pub fn hardware() -> Self {
+        Self {
+            #[cfg(feature = "gps")]
+            gps: Box::new(&*GPS),
+            #[cfg(feature = "fona")]
+            gsm: Box::new(&*FONA),
+            #[cfg(feature = "raspicam")]
+            camera: Box::new(&*CAMERA),
+            #[cfg(feature = "iridium")]
+            satellite: Box::new(&*IRIDIUM),
+            #[cfg(feature = "telemetry")]
+            telemetry: Box::new(&*TELEMETRY),
+            sleep: Box::new(thread::sleep),
+            shutdown_requested: Box::new(recovery::shutdown_requested),
+            #[cfg(all(feature = "gps", feature = "fona"))]
+            clock: Box::new(Utc::now),
+            #[cfg(feature = "gps")]
+            armed: Box::new(arm::is_armed),
+            #[cfg(feature = "gps")]
+            save_stats: Box::new(FlightStats::save),
+            #[cfg(feature = "gps")]
+            stats: FlightStats::default(),
+            #[cfg(feature = "gps")]
+            geofence: Monitor::new(CONFIG.geofence()),
+            #[cfg(feature = "gps")]
+            track: None,
+            #[cfg(feature = "gps")]
+            gpx: None,
+            #[cfg(feature = "sqlite")]
+            db: None,
+            #[cfg(all(feature = "gps", feature = "raspicam"))]
+            pictures: None,
+            #[cfg(feature = "baro")]
+            barometer: CONFIG
+                .baro()
+                .map(|_| -> Box<dyn BarometerDevice> { Box::new(&*BAROMETER) }),
+            #[cfg(feature = "baro")]
+            altitude: AltitudeFilter::new(CONFIG.baro().map_or(DEFAULT_WEIGHT, Baro::weight)),
+        }
+    }
+
+    /// Starts writing the flight logs, the KML track, the GPX log and the flight database, in the
+    /// `data` directory.
+    ///
+    /// Contexts are created without them, so that tests and pre-flight checks do not write any
+    /// file.
+    #[cfg(feature = "gps")]
+    pub fn start_flight_logs(&mut self) {
+        self.track = Some(Track::in_data_dir());
+        self.gpx = match Gpx::in_data_dir(Utc::now()) {
+            Ok(gpx) => Some(gpx),
+            Err(e) => {
+                error!(error = %e, "Error creating the GPX log.");
+                None
+            }
+        };
+        #[cfg(feature = "sqlite")]
+        {
+            self.db = match Database::open_in_data_dir().and_then(Logger::spawn) {
+                Ok(db) => Some(db),
+                Err(e) => {
+                    error!(error = %e, "Error starting the flight database.");
+                    None
+                }
+            };
+        }
+    }
+
+    /// Waits for the next check interval, and returns the latest valid GPS frame.
+    ///
+    /// It will keep waiting while the GPS has no valid fix. The frame is added to the flight logs,
+    /// checked against the geofences and offered to the telemetry link.
+    #[cfg(feature = "gps")]
+    fn next_frame(&mut self) -> Frame {
+        loop {
+            (self.sleep)(CHECK_INTERVAL);
+            match self.gps.latest_data() {
+                Some(frame) if frame.is_valid() => {
+                    self.check_geofence(&frame);
+                    if let Some(ref mut track) = self.track {
+                        if let Err(e) = track.push(&frame) {
+                            error!(error = %e, "Error writing the KML track.");
+                        }
+                    }
+                    if let Some(ref mut gpx) = self.gpx {
+                        if let Err(e) = gpx.push(&frame) {
+                            error!(error = %e, "Error writing the GPX log.");
+                        }
+                    }
+                    #[cfg(feature = "sqlite")]
+                    if let Some(ref db) = self.db {
+                        db.log_frame(&frame);
+                    }
+                    #[cfg(feature = "telemetry")]
+                    {
+                        let packet = self.telemetry_packet(&frame);
+                        if let Err(e) = self.telemetry.offer(packet) {
+                            error!(error = %e, "Could not send the telemetry packet.");
+                        }
+                    }
+                    return frame;
+                }
+                _ => warn!("No valid GPS fix."),
+            }
+        }
+    }
+
+    /// Marks a flight event in the KML track, if it's being written.
+    #[cfg(feature = "gps")]
+    fn mark_event(&mut self, event: Event, frame: &Frame) {
+        if let Some(ref mut track) = self.track {
+            if let Err(e) = track.mark(event, frame) {
+                error!(
+                    event = event.as_str(),
+                    error = %e,
+                    "Error marking the event in the KML track."
+                );
+            }
+        }
+    }
+
+    /// Updates the predicted landing location with the given frame, ascent rate, in *m/s*, and
+    /// burst altitude, in *m*.
+    ///
+    /// The landing area is assumed to be at the altitude of the launch site.
+    #[cfg(feature = "gps")]
+    fn update_prediction(&mut self, frame: &Frame, ascent_rate: f32, burst_altitude: f32) {
+        let descent_model = DescentModel::default()
+            .with_ground_altitude(self.stats.launch_altitude().unwrap_or_default());
+        self.stats.predicted_landing = Some(predict_landing(
+            frame,
+            ascent_rate,
+            burst_altitude,
+            descent_model,
+        ));
+    }
+
+    /// Gets the position of the probe from the freshest valid source.
+    ///
+    /// If the GPS fix is void or stale, the GSM location is used close to the ground. Check the
+    /// [`position`](../position/index.html) module for further information.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn position(&mut self) -> Option<Fix> {
+        position::locate(&*self.gps, &mut *self.gsm, (self.clock)())
+    }
+
+    /// Gets the distance, in *m*, and the bearing, in *°*, from the launch position to the
+    /// position in the given frame, if the launch position is known.
+    #[cfg(all(feature = "gps", any(feature = "fona", feature = "telemetry")))]
+    fn launch_offset(&self, frame: &Frame) -> Option<(f32, f32)> {
+        self.gps
+            .launch_position()
+            .map(|launch| (launch.distance_to(frame), launch.bearing_to(frame)))
+    }
+
+    /// Creates a telemetry packet with the current state and the position in the given frame.
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    fn telemetry_packet(&self, frame: &Frame) -> Packet {
+        let packet = Packet::new(
+            frame.fix_time(),
+            current_state(),
+            Some(Position::from(*frame)),
+            None,
+            None,
+        );
+        if let Some((distance, bearing)) = self.launch_offset(frame) {
+            packet.with_launch(distance, bearing)
+        } else {
+            packet
+        }
+    }
+
+    /// Takes the scheduled picture for the given frame and altitude, in *m*, if any.
+    ///
+    /// The schedule starts with the first frame. If the camera is recording video, the recording
+    /// is stopped to take the picture, and then started again.
+    #[cfg(all(feature = "gps", feature = "raspicam"))]
+    fn check_pictures(&mut self, frame: &Frame, altitude: f32) {
+        let Some(shot) = self
+            .pictures
+            .get_or_insert_with(|| PictureSchedule::new(frame.fix_time(), CONFIG.picture()))
+            .check(frame.fix_time(), altitude)
+        else {
+            return;
+        };
+
+        info!(altitude, ?shot, "Taking scheduled picture\u{2026}");
+        let recording = self.camera.is_recording();
+        if recording {
+            if let Err(e) = self.camera.stop_recording() {
+                error!(error = %e, "Error stopping the video recording.");
+            }
+        }
+        if let Err(e) = self.camera.take_picture(shot.file_name().as_deref()) {
+            error!(altitude, error = %e, "Error taking the scheduled picture.");
+        }
+        if recording {
+            if let Err(e) = self.camera.record(None, None) {
+                error!(error = %e, "Error restarting the video recording.");
+            }
+        }
+    }
+
+    /// Gets the altitude of the probe in the given frame, in *m*.
+    ///
+    /// If there is a barometric sensor, the GPS altitude is fused with the barometric one.
+    #[cfg(feature = "gps")]
+    #[cfg_attr(not(feature = "baro"), allow(clippy::unused_self))]
+    fn altitude(&mut self, frame: &Frame) -> f32 {
+        #[cfg(feature = "baro")]
+        if let Some(barometer) = &mut self.barometer {
+            let baro = match barometer.pressure() {
+                Ok(pressure) => Some(pressure_to_altitude(pressure)),
+                Err(e) => {
+                    error!(error = %e, "Error reading the barometric sensor.");
+                    None
+                }
+            };
+            return self
+                .altitude
+                .update(Some(frame.altitude()), baro)
+                .unwrap_or_else(|| frame.altitude());
+        }
+
+        frame.altitude()
+    }
+
+    /// Checks the frame against the geofences, sending the alerts of the breached fences by SMS
+    /// and telemetry.
+    #[cfg(feature = "gps")]
+    fn check_geofence(&mut self, frame: &Frame) {
+        for alert in self.geofence.check(frame) {
+            warn!(
+                latitude = frame.latitude(),
+                longitude = frame.longitude(),
+                "Geofence alert: {}",
+                alert
+            );
+
+            #[cfg(feature = "fona")]
+            {
+                let message = self.status_sms("Geofence alert.", frame, &alert.to_string());
+                if !self.send_sms(&message) {
+                    error!(
+                        fence = alert.name(),
+                        "Could not send the geofence alert SMS."
+                    );
+                }
+            }
+
+            #[cfg(feature = "telemetry")]
+            {
+                let packet = self.telemetry_packet(frame);
+                if let Err(e) = self.telemetry.send(&packet) {
+                    error!(
+                        fence = alert.name(),
+                        error = %e,
+                        "Could not send the geofence alert packet."
+                    );
+                }
+            }
+        }
+    }
+
+    /// Sends an SMS, trying a second time if the first one fails.
+    ///
+    /// Returns wether the SMS was sent.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn send_sms(&mut self, message: &str) -> bool {
+        for attempt in 1..=2 {
+            match self.gsm.send_sms(message) {
+                Ok(()) => return true,
+                Err(e) => error!(attempt, error = %e, "Error sending SMS."),
+            }
+        }
+        false
+    }
+
+    /// Sends an SMS, and waits for its delivery report for the given time.
+    ///
+    /// Returns wether the network confirmed the delivery of the SMS.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn send_confirmed_sms(&mut self, message: &str, timeout: Duration) -> bool {
+        match self.gsm.send_sms_confirmed(message, timeout) {
+            Ok(Delivery::Delivered) => true,
+            Ok(Delivery::Failed) => {
+                error!("The network could not deliver the SMS.");
+                false
+            }
+            Err(e) => {
+                error!(error = %e, "Error sending the SMS.");
+                false
+            }
+        }
+    }
+
+    /// Generates a status SMS, with the position in the given frame and the battery status, between
+    /// the given header and footer lines.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn status_sms(&mut self, header: &str, frame: &Frame, footer: &str) -> String {
+        let mut message = String::with_capacity(160);
+        // Writing to a `String` never fails.
+        let _ = write!(
+            message,
+            "{header}\nAlt: {:.0} m\nLat: {:.4}\nLon: {:.4}\nPDOP: {:.2}\nSat: {}\nFix: {}\n{}",
+            frame.altitude(),
+            frame.latitude(),
+            frame.longitude(),
+            frame.pdop(),
+            frame.satellites(),
+            if frame.is_valid() { "OK" } else { "ERR" },
+            self.battery_status(),
+        );
+        if !footer.is_empty() {
+            message.push('\n');
+            message.push_str(footer);
+        }
+        message
+    }
+
+    /// Generates a status SMS with the position of the probe, given the latest frame, between the
+    /// given header and footer lines.
+    ///
+    /// If the frame is stale, the GSM location is used close to the ground, as with
+    /// [`position()`](#method.position), and the SMS is generated with
+    /// [`fix_sms()`](#method.fix_sms).
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn position_sms(&mut self, header: &str, frame: &Frame, footer: &str) -> String {
+        let fix = position::locate_from(*frame, &mut *self.gsm, (self.clock)());
+        self.fix_sms(header, &fix, footer)
+    }
+
+    /// Generates the footer of the status SMSs with the given line, followed by the predicted
+    /// landing location, if there is a prediction.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn prediction_footer(&self, footer: &str) -> String {
+        let Some((latitude, longitude)) = self.stats.predicted_landing() else {
+            return footer.to_owned();
+        };
+        let prediction = format!("Pred: {latitude:.4} {longitude:.4}");
+        if footer.is_empty() {
+            prediction
+        } else {
+            format!("{footer}\n{prediction}")
+        }
+    }
+
+    /// Generates a status SMS, with the given position fix and the battery status, between the
+    /// given header and footer lines.
+    ///
+    /// GPS fixes generate the same SMS as [`status_sms()`](#method.status_sms), while GSM
+    /// locations only have the latitude and longitude, after a line stating their source.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn fix_sms(&mut self, header: &str, fix: &Fix, footer: &str) -> String {
+        let location = match fix {
+            Fix::Gps(frame) => return self.status_sms(header, frame, footer),
+            Fix::Gsm(location) => location,
+        };
+
+        let mut message = String::with_capacity(160);
+        // Writing to a `String` never fails.
+        let _ = write!(
+            message,
+            "{header}\nGSM location\nLat: {:.4}\nLon: {:.4}\n{}",
+            location.latitude(),
+            location.longitude(),
+            self.battery_status(),
+        );
+        if !footer.is_empty() {
+            message.push('\n');
+            message.push_str(footer);
+        }
+        message
+    }
+
+    /// Generates the battery status lines of the status SMSs.
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn battery_status(&mut self) -> String {
+        let main_bat = self.gsm.adc_voltage().ok().map(|voltage| {
+            (voltage - CONFIG.battery().main_min())
+                / (CONFIG.battery().main_max() - CONFIG.battery().main_min())
+        });
+        let gsm_bat = self.gsm.battery_percent().ok();
+        #[cfg(feature = "sqlite")]
+        if let Some(ref db) = self.db {
+            db.log_battery((self.clock)(), main_bat, gsm_bat);
+        }
+        let percent = |battery: Option<f32>| {
+            battery.map_or_else(|| "N/A".to_owned(), |b| format!("{:.0}%", b * 100_f32))
+        };
+
+        format!(
+            "Main bat: {}\nGSM bat: {}",
+            percent(main_bat),
+            percent(gsm_bat)
+        )
+    }
+
+    /// Sends the position in the given frame over Iridium SBD, if the GSM module has no
+    /// connectivity.
+    ///
+    /// Returns wether the position was sent.
+    #[cfg(feature = "iridium")]
+    fn satellite_fallback(&mut self, frame: &Frame) -> bool {
+        match self.gsm.has_connectivity() {
+            Ok(true) => return false,
+            Ok(false) => info!("No GSM connectivity, sending the position over Iridium\u{2026}"),
+            Err(e) => error!(error = %e, "Error checking GSM connectivity."),
+        }
+
+        let mut packet = Packet::new(
+            frame.fix_time(),
+            current_state(),
+            Some(Position::from(*frame)),
+            None,
+            self.gsm.battery_percent().ok(),
+        )
+        .with_sequence(SEQUENCE.next());
+        if let Some((distance, bearing)) = self.launch_offset(frame) {
+            packet = packet.with_launch(distance, bearing);
+        }
+        match self.satellite.send_message(&packet.to_bytes()) {
+            Ok(_) => true,
+            Err(e) => {
+                error!(
+                    altitude = frame.altitude(),
+                    error = %e,
+                    "Error sending the position over Iridium."
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Time between checks of the GPS data during the flight.
+#[cfg(feature = "gps")]
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Default time to wait after acquiring the fix, so that the GPS position stabilizes.
+pub const DEFAULT_FIX_STABILIZATION_TIME: Duration = Duration::from_secs(10);
+/// Minimum time between saves of the flight statistics while they change, in seconds.
+///
+/// They are saved in every state transition too. Saving them more often would only wear the SD
+/// card out.
+#[cfg(feature = "gps")]
+pub const STATS_SAVE_INTERVAL: i64 = 30;
+
+/// Statistics of the flight, updated by the logic as the flight goes on.
+///
+/// They are saved in the statistics file in the `data` directory, so that they survive a restart.
+#[cfg(feature = "gps")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlightStats {
+    /// Altitude of the launch site, in *m*.
+    launch_altitude: Option<f32>,
+    /// Time of the launch.
+    launch_time: Option<DateTime<Utc>>,
+    /// Maximum altitude reached, in *m*.
+    max_altitude: Option<f32>,
+    /// Start time of the float, if the probe floated.
+    float_time: Option<DateTime<Utc>>,
+    /// Time of the burst.
+    burst_time: Option<DateTime<Utc>>,
+    /// Time of the landing.
+    landing_time: Option<DateTime<Utc>>,
+    /// Latest prediction of the landing location, as `(latitude, longitude)` in *°*.
+    predicted_landing: Option<(f32, f32)>,
+}
+
+#[cfg(feature = "gps")]
+impl FlightStats {
+    /// Gets the altitude of the launch site, in *m*, once it's known.
+    #[must_use]
+    pub fn launch_altitude(&self) -> Option<f32> {
+        self.launch_altitude
+    }
+
+    /// Gets the time of the launch, once it has been detected.
+    #[must_use]
+    pub fn launch_time(&self) -> Option<DateTime<Utc>> {
+        self.launch_time
+    }
+
+    /// Gets the maximum altitude reached during the ascent, in *m*.
+    #[must_use]
+    pub fn max_altitude(&self) -> Option<f32> {
+        self.max_altitude
+    }
+
+    /// Gets the start time of the float, if the probe floated before the burst.
+    #[must_use]
+    pub fn float_time(&self) -> Option<DateTime<Utc>> {
+        self.float_time
+    }
+
+    /// Gets the time of the burst, once it has been detected.
+    #[must_use]
+    pub fn burst_time(&self) -> Option<DateTime<Utc>> {
+        self.burst_time
+    }
+
+    /// Gets the time of the landing, once it has been detected.
+    #[must_use]
+    pub fn landing_time(&self) -> Option<DateTime<Utc>> {
+        self.landing_time
+    }
+
+    /// Gets the latest prediction of the landing location, as `(latitude, longitude)` in *°*,
+    /// updated with every frame of the ascent and the descent.
+    #[must_use]
+    pub fn predicted_landing(&self) -> Option<(f32, f32)> {
+        self.predicted_landing
+    }
+
+    /// Saves the statistics in the statistics file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self) -> Result<(), Error> {
+        self.save_to(&CONFIG.data_dir().join(STATS_FILE))
+    }
+
+    /// Loads the statistics saved in the statistics file, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, or if its contents are invalid.
+    pub fn load() -> Result<Option<Self>, Error> {
+        Self::load_from(&CONFIG.data_dir().join(STATS_FILE))
+    }
+
+    /// Saves the statistics in the given file.
+    ///
+    /// They are written to a temporary file that then replaces the given one, so that a power cut
+    /// can't leave a half written file.
+    fn save_to(&self, path: &Path) -> Result<(), Error> {
+        let contents = toml::to_string(self).context(error::Stats::FileWrite)?;
+        let temp_path = path.with_extension("tmp");
+        {
+            let mut file = File::create(&temp_path).context(error::Stats::FileWrite)?;
+            file.write_all(contents.as_bytes())
+                .context(error::Stats::FileWrite)?;
+            file.sync_all().context(error::Stats::FileWrite)?;
+        }
+        fs::rename(&temp_path, path).context(error::Stats::FileWrite)?;
+        Ok(())
+    }
+
+    /// Loads the statistics saved in the given file, if it exists.
+    fn load_from(path: &Path) -> Result<Option<Self>, Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path).context(error::Stats::FileRead)?;
+        Ok(Some(
+            toml::from_str(&contents).context(error::Stats::Invalid)?,
+        ))
+    }
+}
+
 /// Trait representing a state machine.
 pub trait StateMachine {
     /// The logic to run after the current state.
     type Next: MainLogic;
 
-    /// Executes this state and returns the next one.
-    fn execute(self) -> Result<Self::Next, Error>;
+    /// Executes this state with the given context and returns the next one.
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error>;
 }
 
 /// Trait to get the current state in the `State` enum for the current state in the state machine.
@@ -50,35 +759,86 @@ pub trait GetState {
 /// Trait implementing the main logic of the program.
 #[allow(clippy::module_name_repetitions)]
 pub trait MainLogic: GetState {
-    /// Performs the main logic of the state.
-    fn main_logic(self) -> Result<(), Error>;
+    /// Performs the main logic of the state, with the given context.
+    fn main_logic(self, context: &mut Context) -> Result<(), Error>;
 }
 
 impl<S> MainLogic for S
 where
     S: StateMachine + GetState,
 {
-    fn main_logic(self) -> Result<(), Error> {
-        let new_state = self.execute()?;
+    fn main_logic(self, context: &mut Context) -> Result<(), Error> {
+        let new_state = self.execute(context)?;
+        set_current_state(new_state.get_state());
+        info!(state = new_state.get_state().as_str(), "State changed.");
+        #[cfg(feature = "sqlite")]
+        if let Some(ref db) = context.db {
+            db.log_state(Utc::now(), new_state.get_state());
+        }
+        #[cfg(feature = "webhook")]
         {
-            let mut current_state = match CURRENT_STATE.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => {
-                    error!("The CURRENT_STATE mutex was poisoned.");
-                    poisoned.into_inner()
-                }
-            };
-            *current_state = new_state.get_state();
+            #[cfg(feature = "gps")]
+            let altitude = context.gps.latest_data().map(|frame| frame.altitude());
+            #[cfg(not(feature = "gps"))]
+            let altitude = None;
+            crate::webhook::notify(new_state.get_state(), altitude);
         }
 
         save_current_state()?;
+        #[cfg(feature = "gps")]
+        if let Err(e) = (context.save_stats)(&context.stats) {
+            error!(error = %e, "Error saving the flight statistics.");
+        }
+
+        new_state.main_logic(context)
+    }
+}
 
-        new_state.main_logic()
+/// Gets the current state of the probe.
+pub fn current_state() -> State {
+    match CURRENT_STATE.lock() {
+        Ok(guard) => *guard,
+        Err(poisoned) => {
+            error!("The CURRENT_STATE mutex was poisoned.");
+            *poisoned.into_inner()
+        }
+    }
+}
+
+/// Sets the current state of the probe.
+fn set_current_state(state: State) {
+    let mut current_state = match CURRENT_STATE.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The CURRENT_STATE mutex was poisoned.");
+            poisoned.into_inner()
+        }
+    };
+    *current_state = state;
+}
+
+/// Powers the system off.
+///
+/// It takes care of disk synchronization.
+#[cfg(not(feature = "no_power_off"))]
+pub(crate) fn power_off() -> Result<(), io::Error> {
+    use libc::{reboot, sync, RB_POWER_OFF};
+
+    // Safe because `sync()` is always successful.
+    unsafe {
+        sync();
+    }
+
+    // TODO: Why is it safe?
+    if unsafe { reboot(RB_POWER_OFF) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
     }
 }
 
 /// Saves the current state into the state file.
-fn save_current_state() -> Result<(), Error> {
+pub(crate) fn save_current_state() -> Result<(), Error> {
     let path = CONFIG.data_dir().join(STATE_FILE);
     let mut file = OpenOptions::new()
         .write(true)
@@ -122,6 +882,86 @@ pub fn init() -> Result<OpenStratos<Init>, Error> {
     Ok(OpenStratos { state: Init })
 }
 
+/// Resumes the logic after a restart, from the last state saved in the state file.
+///
+/// If the probe was flying or had landed, the logic continues in that state, with the saved flight
+/// statistics. Otherwise, a new flight starts from the initialization.
+///
+/// # Errors
+///
+/// Returns an error if the saved flight statistics can't be read, or if the logic fails.
+pub fn resume(last_state: State, context: &mut Context) -> Result<(), Error> {
+    match last_state {
+        #[cfg(feature = "gps")]
+        State::GoingUp => {
+            prepare_resume(last_state, context)?;
+            OpenStratos { state: GoingUp }.main_logic(context)
+        }
+        #[cfg(feature = "gps")]
+        State::GoingDown => {
+            prepare_resume(last_state, context)?;
+            OpenStratos { state: GoingDown }.main_logic(context)
+        }
+        #[cfg(feature = "gps")]
+        State::Landed => {
+            prepare_resume(last_state, context)?;
+            OpenStratos { state: Landed }.main_logic(context)
+        }
+        _ => {
+            info!(
+                last_state = last_state.as_str(),
+                "Starting a new flight after a restart\u{2026}"
+            );
+            init().context(error::Logic::Init)?.main_logic(context)
+        }
+    }
+}
+
+/// Prepares the context to resume the flight in the given state.
+///
+/// The saved flight statistics are restored, and the devices are initialized again. Errors
+/// initializing the devices are only logged, since the flight goes on anyway.
+#[cfg(feature = "gps")]
+fn prepare_resume(state: State, context: &mut Context) -> Result<(), Error> {
+    info!(state = state.as_str(), "Resuming the flight\u{2026}");
+    set_current_state(state);
+    if let Some(stats) = FlightStats::load().context(error::Stats::Read)? {
+        context.stats = stats;
+    }
+
+    if let Err(e) = init::initialize_gps(context) {
+        error!(error = %e, "Error initializing the GPS.");
+    }
+    #[cfg(feature = "telemetry")]
+    if let Err(e) = init::initialize_telemetry() {
+        error!(error = %e, "Error initializing the telemetry.");
+    }
+    // The going down logic turns the GSM module on once it's needed, and the going up logic turns
+    // it off above the connectivity altitude.
+    #[cfg(feature = "fona")]
+    if state != State::GoingDown {
+        if let Err(e) = context.gsm.initialize() {
+            error!(error = %e, "Error initializing the GSM module.");
+        }
+    }
+    #[cfg(feature = "raspicam")]
+    if state != State::Landed {
+        if let Err(e) = context.camera.record(None, None) {
+            error!(error = %e, "Error starting the video recording.");
+        }
+    }
+    Ok(())
+}
+
+/// Creates a state machine waiting for the GPS fix, skipping the initialization, for simulations.
+#[cfg(feature = "sim")]
+#[must_use]
+pub fn simulation() -> OpenStratos<AcquiringFix> {
+    OpenStratos {
+        state: AcquiringFix,
+    }
+}
+
 /// States of the onboard computer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum State {
@@ -156,6 +996,11 @@ pub enum State {
 
 impl State {
     /// Gets the last state of the application if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the last state file can't be read, or if it does not contain a valid
+    /// state.
     pub fn get_last() -> Result<Option<Self>, Error> {
         let path = CONFIG.data_dir().join(STATE_FILE);
         if !path.exists() {
@@ -175,6 +1020,7 @@ impl State {
     }
 
     /// Gets the state as a string to be stored in the `LAST_STATE` file.
+    #[must_use]
     pub fn as_str(&self) -> &str {
         match *self {
             State::Init => "INITIALIZING",
@@ -196,6 +1042,57 @@ impl State {
             State::EternalLoop => "ETERNAL_LOOP",
         }
     }
+
+    /// Gets the numeric code of the state, used in binary telemetry.
+    ///
+    /// Codes are stable across builds, even if some states are disabled by features.
+    #[must_use]
+    pub fn as_u8(self) -> u8 {
+        match self {
+            State::Init => 0,
+            #[cfg(feature = "gps")]
+            State::AcquiringFix => 1,
+            #[cfg(feature = "gps")]
+            State::FixAcquired => 2,
+            #[cfg(feature = "gps")]
+            State::WaitingLaunch => 3,
+            #[cfg(feature = "gps")]
+            State::GoingUp => 4,
+            #[cfg(feature = "gps")]
+            State::GoingDown => 5,
+            #[cfg(feature = "gps")]
+            State::Landed => 6,
+            State::ShutDown => 7,
+            State::SafeMode => 8,
+            #[cfg(not(feature = "gps"))]
+            State::EternalLoop => 9,
+        }
+    }
+
+    /// Gets the state for the given numeric code, if it's valid in this build.
+    #[must_use]
+    pub fn from_u8(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(State::Init),
+            #[cfg(feature = "gps")]
+            1 => Some(State::AcquiringFix),
+            #[cfg(feature = "gps")]
+            2 => Some(State::FixAcquired),
+            #[cfg(feature = "gps")]
+            3 => Some(State::WaitingLaunch),
+            #[cfg(feature = "gps")]
+            4 => Some(State::GoingUp),
+            #[cfg(feature = "gps")]
+            5 => Some(State::GoingDown),
+            #[cfg(feature = "gps")]
+            6 => Some(State::Landed),
+            7 => Some(State::ShutDown),
+            8 => Some(State::SafeMode),
+            #[cfg(not(feature = "gps"))]
+            9 => Some(State::EternalLoop),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for State {
@@ -346,8 +1243,36 @@ mod tests {
     #[cfg(not(feature = "gps"))]
     use super::EternalLoop;
     #[cfg(feature = "gps")]
+    use super::FlightStats;
+    #[cfg(feature = "gps")]
     use super::{AcquiringFix, FixAcquired, GoingDown, GoingUp, Landed, WaitingLaunch};
     use super::{GetState, Init, SafeMode, ShutDown, State};
+    #[cfg(feature = "sqlite")]
+    use crate::db::{Database, Logger};
+    #[cfg(all(feature = "gps", any(feature = "sqlite", feature = "telemetry")))]
+    use crate::devices::fake::FakeGps;
+    #[cfg(all(
+        feature = "gps",
+        any(feature = "fona", feature = "sqlite", feature = "telemetry")
+    ))]
+    use crate::{
+        devices::fake,
+        gps::{FixStatus, Frame},
+    };
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    use crate::{devices::fake::FakeGsm, fona::Location};
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    use crate::{devices::TelemetryDevice, telemetry::Packet};
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    use anyhow::Error;
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    use chrono::Duration;
+    #[cfg(feature = "gps")]
+    use chrono::{TimeZone, Utc};
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    use std::{cell::RefCell, rc::Rc};
+    #[cfg(feature = "gps")]
+    use std::{env, fs, process};
 
     /// Tests if the `Init` state generates the correct `State` enumeration variant in
     /// `get_state()`.
@@ -631,4 +1556,126 @@ mod tests {
     fn it_as_str_eternal_loop() {
         assert_eq!("ETERNAL_LOOP", State::EternalLoop.as_str());
     }
+
+    /// Checks that the flight statistics survive a restart, through the statistics file.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn stats_survive_restart() {
+        let path = env::temp_dir().join(format!("os_balloon_stats_{}.toml", process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(FlightStats::load_from(&path).unwrap(), None);
+
+        let stats = FlightStats {
+            launch_altitude: Some(650.5),
+            launch_time: Some(Utc.with_ymd_and_hms(2017, 3, 25, 12, 30, 0).unwrap()),
+            max_altitude: Some(31_250.75),
+            float_time: None,
+            burst_time: None,
+            landing_time: None,
+            predicted_landing: Some((42.25, -2.5)),
+        };
+        stats.save_to(&path).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+
+        // The new process starts with empty statistics, and recovers them from the file.
+        assert_eq!(FlightStats::load_from(&path).unwrap(), Some(stats));
+
+        fs::write(&path, "max_altitude = \"high\"").unwrap();
+        assert!(FlightStats::load_from(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Telemetry link that keeps the offered packets.
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    struct OfferRecorder(Rc<RefCell<Vec<Packet>>>);
+
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    impl TelemetryDevice for OfferRecorder {
+        fn offer(&mut self, packet: Packet) -> Result<(), Error> {
+            self.0.borrow_mut().push(packet);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn send(&mut self, _packet: &Packet) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Checks that every valid frame returned by `next_frame()` is offered to the telemetry link.
+    #[test]
+    #[cfg(all(feature = "gps", feature = "telemetry"))]
+    fn next_frame_offers_telemetry() {
+        let offered = Rc::new(RefCell::new(Vec::new()));
+        let mut context = fake::context(|_| {});
+        context.gps = Box::new(FakeGps::new(vec![
+            None,
+            Some(Frame::new(
+                Utc.with_ymd_and_hms(2017, 3, 25, 12, 30, 0).unwrap(),
+                FixStatus::Active,
+                7,
+                42.5,
+                -2.75,
+                1_250_f32,
+            )),
+        ]));
+        context.telemetry = Box::new(OfferRecorder(Rc::clone(&offered)));
+
+        let frame = context.next_frame();
+        let _ = context.next_frame();
+
+        let offered = offered.borrow();
+        assert_eq!(offered.len(), 2);
+        assert_eq!(offered[0].timestamp(), frame.fix_time());
+        let position = offered[0].position().unwrap();
+        assert!((position.altitude() - 1_250_f32).abs() < f32::EPSILON);
+    }
+
+    /// Checks that every valid frame returned by `next_frame()` is logged in the flight database.
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn next_frame_logs_frames() {
+        let fix_time = Utc.with_ymd_and_hms(2017, 3, 25, 12, 30, 0).unwrap();
+        let mut context = fake::context(|_| {});
+        context.gps = Box::new(FakeGps::new(vec![
+            None,
+            Some(Frame::new(
+                fix_time,
+                FixStatus::Active,
+                7,
+                42.5,
+                -2.75,
+                1_250_f32,
+            )),
+        ]));
+        context.db = Some(Logger::spawn(Database::open_in_memory().unwrap()).unwrap());
+
+        let frame = context.next_frame();
+
+        let db = context.db.take().unwrap().finish().unwrap();
+        assert_eq!(db.frames(fix_time, fix_time).unwrap(), vec![frame]);
+    }
+
+    /// Checks that the status SMSs use the GSM location when the latest frame is stale.
+    #[test]
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn position_sms_falls_back_to_gsm() {
+        let fix_time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let frame = Frame::new(fix_time, FixStatus::Active, 7, 42.5, -2.75, 650_f32);
+        let mut context = fake::context(|_| {});
+        context.gsm = Box::new(FakeGsm {
+            location: Some(Location::new(42.51, -2.74)),
+        });
+
+        context.clock = Box::new(move || fix_time);
+        let message = context.position_sms("Landed.", &frame, "");
+        assert!(message.starts_with("Landed.\nAlt: 650 m\nLat: 42.5000\nLon: -2.7500\n"));
+
+        context.clock = Box::new(move || fix_time + Duration::minutes(1));
+        let message = context.position_sms("Landed.", &frame, "");
+        assert!(message.starts_with("Landed.\nGSM location\nLat: 42.5100\nLon: -2.7400\n"));
+    }
 }
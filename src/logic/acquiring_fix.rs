@@ -1,13 +1,159 @@
 //! Acquiring fix logic.
 
+use std::time::Duration;
+
 use anyhow::Error;
+use tracing::{info, warn};
+
+use super::{AcquiringFix, Context, FixAcquired, OpenStratos, StateMachine};
+use crate::{config::CONFIG, error, gps::Frame};
 
-use super::{AcquiringFix, FixAcquired, OpenStratos, StateMachine};
+/// Time between checks of the GPS fix.
+const FIX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 impl StateMachine for OpenStratos<AcquiringFix> {
     type Next = OpenStratos<FixAcquired>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
-        unimplemented!()
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
+        info!("Acquiring GPS fix\u{2026}");
+        loop {
+            while !has_fix(context) {
+                check_shutdown(context)?;
+                (context.sleep)(FIX_CHECK_INTERVAL);
+            }
+
+            let stabilization_time = CONFIG.flight().fix_stabilization_time();
+            info!(
+                "GPS fix acquired, waiting {} seconds for stabilization\u{2026}",
+                stabilization_time.as_secs()
+            );
+            let mut waited = Duration::ZERO;
+            while waited < stabilization_time {
+                check_shutdown(context)?;
+                let interval = FIX_CHECK_INTERVAL.min(stabilization_time.saturating_sub(waited));
+                (context.sleep)(interval);
+                waited += interval;
+            }
+
+            if has_fix(context) {
+                return Ok(OpenStratos { state: FixAcquired });
+            }
+            warn!("The GPS fix was lost during the stabilization.");
+        }
+    }
+}
+
+/// Checks if the GPS has a valid fix.
+fn has_fix(context: &Context) -> bool {
+    context
+        .gps
+        .latest_data()
+        .as_ref()
+        .is_some_and(Frame::is_valid)
+}
+
+/// Returns an error if a shutdown was requested, to stop waiting.
+fn check_shutdown(context: &Context) -> Result<(), Error> {
+    if (context.shutdown_requested)() {
+        warn!("Shutdown requested while acquiring the GPS fix.");
+        Err(error::Logic::ShutdownRequested.into())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        rc::Rc,
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        config::CONFIG,
+        devices::fake::{self, FakeGps},
+        error,
+        gps::{FixStatus, Frame},
+        logic::{AcquiringFix, GetState, OpenStratos, State, StateMachine},
+    };
+
+    /// Total time the fake sleep function has waited, in milliseconds.
+    static SLEPT: AtomicU64 = AtomicU64::new(0);
+
+    /// Fake sleep function, that only accounts for the time.
+    #[allow(clippy::cast_possible_truncation)]
+    fn fake_sleep(time: Duration) {
+        let _ = SLEPT.fetch_add(time.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Checks that the state waits for a valid fix, using a fake GPS.
+    #[test]
+    fn waits_for_fix() {
+        let frame = |status| {
+            Frame::new(
+                Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+                status,
+                5,
+                42.5,
+                -2.75,
+                650_f32,
+            )
+        };
+        let mut context = fake::context(fake_sleep);
+        context.gps = Box::new(FakeGps::new(vec![
+            None,
+            None,
+            Some(frame(FixStatus::Void)),
+            Some(frame(FixStatus::Active)),
+        ]));
+
+        let next = OpenStratos {
+            state: AcquiringFix,
+        }
+        .execute(&mut context)
+        .unwrap();
+
+        assert_eq!(next.get_state(), State::FixAcquired);
+        // One second for each check without fix, and the stabilization time.
+        assert_eq!(
+            u128::from(SLEPT.load(Ordering::SeqCst)),
+            3_000 + CONFIG.flight().fix_stabilization_time().as_millis()
+        );
+    }
+
+    /// Checks that the stabilization wait stops as soon as a shutdown is requested.
+    #[test]
+    fn shutdown_interrupts_stabilization() {
+        let slept = Rc::new(Cell::new(Duration::ZERO));
+        let sleep_slept = Rc::clone(&slept);
+        let mut context = fake::context(move |time| sleep_slept.set(sleep_slept.get() + time));
+        let shutdown_slept = Rc::clone(&slept);
+        context.shutdown_requested =
+            Box::new(move || shutdown_slept.get() >= Duration::from_secs(3));
+        context.gps = Box::new(FakeGps::new(vec![Some(Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            FixStatus::Active,
+            5,
+            42.5,
+            -2.75,
+            650_f32,
+        ))]));
+
+        let error = OpenStratos {
+            state: AcquiringFix,
+        }
+        .execute(&mut context)
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(error::Logic::ShutdownRequested)
+        ));
+        assert_eq!(slept.get(), Duration::from_secs(3));
     }
 }
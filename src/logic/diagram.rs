@@ -0,0 +1,307 @@
+//! State diagram.
+//!
+//! The transitions of the state machine are encoded at the type level, in the `Next` type of each
+//! `StateMachine` implementation, so they can't be inspected at runtime. This module keeps a table
+//! of those transitions, checked against the real implementations by the tests, and generates a
+//! [Graphviz](https://graphviz.org/) DOT description of the state machine from it.
+//!
+//! Running the launcher with the `--state-diagram` flag prints the diagram, that can be rendered
+//! with `cargo run -- --state-diagram | dot -Tsvg > states.svg`. Every state and transition is
+//! included, even those disabled in the current build, which are drawn dashed. Feature-gated states
+//! and transitions are labelled with the feature that enables them.
+
+use std::fmt::Write;
+
+/// Transition of the state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// State the transition starts at, as stored in the state file.
+    from: &'static str,
+    /// State the transition ends at, as stored in the state file.
+    to: &'static str,
+    /// Feature condition required for the transition, if any.
+    feature: Option<&'static str>,
+    /// Wether the transition exists in the current build.
+    enabled: bool,
+}
+
+impl Transition {
+    /// Gets the state the transition starts at, as stored in the state file.
+    #[must_use]
+    pub fn from(&self) -> &'static str {
+        self.from
+    }
+
+    /// Gets the state the transition ends at, as stored in the state file.
+    #[must_use]
+    pub fn to(&self) -> &'static str {
+        self.to
+    }
+
+    /// Gets the feature condition required for the transition, if any.
+    #[must_use]
+    pub fn feature(&self) -> Option<&'static str> {
+        self.feature
+    }
+
+    /// Checks if the transition exists in the current build.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// States of the state machine, with the feature condition required for them, if any.
+pub const STATES: [(&str, Option<&str>); 10] = [
+    ("INITIALIZING", None),
+    ("ACQUIRING_FIX", Some("gps")),
+    ("FIX_ACQUIRED", Some("gps")),
+    ("WAITING_LAUNCH", Some("gps")),
+    ("GOING_UP", Some("gps")),
+    ("GOING_DOWN", Some("gps")),
+    ("LANDED", Some("gps")),
+    ("ETERNAL_LOOP", Some("not gps")),
+    ("SHUT_DOWN", None),
+    ("SAFE_MODE", None),
+];
+
+/// Transitions of the state machine.
+///
+/// It must be kept in sync with the `Next` types of the `StateMachine` implementations.
+pub const TRANSITIONS: [Transition; 9] = [
+    Transition {
+        from: "INITIALIZING",
+        to: "ACQUIRING_FIX",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "INITIALIZING",
+        to: "ETERNAL_LOOP",
+        feature: Some("not gps"),
+        enabled: cfg!(not(feature = "gps")),
+    },
+    Transition {
+        from: "ACQUIRING_FIX",
+        to: "FIX_ACQUIRED",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "FIX_ACQUIRED",
+        to: "WAITING_LAUNCH",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "WAITING_LAUNCH",
+        to: "GOING_UP",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "GOING_UP",
+        to: "GOING_DOWN",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "GOING_DOWN",
+        to: "LANDED",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "LANDED",
+        to: "SHUT_DOWN",
+        feature: Some("gps"),
+        enabled: cfg!(feature = "gps"),
+    },
+    Transition {
+        from: "ETERNAL_LOOP",
+        to: "SHUT_DOWN",
+        feature: Some("not gps"),
+        enabled: cfg!(not(feature = "gps")),
+    },
+];
+
+/// Generates the Graphviz DOT description of the state machine.
+#[must_use]
+pub fn dot() -> String {
+    let mut dot = String::from("digraph OpenStratos {\n    node [shape = box];\n");
+
+    // Writing to a `String` never fails.
+    for (state, feature) in &STATES {
+        match feature {
+            Some(feature) => {
+                let enabled = TRANSITIONS.iter().any(|transition| {
+                    transition.enabled && (transition.from == *state || transition.to == *state)
+                });
+                let style = if enabled { "solid" } else { "dashed" };
+                let _ = writeln!(
+                    dot,
+                    "    {state} [label = \"{state}\\n({feature})\", style = {style}];"
+                );
+            }
+            None => {
+                let _ = writeln!(dot, "    {state};");
+            }
+        }
+    }
+    for transition in &TRANSITIONS {
+        let style = if transition.enabled {
+            "solid"
+        } else {
+            "dashed"
+        };
+        let _ = write!(
+            dot,
+            "    {} -> {} [style = {style}",
+            transition.from, transition.to
+        );
+        if let Some(feature) = transition.feature {
+            let _ = write!(dot, ", label = \"{feature}\"");
+        }
+        dot.push_str("];\n");
+    }
+    dot.push_str("}\n");
+
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{dot, STATES, TRANSITIONS};
+    #[cfg(not(feature = "gps"))]
+    use crate::logic::EternalLoop;
+    #[cfg(feature = "gps")]
+    use crate::logic::{AcquiringFix, FixAcquired, GoingDown, GoingUp, Landed, WaitingLaunch};
+    use crate::logic::{GetState, Init, OpenStratos, SafeMode, ShutDown, State, StateMachine};
+
+    /// States that can be created out of thin air, to inspect the `Next` type of a state.
+    trait Unit: GetState {
+        /// Creates the state.
+        fn unit() -> Self;
+    }
+
+    /// Implements `Unit` for the given states, and for the state machine in them.
+    macro_rules! unit {
+        ($($state:ident),*) => {
+            $(
+                impl Unit for $state {
+                    fn unit() -> Self {
+                        $state
+                    }
+                }
+
+                impl Unit for OpenStratos<$state> {
+                    fn unit() -> Self {
+                        OpenStratos { state: $state }
+                    }
+                }
+            )*
+        };
+    }
+
+    unit!(Init, ShutDown, SafeMode);
+    #[cfg(feature = "gps")]
+    unit!(
+        AcquiringFix,
+        FixAcquired,
+        WaitingLaunch,
+        GoingUp,
+        GoingDown,
+        Landed
+    );
+    #[cfg(not(feature = "gps"))]
+    unit!(EternalLoop);
+
+    /// Gets the transition defined by the `Next` type of the given state machine.
+    fn transition<S>() -> (&'static str, &'static str)
+    where
+        S: StateMachine + Unit,
+        S::Next: Unit,
+    {
+        (
+            state_name(S::unit().get_state()),
+            state_name(S::Next::unit().get_state()),
+        )
+    }
+
+    /// Gets the name of the state, as stored in the state file.
+    fn state_name(state: State) -> &'static str {
+        STATES
+            .iter()
+            .map(|&(name, _)| name)
+            .find(|name| *name == state.as_str())
+            .expect("state missing in the diagram")
+    }
+
+    /// Checks that the transition table matches the `StateMachine` implementations of the build.
+    #[test]
+    fn transitions_match_implementations() {
+        let mut implemented = BTreeSet::new();
+        let _ = implemented.insert(transition::<OpenStratos<Init>>());
+        #[cfg(feature = "gps")]
+        {
+            let _ = implemented.insert(transition::<OpenStratos<AcquiringFix>>());
+            let _ = implemented.insert(transition::<OpenStratos<FixAcquired>>());
+            let _ = implemented.insert(transition::<OpenStratos<WaitingLaunch>>());
+            let _ = implemented.insert(transition::<OpenStratos<GoingUp>>());
+            let _ = implemented.insert(transition::<OpenStratos<GoingDown>>());
+            let _ = implemented.insert(transition::<OpenStratos<Landed>>());
+        }
+        #[cfg(not(feature = "gps"))]
+        {
+            let _ = implemented.insert(transition::<OpenStratos<EternalLoop>>());
+        }
+
+        let table: BTreeSet<_> = TRANSITIONS
+            .iter()
+            .filter(|transition| transition.is_enabled())
+            .map(|transition| (transition.from(), transition.to()))
+            .collect();
+        assert_eq!(table, implemented);
+
+        // Terminal states are in the diagram too.
+        let _ = state_name(ShutDown::unit().get_state());
+        let _ = state_name(SafeMode::unit().get_state());
+    }
+
+    /// Checks that every transition joins states of the diagram.
+    #[test]
+    fn transitions_join_states() {
+        for transition in &TRANSITIONS {
+            for state in [transition.from(), transition.to()] {
+                assert!(STATES.iter().any(|&(name, _)| name == state), "{state}");
+            }
+        }
+    }
+
+    /// Checks the generated DOT description.
+    #[test]
+    fn dot_description() {
+        let dot = dot();
+
+        assert!(dot.starts_with("digraph OpenStratos {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    INITIALIZING;\n"));
+        assert!(dot.contains("    SAFE_MODE;\n"));
+        #[cfg(feature = "gps")]
+        {
+            assert!(dot.contains("    LANDED [label = \"LANDED\\n(gps)\", style = solid];\n"));
+            assert!(dot.contains(
+                "    ETERNAL_LOOP [label = \"ETERNAL_LOOP\\n(not gps)\", style = dashed];\n"
+            ));
+            assert!(dot.contains("    GOING_UP -> GOING_DOWN [style = solid, label = \"gps\"];\n"));
+        }
+        #[cfg(not(feature = "gps"))]
+        {
+            assert!(dot.contains("    LANDED [label = \"LANDED\\n(gps)\", style = dashed];\n"));
+            assert!(dot.contains("    GOING_UP -> GOING_DOWN [style = dashed, label = \"gps\"];\n"));
+        }
+        assert_eq!(dot.matches(" -> ").count(), TRANSITIONS.len());
+    }
+}
@@ -2,12 +2,12 @@
 
 use anyhow::Error;
 
-use super::{EternalLoop, OpenStratos, ShutDown, StateMachine};
+use super::{Context, EternalLoop, OpenStratos, ShutDown, StateMachine};
 
 impl StateMachine for OpenStratos<EternalLoop> {
     type Next = OpenStratos<ShutDown>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
+    fn execute(self, _context: &mut Context) -> Result<Self::Next, Error> {
         unimplemented!()
     }
 }
@@ -1,13 +1,147 @@
 //! Fix acquired logic.
+//!
+//! If arming is required in the configuration, the probe holds here after starting the video
+//! recording, until it's armed. Check the [`arm`](../../arm/index.html) module for further
+//! information.
 
 use anyhow::Error;
+use tracing::{info, warn};
 
-use super::{FixAcquired, OpenStratos, StateMachine, WaitingLaunch};
+// Only required for FONA or Raspicam
+#[cfg(any(feature = "fona", feature = "raspicam"))]
+use tracing::error;
+
+use super::{Context, FixAcquired, OpenStratos, StateMachine, WaitingLaunch};
+use crate::error;
 
 impl StateMachine for OpenStratos<FixAcquired> {
     type Next = OpenStratos<WaitingLaunch>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
-        unimplemented!()
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
+        #[cfg(feature = "raspicam")]
+        {
+            info!("Starting video recording\u{2026}");
+            if let Err(e) = context.camera.record(None, None) {
+                error!(error = %e, "Error starting the video recording.");
+            }
+        }
+
+        if !(context.armed)() {
+            wait_arm(context)?;
+        }
+
+        #[cfg(feature = "fona")]
+        {
+            let frame = context.next_frame();
+            let message = context.position_sms("Init: OK.", &frame, "Waiting launch.");
+            if !context.send_sms(&message) {
+                error!(
+                    altitude = frame.altitude(),
+                    "Could not send the initialization SMS."
+                );
+            }
+        }
+
+        info!("Waiting for launch\u{2026}");
+        Ok(OpenStratos {
+            state: WaitingLaunch,
+        })
+    }
+}
+
+/// Holds, keeping track of the GPS data, until the probe is armed.
+///
+/// Returns an error if a shutdown is requested while waiting.
+fn wait_arm(context: &mut Context) -> Result<(), Error> {
+    info!("Waiting for the probe to be armed\u{2026}");
+
+    #[cfg(feature = "fona")]
+    {
+        let frame = context.next_frame();
+        let message = context.position_sms("Not armed.", &frame, "Waiting arm.");
+        if !context.send_sms(&message) {
+            error!(
+                altitude = frame.altitude(),
+                "Could not send the arm waiting SMS."
+            );
+        }
+    }
+
+    loop {
+        if (context.shutdown_requested)() {
+            warn!("Shutdown requested while waiting for the probe to be armed.");
+            return Err(error::Logic::ShutdownRequested.into());
+        }
+        if (context.armed)() {
+            info!("Probe armed.");
+            return Ok(());
+        }
+        let _ = context.next_frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        devices::fake::{self, FakeGps},
+        error,
+        gps::{FixStatus, Frame},
+        logic::{Context, FixAcquired, GetState, OpenStratos, State, StateMachine},
+    };
+
+    /// Creates a fake context with a valid fix, and the total time it has waited.
+    fn context() -> (Context, Rc<Cell<Duration>>) {
+        let slept = Rc::new(Cell::new(Duration::ZERO));
+        let sleep_slept = Rc::clone(&slept);
+        let mut context = fake::context(move |time| sleep_slept.set(sleep_slept.get() + time));
+        context.gps = Box::new(FakeGps::new(vec![Some(Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            FixStatus::Active,
+            5,
+            42.5,
+            -2.75,
+            650_f32,
+        ))]));
+        (context, slept)
+    }
+
+    /// Checks that the launch sequence is not started until the probe is armed.
+    #[test]
+    fn holds_until_armed() {
+        let (mut context, slept) = context();
+        let armed_slept = Rc::clone(&slept);
+        context.armed = Box::new(move || armed_slept.get() >= Duration::from_secs(5));
+
+        let next = OpenStratos { state: FixAcquired }
+            .execute(&mut context)
+            .unwrap();
+
+        assert_eq!(next.get_state(), State::WaitingLaunch);
+        assert!(slept.get() >= Duration::from_secs(5));
+    }
+
+    /// Checks that the arming wait stops as soon as a shutdown is requested.
+    #[test]
+    fn shutdown_interrupts_arm_wait() {
+        let (mut context, slept) = context();
+        context.armed = Box::new(|| false);
+        let shutdown_slept = Rc::clone(&slept);
+        context.shutdown_requested =
+            Box::new(move || shutdown_slept.get() >= Duration::from_secs(3));
+
+        let error = OpenStratos { state: FixAcquired }
+            .execute(&mut context)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(error::Logic::ShutdownRequested)
+        ));
+        assert_eq!(slept.get(), Duration::from_secs(3));
     }
 }
@@ -1,13 +1,207 @@
 //! Going down logic.
 
 use anyhow::Error;
+use chrono::Duration;
+use tracing::{error, info};
 
-use super::{GoingDown, Landed, OpenStratos, StateMachine};
+use super::{Context, GoingDown, Landed, OpenStratos, StateMachine};
+use crate::{config::CONFIG, kml::Event};
 
 impl StateMachine for OpenStratos<GoingDown> {
     type Next = OpenStratos<Landed>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
-        unimplemented!()
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
+        #[cfg(feature = "fona")]
+        let mut gsm_on = false;
+        let flight = CONFIG.flight();
+        #[cfg(feature = "fona")]
+        let mut sms_altitudes = flight.descent_sms_altitudes().iter().peekable();
+
+        // The landing is detected once the altitude stays within the tolerance for the stable time.
+        let stable_time = Duration::seconds(i64::from(flight.landing_stable_secs()));
+        let tolerance = flight.landing_altitude_tolerance();
+        loop {
+            let frame = context.next_frame();
+            #[cfg(feature = "raspicam")]
+            context.check_pictures(&frame, frame.altitude());
+            context.update_prediction(&frame, 0_f32, frame.altitude());
+
+            #[cfg(feature = "fona")]
+            if sms_altitudes
+                .next_if(|&&altitude| frame.altitude() < altitude)
+                .is_some()
+            {
+                // Only one SMS is sent if several marks were crossed at once.
+                while sms_altitudes
+                    .next_if(|&&altitude| frame.altitude() < altitude)
+                    .is_some()
+                {}
+
+                if !gsm_on {
+                    gsm_on = initialize_gsm(context);
+                }
+                let footer = context.prediction_footer("");
+                let message = context.position_sms("Descending.", &frame, &footer);
+                if !context.send_sms(&message) {
+                    error!(
+                        altitude = frame.altitude(),
+                        "Could not send the descent SMS."
+                    );
+                    #[cfg(feature = "iridium")]
+                    let _ = context.satellite_fallback(&frame);
+                }
+            }
+
+            let history = context.gps.history();
+            let landed = history.window(stable_time).is_some_and(|window| {
+                let (min_altitude, max_altitude) = window.iter().fold(
+                    (f32::INFINITY, f32::NEG_INFINITY),
+                    |(min_altitude, max_altitude), frame| {
+                        (
+                            min_altitude.min(frame.altitude()),
+                            max_altitude.max(frame.altitude()),
+                        )
+                    },
+                );
+                max_altitude - min_altitude <= tolerance
+            });
+            if landed {
+                context.stats.landing_time = Some(frame.fix_time());
+                info!(
+                    "Landing detected at {}, altitude: {} m.",
+                    frame.fix_time(),
+                    frame.altitude()
+                );
+                context.mark_event(Event::Landing, &frame);
+                if let Some(ref mut gpx) = context.gpx {
+                    if let Err(e) = gpx.end_segment() {
+                        error!(error = %e, "Error closing the GPX track segment.");
+                    }
+                }
+
+                #[cfg(feature = "fona")]
+                if !gsm_on {
+                    let _ = initialize_gsm(context);
+                }
+
+                return Ok(OpenStratos { state: Landed });
+            }
+        }
+    }
+}
+
+/// Turns the GSM module back on after the flight, returning wether it was initialized.
+#[cfg(feature = "fona")]
+fn initialize_gsm(context: &mut Context) -> bool {
+    info!("Turning the GSM module on\u{2026}");
+    match context.gsm.initialize() {
+        Ok(()) => true,
+        Err(e) => {
+            error!(error = %e, "Error initializing the GSM module.");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use crate::{
+        config::CONFIG,
+        devices::fake::{self, FakeGps},
+        gps::{FixStatus, Frame},
+        gpx::Gpx,
+        kml::Track,
+        logic::{GetState, GoingDown, OpenStratos, State, StateMachine},
+    };
+
+    /// Checks that the landing is detected once the altitude stays constant, using a fake GPS, that
+    /// it's marked in the KML track, and that the GPX log is written up to the landing.
+    #[test]
+    fn detects_landing() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let frames: Vec<_> = (0..300)
+            .map(|second| {
+                #[allow(clippy::cast_precision_loss)]
+                let altitude = (2_000_f32 - 10_f32 * second as f32).max(650_f32);
+                Some(frame(start + Duration::seconds(second), altitude))
+            })
+            .collect();
+        let dir = env::temp_dir().join(format!("os_balloon_landing_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let track = dir.join("track.kml");
+        let mut context = fake::context(|_| {});
+        context.gps = Box::new(FakeGps::new(frames));
+        context.track = Some(Track::new(&track));
+        context.gpx = Some(Gpx::create(&dir, start).unwrap());
+
+        let next = OpenStratos { state: GoingDown }
+            .execute(&mut context)
+            .unwrap();
+
+        assert_eq!(next.get_state(), State::Landed);
+        // The probe reaches the ground after 135 seconds, but it's already within the altitude
+        // tolerance of the ground 2 seconds before.
+        let flight = CONFIG.flight();
+        assert!((flight.landing_altitude_tolerance() - 20_f32).abs() < f32::EPSILON);
+        let stable_secs = i64::from(flight.landing_stable_secs());
+        assert_eq!(
+            context.stats.landing_time(),
+            Some(start + Duration::seconds(133 + stable_secs))
+        );
+        assert!(fs::read_to_string(&track)
+            .unwrap()
+            .contains("<name>Landing</name>"));
+        // Every frame until the landing is written, even if it's not a full batch.
+        let gpx = fs::read_to_string(context.gpx.as_ref().unwrap().path()).unwrap();
+        assert_eq!(
+            gpx.matches("<trkpt").count(),
+            usize::try_from(134 + stable_secs).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Checks that a slow descent is not detected as a landing, and that a noisy plateau is.
+    #[test]
+    fn detects_landing_plateau() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        // A slow parachute descends at 0.5 m/s for 5 minutes, 150 m, and then the probe lands at
+        // 650 m, with ±8 m of GPS noise.
+        let frames: Vec<_> = (0..900)
+            .map(|second| {
+                #[allow(clippy::cast_precision_loss)]
+                let altitude = if second < 300 {
+                    800_f32 - 0.5 * second as f32
+                } else if second % 2 == 0 {
+                    658_f32
+                } else {
+                    642_f32
+                };
+                Some(frame(start + Duration::seconds(second), altitude))
+            })
+            .collect();
+        let mut context = fake::context(|_| {});
+        context.gps = Box::new(FakeGps::new(frames));
+
+        let _ = OpenStratos { state: GoingDown }
+            .execute(&mut context)
+            .unwrap();
+
+        // The descent loses 30 m during the stable time, more than the tolerance, so the landing
+        // window starts at the first descent frame within 20 m of the lowest noisy altitude, at
+        // 276 s.
+        let stable_secs = i64::from(CONFIG.flight().landing_stable_secs());
+        assert_eq!(
+            context.stats.landing_time(),
+            Some(start + Duration::seconds(276 + stable_secs))
+        );
+    }
+
+    /// Creates a valid frame with the given time and altitude.
+    fn frame(fix_time: DateTime<Utc>, altitude: f32) -> Frame {
+        Frame::new(fix_time, FixStatus::Active, 8, 42.5, -2.75, altitude)
     }
 }
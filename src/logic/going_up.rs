@@ -1,13 +1,188 @@
 //! Going up logic.
 
 use anyhow::Error;
+use chrono::Duration;
+use tracing::{error, info};
 
-use super::{GoingDown, GoingUp, OpenStratos, StateMachine};
+use super::{Context, GoingDown, GoingUp, OpenStratos, StateMachine, STATS_SAVE_INTERVAL};
+use crate::{config::CONFIG, kml::Event};
+
+/// Altitude at which the GSM module is turned off before losing connectivity, in *m*.
+#[cfg(feature = "fona")]
+pub const GSM_OFF_ALTITUDE: f32 = 1_500_f32;
+/// Time between status reports while floating, in seconds.
+const FLOAT_REPORT_INTERVAL: i64 = 1_800;
 
 impl StateMachine for OpenStratos<GoingUp> {
     type Next = OpenStratos<GoingDown>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
-        unimplemented!()
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
+        let first = context.next_frame();
+
+        #[cfg(feature = "fona")]
+        let mut gsm_on = {
+            let message = context.position_sms("Launched.", &first, "");
+            if !context.send_sms(&message) {
+                error!(
+                    altitude = first.altitude(),
+                    "Could not send the launch SMS."
+                );
+            }
+            true
+        };
+
+        // The maximum altitude of a previous run is kept, in case the probe was restarted.
+        let altitude = context.altitude(&first);
+        let mut max_altitude = context
+            .stats
+            .max_altitude()
+            .map_or(altitude, |max_altitude| max_altitude.max(altitude));
+        let mut stats_saved = first.fix_time();
+        let mut level_since = None;
+        let mut float_reported = None;
+        loop {
+            let frame = context.next_frame();
+            let altitude = context.altitude(&frame);
+            #[cfg(feature = "raspicam")]
+            context.check_pictures(&frame, altitude);
+
+            #[cfg(feature = "fona")]
+            if gsm_on && frame.altitude() > GSM_OFF_ALTITUDE {
+                let footer = context.prediction_footer("Going dark.");
+                let message = context.position_sms("Launch OK.", &frame, &footer);
+                if !context.send_sms(&message) {
+                    error!(
+                        altitude = frame.altitude(),
+                        "Could not send the going dark SMS."
+                    );
+                }
+                if let Err(e) = context.gsm.turn_off() {
+                    error!(error = %e, "Error turning the GSM module off.");
+                }
+                gsm_on = false;
+            }
+
+            max_altitude = max_altitude.max(altitude);
+            context.stats.max_altitude = Some(max_altitude);
+            if frame.fix_time() - stats_saved >= Duration::seconds(STATS_SAVE_INTERVAL) {
+                if let Err(e) = (context.save_stats)(&context.stats) {
+                    error!(error = %e, "Error saving the flight statistics.");
+                }
+                stats_saved = frame.fix_time();
+            }
+
+            let flight = CONFIG.flight();
+            let rate = context.gps.smoothed_ascent_rate();
+            if let Some(rate) = rate {
+                #[allow(clippy::cast_precision_loss)]
+                context.update_prediction(&frame, rate, flight.expected_max_height() as f32);
+            }
+            if context.stats.float_time.is_none() {
+                let level = altitude >= flight.float_min_altitude()
+                    && rate.is_some_and(|rate| rate.abs() < flight.float_ascent_rate());
+                if level {
+                    let since = *level_since.get_or_insert(frame.fix_time());
+                    let detection_time =
+                        Duration::seconds(i64::from(flight.float_detection_secs()));
+                    if frame.fix_time() - since >= detection_time {
+                        context.stats.float_time = Some(since);
+                        info!("Float detected since {}, altitude: {} m.", since, altitude);
+                    }
+                } else {
+                    level_since = None;
+                }
+            }
+
+            if context.stats.float_time.is_some()
+                && float_reported.is_none_or(|reported| {
+                    frame.fix_time() - reported >= Duration::seconds(FLOAT_REPORT_INTERVAL)
+                })
+            {
+                // The GSM module is off at float altitudes, so the position is only sent in the
+                // telemetry packets.
+                info!(altitude, "Floating.");
+                float_reported = Some(frame.fix_time());
+            }
+
+            // The vertical oscillations of a float can be fast for a while, so while floating only
+            // a sustained altitude loss is detected as the burst.
+            let fast_descent = context.stats.float_time.is_none()
+                && rate.is_some_and(|rate| -rate > flight.burst_descent_rate());
+            if fast_descent || altitude < max_altitude - flight.burst_altitude_loss() {
+                context.stats.burst_time = Some(frame.fix_time());
+                info!(
+                    "Burst detected at {}, maximum altitude: {} m.",
+                    frame.fix_time(),
+                    max_altitude
+                );
+                context.mark_event(Event::Burst, &frame);
+
+                return Ok(OpenStratos { state: GoingDown });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use crate::{
+        config::CONFIG,
+        devices::fake::{self, FakeGps},
+        gps::{FixStatus, Frame},
+        logic::{GetState, GoingUp, OpenStratos, State, StateMachine},
+    };
+
+    /// Checks that a float is detected, that a fast oscillation while floating is not detected as
+    /// the burst, and that the following sustained descent is.
+    #[test]
+    fn detects_float() {
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        // The balloon ascends at 5 m/s up to 12,000 m in 2,200 s, and floats there for an hour,
+        // with a 200 m downdraft at 20 m/s after half an hour. Then, it descends at 5 m/s.
+        let frames: Vec<_> = (0..7_000)
+            .map(|second| {
+                #[allow(clippy::cast_precision_loss)]
+                let altitude = match second {
+                    0..=2_199 => 1_000_f32 + 5_f32 * second as f32,
+                    4_000..=4_009 => 12_000_f32 - 20_f32 * (second - 3_999) as f32,
+                    4_010..=4_019 => 11_800_f32 + 20_f32 * (second - 4_009) as f32,
+                    5_800.. => 12_000_f32 - 5_f32 * (second - 5_799) as f32,
+                    _ => 12_000_f32,
+                };
+                Some(frame(start + Duration::seconds(second), altitude))
+            })
+            .collect();
+        let mut context = fake::context(|_| {});
+        context.gps = Box::new(FakeGps::new(frames));
+
+        let next = OpenStratos { state: GoingUp }
+            .execute(&mut context)
+            .unwrap();
+
+        assert_eq!(next.get_state(), State::GoingDown);
+        // The balloon reaches 12,000 m at 2,200 s, and the smoothed rate is level once the last
+        // ascending rate is out of the averaging window.
+        let flight = CONFIG.flight();
+        let window = i64::try_from(CONFIG.gps().rate_window()).unwrap();
+        let float_time = start + Duration::seconds(2_200 + window);
+        assert_eq!(context.stats.float_time(), Some(float_time));
+        // The burst altitude loss is reached during the sustained descent.
+        #[allow(clippy::cast_possible_truncation)]
+        let loss = flight.burst_altitude_loss() as i64 / 5;
+        assert_eq!(
+            context.stats.burst_time(),
+            Some(start + Duration::seconds(5_799 + loss + 1))
+        );
+        // Without horizontal speed, the probe is predicted to land right below it.
+        let (latitude, longitude) = context.stats.predicted_landing().unwrap();
+        assert!((latitude - 42.5).abs() < 1e-4);
+        assert!((longitude + 2.75).abs() < 1e-4);
+    }
+
+    /// Creates a valid frame with the given time and altitude.
+    fn frame(fix_time: DateTime<Utc>, altitude: f32) -> Frame {
+        Frame::new(fix_time, FixStatus::Active, 8, 42.5, -2.75, altitude)
     }
 }
@@ -1,37 +1,43 @@
 //! Initialization logic.
 
-use std::io;
+use std::{io, path::Path};
 
 // Only required for FONA or Raspicam
 #[cfg(any(feature = "fona", feature = "raspicam"))]
 use std::time::Duration;
 
-// Only required for FONA
-#[cfg(feature = "fona")]
-use std::thread;
-
 // Only required when no powering off
 #[cfg(feature = "no_power_off")]
 use std::process;
 
-#[cfg(any(feature = "gps", feature = "fona", feature = "raspicam"))]
-use anyhow::Context;
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
+use anyhow::Context as _;
 use tracing::{error, info};
 
-#[cfg(any(feature = "gps", feature = "fona", feature = "raspicam"))]
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
 use super::error as crate_error;
+#[cfg(not(feature = "no_power_off"))]
+use super::power_off;
 #[cfg(feature = "gps")]
 use super::AcquiringFix;
 #[cfg(not(feature = "gps"))]
 use super::EternalLoop;
-use super::{Error, Init, OpenStratos, StateMachine, CONFIG};
+use super::{Context, Error, Init, OpenStratos, StateMachine, CONFIG};
 
-#[cfg(feature = "fona")]
-use crate::fona::FONA;
-#[cfg(feature = "gps")]
-use crate::gps::GPS;
 #[cfg(feature = "raspicam")]
-use crate::raspicam::VIDEO_DIR;
+use crate::data::VIDEO_DIR;
+#[cfg(feature = "telemetry")]
+use crate::telemetry::TELEMETRY;
 
 /// Test video file.
 #[cfg(feature = "raspicam")]
@@ -44,13 +50,17 @@ impl StateMachine for OpenStratos<Init> {
     #[cfg(not(feature = "gps"))]
     type Next = OpenStratos<EternalLoop>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
+    #[cfg_attr(
+        not(any(feature = "gps", feature = "fona", feature = "raspicam")),
+        allow(unused_variables)
+    )]
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
         check_disk_space()?;
 
         #[cfg(feature = "gps")]
         #[allow(clippy::question_mark)]
         {
-            if let Err(e) = initialize_gps() {
+            if let Err(e) = initialize_gps(context) {
                 // TODO: shut down GPS.
                 return Err(e);
             }
@@ -59,16 +69,25 @@ impl StateMachine for OpenStratos<Init> {
         #[cfg(feature = "fona")]
         #[allow(clippy::question_mark)]
         {
-            if let Err(e) = initialize_fona() {
+            if let Err(e) = initialize_fona(context) {
                 // TODO: shut down GPS (if feature enabled) and FONA.
                 return Err(e);
             }
         }
 
+        #[cfg(feature = "telemetry")]
+        #[allow(clippy::question_mark)]
+        {
+            if let Err(e) = initialize_telemetry() {
+                // TODO: shut down GPS (if feature enabled) and FONA (if feature enabled).
+                return Err(e);
+            }
+        }
+
         #[cfg(feature = "raspicam")]
         #[allow(clippy::question_mark)]
         {
-            if let Err(e) = test_raspicam() {
+            if let Err(e) = test_raspicam(context) {
                 // TODO: shut down GPS (if feature enabled) and FONA (if feature enabled).
                 return Err(e);
             }
@@ -88,8 +107,22 @@ impl StateMachine for OpenStratos<Init> {
     }
 }
 
-/// Checks if the available disk space is enough.
+/// Checks if the available disk space is enough, powering off if it's not.
 fn check_disk_space() -> Result<(), Error> {
+    if !has_enough_disk_space()?.0 {
+        #[cfg(not(feature = "no_power_off"))]
+        power_off()?;
+        #[cfg(feature = "no_power_off")]
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Checks if the available disk space is enough for the flight.
+///
+/// Returns wether it's enough, and the available disk space, in bytes.
+pub(super) fn has_enough_disk_space() -> Result<(bool, u64), Error> {
     let disk_space = get_available_disk_space()?;
 
     #[allow(clippy::cast_precision_loss)]
@@ -116,103 +149,80 @@ fn check_disk_space() -> Result<(), Error> {
     let enough_space = disk_space > 2 * 1024 * 1024 * 1024; // 2 GiB
 
     if !enough_space {
-        error!("Not enough disk space.");
-        #[cfg(not(feature = "no_power_off"))]
-        power_off()?;
-        #[cfg(feature = "no_power_off")]
-        process::exit(1);
+        error!(available = disk_space, "Not enough disk space.");
     }
 
-    Ok(())
+    Ok((enough_space, disk_space))
 }
 
 /// Initializes the GPS module.
 #[cfg(feature = "gps")]
-fn initialize_gps() -> Result<(), Error> {
+pub(super) fn initialize_gps(context: &mut Context) -> Result<(), Error> {
     info!("Initializing GPS\u{2026}");
-    match GPS.lock() {
-        Ok(mut gps) => gps.initialize().context(crate_error::Init::Gps)?,
-        Err(poisoned) => {
-            error!("The GPS mutex was poisoned.");
-            poisoned
-                .into_inner()
-                .initialize()
-                .context(crate_error::Init::Gps)?;
-        }
-    }
+    context.gps.initialize().context(crate_error::Init::Gps)?;
     info!("GPS initialized.");
     Ok(())
 }
 
 /// Initializes the FONA module.
 #[cfg(feature = "fona")]
-fn initialize_fona() -> Result<(), Error> {
+pub(super) fn initialize_fona(context: &mut Context) -> Result<(), Error> {
     info!("Initializing Adafruit FONA GSM module\u{2026}");
-    match FONA.lock() {
-        Ok(mut fona) => fona.initialize().context(crate_error::Init::Fona)?,
-        Err(poisoned) => {
-            error!("The FONA mutex was poisoned.");
-            poisoned
-                .into_inner()
-                .initialize()
-                .context(crate_error::Init::Fona)?;
-        }
-    }
+    context.gsm.initialize().context(crate_error::Init::Fona)?;
     info!("Adafruit FONA GSM module initialized.");
 
-    check_batteries()?;
+    let _ = check_batteries(context)?;
 
     info!("Waiting for GSM connectivity\u{2026}");
-    while {
-        match FONA.lock() {
-            Ok(mut fona) => !fona
-                .has_connectivity()
-                .context(crate_error::Init::CheckGsmConnectivity)?,
-            Err(poisoned) => {
-                error!("The FONA mutex was poisoned.");
-                !poisoned
-                    .into_inner()
-                    .has_connectivity()
-                    .context(crate_error::Init::CheckGsmConnectivity)?
-            }
-        }
-    } {
-        thread::sleep(Duration::from_secs(1));
+    while !context
+        .gsm
+        .has_connectivity()
+        .context(crate_error::Init::CheckGsmConnectivity)?
+    {
+        (context.sleep)(Duration::from_secs(1));
     }
     info!("GSM connected.");
 
     Ok(())
 }
 
+/// Initializes the telemetry serial.
+#[cfg(feature = "telemetry")]
+pub(super) fn initialize_telemetry() -> Result<(), Error> {
+    info!("Initializing telemetry\u{2026}");
+    let mut telemetry = match TELEMETRY.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The TELEMETRY mutex was poisoned.");
+            poisoned.into_inner()
+        }
+    };
+    telemetry
+        .initialize()
+        .context(crate_error::Init::Telemetry)?;
+    telemetry
+        .start_command_reader()
+        .context(crate_error::Init::Telemetry)?;
+    info!("Telemetry initialized.");
+    Ok(())
+}
+
 /// Checks the batteries of the probe using the FONA's built-in ADC.
+///
+/// Returns the charge of the main and FONA batteries, from 0 to 1. The main battery charge is
+/// negative if it's disconnected.
 #[cfg(feature = "fona")]
-fn check_batteries() -> Result<(), Error> {
+pub(super) fn check_batteries(context: &mut Context) -> Result<(f32, f32), Error> {
     info!("Checking batteries\u{2026}");
 
-    let fona_bat_percent = match FONA.lock() {
-        Ok(mut fona) => fona
-            .battery_percent()
-            .context(crate_error::Init::CheckBatteries)?,
-        Err(poisoned) => {
-            error!("The FONA mutex was poisoned.");
-            poisoned
-                .into_inner()
-                .battery_percent()
-                .context(crate_error::Init::CheckBatteries)?
-        }
-    };
-    let adc_voltage = match FONA.lock() {
-        Ok(mut fona) => fona
-            .adc_voltage()
-            .context(crate_error::Init::CheckBatteries)?,
-        Err(poisoned) => {
-            error!("The FONA mutex was poisoned.");
-            poisoned
-                .into_inner()
-                .adc_voltage()
-                .context(crate_error::Init::CheckBatteries)?
-        }
-    };
+    let fona_bat_percent = context
+        .gsm
+        .battery_percent()
+        .context(crate_error::Init::CheckBatteries)?;
+    let adc_voltage = context
+        .gsm
+        .adc_voltage()
+        .context(crate_error::Init::CheckBatteries)?;
     let main_bat_percent = (adc_voltage - CONFIG.battery().main_min())
         / (CONFIG.battery().main_max() - CONFIG.battery().main_min());
 
@@ -233,45 +243,21 @@ fn check_batteries() -> Result<(), Error> {
     if (main_bat_percent < CONFIG.battery().main_min_percent() && main_bat_percent > -1_f32)
         || fona_bat_percent < CONFIG.battery().fona_min_percent()
     {
-        error!("Not enough battery.");
+        error!(
+            main_battery = main_bat_percent,
+            gsm_battery = fona_bat_percent,
+            "Not enough battery."
+        );
         Err(crate_error::Init::NotEnoughBattery.into())
     } else {
-        Ok(())
+        Ok((main_bat_percent, fona_bat_percent))
     }
 }
 
-/// Performs a test in the Raspicam module.
+/// Performs a test in the Raspicam module, powering off if it fails.
 #[cfg(feature = "raspicam")]
-fn test_raspicam() -> Result<(), Error> {
-    use std::fs::remove_file;
-
-    use crate::raspicam::CAMERA;
-
-    info!("Testing camera recording\u{2026}");
-    info!("Recording 10 seconds as test\u{2026}");
-    match CAMERA.lock() {
-        Ok(mut cam) => cam
-            .record(Duration::from_secs(10), TEST_VIDEO_FILE)
-            .context(crate_error::Raspicam::Test)?,
-        Err(poisoned) => {
-            error!("The CAMERA mutex was poisoned.");
-            poisoned
-                .into_inner()
-                .record(Duration::from_secs(10), TEST_VIDEO_FILE)
-                .context(crate_error::Raspicam::Test)?;
-        }
-    }
-
-    let video_path = CONFIG.data_dir().join(VIDEO_DIR).join(TEST_VIDEO_FILE);
-    if video_path.exists() {
-        info!("Camera test OK.");
-        info!("Removing test file\u{2026}");
-        remove_file(&video_path).context(crate_error::Raspicam::TestRemove {
-            test_file: video_path,
-        })?;
-        info!("Test file removed.");
-    } else {
-        error!("Camera test file was not created.");
+fn test_raspicam(context: &mut Context) -> Result<(), Error> {
+    if !check_camera(context, CONFIG.video().skip_camera_test())? {
         // TODO
         // logger.log("Turning GSM off...");
         // if (GSM::get_instance().turn_off())
@@ -293,42 +279,109 @@ fn test_raspicam() -> Result<(), Error> {
     Ok(())
 }
 
+/// Checks the camera, only detecting it if `skip_test` is set, or recording a 10 second test
+/// video otherwise.
+///
+/// Returns wether the check passed.
+#[cfg(feature = "raspicam")]
+fn check_camera(context: &mut Context, skip_test: bool) -> Result<bool, Error> {
+    if skip_test {
+        info!("Skipping the camera test recording, only detecting the camera\u{2026}");
+        context
+            .camera
+            .detect()
+            .context(crate_error::Raspicam::Detect)
+    } else {
+        camera_test(context, Duration::from_secs(10))
+    }
+}
+
+/// Records a test video for the given time, and removes it.
+///
+/// Returns wether the test video was created.
+#[cfg(feature = "raspicam")]
+pub(super) fn camera_test(context: &mut Context, time: Duration) -> Result<bool, Error> {
+    use std::fs::remove_file;
+
+    info!("Testing camera recording\u{2026}");
+    info!("Recording {} seconds as test\u{2026}", time.as_secs());
+    context
+        .camera
+        .record(Some(time), Some(Path::new(TEST_VIDEO_FILE)))
+        .context(crate_error::Raspicam::Test)?;
+
+    let video_path = CONFIG.data_dir().join(VIDEO_DIR).join(TEST_VIDEO_FILE);
+    if video_path.exists() {
+        info!("Camera test OK.");
+        info!("Removing test file\u{2026}");
+        remove_file(&video_path).context(crate_error::Raspicam::TestRemove {
+            test_file: video_path,
+        })?;
+        info!("Test file removed.");
+        Ok(true)
+    } else {
+        error!("Camera test file was not created.");
+        Ok(false)
+    }
+}
+
 /// Gets the available disk space for OpenStratos.
-fn get_available_disk_space() -> Result<u64, Error> {
-    use std::{ffi::CString, mem, os::unix::ffi::OsStrExt};
+pub(crate) fn get_available_disk_space() -> Result<u64, Error> {
+    available_disk_space(CONFIG.data_dir())
+}
 
-    let dir = CString::new(CONFIG.data_dir().as_os_str().as_bytes())?;
+/// Gets the available disk space, in bytes, in the file system containing the given path.
+fn available_disk_space<P>(path: P) -> Result<u64, Error>
+where
+    P: AsRef<Path>,
+{
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
 
-    let mut stats: libc::statvfs;
-    // TODO: Why is it safe?
-    let res = unsafe {
-        stats = mem::uninitialized();
-        libc::statvfs(dir.as_ptr(), &mut stats)
-    };
+    let dir = CString::new(path.as_ref().as_os_str().as_bytes())?;
+
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+    // Safe because `dir` is a valid NUL-terminated string and `stats` points to writable memory
+    // with the size and alignment of `statvfs`, that will not be read unless the call succeeds.
+    let res = unsafe { libc::statvfs(dir.as_ptr(), stats.as_mut_ptr()) };
 
     if res == 0 {
+        // Safe because `statvfs()` fully initializes the structure when it succeeds.
+        let stats = unsafe { stats.assume_init() };
         Ok(stats.f_bsize * stats.f_bavail)
     } else {
         Err(io::Error::last_os_error().into())
     }
 }
 
-/// Powers the system off.
-///
-/// It takes care of disk synchronization.
-#[cfg(not(feature = "no_power_off"))]
-fn power_off() -> Result<(), io::Error> {
-    use libc::{reboot, sync, RB_POWER_OFF};
+#[cfg(test)]
+mod tests {
+    use super::available_disk_space;
+    #[cfg(feature = "raspicam")]
+    use super::check_camera;
+    #[cfg(feature = "raspicam")]
+    use crate::devices::fake;
 
-    // Safe because `sync()` is always successful.
-    unsafe {
-        sync();
+    /// Checks that the available disk space of the current directory is plausible.
+    #[test]
+    fn disk_space() {
+        let space = available_disk_space(".").unwrap();
+        assert!(space > 0);
     }
 
-    // TODO: Why is it safe?
-    if unsafe { reboot(RB_POWER_OFF) } == -1 {
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(())
+    /// Checks that a non-existent directory produces an error.
+    #[test]
+    fn disk_space_error() {
+        assert!(available_disk_space("/this/path/should/not/exist").is_err());
+    }
+
+    /// Checks that skipping the camera test only detects the camera, while the full test records
+    /// a video, that the fake camera never creates.
+    #[test]
+    #[cfg(feature = "raspicam")]
+    fn camera_check_paths() {
+        let mut context = fake::context(|_| {});
+        assert!(check_camera(&mut context, true).unwrap());
+        assert!(!check_camera(&mut context, false).unwrap());
+        assert!(!context.camera.is_recording());
     }
 }
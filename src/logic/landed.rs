@@ -1,13 +1,114 @@
 //! Landed logic.
 
+use std::time::Duration;
+
 use anyhow::Error;
+use tracing::info;
+
+// Only required for FONA, Raspicam or telemetry
+#[cfg(any(feature = "fona", feature = "raspicam", feature = "telemetry"))]
+use tracing::error;
+
+use super::{Context, Landed, OpenStratos, ShutDown, StateMachine};
+// Only required for FONA
+#[cfg(feature = "fona")]
+use super::CHECK_INTERVAL;
+// Only required for FONA
+#[cfg(feature = "fona")]
+use crate::{config::CONFIG, position::Fix};
+// Only required for FONA
+#[cfg(feature = "fona")]
+use tracing::warn;
 
-use super::{Landed, OpenStratos, ShutDown, StateMachine};
+/// Time to wait between the two landed SMSs.
+pub const SECOND_SMS_WAIT: Duration = Duration::from_mins(10);
+/// Time to wait before retrying a failed landed SMS.
+#[cfg(feature = "fona")]
+const SMS_RETRY_INTERVAL: Duration = Duration::from_mins(1);
 
 impl StateMachine for OpenStratos<Landed> {
     type Next = OpenStratos<ShutDown>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
-        unimplemented!()
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
+        // No more frames are offered after the landing, so the landing position is sent now.
+        #[cfg(feature = "telemetry")]
+        if let Err(e) = context.telemetry.flush() {
+            error!(error = %e, "Could not send the landing telemetry packet.");
+        }
+
+        #[cfg(feature = "raspicam")]
+        if context.camera.is_recording() {
+            info!("Stopping video recording\u{2026}");
+            if let Err(e) = context.camera.stop_recording() {
+                error!(error = %e, "Error stopping the video recording.");
+            }
+        }
+
+        #[cfg(feature = "fona")]
+        send_landed_sms(context, "Landed.");
+
+        info!(
+            "Waiting {} minutes for the second landed SMS\u{2026}",
+            SECOND_SMS_WAIT.as_secs() / 60
+        );
+        (context.sleep)(SECOND_SMS_WAIT);
+
+        #[cfg(feature = "fona")]
+        send_landed_sms(context, "Landed 2nd.");
+
+        Ok(OpenStratos { state: ShutDown })
+    }
+}
+
+/// Sends a landed SMS with the current position, retrying until it gets sent.
+///
+/// If the GPS fix is void or stale, the GSM location is sent instead. If a delivery timeout is
+/// configured, the SMS is retried until the network confirms its delivery.
+///
+/// The distance and bearing from the launch position are added at the end of the SMS, so that
+/// the recovery team knows where to head from the launch site.
+///
+/// If the GSM module has no connectivity, the GPS position is also sent once over Iridium.
+#[cfg(feature = "fona")]
+fn send_landed_sms(context: &mut Context, header: &str) {
+    #[cfg(feature = "iridium")]
+    let mut satellite_sent = false;
+    loop {
+        (context.sleep)(CHECK_INTERVAL);
+        let Some(fix) = context.position() else {
+            warn!("No GPS fix or GSM location available.");
+            continue;
+        };
+        let offset = match &fix {
+            Fix::Gps(frame) => context.launch_offset(frame),
+            Fix::Gsm(_) => None,
+        };
+        let footer = offset.map_or_else(
+            || "Launch: N/A".to_owned(),
+            |(distance, bearing)| {
+                format!(
+                    "Launch: {:.1} km {:.0}\u{b0}",
+                    distance / 1_000_f32,
+                    bearing
+                )
+            },
+        );
+        let message = context.fix_sms(header, &fix, &footer);
+        let sent = match CONFIG.fona().delivery_timeout() {
+            Some(timeout) => context.send_confirmed_sms(&message, timeout),
+            None => context.send_sms(&message),
+        };
+        if sent {
+            return;
+        }
+        error!(
+            source = fix.source().as_str(),
+            "Could not send the landed SMS, retrying\u{2026}"
+        );
+        #[cfg(feature = "iridium")]
+        if let (false, Fix::Gps(frame)) = (satellite_sent, &fix) {
+            satellite_sent = context.satellite_fallback(frame);
+        }
+        (context.sleep)(SMS_RETRY_INTERVAL);
     }
 }
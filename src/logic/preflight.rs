@@ -0,0 +1,327 @@
+//! Pre-flight checkout.
+//!
+//! Before a real launch, every subsystem of the probe can be exercised without starting the flight
+//! logic, by running the launcher with the `--preflight` flag. These checks are run, in order:
+//!
+//! 1. The available disk space is enough for the flight.
+//! 2. The camera records a 3 second test video.
+//! 3. The GPS gets initialized and acquires a valid fix.
+//! 4. The FONA module gets initialized and registered in the GSM network, and sends a test SMS to
+//!    the configured phone number.
+//! 5. The batteries have enough charge.
+//! 6. A telemetry packet is sent through the telemetry radio, so that the ground station can check
+//!    the link.
+//!
+//! Checks of disabled features are skipped. A failed check does not stop the checkout, and a
+//! summary with the result of every check is printed at the end.
+
+use std::fmt;
+
+use anyhow::Error;
+use tracing::{error, info};
+
+// Only required for GPS or Raspicam
+#[cfg(any(feature = "gps", feature = "raspicam"))]
+use std::time::Duration;
+
+// Only required for GPS or Raspicam
+#[cfg(any(feature = "gps", feature = "raspicam"))]
+use anyhow::bail;
+// Only required for telemetry
+#[cfg(feature = "telemetry")]
+use chrono::Utc;
+
+use super::{init, Context};
+// Only required for GPS
+#[cfg(feature = "gps")]
+use super::CHECK_INTERVAL;
+// Only required for GPS
+#[cfg(feature = "gps")]
+use crate::gps::Frame;
+// Only required for telemetry
+#[cfg(feature = "telemetry")]
+use super::current_state;
+// Only required for telemetry
+#[cfg(feature = "telemetry")]
+use crate::telemetry::{Packet, TELEMETRY};
+
+/// Length of the test video, in seconds.
+#[cfg(feature = "raspicam")]
+pub const CAMERA_TEST_TIME: Duration = Duration::from_secs(3);
+/// Maximum time to wait for a valid GPS fix.
+#[cfg(feature = "gps")]
+pub const FIX_TIMEOUT: Duration = Duration::from_mins(5);
+/// Text of the test SMS.
+#[cfg(feature = "fona")]
+pub const TEST_SMS: &str = "OpenStratos pre-flight test SMS.";
+
+/// Runs the pre-flight checkout with the devices in the given context.
+#[cfg_attr(
+    not(any(feature = "gps", feature = "fona", feature = "raspicam")),
+    allow(unused_variables)
+)]
+pub fn run(context: &mut Context) -> Report {
+    let mut report = Report::default();
+
+    let _ = report.check("Disk space", || {
+        let (enough, available) = init::has_enough_disk_space()?;
+        #[allow(clippy::cast_precision_loss)]
+        let gib = available as f32 / 1024_f32 / 1024_f32 / 1024_f32;
+        if enough {
+            Ok(format!("{gib:.2} GiB available"))
+        } else {
+            Err(Error::msg(format!("only {gib:.2} GiB available")))
+        }
+    });
+
+    #[cfg(feature = "raspicam")]
+    let _ = report.check("Camera", || {
+        if init::camera_test(context, CAMERA_TEST_TIME)? {
+            Ok(format!(
+                "{} s test video recorded",
+                CAMERA_TEST_TIME.as_secs()
+            ))
+        } else {
+            bail!("the test video was not created")
+        }
+    });
+
+    #[cfg(feature = "gps")]
+    {
+        let gps_ok = report.check("GPS", || {
+            init::initialize_gps(context)?;
+            Ok("initialized".to_owned())
+        });
+        if gps_ok {
+            let _ = report.check("GPS fix", || acquire_fix(context));
+        }
+    }
+
+    #[cfg(feature = "fona")]
+    {
+        let fona_ok = report.check("GSM registration", || {
+            init::initialize_fona(context)?;
+            Ok("registered".to_owned())
+        });
+        if fona_ok {
+            let _ = report.check("Test SMS", || {
+                context.gsm.send_sms(TEST_SMS)?;
+                Ok("sent".to_owned())
+            });
+        }
+        let _ = report.check("Batteries", || {
+            let (main, fona) = init::check_batteries(context)?;
+            Ok(format!(
+                "main: {}, GSM: {:.0}%",
+                if main > -1_f32 {
+                    format!("{:.0}%", main * 100_f32)
+                } else {
+                    "disconnected".to_owned()
+                },
+                fona * 100_f32
+            ))
+        });
+    }
+
+    #[cfg(feature = "telemetry")]
+    {
+        let _ = report.check("Telemetry", || {
+            init::initialize_telemetry()?;
+            let packet = Packet::new(Utc::now(), current_state(), None, None, None);
+            match TELEMETRY.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    error!("The TELEMETRY mutex was poisoned.");
+                    poisoned.into_inner()
+                }
+            }
+            .send(&packet)?;
+            Ok("test packet sent".to_owned())
+        });
+    }
+
+    report
+}
+
+/// Waits for a valid GPS fix, for at most [`FIX_TIMEOUT`](constant.FIX_TIMEOUT.html).
+#[cfg(feature = "gps")]
+fn acquire_fix(context: &mut Context) -> Result<String, Error> {
+    info!("Waiting for a valid GPS fix\u{2026}");
+    let attempts = FIX_TIMEOUT.as_secs() / CHECK_INTERVAL.as_secs();
+    for _ in 0..attempts {
+        (context.sleep)(CHECK_INTERVAL);
+        if let Some(frame) = context.gps.latest_data().filter(Frame::is_valid) {
+            return Ok(format!(
+                "lat: {:.4}, lon: {:.4}, alt: {:.0} m, {} satellites",
+                frame.latitude(),
+                frame.longitude(),
+                frame.altitude(),
+                frame.satellites()
+            ));
+        }
+    }
+    bail!("no valid fix after {} minutes", FIX_TIMEOUT.as_secs() / 60)
+}
+
+/// Result of a pre-flight check.
+#[derive(Debug)]
+pub struct Check {
+    /// Name of the check.
+    name: &'static str,
+    /// Details of the result, if the check passed, or the error, if it failed.
+    result: Result<String, Error>,
+}
+
+impl Check {
+    /// Gets the name of the check.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Checks if the check passed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Gets the result of the check.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the check, if it failed.
+    pub fn result(&self) -> Result<&str, &Error> {
+        self.result.as_deref()
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.result {
+            Ok(details) => write!(f, "[PASS] {}: {details}", self.name),
+            Err(e) => write!(f, "[FAIL] {}: {e:#}", self.name),
+        }
+    }
+}
+
+/// Pre-flight checkout report, with the results of all the checks.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Results of the checks, in order.
+    checks: Vec<Check>,
+}
+
+impl Report {
+    /// Runs a check, and adds its result to the report.
+    ///
+    /// Returns wether the check passed.
+    pub fn check<F>(&mut self, name: &'static str, check: F) -> bool
+    where
+        F: FnOnce() -> Result<String, Error>,
+    {
+        info!("Pre-flight check: {}\u{2026}", name);
+        let check = Check {
+            name,
+            result: check(),
+        };
+        match &check.result {
+            Ok(details) => info!("Pre-flight check passed: {}: {}", name, details),
+            Err(e) => error!("Pre-flight check failed: {}: {:#}", name, e),
+        }
+
+        let passed = check.passed();
+        self.checks.push(check);
+        passed
+    }
+
+    /// Gets the results of all the checks, in order.
+    #[must_use]
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    /// Gets the checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| !check.passed())
+    }
+
+    /// Checks if all the checks passed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pre-flight checkout:")?;
+        for check in &self.checks {
+            writeln!(f, "  {check}")?;
+        }
+        let failures = self.failures().count();
+        if failures == 0 {
+            write!(f, "All {} checks passed.", self.checks.len())
+        } else {
+            write!(f, "{failures} of {} checks failed.", self.checks.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+
+    use super::Report;
+
+    /// Checks that the report keeps the failures of the checks, in order.
+    #[test]
+    fn report_failures() {
+        let mut report = Report::default();
+        assert!(report.check("Disk space", || Ok("10.00 GiB available".to_owned())));
+        assert!(!report.check("GPS fix", || bail!("no valid fix after 5 minutes")));
+        assert!(report.check("Test SMS", || Ok("sent".to_owned())));
+        assert!(!report.check("Telemetry", || bail!("no serial")));
+
+        assert!(!report.passed());
+        assert_eq!(report.checks().len(), 4);
+        let failures: Vec<_> = report.failures().map(|check| check.name()).collect();
+        assert_eq!(failures, ["GPS fix", "Telemetry"]);
+        assert_eq!(
+            report.checks()[1].result().unwrap_err().to_string(),
+            "no valid fix after 5 minutes"
+        );
+        assert_eq!(
+            report.to_string(),
+            "Pre-flight checkout:\n  [PASS] Disk space: 10.00 GiB available\n  \
+             [FAIL] GPS fix: no valid fix after 5 minutes\n  [PASS] Test SMS: sent\n  \
+             [FAIL] Telemetry: no serial\n2 of 4 checks failed."
+        );
+    }
+
+    /// Checks that a report passes if all the checks pass.
+    #[test]
+    fn report_passed() {
+        let mut report = Report::default();
+        assert!(report.check("Disk space", || Ok("10.00 GiB available".to_owned())));
+        assert!(report.passed());
+        assert!(report.to_string().ends_with("All 1 checks passed."));
+    }
+
+    /// Runs the checkout with fake devices, checking that the failures of the fake GPS and camera
+    /// are reported.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn fake_devices() {
+        use crate::devices::fake;
+
+        let report = super::run(&mut fake::context(|_| {}));
+
+        assert!(!report.passed());
+        assert_eq!(report.checks()[0].name(), "Disk space");
+        let failures: Vec<_> = report.failures().map(|check| check.name()).collect();
+        assert!(failures.contains(&"GPS fix"));
+        assert!(!failures.contains(&"GPS"));
+        #[cfg(feature = "raspicam")]
+        assert!(failures.contains(&"Camera"));
+    }
+}
@@ -1,11 +1,64 @@
 //! Shut down logic.
 
+// Only required when no powering off
+#[cfg(feature = "no_power_off")]
+use std::process;
+
 use anyhow::Error;
+use tracing::info;
+
+// Only required for GPS, FONA, Raspicam or telemetry
+#[cfg(any(
+    feature = "gps",
+    feature = "fona",
+    feature = "raspicam",
+    feature = "telemetry"
+))]
+use tracing::error;
 
-use super::{MainLogic, OpenStratos, ShutDown};
+#[cfg(not(feature = "no_power_off"))]
+use super::power_off;
+use super::{Context, MainLogic, OpenStratos, ShutDown};
 
 impl MainLogic for OpenStratos<ShutDown> {
-    fn main_logic(self) -> Result<(), Error> {
-        unimplemented!()
+    #[cfg_attr(
+        not(any(
+            feature = "gps",
+            feature = "fona",
+            feature = "raspicam",
+            feature = "telemetry"
+        )),
+        allow(unused_variables)
+    )]
+    fn main_logic(self, context: &mut Context) -> Result<(), Error> {
+        info!("Shutting down\u{2026}");
+
+        #[cfg(feature = "telemetry")]
+        if let Err(e) = context.telemetry.flush() {
+            error!(error = %e, "Could not send the last telemetry packet.");
+        }
+
+        #[cfg(feature = "raspicam")]
+        if context.camera.is_recording() {
+            if let Err(e) = context.camera.stop_recording() {
+                error!(error = %e, "Error stopping the video recording.");
+            }
+        }
+        #[cfg(feature = "fona")]
+        if let Err(e) = context.gsm.turn_off() {
+            error!(error = %e, "Error turning the GSM module off.");
+        }
+        #[cfg(feature = "gps")]
+        if let Err(e) = context.gps.turn_off() {
+            error!(error = %e, "Error turning the GPS off.");
+        }
+
+        #[cfg(not(feature = "no_power_off"))]
+        power_off()?;
+        #[cfg(feature = "no_power_off")]
+        process::exit(0);
+
+        #[cfg(not(feature = "no_power_off"))]
+        Ok(())
     }
 }
@@ -1,13 +1,35 @@
 //! Waiting launch logic.
 
 use anyhow::Error;
+use tracing::info;
 
-use super::{GoingUp, OpenStratos, StateMachine, WaitingLaunch};
+use super::{Context, GoingUp, OpenStratos, StateMachine, WaitingLaunch};
+use crate::{config::CONFIG, kml::Event};
 
 impl StateMachine for OpenStratos<WaitingLaunch> {
     type Next = OpenStratos<GoingUp>;
 
-    fn execute(self) -> Result<Self::Next, Error> {
-        unimplemented!()
+    fn execute(self, context: &mut Context) -> Result<Self::Next, Error> {
+        let launch_altitude = context.next_frame().altitude();
+        context.stats.launch_altitude = Some(launch_altitude);
+        info!("Launch altitude: {} m", launch_altitude);
+
+        loop {
+            let frame = context.next_frame();
+            let flight = CONFIG.flight();
+            let fast_ascent = context
+                .gps
+                .smoothed_ascent_rate()
+                .is_some_and(|rate| rate > flight.launch_ascent_rate());
+            if fast_ascent
+                || frame.altitude() > launch_altitude + flight.launch_altitude_threshold()
+            {
+                context.stats.launch_time = Some(frame.fix_time());
+                info!("Launch detected at {}.", frame.fix_time());
+                context.mark_event(Event::Launch, &frame);
+
+                return Ok(OpenStratos { state: GoingUp });
+            }
+        }
     }
 }
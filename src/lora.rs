@@ -0,0 +1,498 @@
+//! LoRa telemetry.
+//!
+//! The range of the XBee telemetry is limited, so if the `[lora]` section is present in the
+//! configuration, every telemetry [`Packet`](../telemetry/struct.Packet.html) is also sent over a
+//! LoRa radio, such as a HopeRF RFM96 (Semtech SX1276) connected to the SPI bus of the Raspberry
+//! Pi. LoRa trades bandwidth for range, so packets are sent with the same compact binary encoding
+//! and CRC as the serial telemetry, with the configured frequency, spreading factor and
+//! bandwidth, and a 4/5 coding rate.
+//!
+//! The radio is initialized the first time a packet is sent. Errors are only logged, since LoRa
+//! is an additional telemetry output.
+
+#![allow(missing_debug_implementations)]
+
+use std::{
+    fs::{File, OpenOptions},
+    os::unix::io::AsRawFd,
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Error};
+use once_cell::sync::Lazy;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{Lora as LoraConfig, CONFIG},
+    error,
+    telemetry::Packet,
+};
+
+/// Default SPI device of the radio.
+pub const DEFAULT_SPI_DEVICE: &str = "/dev/spidev0.0";
+/// Default carrier frequency, in *Hz*.
+pub const DEFAULT_FREQUENCY: u32 = 434_250_000;
+/// Default spreading factor.
+pub const DEFAULT_SPREADING_FACTOR: u8 = 11;
+/// Default bandwidth, in *Hz*.
+pub const DEFAULT_BANDWIDTH: u32 = 125_000;
+/// Maximum length of a LoRa payload, in bytes.
+pub const MAX_PAYLOAD_LENGTH: usize = 255;
+/// Bandwidths supported by the radio, in *Hz*, in the order of their register values.
+pub const BANDWIDTHS: [u32; 10] = [
+    7_800, 10_400, 15_600, 20_800, 31_250, 41_700, 62_500, 125_000, 250_000, 500_000,
+];
+/// Range of carrier frequencies supported by the radio, in *Hz*.
+pub const FREQUENCY_RANGE: std::ops::RangeInclusive<u32> = 137_000_000..=1_020_000_000;
+/// Range of spreading factors supported with an explicit header.
+pub const SPREADING_FACTOR_RANGE: std::ops::RangeInclusive<u8> = 7..=12;
+
+/// The LoRa radio, once it has been initialized.
+pub static LORA: Lazy<Mutex<Option<Radio<SpiDev>>>> = Lazy::new(|| Mutex::new(None));
+
+/// `ioctl()` request to set the maximum speed of the SPI bus.
+const SPI_IOC_WR_MAX_SPEED_HZ: libc::c_ulong = 0x4004_6B04;
+/// `ioctl()` request to make a single SPI transfer.
+const SPI_IOC_MESSAGE_1: libc::c_ulong = 0x4020_6B00;
+/// Clock speed of the SPI bus, in *Hz*.
+const SPI_SPEED_HZ: u32 = 1_000_000;
+/// Frequency of the crystal oscillator of the radio, in *Hz*.
+const OSCILLATOR_FREQUENCY: u64 = 32_000_000;
+/// Length of the preamble, in symbols.
+const PREAMBLE_LENGTH: u16 = 8;
+/// Silicon version of the SX1276/77/78/79 radios.
+const SILICON_VERSION: u8 = 0x12;
+/// Time between checks of the end of the transmission.
+const TX_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// FIFO register.
+const REG_FIFO: u8 = 0x00;
+/// Operating mode register.
+const REG_OP_MODE: u8 = 0x01;
+/// Most significant byte of the carrier frequency register.
+const REG_FRF_MSB: u8 = 0x06;
+/// Power amplifier configuration register.
+const REG_PA_CONFIG: u8 = 0x09;
+/// FIFO pointer register.
+const REG_FIFO_ADDR_PTR: u8 = 0x0D;
+/// FIFO transmission base address register.
+const REG_FIFO_TX_BASE_ADDR: u8 = 0x0E;
+/// Interrupt flags register.
+const REG_IRQ_FLAGS: u8 = 0x12;
+/// First modem configuration register (bandwidth, coding rate and header mode).
+const REG_MODEM_CONFIG_1: u8 = 0x1D;
+/// Second modem configuration register (spreading factor and CRC).
+const REG_MODEM_CONFIG_2: u8 = 0x1E;
+/// Most significant byte of the preamble length register.
+const REG_PREAMBLE_MSB: u8 = 0x20;
+/// Payload length register.
+const REG_PAYLOAD_LENGTH: u8 = 0x22;
+/// Third modem configuration register (low data rate optimization and AGC).
+const REG_MODEM_CONFIG_3: u8 = 0x26;
+/// Silicon version register.
+const REG_VERSION: u8 = 0x42;
+
+/// LoRa long range mode bit of the operating mode.
+const MODE_LONG_RANGE: u8 = 0b1000_0000;
+/// Sleep operating mode.
+const MODE_SLEEP: u8 = 0b000;
+/// Standby operating mode.
+const MODE_STANDBY: u8 = 0b001;
+/// Transmission operating mode.
+const MODE_TX: u8 = 0b011;
+/// Transmission done interrupt flag.
+const IRQ_TX_DONE: u8 = 0b0000_1000;
+
+/// Sends a telemetry packet over LoRa, if LoRa telemetry is configured.
+///
+/// The radio is initialized the first time. Errors are only logged.
+pub fn transmit(packet: &Packet) {
+    let Some(config) = CONFIG.lora() else {
+        return;
+    };
+    let mut lora = match LORA.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The LORA mutex was poisoned.");
+            poisoned.into_inner()
+        }
+    };
+
+    if lora.is_none() {
+        match Radio::open(config) {
+            Ok(radio) => *lora = Some(radio),
+            Err(e) => {
+                error!(error = ?e, "Could not initialize the LoRa radio.");
+                return;
+            }
+        }
+    }
+    if let Some(radio) = lora.as_mut() {
+        if let Err(e) = radio.send(&packet.to_bytes()) {
+            warn!(error = ?e, "Could not send the telemetry packet over LoRa.");
+        }
+    }
+}
+
+/// Gets the register value of the given bandwidth, in *Hz*, if the radio supports it.
+#[must_use]
+pub fn bandwidth_bits(bandwidth: u32) -> Option<u8> {
+    BANDWIDTHS
+        .iter()
+        .position(|&supported| supported == bandwidth)
+        .and_then(|bits| u8::try_from(bits).ok())
+}
+
+/// Gets the duration of a symbol with the given spreading factor and bandwidth, in *Hz*.
+#[must_use]
+pub fn symbol_time(spreading_factor: u8, bandwidth: u32) -> Duration {
+    Duration::from_secs(1 << spreading_factor) / bandwidth
+}
+
+/// Gets the time on air of a payload of the given length, with the given spreading factor and
+/// bandwidth, in *Hz*.
+///
+/// This is the formula of the SX1276 datasheet, with an explicit header, a CRC and a 4/5 coding
+/// rate.
+#[must_use]
+pub fn time_on_air(length: usize, spreading_factor: u8, bandwidth: u32) -> Duration {
+    let symbol = symbol_time(spreading_factor, bandwidth);
+    let low_data_rate = i64::from(low_data_rate_optimize(spreading_factor, bandwidth));
+
+    let bits =
+        8 * i64::try_from(length).unwrap_or(i64::MAX) - 4 * i64::from(spreading_factor) + 28 + 16;
+    let bits_per_block = 4 * (i64::from(spreading_factor) - 2 * low_data_rate);
+    let blocks = (bits + bits_per_block - 1)
+        .div_euclid(bits_per_block)
+        .max(0);
+    let payload_symbols = 8 + u32::try_from(blocks * 5).unwrap_or(u32::MAX);
+
+    // The preamble has 4.25 more symbols than its programmed length.
+    symbol * (4 * u32::from(PREAMBLE_LENGTH) + 17) / 4 + symbol * payload_symbols
+}
+
+/// Checks if the low data rate optimization is mandated for the given settings.
+///
+/// It's required when the symbol time is longer than 16 ms.
+fn low_data_rate_optimize(spreading_factor: u8, bandwidth: u32) -> bool {
+    symbol_time(spreading_factor, bandwidth) > Duration::from_millis(16)
+}
+
+/// SPI bus device.
+pub trait Spi {
+    /// Makes a full duplex transfer, sending the bytes in the buffer and replacing them with the
+    /// received ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transfer fails.
+    fn transfer(&mut self, buffer: &mut [u8]) -> Result<(), Error>;
+}
+
+/// Linux `spidev` SPI device.
+pub struct SpiDev {
+    /// Device file.
+    device: File,
+}
+
+/// SPI transfer description, as expected by the `spidev` driver.
+#[repr(C)]
+#[derive(Default)]
+struct SpiIocTransfer {
+    tx_buf: u64,
+    rx_buf: u64,
+    len: u32,
+    speed_hz: u32,
+    delay_usecs: u16,
+    bits_per_word: u8,
+    cs_change: u8,
+    tx_nbits: u8,
+    rx_nbits: u8,
+    word_delay_usecs: u8,
+    pad: u8,
+}
+
+impl SpiDev {
+    /// Opens the given SPI device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device can't be opened, or if its speed can't be set.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let device = OpenOptions::new().read(true).write(true).open(path)?;
+        let speed = SPI_SPEED_HZ;
+        // Safe because the descriptor is open, and the request takes a pointer to a `u32`.
+        if unsafe { libc::ioctl(device.as_raw_fd(), SPI_IOC_WR_MAX_SPEED_HZ, &speed) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self { device })
+    }
+}
+
+impl Spi for SpiDev {
+    fn transfer(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let transfer = SpiIocTransfer {
+            tx_buf: buffer.as_ptr() as u64,
+            rx_buf: buffer.as_mut_ptr() as u64,
+            len: u32::try_from(buffer.len())?,
+            speed_hz: SPI_SPEED_HZ,
+            bits_per_word: 8,
+            ..SpiIocTransfer::default()
+        };
+        // Safe because the descriptor is open, and the transfer buffers live during the call.
+        if unsafe { libc::ioctl(self.device.as_raw_fd(), SPI_IOC_MESSAGE_1, &transfer) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+/// LoRa radio of the Semtech SX1276 family, such as the HopeRF RFM95/96/98 modules.
+pub struct Radio<S> {
+    /// SPI bus of the radio.
+    spi: S,
+    /// Configured spreading factor.
+    spreading_factor: u8,
+    /// Configured bandwidth, in *Hz*.
+    bandwidth: u32,
+}
+
+impl Radio<SpiDev> {
+    /// Opens the radio in the configured SPI device, and configures it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SPI device can't be opened, or if the radio can't be configured.
+    pub fn open(config: &LoraConfig) -> Result<Self, Error> {
+        info!("Initializing LoRa radio\u{2026}");
+        let spi = SpiDev::open(config.spi_device()).context(error::Lora::Init)?;
+        let mut radio = Self::new(spi);
+        radio.configure(
+            config.frequency(),
+            config.spreading_factor(),
+            config.bandwidth(),
+        )?;
+        info!("LoRa radio initialized.");
+        Ok(radio)
+    }
+}
+
+impl<S: Spi> Radio<S> {
+    /// Creates a new radio in the given SPI bus, with the default settings.
+    pub fn new(spi: S) -> Self {
+        Self {
+            spi,
+            spreading_factor: DEFAULT_SPREADING_FACTOR,
+            bandwidth: DEFAULT_BANDWIDTH,
+        }
+    }
+
+    /// Configures the radio in LoRa mode with the given frequency and bandwidth, in *Hz*, and
+    /// spreading factor, leaving it in standby.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bandwidth is not supported, if the radio is not detected, or if its
+    /// registers can't be written.
+    pub fn configure(
+        &mut self,
+        frequency: u32,
+        spreading_factor: u8,
+        bandwidth: u32,
+    ) -> Result<(), Error> {
+        let bandwidth_bits =
+            bandwidth_bits(bandwidth).ok_or(error::Lora::InvalidBandwidth { bandwidth })?;
+
+        let version = self.read_register(REG_VERSION).context(error::Lora::Init)?;
+        if version != SILICON_VERSION {
+            return Err(error::Lora::Version { version }.into());
+        }
+
+        // The long range mode can only be changed in sleep mode.
+        self.write_register(REG_OP_MODE, MODE_LONG_RANGE | MODE_SLEEP)
+            .context(error::Lora::Init)?;
+
+        let frf = (u64::from(frequency) << 19) / OSCILLATOR_FREQUENCY;
+        self.write_registers(REG_FRF_MSB, &frf.to_be_bytes()[5..])
+            .context(error::Lora::Init)?;
+
+        // Explicit header and 4/5 coding rate.
+        self.write_register(REG_MODEM_CONFIG_1, bandwidth_bits << 4 | 0b0010)
+            .context(error::Lora::Init)?;
+        // Payload CRC enabled.
+        self.write_register(REG_MODEM_CONFIG_2, spreading_factor << 4 | 0b0100)
+            .context(error::Lora::Init)?;
+        // Automatic gain control, with the low data rate optimization if needed.
+        let low_data_rate = if low_data_rate_optimize(spreading_factor, bandwidth) {
+            0b1000
+        } else {
+            0
+        };
+        self.write_register(REG_MODEM_CONFIG_3, low_data_rate | 0b0100)
+            .context(error::Lora::Init)?;
+        self.write_registers(REG_PREAMBLE_MSB, &PREAMBLE_LENGTH.to_be_bytes())
+            .context(error::Lora::Init)?;
+
+        // PA_BOOST output, at 17 dBm.
+        self.write_register(REG_PA_CONFIG, 0x8F)
+            .context(error::Lora::Init)?;
+        self.write_register(REG_FIFO_TX_BASE_ADDR, 0)
+            .context(error::Lora::Init)?;
+        self.write_register(REG_OP_MODE, MODE_LONG_RANGE | MODE_STANDBY)
+            .context(error::Lora::Init)?;
+
+        self.spreading_factor = spreading_factor;
+        self.bandwidth = bandwidth;
+        Ok(())
+    }
+
+    /// Sends the given payload, waiting until the transmission ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is too long, if the radio can't be accessed, or if the
+    /// transmission does not end in time.
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let length = u8::try_from(payload.len()).map_err(|_| error::Lora::PayloadTooLong {
+            length: payload.len(),
+        })?;
+
+        self.write_register(REG_OP_MODE, MODE_LONG_RANGE | MODE_STANDBY)
+            .context(error::Lora::Send)?;
+        self.write_register(REG_FIFO_ADDR_PTR, 0)
+            .context(error::Lora::Send)?;
+        self.write_registers(REG_FIFO, payload)
+            .context(error::Lora::Send)?;
+        self.write_register(REG_PAYLOAD_LENGTH, length)
+            .context(error::Lora::Send)?;
+        self.write_register(REG_OP_MODE, MODE_LONG_RANGE | MODE_TX)
+            .context(error::Lora::Send)?;
+
+        // The transmission should end in its time on air, so twice that is a safe timeout.
+        let timeout = 2 * time_on_air(payload.len(), self.spreading_factor, self.bandwidth);
+        let start = Instant::now();
+        loop {
+            let flags = self
+                .read_register(REG_IRQ_FLAGS)
+                .context(error::Lora::Send)?;
+            if flags & IRQ_TX_DONE != 0 {
+                self.write_register(REG_IRQ_FLAGS, 0xFF)
+                    .context(error::Lora::Send)?;
+                debug!(length, "LoRa packet sent.");
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(error::Lora::TxTimeout.into());
+            }
+            thread::sleep(TX_POLL_INTERVAL);
+        }
+    }
+
+    /// Reads a register of the radio.
+    fn read_register(&mut self, register: u8) -> Result<u8, Error> {
+        let mut buffer = [register & 0x7F, 0];
+        self.spi.transfer(&mut buffer)?;
+        Ok(buffer[1])
+    }
+
+    /// Writes a register of the radio.
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        self.write_registers(register, &[value])
+    }
+
+    /// Writes consecutive registers of the radio, starting at the given one.
+    ///
+    /// Writes to the FIFO register are not incremented, so all the data goes to the FIFO.
+    fn write_registers(&mut self, register: u8, values: &[u8]) -> Result<(), Error> {
+        let mut buffer = Vec::with_capacity(values.len() + 1);
+        buffer.push(register | 0x80);
+        buffer.extend_from_slice(values);
+        self.spi.transfer(&mut buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Error;
+    use chrono::Utc;
+
+    use super::{
+        bandwidth_bits, time_on_air, Radio, Spi, IRQ_TX_DONE, MAX_PAYLOAD_LENGTH, REG_FIFO,
+        REG_IRQ_FLAGS, REG_PAYLOAD_LENGTH, REG_VERSION, SILICON_VERSION,
+    };
+    use crate::{
+        logic::State,
+        telemetry::{Packet, PACKET_LENGTH},
+    };
+
+    /// Mock SPI bus, recording the register writes and answering the version and interrupt
+    /// flag reads.
+    #[derive(Debug, Default)]
+    struct MockSpi {
+        writes: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl Spi for MockSpi {
+        fn transfer(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+            if buffer[0] & 0x80 == 0 {
+                buffer[1] = match buffer[0] {
+                    REG_VERSION => SILICON_VERSION,
+                    REG_IRQ_FLAGS => IRQ_TX_DONE,
+                    _ => 0,
+                };
+            } else {
+                self.writes.push((buffer[0] & 0x7F, buffer[1..].to_vec()));
+            }
+            Ok(())
+        }
+    }
+
+    /// Checks that telemetry packets fit in a LoRa payload, and that they are short enough on
+    /// air with the slowest settings.
+    #[test]
+    fn payload_sizing() {
+        assert!(PACKET_LENGTH <= MAX_PAYLOAD_LENGTH);
+
+        // 75.25 symbols of 32.768 ms.
+        let slowest = time_on_air(PACKET_LENGTH, 12, 125_000);
+        assert_eq!(slowest, Duration::from_micros(2_465_792));
+        assert!(slowest < Duration::from_secs(3));
+        assert!(time_on_air(PACKET_LENGTH, 7, 500_000) < Duration::from_millis(30));
+
+        assert_eq!(bandwidth_bits(125_000), Some(7));
+        assert_eq!(bandwidth_bits(100_000), None);
+    }
+
+    /// Checks the configuration and the transmission of a packet with a mock SPI bus.
+    #[test]
+    fn radio_send() {
+        let mut radio = Radio::new(MockSpi::default());
+        radio.configure(434_250_000, 12, 125_000).unwrap();
+
+        // 434.25 MHz is 0x6C9000 in steps of 61.035 Hz, and SF12 at 125 kHz needs the low data
+        // rate optimization.
+        assert!(radio.spi.writes.contains(&(0x06, vec![0x6C, 0x90, 0x00])));
+        assert!(radio.spi.writes.contains(&(0x1D, vec![0x72])));
+        assert!(radio.spi.writes.contains(&(0x1E, vec![0xC4])));
+        assert!(radio.spi.writes.contains(&(0x26, vec![0x0C])));
+
+        radio.spi.writes.clear();
+        let payload = Packet::new(Utc::now(), State::Init, None, None, None).to_bytes();
+        radio.send(&payload).unwrap();
+        assert!(radio.spi.writes.contains(&(REG_FIFO, payload)));
+        assert!(radio.spi.writes.contains(&(
+            REG_PAYLOAD_LENGTH,
+            vec![u8::try_from(PACKET_LENGTH).unwrap()]
+        )));
+
+        assert!(radio.send(&[0; MAX_PAYLOAD_LENGTH + 1]).is_err());
+        assert!(Radio::new(MockSpi::default())
+            .configure(434_250_000, 12, 100_000)
+            .is_err());
+    }
+}
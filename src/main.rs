@@ -55,14 +55,45 @@
 //! * **Live transparent serial telemetry** (`--features="telemetry"`): Enables real time telemetry.
 //! OpenStratos is capable to send telemetry via a serial device. This can be used with an XBee
 //! module in transparent mode, for example, to receive the information via serial in the client
-//! (in a laptop computer in the ground, for example).
-//! * *TODO: commands?*
+//! (in a laptop computer in the ground, for example). If a shared secret is configured, the ground
+//! station can also send authenticated commands to the probe through the same link, to take a
+//! picture, send an SMS, request the current status or shut the system down.
 //!
 //! All these features are enabled by default. You can opt-out to all of them passing the
 //! `--no-default-features` flag to Cargo when building / running the software, and enable each of
 //! them separately. To enable more than one feature, add space between them in the `--features`
 //! option. E.g. `--no-default-features --features="gps telemetry"`.
 //!
+//! The optional **SQLite flight database** (`--features="sqlite"`) is not enabled by default. It
+//! stores every GPS frame, battery reading and state transition in an SQLite database in the
+//! `data` directory, for post-flight analysis. It requires the GPS feature.
+//!
+//! The **simulation mode** (`--features="sim"`) is not enabled by default either. It runs the
+//! flight logic from a recorded flight file instead of the real GPS, logging SMSs and camera
+//! operations instead of executing them. It never powers the system off. Please refer to the
+//! [`sim`](../os_balloon/sim/index.html) module for further information.
+//!
+//! ## Pre-flight checkout
+//!
+//! Running the launcher with the `--preflight` flag (`cargo run -- --preflight`) exercises every
+//! enabled subsystem without starting the flight logic: disk space, camera, GPS fix, GSM
+//! registration with a test SMS, batteries and telemetry. It prints a pass/fail summary and exits
+//! with a non-zero status code if any check failed. Please refer to the
+//! [`preflight`](../os_balloon/logic/preflight/index.html) module for further information.
+//!
+//! ## State diagram
+//!
+//! Running the launcher with the `--state-diagram` flag prints a
+//! [Graphviz](https://graphviz.org/) DOT description of the state machine, and exits. It can be
+//! rendered with `cargo run -- --state-diagram | dot -Tsvg > states.svg`. Please refer to the
+//! [`diagram`](../os_balloon/logic/diagram/index.html) module for further information.
+//!
+//! ## Build information
+//!
+//! Running the launcher with the `--version` or `--features` flags prints its version and the
+//! Cargo features compiled in, and exits. Since the behaviour of the software heavily depends on
+//! them, it's a quick way of checking that the binary in the probe is the expected build.
+//!
 //! ## Configuration
 //!
 //! OpenStratos is highly configurable. Please refer to the
@@ -163,7 +194,16 @@
 )]
 
 use colored::Colorize;
-use os_balloon::{generate_error_string, run, CONFIG};
+use os_balloon::{
+    build_info,
+    config::reload_on_sighup,
+    error, generate_error_string, initialize_data_filesystem,
+    logger::init_loggers,
+    logic::{current_state, diagram, preflight, Context},
+    recovery::{cleanup, handle_termination_signals, install_panic_hook},
+    run, CONFIG,
+};
+use std::{env, process};
 use tracing::{error, info};
 
 /// Program entry point.
@@ -172,23 +212,77 @@ use tracing::{error, info};
 /// balloon software by running [`os_balloon::run()`](../os_balloon/fn.run.html). It will then
 /// handle possible errors and try to recover from them.
 pub fn main() {
+    if env::args().skip(1).any(|arg| arg == "--state-diagram") {
+        print!("{}", diagram::dot());
+        return;
+    }
+    if env::args()
+        .skip(1)
+        .any(|arg| arg == "--version" || arg == "--features")
+    {
+        print!("{}", build_info());
+        return;
+    }
+
     if CONFIG.debug() {
         println!("Debug mode active");
     }
-    // if let Err(e) = init_loggers() {
-    //     println!(
-    //         "{}",
-    //         generate_error_string(&e, "Error initializing loggers").red()
-    //     );
-    //     panic!();
-    // }
+    if let Err(e) = init_loggers() {
+        println!(
+            "{}",
+            generate_error_string(&e, "Error initializing loggers").red()
+        );
+        panic!();
+    }
+    install_panic_hook();
+    if let Err(e) = reload_on_sighup() {
+        error!(
+            "{}",
+            generate_error_string(&e, "Error setting up the configuration reload")
+        );
+    }
+    if let Err(e) = handle_termination_signals() {
+        error!(
+            "{}",
+            generate_error_string(&e, "Error setting up the termination signals")
+        );
+    }
+    #[cfg(feature = "http")]
+    if let Err(e) = os_balloon::http::start() {
+        error!(
+            "{}",
+            generate_error_string(&e, "Error starting the HTTP status endpoint")
+        );
+    }
     info!("OpenStratos {} starting", env!("CARGO_PKG_VERSION"));
 
+    if env::args().skip(1).any(|arg| arg == "--preflight") {
+        if let Err(e) = initialize_data_filesystem() {
+            error!(
+                "{}",
+                generate_error_string(&e, "Error initializing data filesystem")
+            );
+        }
+        let report = preflight::run(&mut Context::hardware());
+        println!("{report}");
+        process::exit(i32::from(!report.passed()));
+    }
+
     if let Err(e) = run() {
+        if matches!(e.downcast_ref(), Some(error::Logic::ShutdownRequested)) {
+            info!(
+                state = current_state().as_str(),
+                "Shutting down as requested\u{2026}"
+            );
+            let _ = cleanup();
+            return;
+        }
+
         let error = generate_error_string(&e, "Error running OpenStratos");
-        error!("{}", error);
+        error!(state = current_state().as_str(), "{}", error);
         println!("{}", error.red());
 
+        // The panic hook saves the state and turns the devices off.
         panic!(); // TODO safe mode / recovery mode / restart...
     }
 }
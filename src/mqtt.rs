@@ -0,0 +1,564 @@
+//! MQTT telemetry over GPRS.
+//!
+//! Teams with a cellular data plan can receive live telemetry without the cost of SMSs. If the
+//! `[mqtt]` section is present in the configuration, every telemetry
+//! [`Packet`](../telemetry/struct.Packet.html) is also published as a JSON object to the
+//! `openstratos/<flight_id>/telemetry` topic of the configured broker, using a TCP connection
+//! through the GPRS data service of the FONA module.
+//!
+//! Messages are published "at most once", and the broker responses are not read, so that the FONA
+//! serial is only busy while sending. If the connection drops, for example because the probe has
+//! no coverage or the GSM module is off during the flight, messages are kept in a bounded buffer,
+//! dropping the oldest ones when it's full, and sent once the connection is open again.
+//! Reconnections are attempted at most once every
+//! [`RECONNECT_INTERVAL`](constant.RECONNECT_INTERVAL.html).
+
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Error};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use tracing::{debug, error, warn};
+
+use crate::{
+    config::{Mqtt as MqttConfig, CONFIG},
+    error,
+    fona::{Fona, FONA},
+    telemetry::Packet,
+};
+
+/// Default port of the MQTT broker.
+pub const DEFAULT_PORT: u16 = 1883;
+/// Default keep alive interval, in seconds.
+pub const DEFAULT_KEEP_ALIVE: u16 = 60;
+/// Default maximum number of messages buffered while offline.
+pub const DEFAULT_BUFFER: usize = 100;
+/// Minimum time between reconnection attempts.
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// MQTT `DISCONNECT` packet.
+const DISCONNECT: [u8; 2] = [0xE0, 0x00];
+
+/// The MQTT publisher, if MQTT telemetry is configured.
+pub static MQTT: Lazy<Mutex<Option<Publisher<&'static Mutex<Fona>>>>> =
+    Lazy::new(|| Mutex::new(CONFIG.mqtt().map(|config| Publisher::new(&*FONA, config))));
+
+/// Publishes a telemetry packet, if MQTT telemetry is configured.
+///
+/// Errors are only logged, since the packet stays buffered until it can be sent.
+pub fn publish(packet: &Packet) {
+    let mut mqtt = match MQTT.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The MQTT mutex was poisoned.");
+            poisoned.into_inner()
+        }
+    };
+
+    if let Some(publisher) = mqtt.as_mut() {
+        if let Err(e) = publisher.publish(packet, Instant::now()) {
+            warn!(
+                buffered = publisher.buffered(),
+                error = %e,
+                "Could not publish the telemetry packet."
+            );
+        }
+    }
+}
+
+/// Transport of MQTT packets, over a TCP connection.
+pub trait Transport {
+    /// Opens the connection to the given host and port.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection can't be opened.
+    fn connect(&mut self, host: &str, port: u16) -> Result<(), Error>;
+
+    /// Sends the given data through the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data can't be sent.
+    fn send(&mut self, data: &[u8]) -> Result<(), Error>;
+
+    /// Closes the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection can't be closed.
+    fn close(&mut self) -> Result<(), Error>;
+}
+
+impl Transport for &'static Mutex<Fona> {
+    fn connect(&mut self, host: &str, port: u16) -> Result<(), Error> {
+        lock(self).tcp_connect(host, port)
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        lock(self).tcp_send(data)
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        lock(self).tcp_close()
+    }
+}
+
+/// Locks the FONA mutex, even if it was poisoned.
+fn lock(fona: &Mutex<Fona>) -> std::sync::MutexGuard<'_, Fona> {
+    match fona.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The FONA mutex was poisoned.");
+            poisoned.into_inner()
+        }
+    }
+}
+
+/// MQTT publisher, that buffers messages while offline.
+#[derive(Debug)]
+pub struct Publisher<T> {
+    /// Transport of the packets.
+    transport: T,
+    /// Host of the broker.
+    host: String,
+    /// Port of the broker.
+    port: u16,
+    /// Topic of the telemetry messages.
+    topic: String,
+    /// `CONNECT` packet sent after opening the connection.
+    connect: Vec<u8>,
+    /// `PUBLISH` packets waiting to be sent, oldest first.
+    buffer: VecDeque<Vec<u8>>,
+    /// Maximum number of buffered packets.
+    capacity: usize,
+    /// Wether the connection is open.
+    connected: bool,
+    /// Time of the last connection attempt.
+    last_attempt: Option<Instant>,
+}
+
+impl<T> Publisher<T>
+where
+    T: Transport,
+{
+    /// Creates a new publisher with the given transport and configuration.
+    pub fn new(transport: T, config: &MqttConfig) -> Self {
+        Self {
+            transport,
+            host: config.host().to_owned(),
+            port: config.port(),
+            topic: topic(config.flight_id()),
+            connect: connect_packet(
+                &config.client_id(),
+                config.keep_alive(),
+                config.username(),
+                config.password(),
+            ),
+            buffer: VecDeque::new(),
+            capacity: config.buffer().max(1),
+            connected: false,
+            last_attempt: None,
+        }
+    }
+
+    /// Gets the topic of the telemetry messages.
+    #[must_use]
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Gets the number of messages waiting to be sent.
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Checks if the connection with the broker is open.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Publishes a telemetry packet, buffering it if it can't be sent right now.
+    ///
+    /// If the buffer is full, the oldest message is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffered messages can't be sent right now. The packet is kept in the
+    /// buffer anyway.
+    pub fn publish(&mut self, packet: &Packet, now: Instant) -> Result<(), Error> {
+        if self.buffer.len() == self.capacity {
+            let _ = self.buffer.pop_front();
+            warn!("MQTT buffer full, dropping the oldest message.");
+        }
+        self.buffer
+            .push_back(publish_packet(&self.topic, payload(packet).as_bytes()));
+
+        self.flush(now)
+    }
+
+    /// Sends the buffered messages, connecting to the broker if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client is not connected and it can't connect yet, or if a message
+    /// can't be sent. The messages that were not sent are kept in the buffer.
+    pub fn flush(&mut self, now: Instant) -> Result<(), Error> {
+        if !self.connected {
+            self.connect(now)?;
+        }
+
+        while let Some(message) = self.buffer.front() {
+            if let Err(e) = self.transport.send(message) {
+                self.connected = false;
+                if let Err(e) = self.transport.close() {
+                    debug!("Error closing the MQTT connection: {}", e);
+                }
+                return Err(e.context(error::Mqtt::Publish));
+            }
+            let _ = self.buffer.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// Disconnects from the broker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the disconnection can't be sent, or if the connection can't be closed.
+    pub fn disconnect(&mut self) -> Result<(), Error> {
+        if self.connected {
+            self.connected = false;
+            self.transport
+                .send(&DISCONNECT)
+                .context(error::Mqtt::Publish)?;
+            self.transport.close()?;
+        }
+        Ok(())
+    }
+
+    /// Connects to the broker, if the last attempt was long enough ago.
+    fn connect(&mut self, now: Instant) -> Result<(), Error> {
+        if self
+            .last_attempt
+            .is_some_and(|last| now.duration_since(last) < RECONNECT_INTERVAL)
+        {
+            return Err(error::Mqtt::Backoff.into());
+        }
+        self.last_attempt = Some(now);
+
+        self.transport
+            .connect(&self.host, self.port)
+            .and_then(|()| self.transport.send(&self.connect))
+            .context(error::Mqtt::Connect)?;
+        self.connected = true;
+        Ok(())
+    }
+}
+
+/// Gets the telemetry topic of the given flight.
+#[must_use]
+pub fn topic(flight_id: &str) -> String {
+    format!("openstratos/{flight_id}/telemetry")
+}
+
+/// Serializes a telemetry packet as a JSON object.
+///
+/// Fields that are not present in the packet are `null`.
+#[must_use]
+pub fn payload(packet: &Packet) -> String {
+    let position = packet.position().map(|position| {
+        json!({
+            "latitude": position.latitude(),
+            "longitude": position.longitude(),
+            "altitude": position.altitude(),
+            "satellites": position.satellites(),
+            "speed": position.speed(),
+            "course": position.course(),
+        })
+    });
+
+    json!({
+        "version": packet.version(),
+        "sequence": packet.sequence(),
+        "timestamp": packet.timestamp().to_rfc3339(),
+        "state": packet.state().as_str(),
+        "position": position,
+        "main_battery": packet.main_battery(),
+        "fona_battery": packet.fona_battery(),
+    })
+    .to_string()
+}
+
+/// Creates an MQTT 3.1.1 `CONNECT` packet, with a clean session.
+#[must_use]
+pub fn connect_packet(
+    client_id: &str,
+    keep_alive: u16,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Vec<u8> {
+    // Clean session.
+    let mut flags = 0b0000_0010;
+    if username.is_some() {
+        flags |= 0b1000_0000;
+    }
+    if password.is_some() {
+        flags |= 0b0100_0000;
+    }
+
+    let mut variable = Vec::new();
+    encode_string(&mut variable, "MQTT");
+    // Protocol level 4, MQTT 3.1.1.
+    variable.push(4);
+    variable.push(flags);
+    variable.extend_from_slice(&keep_alive.to_be_bytes());
+    encode_string(&mut variable, client_id);
+    for field in [username, password].into_iter().flatten() {
+        encode_string(&mut variable, field);
+    }
+
+    packet(0x10, &variable)
+}
+
+/// Creates an MQTT 3.1.1 `PUBLISH` packet, delivered at most once.
+#[must_use]
+pub fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut variable = Vec::with_capacity(topic.len() + payload.len() + 2);
+    encode_string(&mut variable, topic);
+    variable.extend_from_slice(payload);
+
+    packet(0x30, &variable)
+}
+
+/// Creates an MQTT packet with the given fixed header byte and contents.
+fn packet(header: u8, contents: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(contents.len() + 5);
+    packet.push(header);
+
+    let mut length = contents.len();
+    loop {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+
+    packet.extend_from_slice(contents);
+    packet
+}
+
+/// Encodes a length-prefixed UTF-8 string.
+fn encode_string(buffer: &mut Vec<u8>, string: &str) {
+    let length = u16::try_from(string.len()).unwrap_or(u16::MAX);
+    buffer.extend_from_slice(&length.to_be_bytes());
+    buffer.extend_from_slice(&string.as_bytes()[..usize::from(length)]);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use anyhow::{bail, Error};
+    use chrono::{TimeZone, Utc};
+    use serde_json::Value;
+
+    use super::{
+        connect_packet, packet, payload, publish_packet, topic, Publisher, Transport,
+        RECONNECT_INTERVAL,
+    };
+    use crate::{
+        config::Mqtt,
+        logic::State,
+        telemetry::{Packet, Position},
+    };
+
+    /// Fake transport, that records the sent data.
+    #[derive(Debug, Default)]
+    struct FakeTransport {
+        /// Wether there is coverage.
+        online: bool,
+        /// Number of connections opened.
+        connections: usize,
+        /// Data sent.
+        sent: Vec<Vec<u8>>,
+    }
+
+    impl Transport for &mut FakeTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<(), Error> {
+            if !self.online {
+                bail!("no coverage");
+            }
+            self.connections += 1;
+            Ok(())
+        }
+
+        fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+            if !self.online {
+                bail!("no coverage");
+            }
+            self.sent.push(data.to_vec());
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Creates a test packet.
+    fn test_packet() -> Packet {
+        Packet::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            State::Init,
+            Some(Position::new(42.5, -2.75, 1_500_f32, 9, 5_f32, 60_f32)),
+            None,
+            Some(0.75),
+        )
+    }
+
+    /// Creates a test configuration.
+    fn test_config(buffer: usize) -> Mqtt {
+        toml::from_str(&format!(
+            "host = \"broker.example.com\"\nflight_id = \"os-7\"\nbuffer = {buffer}\n"
+        ))
+        .unwrap()
+    }
+
+    /// Checks the topic construction.
+    #[test]
+    fn topic_name() {
+        assert_eq!(topic("os-7"), "openstratos/os-7/telemetry");
+    }
+
+    /// Checks the JSON serialization of a packet.
+    #[test]
+    fn json_payload() {
+        let json: Value = serde_json::from_str(&payload(&test_packet())).unwrap();
+
+        assert_eq!(json["timestamp"], "2017-03-25T12:11:46+00:00");
+        assert_eq!(json["state"], "INITIALIZING");
+        assert_eq!(json["position"]["latitude"], 42.5);
+        assert_eq!(json["position"]["altitude"], 1_500_f64);
+        assert_eq!(json["position"]["satellites"], 9);
+        assert!(json["main_battery"].is_null());
+        assert_eq!(json["fona_battery"], 0.75);
+    }
+
+    /// Checks the encoding of MQTT packets.
+    #[test]
+    fn packets() {
+        assert_eq!(
+            connect_packet("os", 60, None, None),
+            [
+                0x10,
+                14,
+                0,
+                4,
+                b'M',
+                b'Q',
+                b'T',
+                b'T',
+                4,
+                0b0000_0010,
+                0,
+                60,
+                0,
+                2,
+                b'o',
+                b's'
+            ]
+        );
+        assert_eq!(
+            connect_packet("os", 60, Some("u"), Some("p"))[9],
+            0b1100_0010
+        );
+        assert_eq!(
+            publish_packet("a/b", b"{}"),
+            [0x30, 7, 0, 3, b'a', b'/', b'b', b'{', b'}']
+        );
+
+        // Remaining length of 200 bytes takes two bytes.
+        let long = packet(0x30, &[0; 200]);
+        assert_eq!(&long[..3], &[0x30, 0xC8, 0x01]);
+        assert_eq!(long.len(), 203);
+    }
+
+    /// Checks that messages are buffered while offline, and sent once reconnected.
+    #[test]
+    fn reconnection() {
+        let mut transport = FakeTransport::default();
+        let start = Instant::now();
+        {
+            let mut publisher = Publisher::new(&mut transport, &test_config(2));
+            for i in 0..3 {
+                assert!(publisher
+                    .publish(&test_packet(), start + Duration::from_secs(i))
+                    .is_err());
+            }
+            // The oldest message was dropped.
+            assert_eq!(publisher.buffered(), 2);
+            assert!(!publisher.is_connected());
+
+            publisher.transport.online = true;
+            // Too soon to reconnect.
+            assert!(publisher.flush(start + Duration::from_secs(10)).is_err());
+            publisher.flush(start + RECONNECT_INTERVAL).unwrap();
+            assert!(publisher.is_connected());
+            assert_eq!(publisher.buffered(), 0);
+        }
+
+        assert_eq!(transport.connections, 1);
+        // The `CONNECT` packet and the two buffered messages.
+        assert_eq!(transport.sent.len(), 3);
+        assert_eq!(transport.sent[0][0], 0x10);
+        assert_eq!(transport.sent[1][0], 0x30);
+    }
+
+    /// Connects to a real broker, at `localhost:1883`.
+    #[test]
+    #[ignore]
+    fn broker() {
+        use std::{io::Write, net::TcpStream};
+
+        /// Transport over a plain TCP connection.
+        struct Tcp(Option<TcpStream>);
+
+        impl Transport for Tcp {
+            fn connect(&mut self, host: &str, port: u16) -> Result<(), Error> {
+                self.0 = Some(TcpStream::connect((host, port))?);
+                Ok(())
+            }
+
+            fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+                match self.0.as_mut() {
+                    Some(stream) => Ok(stream.write_all(data)?),
+                    None => bail!("not connected"),
+                }
+            }
+
+            fn close(&mut self) -> Result<(), Error> {
+                self.0 = None;
+                Ok(())
+            }
+        }
+
+        let config: Mqtt = toml::from_str("host = \"localhost\"\nflight_id = \"test\"\n").unwrap();
+        let mut publisher = Publisher::new(Tcp(None), &config);
+        publisher.publish(&test_packet(), Instant::now()).unwrap();
+        publisher.disconnect().unwrap();
+    }
+}
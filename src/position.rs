@@ -0,0 +1,236 @@
+//! Position of the probe, from the freshest available source.
+//!
+//! The GPS is the main source of the position of the probe. If its fix is void or stale, the GSM
+//! localization of the FONA module is used instead (safe mode operation). Since the GSM network
+//! can only be reached close to the ground, the GSM location is only requested if the latest known
+//! altitude is below [`GSM_MAX_ALTITUDE`](constant.GSM_MAX_ALTITUDE.html), or if the GPS never had
+//! a fix. If neither source is available, the latest valid GPS data is used, even if it's stale.
+
+use chrono::{DateTime, Utc};
+use tracing::{error, info, warn};
+
+use crate::{
+    devices::{GpsDevice, GsmDevice},
+    fona::Location,
+    gps::Frame,
+};
+
+/// Maximum altitude at which the GSM location is requested, in *m*.
+///
+/// The GSM network can't be reached above this altitude.
+pub const GSM_MAX_ALTITUDE: f32 = 1_200_f32;
+
+/// Source of a position fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// GPS receiver.
+    Gps,
+    /// GSM network localization.
+    Gsm,
+}
+
+impl Source {
+    /// Gets the source as a string.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Gps => "GPS",
+            Source::Gsm => "GSM",
+        }
+    }
+}
+
+/// Position fix of the probe, tagged with its source.
+#[derive(Debug, Clone, Copy)]
+pub enum Fix {
+    /// GPS data.
+    Gps(Frame),
+    /// GSM location.
+    Gsm(Location),
+}
+
+impl Fix {
+    /// Gets the source of the fix.
+    #[must_use]
+    pub fn source(&self) -> Source {
+        match self {
+            Fix::Gps(_) => Source::Gps,
+            Fix::Gsm(_) => Source::Gsm,
+        }
+    }
+
+    /// Gets the latitude of the probe, in *°* (degrees).
+    #[must_use]
+    pub fn latitude(&self) -> f32 {
+        match self {
+            Fix::Gps(frame) => frame.latitude(),
+            Fix::Gsm(location) => location.latitude(),
+        }
+    }
+
+    /// Gets the longitude of the probe, in *°* (degrees).
+    #[must_use]
+    pub fn longitude(&self) -> f32 {
+        match self {
+            Fix::Gps(frame) => frame.longitude(),
+            Fix::Gsm(location) => location.longitude(),
+        }
+    }
+
+    /// Gets the altitude of the probe, in *m*, if the source provides it.
+    #[must_use]
+    pub fn altitude(&self) -> Option<f32> {
+        match self {
+            Fix::Gps(frame) => Some(frame.altitude()),
+            Fix::Gsm(_) => None,
+        }
+    }
+}
+
+/// Gets the position of the probe from the freshest valid source, at the given time.
+///
+/// If the GPS fix is void, the last valid GPS data in the history is used as a stale fix.
+pub fn locate<G, M>(gps: &G, gsm: &mut M, now: DateTime<Utc>) -> Option<Fix>
+where
+    G: GpsDevice + ?Sized,
+    M: GsmDevice + ?Sized,
+{
+    if let Some(frame) = gps.latest_data().filter(Frame::is_valid) {
+        Some(locate_from(frame, gsm, now))
+    } else if let Some(&last) = gps.history().recent().last() {
+        if last.altitude() >= GSM_MAX_ALTITUDE {
+            warn!(
+                altitude = last.altitude(),
+                "The GPS fix is void, and the GSM location is not available at this altitude."
+            );
+            return Some(Fix::Gps(last));
+        }
+
+        warn!("The GPS fix is void, using the GSM location\u{2026}");
+        Some(gsm_location(gsm).map_or(Fix::Gps(last), Fix::Gsm))
+    } else {
+        warn!("The GPS never had a fix, using the GSM location\u{2026}");
+        gsm_location(gsm).map(Fix::Gsm)
+    }
+}
+
+/// Gets the position of the probe at the given time, from the given valid GPS data, or from the
+/// GSM location if that data is stale.
+///
+/// It's used when the latest GPS data has already been read, so that the GPS is not read again.
+pub fn locate_from<M>(frame: Frame, gsm: &mut M, now: DateTime<Utc>) -> Fix
+where
+    M: GsmDevice + ?Sized,
+{
+    if frame.is_fresh(now) {
+        return Fix::Gps(frame);
+    }
+
+    if frame.altitude() >= GSM_MAX_ALTITUDE {
+        warn!(
+            altitude = frame.altitude(),
+            "The GPS fix is stale, and the GSM location is not available at this altitude."
+        );
+        return Fix::Gps(frame);
+    }
+
+    warn!("The GPS fix is stale, using the GSM location\u{2026}");
+    gsm_location(gsm).map_or(Fix::Gps(frame), Fix::Gsm)
+}
+
+/// Gets the GSM location, logging the errors.
+fn gsm_location<M>(gsm: &mut M) -> Option<Location>
+where
+    M: GsmDevice + ?Sized,
+{
+    match gsm.location() {
+        Ok(location) => {
+            info!(
+                latitude = location.latitude(),
+                longitude = location.longitude(),
+                "Got the GSM location."
+            );
+            Some(location)
+        }
+        Err(e) => {
+            error!(error = %e, "Error getting the GSM location.");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{locate, Fix, Source};
+    use crate::{
+        devices::fake::{FakeGps, FakeGsm},
+        fona::Location,
+        gps::{FixStatus, Frame},
+    };
+
+    /// Checks that the GSM location is used when the GPS fix is stale, and tagged accordingly.
+    #[test]
+    fn stale_gps_fallback() {
+        let fix_time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let frame = Frame::new(fix_time, FixStatus::Active, 8, 42.5, -2.75, 650_f32);
+        let gps = FakeGps::new(vec![Some(frame)]);
+        let mut gsm = FakeGsm {
+            location: Some(Location::new(42.51, -2.74)),
+        };
+
+        // The fix is fresh.
+        let fix = locate(&gps, &mut gsm, fix_time + Duration::seconds(1)).unwrap();
+        assert_eq!(fix.source(), Source::Gps);
+        assert_eq!(fix.altitude(), Some(650_f32));
+
+        // The fix is stale, the GSM location is used.
+        let fix = locate(&gps, &mut gsm, fix_time + Duration::minutes(1)).unwrap();
+        assert_eq!(fix.source(), Source::Gsm);
+        assert_eq!(fix.source().as_str(), "GSM");
+        assert!((fix.latitude() - 42.51).abs() < f32::EPSILON);
+        assert!((fix.longitude() + 2.74).abs() < f32::EPSILON);
+        assert_eq!(fix.altitude(), None);
+
+        // Without a GSM location, the stale GPS data is used.
+        let mut gsm = FakeGsm::default();
+        let fix = locate(&gps, &mut gsm, fix_time + Duration::minutes(1)).unwrap();
+        assert!(matches!(fix, Fix::Gps(stale) if stale.fix_time() == fix_time));
+
+        // Above the GSM altitude, the GSM location is not requested.
+        let frame = Frame::new(fix_time, FixStatus::Active, 8, 42.5, -2.75, 5_000_f32);
+        let gps = FakeGps::new(vec![Some(frame)]);
+        let mut gsm = FakeGsm {
+            location: Some(Location::new(42.51, -2.74)),
+        };
+        let fix = locate(&gps, &mut gsm, fix_time + Duration::minutes(1)).unwrap();
+        assert_eq!(fix.source(), Source::Gps);
+
+        // When the fix is lost above the GSM altitude, the last valid GPS data is used.
+        let lost_time = fix_time + Duration::seconds(1);
+        let void = Frame::new(lost_time, FixStatus::Void, 0, 0_f32, 0_f32, 0_f32);
+        let gps = FakeGps::new(vec![Some(frame), Some(void)]);
+        let fix = locate(&gps, &mut gsm, fix_time).unwrap();
+        assert_eq!(fix.altitude(), Some(5_000_f32));
+        let fix = locate(&gps, &mut gsm, lost_time).unwrap();
+        assert_eq!(fix.source(), Source::Gps);
+        assert_eq!(fix.altitude(), Some(5_000_f32));
+
+        // When the fix is lost below the GSM altitude, the GSM location is used.
+        let frame = Frame::new(fix_time, FixStatus::Active, 8, 42.5, -2.75, 650_f32);
+        let gps = FakeGps::new(vec![Some(frame), Some(void)]);
+        assert_eq!(
+            locate(&gps, &mut gsm, fix_time).unwrap().source(),
+            Source::Gps
+        );
+        let fix = locate(&gps, &mut gsm, lost_time).unwrap();
+        assert_eq!(fix.source(), Source::Gsm);
+
+        // Without any GPS data.
+        let gps = FakeGps::new(Vec::new());
+        let fix = locate(&gps, &mut gsm, fix_time).unwrap();
+        assert_eq!(fix.source(), Source::Gsm);
+        assert!(locate(&gps, &mut FakeGsm::default(), fix_time).is_none());
+    }
+}
@@ -0,0 +1,276 @@
+//! Landing location prediction.
+//!
+//! Chase crews need to know where the probe will land well before it does. This module gives a
+//! rough estimate of the landing location, that can be updated with every GPS frame during the
+//! ascent. The estimate assumes that the balloon will keep rising at the given ascent rate until
+//! it reaches the expected burst altitude, and that it will then fall with a parachute whose
+//! terminal velocity scales with the air density, as given by the
+//! [`DescentModel`](struct.DescentModel.html).
+//!
+//! There is no wind forecast on board, so the last known horizontal velocity of the probe is used
+//! as a proxy for the wind during the rest of the flight. It's far from a meteorologically
+//! accurate prediction, but it gets better as the balloon rises, since the remaining part of the
+//! flight gets shorter.
+
+use crate::gps::{Frame, EARTH_RADIUS};
+
+/// Default descent rate of the parachute at sea level, in *m/s*.
+pub const DEFAULT_SEA_LEVEL_DESCENT_RATE: f32 = 5_f32;
+/// Scale height of the atmosphere, in *m*.
+///
+/// The air density decreases by a factor of *e* every scale height.
+pub const DEFAULT_SCALE_HEIGHT: f32 = 7_238_f32;
+
+/// Descent model of the probe after the burst.
+///
+/// The terminal velocity of the parachute is inversely proportional to the square root of the air
+/// density, and the air density is modelled as decreasing exponentially with altitude. This means
+/// that the descent rate at altitude *h* is *v₀·e^(h/2H)*, where *v₀* is the descent rate at sea
+/// level and *H* is the scale height of the atmosphere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DescentModel {
+    /// Descent rate at sea level, in *m/s*.
+    sea_level_rate: f32,
+    /// Scale height of the atmosphere, in *m*.
+    scale_height: f32,
+    /// Altitude of the landing area, in *m*.
+    ground_altitude: f32,
+}
+
+impl Default for DescentModel {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEA_LEVEL_DESCENT_RATE)
+    }
+}
+
+impl DescentModel {
+    /// Creates a new descent model, with the given descent rate at sea level, in *m/s*.
+    #[must_use]
+    pub fn new(sea_level_rate: f32) -> Self {
+        Self {
+            sea_level_rate,
+            scale_height: DEFAULT_SCALE_HEIGHT,
+            ground_altitude: 0_f32,
+        }
+    }
+
+    /// Sets the altitude of the landing area, in *m*.
+    #[must_use]
+    pub fn with_ground_altitude(self, ground_altitude: f32) -> Self {
+        Self {
+            ground_altitude,
+            ..self
+        }
+    }
+
+    /// Gets the descent rate at sea level, in *m/s*.
+    #[must_use]
+    pub fn sea_level_rate(&self) -> f32 {
+        self.sea_level_rate
+    }
+
+    /// Gets the altitude of the landing area, in *m*.
+    #[must_use]
+    pub fn ground_altitude(&self) -> f32 {
+        self.ground_altitude
+    }
+
+    /// Gets the descent rate at the given altitude, in *m/s*.
+    #[must_use]
+    pub fn descent_rate(&self, altitude: f32) -> f32 {
+        self.sea_level_rate * (altitude / (2_f32 * self.scale_height)).exp()
+    }
+
+    /// Gets the time it takes to fall from the given altitude to the ground, in *s*.
+    ///
+    /// This is the integral of *1/v(h)* from the ground to the given altitude, which has a closed
+    /// form for this model.
+    #[must_use]
+    pub fn descent_time(&self, altitude: f32) -> f32 {
+        if altitude <= self.ground_altitude {
+            return 0_f32;
+        }
+
+        let double_scale_height = 2_f32 * self.scale_height;
+        double_scale_height / self.sea_level_rate
+            * ((-self.ground_altitude / double_scale_height).exp()
+                - (-altitude / double_scale_height).exp())
+    }
+}
+
+/// Predicts the landing location of the probe, as a `(latitude, longitude)` pair in *°*.
+///
+/// The ascent rate is given in *m/s* and the burst altitude in *m*. If the probe is already above
+/// the expected burst altitude, the descent is predicted from its current altitude.
+#[must_use]
+pub fn predict_landing(
+    current: &Frame,
+    ascent_rate: f32,
+    burst_altitude: f32,
+    descent_model: DescentModel,
+) -> (f32, f32) {
+    let remaining_ascent = (burst_altitude - current.altitude()).max(0_f32);
+    let ascent_time = if ascent_rate > 0_f32 {
+        remaining_ascent / ascent_rate
+    } else {
+        0_f32
+    };
+    let descent_time = descent_model.descent_time(current.altitude().max(burst_altitude));
+
+    let drift = f64::from(current.speed()) * f64::from(ascent_time + descent_time);
+    let (latitude, longitude) = destination(
+        f64::from(current.latitude()),
+        f64::from(current.longitude()),
+        f64::from(current.course()),
+        drift,
+    );
+
+    #[allow(clippy::cast_possible_truncation)]
+    (latitude as f32, longitude as f32)
+}
+
+/// Gets the point at the given distance, in *m*, from the given point, following the great circle
+/// with the given initial bearing. All angles are in *°*.
+fn destination(latitude: f64, longitude: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let latitude = latitude.to_radians();
+    let bearing = bearing.to_radians();
+    let angle = distance / EARTH_RADIUS;
+
+    let destination_latitude =
+        (latitude.sin() * angle.cos() + latitude.cos() * angle.sin() * bearing.cos()).asin();
+    let longitude_delta = (bearing.sin() * angle.sin() * latitude.cos())
+        .atan2(angle.cos() - latitude.sin() * destination_latitude.sin());
+
+    let destination_longitude = (longitude + longitude_delta.to_degrees() + 540_f64) % 360_f64;
+    (
+        destination_latitude.to_degrees(),
+        destination_longitude - 180_f64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{destination, predict_landing, DescentModel, EARTH_RADIUS};
+    use crate::gps::{FixStatus, Frame};
+
+    /// Ascent rate of the synthetic flight, in *m/s*.
+    const ASCENT_RATE: f32 = 5_f32;
+    /// Burst altitude of the synthetic flight, in *m*.
+    const BURST_ALTITUDE: f32 = 30_000_f32;
+    /// Direction the wind blows towards, in *°*.
+    const WIND_COURSE: f64 = 60_f64;
+
+    /// Wind speed of the synthetic flight at the given altitude, in *m/s*.
+    ///
+    /// It grows from 5 *m/s* at sea level to 20 *m/s* at 10 *km*, and stays constant above.
+    fn wind_speed(altitude: f64) -> f64 {
+        5_f64 + 15_f64 * (altitude / 10_000_f64).min(1_f64)
+    }
+
+    /// Gets the great circle distance between two points, in *m*.
+    fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+        let (latitude_1, latitude_2) = (from.0.to_radians(), to.0.to_radians());
+        let latitude_delta = latitude_2 - latitude_1;
+        let longitude_delta = (to.1 - from.1).to_radians();
+
+        let a = (latitude_delta / 2_f64).sin().powi(2)
+            + latitude_1.cos() * latitude_2.cos() * (longitude_delta / 2_f64).sin().powi(2);
+        2_f64 * EARTH_RADIUS * a.sqrt().asin()
+    }
+
+    /// Checks the descent model against known values.
+    #[test]
+    fn descent_model() {
+        let model = DescentModel::default();
+        assert!((model.descent_rate(0_f32) - 5_f32).abs() < 1e-6);
+        assert!(model.descent_rate(30_000_f32) > 35_f32);
+        assert!(model.descent_rate(30_000_f32) < 45_f32);
+
+        assert!(model.descent_time(0_f32).abs() < f32::EPSILON);
+        assert!(model.descent_time(-100_f32).abs() < f32::EPSILON);
+        // Falling from the burst takes much less than falling at sea level rate.
+        let descent_time = model.descent_time(30_000_f32);
+        assert!(descent_time < 30_000_f32 / 5_f32);
+        assert!(descent_time > 30_000_f32 / 45_f32);
+
+        let high_ground = model.with_ground_altitude(1_000_f32);
+        assert!(high_ground.descent_time(30_000_f32) < descent_time);
+        assert!(high_ground.descent_time(500_f32).abs() < f32::EPSILON);
+    }
+
+    /// Checks that with no horizontal speed, the predicted landing is right below the probe.
+    #[test]
+    fn no_wind() {
+        let frame = Frame::new(
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 11, 46).unwrap(),
+            FixStatus::Active,
+            9,
+            42.5,
+            -2.75,
+            5_000_f32,
+        );
+        assert_eq!(
+            predict_landing(&frame, ASCENT_RATE, BURST_ALTITUDE, DescentModel::default()),
+            (42.5, -2.75)
+        );
+    }
+
+    /// Simulates a flight with altitude dependent wind, and checks that the prediction converges
+    /// towards the actual landing location as the balloon rises.
+    #[test]
+    fn synthetic_flight() {
+        let model = DescentModel::default();
+        let start = Utc.with_ymd_and_hms(2017, 3, 25, 12, 0, 0).unwrap();
+        let mut position = (42.5_f64, -2.75_f64);
+        let mut altitude = 0_f64;
+        let mut second = 0_i64;
+
+        // Ascent, recording a prediction every 5 km.
+        let mut predictions = Vec::new();
+        while altitude < f64::from(BURST_ALTITUDE) {
+            if second % 1_000 == 0 {
+                #[allow(clippy::cast_possible_truncation)]
+                let frame = Frame::new(
+                    start + chrono::Duration::seconds(second),
+                    FixStatus::Active,
+                    9,
+                    position.0 as f32,
+                    position.1 as f32,
+                    altitude as f32,
+                )
+                .with_velocity(wind_speed(altitude) as f32, WIND_COURSE as f32);
+                predictions.push(predict_landing(&frame, ASCENT_RATE, BURST_ALTITUDE, model));
+            }
+
+            position = destination(position.0, position.1, WIND_COURSE, wind_speed(altitude));
+            altitude += f64::from(ASCENT_RATE);
+            second += 1;
+        }
+
+        // Descent.
+        while altitude > 0_f64 {
+            position = destination(position.0, position.1, WIND_COURSE, wind_speed(altitude));
+            #[allow(clippy::cast_possible_truncation)]
+            let descent_rate = model.descent_rate(altitude as f32);
+            altitude -= f64::from(descent_rate);
+        }
+
+        let errors: Vec<_> = predictions
+            .iter()
+            .map(|&(latitude, longitude)| {
+                distance((f64::from(latitude), f64::from(longitude)), position)
+            })
+            .collect();
+
+        assert_eq!(errors.len(), 6);
+        for pair in errors.windows(2) {
+            assert!(pair[1] < pair[0], "prediction error grew: {errors:?}");
+        }
+        // The last prediction is within 15 km, after a flight of more than 100 km.
+        assert!(distance((42.5, -2.75), position) > 100_000_f64);
+        assert!(errors[5] < 15_000_f64, "last error too big: {errors:?}");
+        assert!(errors[0] > 4_f64 * errors[5]);
+    }
+}
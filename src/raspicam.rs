@@ -4,7 +4,7 @@
 
 use anyhow::{bail, Error};
 use std::{
-    fs, io, mem,
+    env, fs, io, mem,
     path::{Path, PathBuf},
     process::{Child, Command, Stdio},
     sync::Mutex,
@@ -12,38 +12,52 @@ use std::{
     time::Duration,
 };
 // Only required for GPS
+#[cfg(feature = "gps")]
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 #[cfg(feature = "gps")]
 use std::fmt;
 use tracing::{debug, error, info, warn};
 
 #[cfg(feature = "gps")]
-use crate::gps::{FixStatus, GPS};
-use crate::{config::CONFIG, error, generate_error_string};
+use crate::{
+    config::AnnotationPosition,
+    gps::{FixStatus, Frame, GPS},
+};
+// Only required for GPS and FONA
+use crate::{
+    config::{Picture, Video, CONFIG},
+    data::{self, IMG_DIR, VIDEO_DIR},
+    error, generate_error_string,
+};
+#[cfg(all(feature = "gps", feature = "fona"))]
+use crate::{
+    fona::FONA,
+    position::{self, Fix},
+};
+
+/// Default size of the picture annotation text.
+#[cfg(feature = "gps")]
+pub const DEFAULT_ANNOTATION_SIZE: u8 = 32;
+
+/// Default camera warm-up time before taking a picture, in milliseconds.
+///
+/// It gives the automatic gain and white balance some time to settle.
+pub const DEFAULT_PICTURE_WARMUP_MS: u32 = 200;
 
-/// Video directory inside data directory.
-pub const VIDEO_DIR: &str = "video";
-/// Image directory inside data directory.
-pub const IMG_DIR: &str = "img";
+/// Maximum number of attempts to run a camera command.
+const COMMAND_ATTEMPTS: u32 = 3;
+/// Time to wait before retrying a failed camera command, doubled after each retry.
+const COMMAND_BACKOFF: Duration = Duration::from_millis(500);
 
 /// Shared static camera object.
-pub static CAMERA: Lazy<Mutex<Camera>> = Lazy::new(|| {
-    Mutex::new(Camera {
-        video_dir: CONFIG.data_dir().join(VIDEO_DIR),
-        picture_dir: CONFIG.data_dir().join(IMG_DIR),
-        process: None,
-    })
-});
+pub static CAMERA: Lazy<Mutex<Camera>> = Lazy::new(|| Mutex::new(Camera { process: None }));
 
 /// Camera structure.
 ///
 /// This structure controls the use of the camera.
 #[derive(Debug)]
 pub struct Camera {
-    /// Directory to save video files.
-    video_dir: PathBuf,
-    /// Directory to save picture files.
-    picture_dir: PathBuf,
     /// Video process handle.
     process: Option<Child>,
 }
@@ -88,15 +102,13 @@ impl Camera {
             error!("The camera is already recording.");
             bail!(error::Raspicam::AlreadyRecording);
         }
-        let file = self.video_dir.join(if cfg!(test) {
+        let video_dir = data::ensure_subdir(VIDEO_DIR)?;
+        let file = video_dir.join(if cfg!(test) {
             PathBuf::from("test.h264")
         } else if let Some(path) = file_name {
             path.as_ref().to_path_buf()
         } else {
-            PathBuf::from(&format!(
-                "video-{}.h264",
-                fs::read_dir(&self.video_dir)?.count()
-            ))
+            PathBuf::from(&format!("video-{}.h264", fs::read_dir(&video_dir)?.count()))
         });
         if file.exists() {
             error!(
@@ -106,7 +118,7 @@ impl Camera {
             bail!(error::Raspicam::FileExists { file });
         }
 
-        let mut command = Self::generate_video_command(time, file);
+        let mut command = Self::generate_video_command(CONFIG.video(), time, file);
 
         #[allow(clippy::use_debug)]
         {
@@ -115,7 +127,12 @@ impl Camera {
         info!("Starting video recording\u{2026}");
 
         if time.is_some() {
-            let output = command.output()?;
+            let output = run_with_retry(
+                "raspivid",
+                || command.output(),
+                |output| output.status.success(),
+                thread::sleep,
+            )?;
             if output.status.success() {
                 info!("Video recording finished successfully.");
             } else {
@@ -130,64 +147,70 @@ impl Camera {
             let _ = command.stdin(Stdio::null());
             let _ = command.stdout(Stdio::null());
             let _ = command.stderr(Stdio::null());
-            let child = command.spawn()?;
+            let child = run_with_retry("raspivid", || command.spawn(), |_| true, thread::sleep)?;
             info!("Video recording started with PID {}.", child.id());
             self.process = Some(child);
         }
         Ok(())
     }
 
-    /// Generates the video command with the configured parameters.
-    fn generate_video_command(time: Option<Duration>, file: PathBuf) -> Command {
+    /// Generates the video command with the given video configuration.
+    fn generate_video_command(video: &Video, time: Option<Duration>, file: PathBuf) -> Command {
         let mut command = Command::new("raspivid");
         let _ = command
             .arg("-n")
             .arg("-o")
             .arg(file)
             .arg("-w")
-            .arg(format!("{}", CONFIG.video().width()))
+            .arg(format!("{}", video.width()))
             .arg("-h")
-            .arg(format!("{}", CONFIG.video().height()))
+            .arg(format!("{}", video.height()))
             .arg("-fps")
-            .arg(format!("{}", CONFIG.video().fps()))
+            .arg(format!("{}", video.fps()))
             .arg("-b")
-            .arg(format!("{}", CONFIG.video().bitrate()));
+            .arg(format!("{}", video.bitrate()));
         if let Some(time) = time {
             let _ = command.arg("-t").arg(format!(
                 "{}",
                 time.as_secs() * 1_000 + u64::from(time.subsec_nanos()) / 1_000_000
             ));
         }
-        if let Some(rot) = CONFIG.video().rotation() {
+        if let Some(rot) = video.rotation() {
             let _ = command.arg("-rot").arg(format!("{}", rot));
         }
-        if let Some(ex) = CONFIG.video().exposure() {
+        if let Some(ex) = video.exposure() {
             let _ = command.arg("-ex").arg(ex);
         }
-        if let Some(br) = CONFIG.video().brightness() {
+        if let Some(br) = video.brightness() {
             let _ = command.arg("-br").arg(format!("{}", br));
         }
-        if let Some(co) = CONFIG.video().contrast() {
+        if let Some(co) = video.contrast() {
             let _ = command.arg("-co").arg(format!("{}", co));
         }
-        if let Some(sh) = CONFIG.video().sharpness() {
+        if let Some(sh) = video.sharpness() {
             let _ = command.arg("-sh").arg(format!("{}", sh));
         }
-        if let Some(sa) = CONFIG.video().saturation() {
+        if let Some(sa) = video.saturation() {
             let _ = command.arg("-sa").arg(format!("{}", sa));
         }
-        if let Some(iso) = CONFIG.video().iso() {
+        if let Some(iso) = video.iso() {
             let _ = command.arg("-ISO").arg(format!("{}", iso));
         }
-        if CONFIG.video().stabilization() {
+        if video.stabilization() {
             let _ = command.arg("-vs");
         }
-        if let Some(ev) = CONFIG.video().ev() {
+        if let Some(ev) = video.ev() {
             let _ = command.arg("-ev").arg(format!("{}", ev));
         }
-        if let Some(awb) = CONFIG.video().white_balance() {
+        if let Some(awb) = video.white_balance() {
             let _ = command.arg("-awb").arg(awb);
         }
+        if let Some(mm) = video.metering() {
+            let _ = command.arg("-mm").arg(mm);
+        }
+        if let Some(drc) = video.drc() {
+            let _ = command.arg("-drc").arg(drc);
+        }
 
         command
     }
@@ -243,6 +266,37 @@ impl Camera {
         }
     }
 
+    /// Checks that the `raspivid` binary is available and that the camera is detected.
+    ///
+    /// This is a quick alternative to a test recording, that uses `vcgencmd get_camera` to check
+    /// that the camera is both supported and detected by the firmware.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `vcgencmd` can't be run, or if its output is not valid UTF-8. A missing
+    /// binary or camera is not an error.
+    pub fn detect() -> Result<bool, Error> {
+        info!("Detecting camera\u{2026}");
+        let binary_found = env::var_os("PATH").is_some_and(|paths| {
+            env::split_paths(&paths).any(|dir| dir.join("raspivid").is_file())
+        });
+        if !binary_found {
+            error!("The raspivid binary could not be found.");
+            return Ok(false);
+        }
+
+        let output = Command::new("vcgencmd").arg("get_camera").output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        debug!("Camera detection output: {}", stdout.trim());
+        if output.status.success() && stdout.contains("detected=1") {
+            info!("Camera detected.");
+            Ok(true)
+        } else {
+            error!("The camera was not detected.");
+            Ok(false)
+        }
+    }
+
     /// Takes a picture with the camera.
     pub fn take_picture<P, FN>(&mut self, file_name: FN) -> Result<(), Error>
     where
@@ -258,15 +312,13 @@ impl Camera {
             );
             self.stop_recording()?;
         }
-        let file = self.picture_dir.join(if cfg!(test) {
+        let picture_dir = data::ensure_subdir(IMG_DIR)?;
+        let file = picture_dir.join(if cfg!(test) {
             PathBuf::from("test.jpg")
         } else if let Some(path) = file_name {
             path.as_ref().to_path_buf()
         } else {
-            PathBuf::from(&format!(
-                "img-{}.jpg",
-                fs::read_dir(&self.picture_dir)?.count()
-            ))
+            PathBuf::from(&format!("img-{}.jpg", fs::read_dir(&picture_dir)?.count()))
         });
         if file.exists() {
             error!(
@@ -276,14 +328,19 @@ impl Camera {
             return Err(error::Raspicam::FileExists { file }.into());
         }
 
-        let mut command = Self::generate_picture_command(file);
+        let mut command = Self::generate_picture_command(CONFIG.picture(), file);
         #[allow(clippy::use_debug)]
         {
             debug!("Picture command: {:?}", command);
         }
         info!("Taking picture\u{2026}");
 
-        let output = command.output()?;
+        let output = run_with_retry(
+            "raspistill",
+            || command.output(),
+            |output| output.status.success(),
+            thread::sleep,
+        )?;
         if output.status.success() {
             info!("Picture taken successfully.");
         } else {
@@ -298,59 +355,114 @@ impl Camera {
         Ok(())
     }
 
-    /// Generates the picture command with the configured parameters.
-    fn generate_picture_command(file: PathBuf) -> Command {
+    /// Generates the picture command with the given picture configuration.
+    fn generate_picture_command(picture: &Picture, file: PathBuf) -> Command {
         let mut command = Command::new("raspistill");
         let _ = command
             .arg("-n")
             .arg("-o")
             .arg(file)
             .arg("-t")
-            .arg("0")
+            .arg(format!("{}", picture.warmup_ms()))
             .arg("-w")
-            .arg(format!("{}", CONFIG.picture().width()))
+            .arg(format!("{}", picture.width()))
             .arg("-h")
-            .arg(format!("{}", CONFIG.picture().height()))
+            .arg(format!("{}", picture.height()))
             .arg("-q")
-            .arg(format!("{}", CONFIG.picture().quality()));
-        if let Some(rot) = CONFIG.picture().rotation() {
+            .arg(format!("{}", picture.quality()));
+        if let Some(rot) = picture.rotation() {
             let _ = command.arg("-rot").arg(format!("{}", rot));
         }
         #[cfg(feature = "gps")]
         {
-            if CONFIG.picture().exif() {
-                let _ = command.arg("-x").arg(ExifData::new().to_string());
+            let gps_data = latest_gps_data();
+            if picture.exif() {
+                #[cfg(feature = "fona")]
+                let exif = ExifData::from_fix(
+                    position::locate(&&*GPS, &mut &*FONA, Utc::now()).as_ref(),
+                    Utc::now(),
+                );
+                #[cfg(not(feature = "fona"))]
+                let exif = ExifData::new(gps_data.as_ref(), Utc::now());
+                let _ = command.arg("-x").arg(exif.to_string());
+            }
+            if let Some((text, settings)) = annotation(picture, gps_data.as_ref()) {
+                let _ = command.arg("-a").arg(text).arg("-ae").arg(settings);
             }
         }
-        if let Some(ex) = CONFIG.picture().exposure() {
+        if let Some(ex) = picture.exposure() {
             let _ = command.arg("-ex").arg(ex);
         }
-        if let Some(br) = CONFIG.picture().brightness() {
+        if let Some(br) = picture.brightness() {
             let _ = command.arg("-br").arg(format!("{}", br));
         }
-        if let Some(co) = CONFIG.picture().contrast() {
+        if let Some(co) = picture.contrast() {
             let _ = command.arg("-co").arg(format!("{}", co));
         }
-        if let Some(sh) = CONFIG.picture().sharpness() {
+        if let Some(sh) = picture.sharpness() {
             let _ = command.arg("-sh").arg(format!("{}", sh));
         }
-        if let Some(sa) = CONFIG.picture().saturation() {
+        if let Some(sa) = picture.saturation() {
             let _ = command.arg("-sa").arg(format!("{}", sa));
         }
-        if let Some(iso) = CONFIG.picture().iso() {
+        if let Some(iso) = picture.iso() {
             let _ = command.arg("-ISO").arg(format!("{}", iso));
         }
-        if let Some(ev) = CONFIG.picture().ev() {
+        if let Some(ev) = picture.ev() {
             let _ = command.arg("-ev").arg(format!("{}", ev));
         }
-        if let Some(awb) = CONFIG.picture().white_balance() {
+        if let Some(awb) = picture.white_balance() {
             let _ = command.arg("-awb").arg(awb);
         }
+        if let Some(mm) = picture.metering() {
+            let _ = command.arg("-mm").arg(mm);
+        }
+        if let Some(drc) = picture.drc() {
+            let _ = command.arg("-drc").arg(drc);
+        }
 
         command
     }
 }
 
+/// Runs a camera command, retrying it with backoff if it fails transiently.
+///
+/// The `run` function executes the command, and `is_success` checks its result. Errors spawning
+/// the command, and unsuccessful results, are retried up to `COMMAND_ATTEMPTS` times, waiting with
+/// the given `sleep` function between attempts. If the binary is missing, it's not retried. The
+/// result of the last attempt is returned.
+fn run_with_retry<T, R, C, S>(program: &str, mut run: R, is_success: C, sleep: S) -> io::Result<T>
+where
+    R: FnMut() -> io::Result<T>,
+    C: Fn(&T) -> bool,
+    S: Fn(Duration),
+{
+    let mut backoff = COMMAND_BACKOFF;
+    for attempt in 1.. {
+        debug!(attempt, "Running {}\u{2026}", program);
+        let result = run();
+        let transient = match &result {
+            Ok(output) => !is_success(output),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                error!("The {} binary could not be found.", program);
+                false
+            }
+            Err(_) => true,
+        };
+        if !transient || attempt == COMMAND_ATTEMPTS {
+            return result;
+        }
+
+        match &result {
+            Ok(_) => warn!(attempt, "{} failed, retrying\u{2026}", program),
+            Err(e) => warn!(attempt, error = %e, "Error running {}, retrying\u{2026}", program),
+        }
+        sleep(backoff);
+        backoff *= 2;
+    }
+    unreachable!("the retry loop only ends by returning")
+}
+
 impl Drop for Camera {
     fn drop(&mut self) {
         info!("Shutting down\u{2026}");
@@ -395,19 +507,14 @@ pub struct ExifData {
 
 #[cfg(feature = "gps")]
 impl ExifData {
-    /// Creates new EXIF data from GPS.
+    /// Creates new EXIF data from the given GPS data.
+    ///
+    /// The GPS tags are only added if the data is from a valid fix that is not stale at the given
+    /// time, so that pictures are not stamped with wrong coordinates.
     ///
     /// *In development…*
-    fn new() -> Self {
-        let gps = match GPS.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => {
-                error!("The GPS mutex was poisoned.");
-                poisoned.into_inner()
-            }
-        };
-
-        if let Some(gps_data) = gps.latest_data() {
+    fn new(gps_data: Option<&Frame>, now: DateTime<Utc>) -> Self {
+        if let Some(gps_data) = gps_data.filter(|gps_data| gps_data.is_fresh(now)) {
             Self {
                 gps_latitude: Some((LatitudeRef::from(gps_data.latitude()), gps_data.latitude())),
                 gps_longitude: Some((
@@ -423,9 +530,75 @@ impl ExifData {
                 gps_track: Some(gps_data.course()),
             }
         } else {
+            info!("No valid and fresh GPS fix, the picture will not have GPS EXIF data.");
             Self::default()
         }
     }
+
+    /// Creates new EXIF data from the given position fix.
+    ///
+    /// GPS fixes are treated as in [`new()`](#method.new), while GSM locations only have the
+    /// latitude and longitude tags.
+    #[cfg(feature = "fona")]
+    fn from_fix(fix: Option<&Fix>, now: DateTime<Utc>) -> Self {
+        match fix {
+            Some(Fix::Gsm(location)) => Self {
+                gps_latitude: Some((LatitudeRef::from(location.latitude()), location.latitude())),
+                gps_longitude: Some((
+                    LongitudeRef::from(location.longitude()),
+                    location.longitude(),
+                )),
+                ..Self::default()
+            },
+            Some(Fix::Gps(frame)) => Self::new(Some(frame), now),
+            None => Self::new(None, now),
+        }
+    }
+}
+
+/// Gets the latest GPS data, to add it to pictures.
+#[cfg(feature = "gps")]
+fn latest_gps_data() -> Option<Frame> {
+    match GPS.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("The GPS mutex was poisoned.");
+            poisoned.into_inner()
+        }
+    }
+    .latest_data()
+}
+
+/// Generates the annotation text of a picture and its `raspistill` settings, from the given GPS
+/// data.
+///
+/// Returns `None` if annotations are disabled, or if there is no valid GPS fix to annotate.
+#[cfg(feature = "gps")]
+fn annotation(picture: &Picture, gps_data: Option<&Frame>) -> Option<(String, String)> {
+    let template = picture.annotation()?;
+    let gps_data = gps_data.filter(|gps_data| gps_data.is_valid())?;
+
+    let text = template
+        .replace(
+            "{date}",
+            &gps_data.fix_time().format("%Y-%m-%d").to_string(),
+        )
+        .replace(
+            "{time}",
+            &gps_data.fix_time().format("%H:%M:%S").to_string(),
+        )
+        .replace("{altitude}", &format!("{:.0}", gps_data.altitude()))
+        .replace("{latitude}", &format!("{:.5}", gps_data.latitude()))
+        .replace("{longitude}", &format!("{:.5}", gps_data.longitude()))
+        .replace("{satellites}", &gps_data.satellites().to_string());
+
+    // Text size and colour, background colour, centered justification and offset.
+    let size = picture.annotation_size();
+    let offset = match picture.annotation_position() {
+        AnnotationPosition::Top => 0,
+        AnnotationPosition::Bottom => picture.height().saturating_sub(2 * u16::from(size)),
+    };
+    Some((text, format!("{size},0xff,0x808000,0,0,{offset}")))
 }
 
 #[cfg(feature = "gps")]
@@ -567,12 +740,290 @@ impl fmt::Display for LongitudeRef {
     }
 }
 
+/// Reason to take a scheduled picture.
+#[cfg(feature = "gps")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shot {
+    /// The interval between pictures has elapsed.
+    Interval,
+    /// The given altitude trigger, in *m*, was crossed upwards.
+    Altitude(u32),
+}
+
+#[cfg(feature = "gps")]
+impl Shot {
+    /// Gets the file name of the picture, if it's not the default one.
+    ///
+    /// Altitude triggered pictures are tagged with their altitude.
+    #[must_use]
+    pub fn file_name(self) -> Option<PathBuf> {
+        match self {
+            Self::Interval => None,
+            Self::Altitude(altitude) => Some(PathBuf::from(format!("img-alt-{altitude}m.jpg"))),
+        }
+    }
+}
+
+/// Schedule of the pictures taken during the flight.
+///
+/// The first picture is taken `first_timeout` seconds after the launch, and then every `interval`
+/// seconds. An extra picture is taken the first time each altitude trigger is crossed upwards,
+/// and it restarts the interval, so that a picture is never taken twice in a row.
+#[cfg(feature = "gps")]
+#[derive(Debug, Clone)]
+pub struct PictureSchedule {
+    /// Time of the launch.
+    launch: DateTime<Utc>,
+    /// Time until the first picture after the launch.
+    first_timeout: chrono::Duration,
+    /// Interval between pictures.
+    interval: chrono::Duration,
+    /// Time of the last picture, if any.
+    last: Option<DateTime<Utc>>,
+    /// Altitude triggers not crossed yet, in *m*, in ascending order.
+    triggers: Vec<u32>,
+    /// Wether the triggers below the initial altitude were discarded.
+    started: bool,
+}
+
+#[cfg(feature = "gps")]
+impl PictureSchedule {
+    /// Creates the schedule of a flight launched at the given time, with the given picture
+    /// configuration.
+    #[must_use]
+    pub fn new(launch: DateTime<Utc>, picture: &Picture) -> Self {
+        Self::with_times(
+            launch,
+            picture.first_timeout(),
+            picture.interval(),
+            picture.altitude_triggers(),
+        )
+    }
+
+    /// Creates the schedule of a flight launched at the given time, with the given first picture
+    /// timeout and interval, in seconds, and altitude triggers, in *m*.
+    #[must_use]
+    pub fn with_times(
+        launch: DateTime<Utc>,
+        first_timeout: u32,
+        interval: u32,
+        triggers: &[u32],
+    ) -> Self {
+        let mut triggers = triggers.to_vec();
+        triggers.sort_unstable();
+        triggers.dedup();
+        Self {
+            launch,
+            first_timeout: chrono::Duration::seconds(i64::from(first_timeout)),
+            interval: chrono::Duration::seconds(i64::from(interval)),
+            last: None,
+            triggers,
+            started: false,
+        }
+    }
+
+    /// Checks if a picture should be taken at the given time and altitude, in *m*.
+    ///
+    /// If several altitude triggers were crossed at once, only one picture is taken, tagged with
+    /// the highest of them. Triggers below the first checked altitude never fire.
+    pub fn check(&mut self, time: DateTime<Utc>, altitude: f32) -> Option<Shot> {
+        #[allow(clippy::cast_precision_loss)]
+        let crossed = self
+            .triggers
+            .iter()
+            .copied()
+            .filter(|&trigger| trigger as f32 <= altitude)
+            .max();
+        if crossed.is_some() {
+            #[allow(clippy::cast_precision_loss)]
+            self.triggers.retain(|&trigger| trigger as f32 > altitude);
+        }
+        let crossed = crossed.filter(|_| self.started);
+        self.started = true;
+
+        let interval_due = match self.last {
+            Some(last) => time - last >= self.interval,
+            None => time - self.launch >= self.first_timeout,
+        };
+
+        let shot = crossed
+            .map(Shot::Altitude)
+            .or_else(|| interval_due.then_some(Shot::Interval));
+        if shot.is_some() {
+            self.last = Some(time);
+        }
+        shot
+    }
+}
+
 /// Tests module.
 #[cfg(test)]
 mod tests {
-    use super::CAMERA;
     #[cfg(feature = "gps")]
-    use super::{ExifData, FixStatus, LatitudeRef, LongitudeRef};
+    use super::{
+        annotation, ExifData, FixStatus, Frame, LatitudeRef, LongitudeRef, Picture,
+        PictureSchedule, Shot,
+    };
+    use super::{run_with_retry, Camera, CAMERA, COMMAND_ATTEMPTS, COMMAND_BACKOFF};
+    use crate::config::Config;
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    use crate::{fona::Location, position::Fix};
+    #[cfg(feature = "gps")]
+    use chrono::{Duration, TimeZone, Utc};
+    use std::{cell::RefCell, ffi::OsStr, fs, io, path::PathBuf};
+
+    /// Checks that each altitude trigger fires once, and that the interval pictures are not
+    /// repeated right after a triggered one.
+    #[cfg(feature = "gps")]
+    #[test]
+    fn altitude_triggers() {
+        let launch = Utc.with_ymd_and_hms(2017, 3, 25, 14, 0, 0).unwrap();
+        let mut schedule = PictureSchedule::with_times(launch, 120, 300, &[20_000, 500, 10_000]);
+
+        // 5 m/s up to 21,100 m, dropping 600 m after crossing 10,000 m, and then a descent.
+        let mut altitude = 300_f32;
+        let mut shots = Vec::new();
+        for second in 1..=6_000 {
+            altitude += match second {
+                1..=2_000 | 2_121..=4_400 => 5_f32,
+                2_001..=2_120 => -5_f32,
+                _ => -10_f32,
+            };
+            let time = launch + Duration::seconds(second);
+            if let Some(shot) = schedule.check(time, altitude) {
+                shots.push((second, shot));
+            }
+        }
+
+        let triggered: Vec<_> = shots
+            .iter()
+            .filter(|(_, shot)| *shot != Shot::Interval)
+            .collect();
+        assert_eq!(
+            triggered,
+            [
+                &(40, Shot::Altitude(500)),
+                &(1_940, Shot::Altitude(10_000)),
+                &(4_180, Shot::Altitude(20_000)),
+            ]
+        );
+
+        // The first picture is taken after the timeout, and a triggered picture restarts the
+        // interval.
+        assert_eq!(shots[0], (40, Shot::Altitude(500)));
+        assert_eq!(shots[1], (340, Shot::Interval));
+        assert!(shots.windows(2).all(|pair| pair[1].0 - pair[0].0 <= 300));
+        assert!(shots.iter().any(|&shot| shot == (2_240, Shot::Interval)));
+        assert_eq!(
+            Shot::Altitude(10_000).file_name().unwrap().to_str(),
+            Some("img-alt-10000m.jpg")
+        );
+        assert_eq!(Shot::Interval.file_name(), None);
+
+        // Triggers below the first altitude never fire.
+        let mut schedule = PictureSchedule::with_times(launch, 120, 300, &[500]);
+        assert_eq!(schedule.check(launch, 600_f32), None);
+        assert_eq!(schedule.check(launch, 700_f32), None);
+    }
+
+    /// Checks that the `-t` argument of the picture command is the configured warm-up time.
+    #[test]
+    fn picture_warmup() {
+        let contents = fs::read_to_string("config.toml").unwrap();
+        for (config, warmup) in [
+            (contents.clone(), "200"),
+            (
+                contents.replace("#warmup_ms = 200", "warmup_ms = 1500"),
+                "1500",
+            ),
+        ] {
+            let config: Config = toml::from_str(&config).unwrap();
+            let command =
+                Camera::generate_picture_command(config.picture(), PathBuf::from("test.jpg"));
+            let args: Vec<_> = command.get_args().collect();
+            let pos = args.iter().position(|&arg| arg == "-t").unwrap();
+            assert_eq!(args[pos + 1], warmup);
+        }
+    }
+
+    /// Checks that the metering and dynamic range compression arguments are only added to the
+    /// picture and video commands when they are configured.
+    #[test]
+    fn metering_drc() {
+        let contents = fs::read_to_string("config.toml").unwrap();
+        let configured = contents
+            .replace("#metering = \"average\"", "metering = \"backlit\"")
+            .replace("#drc = \"off\"", "drc = \"high\"");
+        for (config, expected) in [(contents, None), (configured, Some(("backlit", "high")))] {
+            let config: Config = toml::from_str(&config).unwrap();
+            let picture =
+                Camera::generate_picture_command(config.picture(), PathBuf::from("test.jpg"));
+            let video =
+                Camera::generate_video_command(config.video(), None, PathBuf::from("test.h264"));
+            for command in [picture, video] {
+                let args: Vec<_> = command.get_args().collect();
+                let value = |flag| {
+                    args.iter()
+                        .position(|&arg| arg == flag)
+                        .map(|pos| args[pos + 1])
+                };
+                assert_eq!(value("-mm"), expected.map(|(mm, _)| OsStr::new(mm)));
+                assert_eq!(value("-drc"), expected.map(|(_, drc)| OsStr::new(drc)));
+            }
+        }
+    }
+
+    /// Checks that transient camera command failures are retried with backoff, but a missing
+    /// binary is not.
+    #[test]
+    fn command_retry() {
+        let sleeps = RefCell::new(Vec::new());
+        let mut attempts = 0;
+        let result = run_with_retry(
+            "raspistill",
+            || {
+                attempts += 1;
+                if attempts == 1 {
+                    Err(io::Error::other("failed to create camera component"))
+                } else {
+                    Ok(attempts)
+                }
+            },
+            |_| true,
+            |time| sleeps.borrow_mut().push(time),
+        );
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(*sleeps.borrow(), [COMMAND_BACKOFF]);
+
+        sleeps.borrow_mut().clear();
+        let mut attempts = 0;
+        let result = run_with_retry(
+            "raspistill",
+            || {
+                attempts += 1;
+                Ok(attempts)
+            },
+            |&attempts| attempts > COMMAND_ATTEMPTS,
+            |time| sleeps.borrow_mut().push(time),
+        );
+        assert_eq!(result.unwrap(), COMMAND_ATTEMPTS);
+        assert_eq!(*sleeps.borrow(), [COMMAND_BACKOFF, COMMAND_BACKOFF * 2]);
+
+        sleeps.borrow_mut().clear();
+        let mut attempts = 0;
+        let result: io::Result<()> = run_with_retry(
+            "raspistill",
+            || {
+                attempts += 1;
+                Err(io::ErrorKind::NotFound.into())
+            },
+            |_| true,
+            |time| sleeps.borrow_mut().push(time),
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(attempts, 1);
+        assert!(sleeps.borrow().is_empty());
+    }
 
     /// Tests EXIF generation.
     #[test]
@@ -604,6 +1055,90 @@ mod tests {
         );
     }
 
+    /// Checks that the GPS EXIF tags are only added with a valid and fresh fix.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn exif_data_fix() {
+        let fix_time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 5, 9).unwrap();
+        let frame = |status| Frame::new(fix_time, status, 7, 42.5, -2.75, 1_500.34);
+
+        let exif = ExifData::new(Some(&frame(FixStatus::Active)), fix_time).to_string();
+        assert!(exif.contains("GPS.GPSLatitude="));
+
+        for (gps_data, now) in [
+            (Some(frame(FixStatus::Void)), fix_time),
+            (
+                Some(frame(FixStatus::Active)),
+                fix_time + Duration::seconds(6),
+            ),
+            (None, fix_time),
+        ] {
+            let exif = ExifData::new(gps_data.as_ref(), now).to_string();
+            assert!(!exif.contains("GPS.GPSLatitude"), "{exif}");
+            assert!(!exif.contains("GPS.GPSLongitude"), "{exif}");
+        }
+    }
+
+    /// Checks the EXIF data of the position fixes: GPS fixes get every tag, and GSM locations only
+    /// the coordinates.
+    #[test]
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn exif_data_position() {
+        let fix_time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 5, 9).unwrap();
+        let frame = Frame::new(fix_time, FixStatus::Active, 7, 42.5, -2.75, 1_500.34);
+
+        let exif = ExifData::from_fix(Some(&Fix::Gps(frame)), fix_time).to_string();
+        assert!(exif.contains("GPS.GPSLatitude=42500000/1000000"));
+        assert!(exif.contains("GPS.GPSAltitude="));
+
+        let location = Location::new(42.51, -2.74);
+        let exif = ExifData::from_fix(Some(&Fix::Gsm(location)), fix_time).to_string();
+        assert!(exif.contains("GPS.GPSLatitudeRef=N -x GPS.GPSLatitude=42510000/1000000"));
+        assert!(exif.contains("GPS.GPSLongitudeRef=W"));
+        assert!(!exif.contains("GPS.GPSAltitude"));
+        assert!(!exif.contains("GPS.GPSSatellites"));
+
+        let exif = ExifData::from_fix(None, fix_time).to_string();
+        assert!(!exif.contains("GPS.GPSLatitude"));
+    }
+
+    /// Tests the generation of the picture annotation with the latest GPS data.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn annotation_text() {
+        let picture: Picture = toml::from_str(
+            "height = 2464\n\
+             width = 3280\n\
+             quality = 95\n\
+             interval = 300\n\
+             first_timeout = 120\n\
+             annotation = \"{date} {time} UTC {altitude} m {latitude},{longitude} ({satellites})\"\n\
+             annotation_position = \"bottom\"",
+        )
+        .unwrap();
+        let fix_time = Utc.with_ymd_and_hms(2017, 3, 25, 14, 5, 9).unwrap();
+        let frame = Frame::new(fix_time, FixStatus::Active, 7, 42.5, -2.75, 1_500.34);
+
+        assert_eq!(
+            annotation(&picture, Some(&frame)),
+            Some((
+                String::from("2017-03-25 14:05:09 UTC 1500 m 42.50000,-2.75000 (7)"),
+                String::from("32,0xff,0x808000,0,0,2400"),
+            ))
+        );
+
+        // Without a valid fix there is nothing to annotate.
+        let void = Frame::new(fix_time, FixStatus::Void, 0, 0.0, 0.0, 0.0);
+        assert_eq!(annotation(&picture, Some(&void)), None);
+        assert_eq!(annotation(&picture, None), None);
+
+        let picture: Picture = toml::from_str(
+            "height = 2464\nwidth = 3280\nquality = 95\ninterval = 300\nfirst_timeout = 120",
+        )
+        .unwrap();
+        assert_eq!(annotation(&picture, Some(&frame)), None);
+    }
+
     /// Tests that the camera is not already recording.
     #[test]
     fn is_recording() {
@@ -0,0 +1,146 @@
+//! Panic recovery.
+//!
+//! An unexpected panic must not leave the probe in an unknown state: the `raspivid` process could
+//! keep recording until the SD card gets full, and the last state of the flight could be lost. The
+//! panic hook installed by [`install_panic_hook()`](fn.install_panic_hook.html) logs the panic
+//! with a backtrace and, before unwinding, saves the current state in the state file, stops the
+//! video recording, turns the peripherals off and synchronizes the disks.
+//!
+//! The cleanup only runs once, even if several threads panic, and it never waits for a device that
+//! is being used by another thread, since that thread could be the one panicking.
+//!
+//! A `SIGTERM` or `SIGINT` signal flags a shutdown request, once the handlers are installed with
+//! [`handle_termination_signals()`](fn.handle_termination_signals.html). Long waits of the logic
+//! check it, so that they stop the flight logic, and the launcher runs the same cleanup.
+
+use std::{
+    backtrace::Backtrace,
+    io, panic,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Only required for GPS, FONA or Raspicam
+#[cfg(any(feature = "gps", feature = "fona", feature = "raspicam"))]
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use anyhow::{Context, Error};
+use tracing::{error, info};
+
+// Only required for GPS, FONA or Raspicam
+#[cfg(any(feature = "gps", feature = "fona", feature = "raspicam"))]
+use tracing::warn;
+
+#[cfg(feature = "fona")]
+use crate::fona::FONA;
+#[cfg(feature = "gps")]
+use crate::gps::GPS;
+#[cfg(feature = "raspicam")]
+use crate::raspicam::CAMERA;
+use crate::{
+    error,
+    logic::{current_state, save_current_state},
+};
+
+/// Wether the cleanup has already been run.
+static CLEANED_UP: AtomicBool = AtomicBool::new(false);
+/// Wether a shutdown was requested with a termination signal.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the panic hook, that runs the cleanup before the default panic hook.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        error!(
+            state = current_state().as_str(),
+            "Unexpected panic: {}\n{}",
+            info,
+            Backtrace::force_capture()
+        );
+        let _ = cleanup();
+        default_hook(info);
+    }));
+}
+
+/// Flags a shutdown request each time the process receives a `SIGTERM` or `SIGINT` signal.
+///
+/// # Errors
+///
+/// Returns an error if the signal handlers can't be installed.
+pub fn handle_termination_signals() -> Result<(), Error> {
+    /// Flags the shutdown request, it's async-signal-safe.
+    extern "C" fn request_shutdown(_signal: libc::c_int) {
+        SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    let handler: extern "C" fn(libc::c_int) = request_shutdown;
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        // Safe because the handler only stores an atomic flag.
+        if unsafe { libc::signal(signal, handler as libc::sighandler_t) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error()).context(error::Logic::Signal);
+        }
+    }
+    Ok(())
+}
+
+/// Checks if a shutdown was requested with a termination signal.
+#[must_use]
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Saves the current state and turns the devices off.
+///
+/// It only runs the first time it's called, and returns wether it was run.
+pub fn cleanup() -> bool {
+    if CLEANED_UP.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    info!("Running the emergency cleanup\u{2026}");
+
+    if let Err(e) = save_current_state() {
+        error!(error = %e, "Error saving the current state.");
+    }
+
+    #[cfg(feature = "raspicam")]
+    if let Some(mut camera) = try_lock(&CAMERA, "CAMERA") {
+        if camera.is_recording() {
+            if let Err(e) = camera.stop_recording() {
+                error!(error = %e, "Error stopping the video recording.");
+            }
+        }
+    }
+    #[cfg(feature = "fona")]
+    if let Some(mut fona) = try_lock(&FONA, "FONA") {
+        if let Err(e) = fona.turn_off() {
+            error!(error = %e, "Error turning the GSM module off.");
+        }
+    }
+    #[cfg(feature = "gps")]
+    if let Some(gps) = try_lock(&GPS, "GPS") {
+        if let Err(e) = gps.turn_off() {
+            error!(error = %e, "Error turning the GPS off.");
+        }
+    }
+
+    // Safe because `sync()` is always successful.
+    unsafe {
+        libc::sync();
+    }
+    true
+}
+
+/// Locks the mutex of a shared device, if it's not locked by any other thread.
+#[cfg(any(feature = "gps", feature = "fona", feature = "raspicam"))]
+fn try_lock<'m, D>(mutex: &'m Mutex<D>, name: &str) -> Option<MutexGuard<'m, D>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => {
+            error!("The {} mutex was poisoned.", name);
+            Some(poisoned.into_inner())
+        }
+        Err(TryLockError::WouldBlock) => {
+            warn!("The {} mutex is locked, skipping it.", name);
+            None
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Serial port helpers.
+//!
+//! The GPS, the FONA module, the telemetry radio and the Iridium modem are all connected through
+//! serial ports. They are opened with [`open()`](fn.open.html), so that all of them get the read
+//! and write timeout configured for each device, instead of blocking forever or failing right
+//! away when the device gets wedged. Reads that time out fail with an
+//! [`io::ErrorKind::TimedOut`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html) error.
+
+use std::{path::Path, time::Duration};
+
+use tokio_serial::SerialPort;
+
+/// Default read and write timeout of serial ports.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Opens the serial port in the given path, with the given baud rate and read/write timeout.
+///
+/// # Errors
+///
+/// Returns an error if the serial port can't be opened.
+pub fn open<P>(
+    path: P,
+    baud_rate: u32,
+    timeout: Duration,
+) -> Result<Box<dyn SerialPort>, tokio_serial::Error>
+where
+    P: AsRef<Path>,
+{
+    tokio_serial::new(path.as_ref().to_string_lossy(), baud_rate)
+        .timeout(timeout)
+        .open()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CStr, os::raw::c_char, time::Duration};
+
+    use super::open;
+
+    /// Checks that the opened port gets the configured timeout, using a pseudo-terminal.
+    #[test]
+    fn applies_timeout() {
+        // Safe because the returned descriptor is checked before being used, and `ptsname_r()`
+        // writes a NUL-terminated string of at most the given length in the buffer.
+        let (master, name) = unsafe {
+            let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(master >= 0, "could not open a pseudo-terminal");
+            assert_eq!(libc::grantpt(master), 0);
+            assert_eq!(libc::unlockpt(master), 0);
+
+            let mut name: [c_char; 64] = [0; 64];
+            assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+            let name = CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned();
+            (master, name)
+        };
+
+        let port = open(&name, 9_600, Duration::from_millis(250)).unwrap();
+        assert_eq!(port.timeout(), Duration::from_millis(250));
+        drop(port);
+
+        // Safe because `master` is an open descriptor that is not used anymore.
+        let _ = unsafe { libc::close(master) };
+    }
+}
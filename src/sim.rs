@@ -0,0 +1,632 @@
+//! Flight simulation.
+//!
+//! The simulation mode runs the flight logic without any hardware. GPS frames are read from a
+//! recorded flight file, real or synthetic, and fed to the state machine as the simulated time
+//! goes by. SMSs and camera operations are only logged. This makes it possible to check launch,
+//! burst and landing detection end to end on a development computer.
+//!
+//! Flight files can be in CSV or GPX format, depending on their extension. CSV files have a header
+//! line, and one frame per line, with the following columns:
+//!
+//! ```text
+//! time,latitude,longitude,altitude,satellites
+//! 2017-03-25T12:00:00Z,42.5,-2.75,650.2,9
+//! ```
+//!
+//! The time is in RFC 3339 format, the latitude and longitude in *°* and the altitude in *m*.
+//! Empty lines and lines starting with `#` are ignored. GPX files are read from their `<trkpt>`
+//! elements, that must have an `<ele>` and a `<time>` element, and can have a `<sat>` element,
+//! as written by the [`gpx`](../gpx/index.html) module.
+//!
+//! The simulated time advances every time the logic waits. It can be run in real time, or sped up
+//! by a given factor, as configured in the `[simulation]` section of the configuration file.
+
+use std::{
+    cell::{Cell, RefCell},
+    fs,
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Error};
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+// Only required for telemetry
+#[cfg(feature = "telemetry")]
+use tracing::debug;
+
+// Only required for FONA or Raspicam
+#[cfg(any(feature = "fona", feature = "raspicam"))]
+use anyhow::bail;
+
+// Only required for FONA
+#[cfg(feature = "fona")]
+use crate::{
+    devices::GsmDevice,
+    fona::{Delivery, Location},
+};
+// Only required for Iridium
+#[cfg(feature = "iridium")]
+use crate::{devices::SatelliteDevice, iridium::Sbdix};
+// Only required for Raspicam
+#[cfg(feature = "raspicam")]
+use crate::devices::CameraDevice;
+// Only required for the barometric sensor
+#[cfg(feature = "baro")]
+use crate::baro::AltitudeFilter;
+// Only required for telemetry
+use crate::{
+    config::CONFIG,
+    devices::GpsDevice,
+    error,
+    geofence::Monitor,
+    gps::{self, FixStatus, Frame, FrameHistory, Gps},
+    logic::{self, Context, FlightStats, Landed, OpenStratos, StateMachine},
+    recovery,
+};
+#[cfg(feature = "telemetry")]
+use crate::{devices::TelemetryDevice, telemetry::Packet};
+
+/// Default recorded flight file.
+pub const DEFAULT_FLIGHT_FILE: &str = "sim/flight.csv";
+/// Default speed-up factor of the simulated time.
+pub const DEFAULT_SPEEDUP: u32 = 60;
+
+/// Recorded flight, as a list of GPS frames sorted by time.
+#[derive(Debug, Clone)]
+pub struct RecordedFlight {
+    /// Frames of the flight.
+    frames: Vec<Frame>,
+}
+
+impl RecordedFlight {
+    /// Loads a recorded flight from a CSV or GPX file, depending on its extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, or if the flight in it is invalid or empty.
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).context(error::Sim::Read {
+            path: path.to_owned(),
+        })?;
+
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gpx"))
+        {
+            Self::from_gpx(&contents)
+        } else {
+            Self::from_csv(&contents)
+        }
+    }
+
+    /// Parses a recorded flight in CSV format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a line is invalid, or if the flight is empty.
+    pub fn from_csv(csv: &str) -> Result<Self, Error> {
+        let mut frames = Vec::new();
+        let lines = csv
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            // Header.
+            .skip(1);
+        for (line_number, line) in lines {
+            let invalid = || error::Sim::InvalidCsv { line: line_number };
+            let fields: Vec<_> = line.split(',').map(str::trim).collect();
+            let [time, latitude, longitude, altitude, satellites] = fields[..] else {
+                return Err(invalid().into());
+            };
+
+            frames.push(
+                frame(time, latitude, longitude, altitude, Some(satellites))
+                    .with_context(invalid)?,
+            );
+        }
+
+        Self::new(frames)
+    }
+
+    /// Parses a recorded flight in GPX format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a track point is invalid, or if the flight is empty.
+    pub fn from_gpx(gpx: &str) -> Result<Self, Error> {
+        let mut frames = Vec::new();
+        for (i, point) in gpx.split("<trkpt").skip(1).enumerate() {
+            let invalid = || error::Sim::InvalidGpx { point: i + 1 };
+            let Some((point, _)) = point.split_once("</trkpt>") else {
+                return Err(invalid().into());
+            };
+            let (Some(latitude), Some(longitude), Some(altitude), Some(time)) = (
+                attribute(point, "lat"),
+                attribute(point, "lon"),
+                element(point, "ele"),
+                element(point, "time"),
+            ) else {
+                return Err(invalid().into());
+            };
+
+            frames.push(
+                frame(time, latitude, longitude, altitude, element(point, "sat"))
+                    .with_context(invalid)?,
+            );
+        }
+
+        Self::new(frames)
+    }
+
+    /// Creates a recorded flight from the given frames, sorting them by time.
+    fn new(mut frames: Vec<Frame>) -> Result<Self, Error> {
+        if frames.is_empty() {
+            return Err(error::Sim::Empty.into());
+        }
+        frames.sort_by_key(Frame::fix_time);
+
+        Ok(Self { frames })
+    }
+
+    /// Gets the frames of the flight.
+    #[must_use]
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Gets the time of the first frame of the flight.
+    #[must_use]
+    pub fn start(&self) -> DateTime<Utc> {
+        self.frames[0].fix_time()
+    }
+}
+
+/// Creates a valid frame from its textual fields.
+fn frame(
+    time: &str,
+    latitude: &str,
+    longitude: &str,
+    altitude: &str,
+    satellites: Option<&str>,
+) -> Result<Frame, Error> {
+    Ok(Frame::new(
+        DateTime::parse_from_rfc3339(time)?.with_timezone(&Utc),
+        FixStatus::Active,
+        satellites
+            .map(u8::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+        latitude.parse()?,
+        longitude.parse()?,
+        altitude.parse()?,
+    ))
+}
+
+/// Gets the value of the given attribute in the opening tag of a GPX element.
+fn attribute<'p>(point: &'p str, name: &str) -> Option<&'p str> {
+    let (tag, _) = point.split_once('>')?;
+    let (_, value) = tag.split_once(&format!("{name}=\""))?;
+    Some(value.split_once('"')?.0)
+}
+
+/// Gets the text of the given child element of a GPX element.
+fn element<'p>(point: &'p str, name: &str) -> Option<&'p str> {
+    let (_, value) = point.split_once(&format!("<{name}>"))?;
+    Some(value.split_once(&format!("</{name}>"))?.0.trim())
+}
+
+/// Simulated clock, shared between the simulated devices and the context.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    /// Simulated time elapsed since the start of the flight.
+    elapsed: Rc<Cell<Duration>>,
+}
+
+impl Clock {
+    /// Gets the simulated time elapsed since the start of the flight.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed.get()
+    }
+
+    /// Advances the simulated time.
+    pub fn advance(&self, time: Duration) {
+        self.elapsed.set(self.elapsed.get() + time);
+    }
+
+    /// Gets the simulated current time, for a flight starting at the given time.
+    #[must_use]
+    pub fn now(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        start + chrono::Duration::from_std(self.elapsed()).unwrap_or(chrono::TimeDelta::MAX)
+    }
+}
+
+/// Simulated GPS, that returns the latest frame of the recorded flight at the simulated time.
+#[derive(Debug)]
+pub struct SimGps {
+    /// Recorded flight.
+    flight: RecordedFlight,
+    /// Simulated clock.
+    clock: Clock,
+    /// GPS data updated with the received frames, that keeps the ascent rate and the history.
+    gps: RefCell<Gps>,
+    /// Number of received frames already added to the GPS data.
+    processed: Cell<usize>,
+}
+
+impl SimGps {
+    /// Creates a simulated GPS for the given flight and clock.
+    #[must_use]
+    pub fn new(flight: RecordedFlight, clock: Clock) -> Self {
+        Self {
+            flight,
+            clock,
+            gps: RefCell::new(Gps::new(
+                CONFIG.gps().rate_window(),
+                gps::history_capacity(),
+            )),
+            processed: Cell::new(0),
+        }
+    }
+
+    /// Adds the frames received since the last call to the GPS data, and returns it.
+    fn gps(&self) -> &RefCell<Gps> {
+        let received = self.received();
+        for &frame in &received[self.processed.get()..] {
+            self.gps.borrow_mut().update(frame);
+        }
+        self.processed.set(received.len());
+        &self.gps
+    }
+
+    /// Gets the frames of the recorded flight received up to the simulated time.
+    fn received(&self) -> &[Frame] {
+        let now = self.clock.now(self.flight.start());
+        let count = self
+            .flight
+            .frames
+            .partition_point(|frame| frame.fix_time() <= now);
+        &self.flight.frames[..count]
+    }
+}
+
+impl GpsDevice for SimGps {
+    fn initialize(&mut self) -> Result<(), Error> {
+        info!("[SIM] GPS initialized.");
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        info!("[SIM] GPS turned off.");
+        Ok(())
+    }
+
+    fn latest_data(&self) -> Option<Frame> {
+        let _ = self.gps();
+        self.received().last().copied()
+    }
+
+    fn launch_position(&self) -> Option<Frame> {
+        self.received()
+            .iter()
+            .find(|frame| frame.is_valid())
+            .copied()
+    }
+
+    fn smoothed_ascent_rate(&self) -> Option<f32> {
+        self.gps().borrow().smoothed_ascent_rate()
+    }
+
+    fn history(&self) -> FrameHistory {
+        self.gps().borrow().history().clone()
+    }
+}
+
+/// Simulated GSM module, that logs SMSs instead of sending them.
+#[cfg(feature = "fona")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingGsm;
+
+#[cfg(feature = "fona")]
+impl GsmDevice for LoggingGsm {
+    fn initialize(&mut self) -> Result<(), Error> {
+        info!("[SIM] GSM module initialized.");
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        info!("[SIM] GSM module turned off.");
+        Ok(())
+    }
+
+    fn send_sms(&mut self, message: &str) -> Result<(), Error> {
+        info!("[SIM] SMS sent:\n{}", message);
+        Ok(())
+    }
+
+    fn send_sms_confirmed(&mut self, message: &str, _timeout: Duration) -> Result<Delivery, Error> {
+        info!("[SIM] SMS sent and delivered:\n{}", message);
+        Ok(Delivery::Delivered)
+    }
+
+    fn location(&mut self) -> Result<Location, Error> {
+        bail!("GSM location is not available in simulations")
+    }
+
+    fn battery_percent(&mut self) -> Result<f32, Error> {
+        Ok(1_f32)
+    }
+
+    fn adc_voltage(&mut self) -> Result<f32, Error> {
+        Ok(CONFIG.battery().main_max())
+    }
+
+    fn has_connectivity(&mut self) -> Result<bool, Error> {
+        Ok(true)
+    }
+}
+
+/// Simulated satellite modem, that logs SBD messages instead of sending them.
+#[cfg(feature = "iridium")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingSatellite {
+    /// Sequence number of the last message.
+    momsn: u16,
+}
+
+#[cfg(feature = "iridium")]
+impl SatelliteDevice for LoggingSatellite {
+    fn send_message(&mut self, message: &[u8]) -> Result<Sbdix, Error> {
+        self.momsn = self.momsn.wrapping_add(1);
+        info!("[SIM] SBD message sent: {} bytes.", message.len());
+        Ok(format!("+SBDIX: 0, {}, 0, 0, 0, 0", self.momsn).parse()?)
+    }
+}
+
+/// Simulated telemetry link, that logs the packets instead of sending them.
+#[cfg(feature = "telemetry")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingTelemetry;
+
+#[cfg(feature = "telemetry")]
+impl TelemetryDevice for LoggingTelemetry {
+    fn offer(&mut self, packet: Packet) -> Result<(), Error> {
+        debug!("[SIM] Telemetry packet offered: {}", packet);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        debug!("[SIM] Telemetry flushed.");
+        Ok(())
+    }
+
+    fn send(&mut self, packet: &Packet) -> Result<(), Error> {
+        info!("[SIM] Telemetry packet sent: {}", packet);
+        Ok(())
+    }
+}
+
+/// Simulated camera, that logs recordings and pictures instead of taking them.
+#[cfg(feature = "raspicam")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingCamera {
+    /// Wether the camera is recording.
+    recording: bool,
+}
+
+#[cfg(feature = "raspicam")]
+impl CameraDevice for LoggingCamera {
+    fn record(&mut self, time: Option<Duration>, file_name: Option<&Path>) -> Result<(), Error> {
+        if self.recording {
+            bail!("the camera is already recording");
+        }
+        info!(
+            "[SIM] Recording video for {} to {}.",
+            time.map_or_else(|| "ever".to_owned(), |t| format!("{t:?}")),
+            file_name.map_or_else(
+                || "the default file".to_owned(),
+                |f| f.display().to_string()
+            )
+        );
+        self.recording = time.is_none();
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<(), Error> {
+        info!("[SIM] Video recording stopped.");
+        self.recording = false;
+        Ok(())
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    fn take_picture(&mut self, file_name: Option<&Path>) -> Result<(), Error> {
+        if self.recording {
+            bail!("the camera is recording");
+        }
+        info!(
+            "[SIM] Picture taken to {}.",
+            file_name.map_or_else(
+                || "the default file".to_owned(),
+                |f| f.display().to_string()
+            )
+        );
+        Ok(())
+    }
+
+    fn detect(&self) -> Result<bool, Error> {
+        info!("[SIM] Camera detected.");
+        Ok(true)
+    }
+}
+
+/// Creates a simulation context for the given flight.
+///
+/// Every wait of the logic advances the simulated time, and waits for that time divided by the
+/// speed-up factor. A factor of 0 does not wait at all.
+#[must_use]
+pub fn context(flight: RecordedFlight, speedup: u32) -> Context {
+    let clock = Clock::default();
+    let sleep_clock = clock.clone();
+    #[cfg(feature = "fona")]
+    let (start, now_clock) = (flight.start(), clock.clone());
+
+    Context {
+        gps: Box::new(SimGps::new(flight, clock)),
+        #[cfg(feature = "fona")]
+        gsm: Box::new(LoggingGsm),
+        #[cfg(feature = "raspicam")]
+        camera: Box::new(LoggingCamera::default()),
+        #[cfg(feature = "iridium")]
+        satellite: Box::new(LoggingSatellite::default()),
+        #[cfg(feature = "telemetry")]
+        telemetry: Box::new(LoggingTelemetry),
+        sleep: Box::new(move |time| {
+            sleep_clock.advance(time);
+            if speedup > 0 {
+                thread::sleep(time / speedup);
+            }
+        }),
+        shutdown_requested: Box::new(recovery::shutdown_requested),
+        #[cfg(feature = "fona")]
+        clock: Box::new(move || now_clock.now(start)),
+        armed: Box::new(|| true),
+        save_stats: Box::new(FlightStats::save),
+        stats: FlightStats::default(),
+        geofence: Monitor::new(CONFIG.geofence()),
+        track: None,
+        gpx: None,
+        #[cfg(feature = "sqlite")]
+        db: None,
+        #[cfg(feature = "raspicam")]
+        pictures: None,
+        #[cfg(feature = "baro")]
+        barometer: None,
+        #[cfg(feature = "baro")]
+        altitude: AltitudeFilter::default(),
+    }
+}
+
+/// Loads the flight file and speed-up factor from the configuration, and creates its context.
+///
+/// # Errors
+///
+/// Returns an error if the flight file can't be loaded.
+pub fn configured_context() -> Result<Context, Error> {
+    let simulation = CONFIG.simulation();
+    info!(
+        "Simulating flight from {} with a {}x speed-up\u{2026}",
+        simulation.flight_file().display(),
+        simulation.speedup()
+    );
+
+    Ok(context(
+        RecordedFlight::from_file(simulation.flight_file())?,
+        simulation.speedup(),
+    ))
+}
+
+/// Runs the flight logic from the GPS fix acquisition until the landing, in the given context.
+///
+/// # Errors
+///
+/// Returns an error if the flight logic fails before the landing.
+pub fn fly(context: &mut Context) -> Result<OpenStratos<Landed>, Error> {
+    logic::simulation()
+        .execute(context)?
+        .execute(context)?
+        .execute(context)?
+        .execute(context)?
+        .execute(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{context, fly, RecordedFlight, DEFAULT_FLIGHT_FILE};
+    use crate::logic::{GetState, State};
+
+    /// Checks the parsing of CSV flight files.
+    #[test]
+    fn csv() {
+        let flight = RecordedFlight::from_csv(
+            "# Test flight\n\
+             time,latitude,longitude,altitude,satellites\n\
+             2017-03-25T12:00:01Z, 42.5, -2.75, 651.5, 8\n\
+             \n\
+             2017-03-25T12:00:00Z,42.5,-2.75,650,7\n",
+        )
+        .unwrap();
+
+        assert_eq!(flight.frames().len(), 2);
+        assert_eq!(
+            flight.start(),
+            Utc.with_ymd_and_hms(2017, 3, 25, 12, 0, 0).unwrap()
+        );
+        assert_eq!(flight.frames()[0].satellites(), 7);
+        assert!((flight.frames()[1].altitude() - 651.5).abs() < f32::EPSILON);
+
+        assert!(RecordedFlight::from_csv("time,latitude,longitude,altitude,satellites\n").is_err());
+        let error = RecordedFlight::from_csv(
+            "time,latitude,longitude,altitude,satellites\n2017-03-25T12:00:00Z,42.5,-2.75\n",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "invalid flight data in line 2");
+    }
+
+    /// Checks the parsing of GPX flight files.
+    #[test]
+    fn gpx() {
+        let flight = RecordedFlight::from_gpx(
+            "<gpx><trk><trkseg>\n\
+             <trkpt lat=\"42.500000\" lon=\"-2.750000\">\n<ele>650.0</ele>\n\
+             <time>2017-03-25T12:00:00Z</time>\n<sat>7</sat>\n</trkpt>\n\
+             <trkpt lat=\"42.500100\" lon=\"-2.749900\">\n<ele>655.0</ele>\n\
+             <time>2017-03-25T12:00:01Z</time>\n</trkpt>\n\
+             </trkseg></trk></gpx>\n",
+        )
+        .unwrap();
+
+        assert_eq!(flight.frames().len(), 2);
+        assert_eq!(flight.frames()[0].satellites(), 7);
+        assert_eq!(flight.frames()[1].satellites(), 0);
+        assert!((flight.frames()[1].latitude() - 42.5001).abs() < 1e-6);
+
+        let error = RecordedFlight::from_gpx("<trkpt lat=\"42.5\">\n<ele>650.0</ele>\n</trkpt>")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "invalid GPX track point number 1");
+    }
+
+    /// Runs the simulation through the sample flight, and checks that it reaches the landing.
+    #[test]
+    fn sample_flight() {
+        let flight = RecordedFlight::from_file(DEFAULT_FLIGHT_FILE).unwrap();
+        let mut context = context(flight, 0);
+
+        let landed = fly(&mut context).unwrap();
+        assert_eq!(landed.get_state(), State::Landed);
+
+        let stats = context.stats;
+        let launch_altitude = stats.launch_altitude().unwrap();
+        assert!((launch_altitude - 650_f32).abs() < 10_f32);
+        assert!(stats.max_altitude().unwrap() > 29_000_f32);
+        let launch_time = stats.launch_time().unwrap();
+        let burst_time = stats.burst_time().unwrap();
+        let landing_time = stats.landing_time().unwrap();
+        assert!(launch_time < burst_time);
+        assert!(burst_time < landing_time);
+    }
+}
@@ -1,3 +1,1082 @@
 //! Transparent serial telemetry module.
 //!
-//! *In development…*
+//! Telemetry is sent as [`Packet`](struct.Packet.html)s, a compact, versioned binary format that
+//! a ground decoder can parse without depending on ad-hoc strings. All multi-byte fields are
+//! encoded in big-endian (network) byte order, with the following layout:
+//!
+//! | Offset | Size | Field                                           |
+//! |--------|------|-------------------------------------------------|
+//! | 0      | 1    | Protocol version                                |
+//! | 1      | 2    | Sequence number                                 |
+//! | 3      | 8    | Timestamp, in milliseconds since the UNIX epoch |
+//! | 11     | 1    | State code                                      |
+//! | 12     | 1    | Flags (bit 0: position, bit 1: main battery, bit 2: FONA battery, bit 3: launch) |
+//! | 13     | 4    | Latitude, in degrees (`f32`)                    |
+//! | 17     | 4    | Longitude, in degrees (`f32`)                   |
+//! | 21     | 4    | Altitude, in meters (`f32`)                     |
+//! | 25     | 1    | Number of satellites                            |
+//! | 26     | 4    | Speed, in m/s (`f32`)                           |
+//! | 30     | 4    | Course, in degrees (`f32`)                      |
+//! | 34     | 4    | Main battery charge (`f32`)                     |
+//! | 38     | 4    | FONA battery charge (`f32`)                     |
+//! | 42     | 4    | Distance from the launch position, in m (`f32`) |
+//! | 46     | 4    | Bearing from the launch position, in degrees (`f32`) |
+//! | 50     | 2    | CRC16-CCITT of the previous 50 bytes            |
+//!
+//! Fields whose flag is not set are sent as zeroes and must be ignored by the decoder. Records
+//! whose CRC does not match their payload are corrupt and are rejected when decoding. The sequence
+//! number is incremented with every transmitted packet, wrapping around after `u16::MAX`, so that
+//! the ground can detect lost and reordered packets.
+//!
+//! Packets are written to the serial as they are in transparent mode, or wrapped in XBee API
+//! frames in API mode (check the [`xbee`](xbee/index.html) module). They are also sent over LoRa
+//! with the `lora` feature (check the [`lora`](../lora/index.html) module).
+
+#![allow(missing_debug_implementations)]
+
+pub mod command;
+pub mod throttle;
+pub mod xbee;
+
+use std::{
+    convert::TryInto,
+    fmt,
+    io::{BufReader, Write},
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Error};
+use chrono::{DateTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use tokio_serial::SerialPort;
+use tracing::{debug, error, info, warn};
+
+use self::{
+    command::{read_commands, Command, CommandHandler, CommandParser},
+    throttle::Throttle,
+};
+#[cfg(feature = "fona")]
+use crate::fona::FONA;
+#[cfg(feature = "gps")]
+use crate::gps::{Frame, GPS};
+#[cfg(not(feature = "no_power_off"))]
+use crate::logic::power_off;
+#[cfg(all(feature = "gps", feature = "fona"))]
+use crate::position::{self, Fix};
+#[cfg(feature = "raspicam")]
+use crate::raspicam::CAMERA;
+use crate::{
+    config::{TelemetryMode, CONFIG},
+    crc::crc16_ccitt,
+    error,
+    logic::{current_state, State},
+    recovery, serial,
+};
+
+/// Current version of the telemetry protocol.
+pub const PROTOCOL_VERSION: u8 = 4;
+/// Length of an encoded telemetry packet, in bytes, including the CRC.
+pub const PACKET_LENGTH: usize = 52;
+/// Length of the payload of an encoded telemetry packet, in bytes, without the CRC.
+const PAYLOAD_LENGTH: usize = PACKET_LENGTH - 2;
+
+/// Flag set if the packet contains position information.
+const FLAG_POSITION: u8 = 0b0000_0001;
+/// Flag set if the packet contains the main battery charge.
+const FLAG_MAIN_BATTERY: u8 = 0b0000_0010;
+/// Flag set if the packet contains the FONA battery charge.
+const FLAG_FONA_BATTERY: u8 = 0b0000_0100;
+/// Flag set if the packet contains the distance and bearing from the launch position.
+const FLAG_LAUNCH: u8 = 0b0000_1000;
+
+/// Sequence number of the transmitted telemetry packets.
+pub(crate) static SEQUENCE: SequenceCounter = SequenceCounter::new(0);
+
+/// The telemetry control structure.
+pub static TELEMETRY: Lazy<Mutex<Telemetry>> = Lazy::new(|| {
+    Mutex::new(Telemetry {
+        serial: None,
+        frame_id: 0,
+        throttle: Throttle::new(Duration::ZERO),
+    })
+});
+
+/// Telemetry control structure.
+pub struct Telemetry {
+    /// Serial connection to the telemetry module.
+    serial: Option<Box<dyn SerialPort>>,
+    /// Identifier of the last API frame sent.
+    frame_id: u8,
+    /// Rate limiter of the sent packets.
+    throttle: Throttle<Packet>,
+}
+
+impl fmt::Debug for Telemetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Telemetry {{ serial: {:?}, frame_id: {} }}",
+            if let Some(ref serial) = &self.serial {
+                serial.name()
+            } else {
+                None
+            },
+            self.frame_id
+        )
+    }
+}
+
+impl Telemetry {
+    /// Initializes the telemetry serial.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the serial can't be opened.
+    pub fn initialize(&mut self) -> Result<(), Error> {
+        info!("Starting telemetry serial connection\u{2026}");
+        let serial = serial::open(
+            CONFIG.telemetry().uart(),
+            CONFIG.telemetry().baud_rate(),
+            CONFIG.telemetry().timeout(),
+        )
+        .context(error::Telemetry::Init)?;
+        self.serial = Some(serial);
+        self.throttle.set_interval(CONFIG.telemetry().interval());
+        info!("Telemetry serial connection started.");
+
+        Ok(())
+    }
+
+    /// Starts reading uplink commands from the telemetry serial in a background thread.
+    ///
+    /// Received commands are dispatched to the probe subsystems by a
+    /// [`Dispatcher`](struct.Dispatcher.html). Nothing is done if no command key is configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no telemetry serial, or if the reader thread can't be started.
+    pub fn start_command_reader(&self) -> Result<(), Error> {
+        let Some(key) = CONFIG.telemetry().command_key() else {
+            info!("No command key configured, uplink commands are disabled.");
+            return Ok(());
+        };
+
+        let serial = if let Some(ref serial) = self.serial {
+            serial.try_clone().context(error::Telemetry::Init)?
+        } else {
+            error!("No serial when trying to start the command reader.");
+            return Err(error::Telemetry::NoSerial.into());
+        };
+
+        let mut parser = CommandParser::new(key);
+        let _ = thread::Builder::new()
+            .name("telemetry commands".to_owned())
+            .spawn(move || {
+                if let Err(e) = read_commands(BufReader::new(serial), &mut parser, &mut Dispatcher)
+                {
+                    error!("Error reading uplink commands: {}", e);
+                }
+            })
+            .context(error::Telemetry::Init)?;
+        info!("Listening for uplink commands.");
+
+        Ok(())
+    }
+
+    /// Offers a new telemetry packet, to be sent respecting the configured interval.
+    ///
+    /// If a packet was sent less than an interval ago, this packet will wait until the interval
+    /// elapses, and will be dropped if a newer packet is offered in the meantime. Waiting packets
+    /// are sent in later calls to this method or to [`flush()`](#method.flush).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet, or a waiting one, is due and can't be sent.
+    pub fn offer(&mut self, packet: Packet) -> Result<(), Error> {
+        if let Some(packet) = self.throttle.offer(packet, Instant::now()) {
+            self.send(&packet)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sends the waiting telemetry packet right away, if any.
+    ///
+    /// It's used when no more packets will be offered, at the landing and at the shutdown, so
+    /// that the latest position is not lost.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is a waiting packet and it can't be sent.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if let Some(packet) = self.throttle.flush(Instant::now()) {
+            self.send(&packet)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sends a telemetry packet right away, without throttling.
+    ///
+    /// The packet will be sent with the next sequence number, regardless of the one it had. It's
+    /// also sent over LoRa and published to MQTT if they are configured, even if there is no
+    /// serial.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no telemetry serial, or if the packet can't be written to it.
+    pub fn send(&mut self, packet: &Packet) -> Result<(), Error> {
+        let packet = &packet.with_sequence(SEQUENCE.next());
+
+        #[cfg(feature = "lora")]
+        crate::lora::transmit(packet);
+        #[cfg(feature = "mqtt")]
+        crate::mqtt::publish(packet);
+
+        let Some(ref mut serial) = self.serial else {
+            error!("No serial when trying to send telemetry packet: {}", packet);
+            return Err(error::Telemetry::NoSerial.into());
+        };
+
+        let bytes = match CONFIG.telemetry().mode() {
+            TelemetryMode::Transparent => packet.to_bytes(),
+            TelemetryMode::Api => {
+                // Frame ID 0 disables the transmit status response, so we skip it.
+                self.frame_id = self.frame_id.checked_add(1).unwrap_or(1);
+                xbee::transmit_request(
+                    self.frame_id,
+                    CONFIG.telemetry().destination(),
+                    &packet.to_bytes(),
+                    CONFIG.telemetry().escaped(),
+                )
+            }
+        };
+
+        serial.write_all(&bytes).context(error::Telemetry::Send)?;
+        debug!("Sent telemetry packet: {}", packet);
+        Ok(())
+    }
+}
+
+/// Wrapping counter of telemetry packet sequence numbers.
+///
+/// It can be safely shared between threads.
+#[derive(Debug)]
+pub struct SequenceCounter {
+    /// Next sequence number.
+    next: AtomicU16,
+}
+
+impl SequenceCounter {
+    /// Creates a new counter, starting at the given sequence number.
+    #[must_use]
+    pub const fn new(start: u16) -> Self {
+        Self {
+            next: AtomicU16::new(start),
+        }
+    }
+
+    /// Gets the next sequence number, wrapping around after `u16::MAX`.
+    pub fn next(&self) -> u16 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Command handler that dispatches commands to the relevant subsystem of the probe.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dispatcher;
+
+impl CommandHandler for Dispatcher {
+    fn handle(&mut self, command: Command) -> Result<(), Error> {
+        match command {
+            Command::TakePicture => {
+                #[cfg(feature = "raspicam")]
+                {
+                    let mut camera = match CAMERA.lock() {
+                        Ok(guard) => guard,
+                        Err(poisoned) => {
+                            error!("The CAMERA mutex was poisoned.");
+                            poisoned.into_inner()
+                        }
+                    };
+                    camera.take_picture::<&str, _>(None)?;
+                }
+                #[cfg(not(feature = "raspicam"))]
+                warn!("Picture requested, but the camera is not enabled.");
+            }
+            Command::SendSms(message) => {
+                #[cfg(feature = "fona")]
+                {
+                    let mut fona = match FONA.lock() {
+                        Ok(guard) => guard,
+                        Err(poisoned) => {
+                            error!("The FONA mutex was poisoned.");
+                            poisoned.into_inner()
+                        }
+                    };
+                    fona.send_sms(message)?;
+                }
+                #[cfg(not(feature = "fona"))]
+                warn!(
+                    "SMS `{}` requested, but the FONA module is not enabled.",
+                    message
+                );
+            }
+            Command::Status => {
+                #[cfg(feature = "gps")]
+                let (position, launch) = {
+                    // The position is located before locking the GPS, since it locks it.
+                    #[cfg(feature = "fona")]
+                    let position =
+                        position::locate(&&*GPS, &mut &*FONA, Utc::now()).map(Position::from);
+                    let gps = match GPS.lock() {
+                        Ok(guard) => guard,
+                        Err(poisoned) => {
+                            error!("The GPS mutex was poisoned.");
+                            poisoned.into_inner()
+                        }
+                    };
+                    #[cfg(not(feature = "fona"))]
+                    let position = gps.latest_data().map(Position::from);
+                    (
+                        position,
+                        gps.distance_from_launch().zip(gps.bearing_from_launch()),
+                    )
+                };
+                #[cfg(not(feature = "gps"))]
+                let (position, launch) = (None, None);
+
+                #[cfg(feature = "fona")]
+                let fona_battery = match FONA.lock() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => {
+                        error!("The FONA mutex was poisoned.");
+                        poisoned.into_inner()
+                    }
+                }
+                .battery_percent()
+                .ok();
+                #[cfg(not(feature = "fona"))]
+                let fona_battery = None;
+
+                let mut packet =
+                    Packet::new(Utc::now(), current_state(), position, None, fona_battery);
+                if let Some((distance, bearing)) = launch {
+                    packet = packet.with_launch(distance, bearing);
+                }
+                match TELEMETRY.lock() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => {
+                        error!("The TELEMETRY mutex was poisoned.");
+                        poisoned.into_inner()
+                    }
+                }
+                .send(&packet)?;
+            }
+            Command::ShutDown => {
+                warn!("Shut down requested from the ground.");
+                // The video recording must be stopped and the state saved before powering off.
+                let _ = recovery::cleanup();
+                #[cfg(not(feature = "no_power_off"))]
+                power_off()?;
+                #[cfg(feature = "no_power_off")]
+                std::process::exit(0);
+            }
+            Command::Arm => {
+                #[cfg(feature = "gps")]
+                crate::arm::arm();
+                #[cfg(not(feature = "gps"))]
+                warn!("Arming requested, but the GPS is not enabled.");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Telemetry packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Packet {
+    /// Protocol version of the packet.
+    version: u8,
+    /// Sequence number of the packet.
+    sequence: u16,
+    /// Time of the packet generation.
+    timestamp: DateTime<Utc>,
+    /// Current state of the probe.
+    state: State,
+    /// Position of the probe, if known.
+    position: Option<Position>,
+    /// Charge of the main battery, from 0 to 1, if known.
+    main_battery: Option<f32>,
+    /// Charge of the FONA battery, from 0 to 1, if known.
+    fona_battery: Option<f32>,
+    /// Distance, in *m*, and bearing, in *°*, from the launch position, if known.
+    launch: Option<(f32, f32)>,
+}
+
+impl Packet {
+    /// Creates a new telemetry packet, using the current protocol version.
+    ///
+    /// The sequence number of the packet will be 0 until it's sent.
+    #[must_use]
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        state: State,
+        position: Option<Position>,
+        main_battery: Option<f32>,
+        fona_battery: Option<f32>,
+    ) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            sequence: 0,
+            timestamp,
+            state,
+            position,
+            main_battery,
+            fona_battery,
+            launch: None,
+        }
+    }
+
+    /// Gets the protocol version of the packet.
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Gets the sequence number of the packet.
+    #[must_use]
+    pub fn sequence(&self) -> u16 {
+        self.sequence
+    }
+
+    /// Returns the same packet with the given sequence number.
+    #[must_use]
+    pub fn with_sequence(self, sequence: u16) -> Self {
+        Self { sequence, ..self }
+    }
+
+    /// Returns the same packet with the given distance, in *m*, and bearing, in *°*, from the
+    /// launch position.
+    #[must_use]
+    pub fn with_launch(self, distance: f32, bearing: f32) -> Self {
+        Self {
+            launch: Some((distance, bearing)),
+            ..self
+        }
+    }
+
+    /// Gets the time of the packet generation.
+    #[must_use]
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Gets the state of the probe.
+    #[must_use]
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Gets the position of the probe, if known.
+    #[must_use]
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Gets the charge of the main battery, from 0 to 1, if known.
+    #[must_use]
+    pub fn main_battery(&self) -> Option<f32> {
+        self.main_battery
+    }
+
+    /// Gets the charge of the FONA battery, from 0 to 1, if known.
+    #[must_use]
+    pub fn fona_battery(&self) -> Option<f32> {
+        self.fona_battery
+    }
+
+    /// Gets the distance from the launch position, in *m*, if known.
+    #[must_use]
+    pub fn launch_distance(&self) -> Option<f32> {
+        self.launch.map(|(distance, _)| distance)
+    }
+
+    /// Gets the bearing from the launch position, in *°* (degrees), if known.
+    #[must_use]
+    pub fn launch_bearing(&self) -> Option<f32> {
+        self.launch.map(|(_, bearing)| bearing)
+    }
+
+    /// Encodes the packet in its compact binary representation.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PACKET_LENGTH);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.sequence.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.timestamp_millis().to_be_bytes());
+        bytes.push(self.state.as_u8());
+
+        let mut flags = 0;
+        if self.position.is_some() {
+            flags |= FLAG_POSITION;
+        }
+        if self.main_battery.is_some() {
+            flags |= FLAG_MAIN_BATTERY;
+        }
+        if self.fona_battery.is_some() {
+            flags |= FLAG_FONA_BATTERY;
+        }
+        if self.launch.is_some() {
+            flags |= FLAG_LAUNCH;
+        }
+        bytes.push(flags);
+
+        let position = self.position.unwrap_or_default();
+        bytes.extend_from_slice(&position.latitude.to_be_bytes());
+        bytes.extend_from_slice(&position.longitude.to_be_bytes());
+        bytes.extend_from_slice(&position.altitude.to_be_bytes());
+        bytes.push(position.satellites);
+        bytes.extend_from_slice(&position.speed.to_be_bytes());
+        bytes.extend_from_slice(&position.course.to_be_bytes());
+
+        bytes.extend_from_slice(&self.main_battery.unwrap_or_default().to_be_bytes());
+        bytes.extend_from_slice(&self.fona_battery.unwrap_or_default().to_be_bytes());
+
+        let (distance, bearing) = self.launch.unwrap_or_default();
+        bytes.extend_from_slice(&distance.to_be_bytes());
+        bytes.extend_from_slice(&bearing.to_be_bytes());
+
+        let crc = crc16_ccitt(&bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+
+        bytes
+    }
+
+    /// Decodes a packet from its compact binary representation.
+    ///
+    /// The length of the record and its CRC are checked before decoding any field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length or the CRC of the record are wrong, if its protocol version
+    /// is not supported, or if a field is invalid.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != PACKET_LENGTH {
+            return Err(error::Telemetry::InvalidLength {
+                expected: PACKET_LENGTH,
+                found: bytes.len(),
+            }
+            .into());
+        }
+        if !verify_crc(bytes) {
+            return Err(error::Telemetry::InvalidCrc {
+                expected: crc16_ccitt(&bytes[..PAYLOAD_LENGTH]),
+                found: u16::from_be_bytes(read_array(bytes, PAYLOAD_LENGTH)),
+            }
+            .into());
+        }
+        if bytes[0] != PROTOCOL_VERSION {
+            return Err(error::Telemetry::UnsupportedVersion { version: bytes[0] }.into());
+        }
+
+        let sequence = u16::from_be_bytes(read_array(bytes, 1));
+        let millis = i64::from_be_bytes(read_array(bytes, 3));
+        let timestamp = Utc
+            .timestamp_millis_opt(millis)
+            .single()
+            .ok_or(error::Telemetry::InvalidTimestamp { timestamp: millis })?;
+        let state =
+            State::from_u8(bytes[11]).ok_or(error::Telemetry::InvalidState { code: bytes[11] })?;
+        let flags = bytes[12];
+
+        let position = if flags & FLAG_POSITION == 0 {
+            None
+        } else {
+            Some(Position {
+                latitude: f32::from_be_bytes(read_array(bytes, 13)),
+                longitude: f32::from_be_bytes(read_array(bytes, 17)),
+                altitude: f32::from_be_bytes(read_array(bytes, 21)),
+                satellites: bytes[25],
+                speed: f32::from_be_bytes(read_array(bytes, 26)),
+                course: f32::from_be_bytes(read_array(bytes, 30)),
+            })
+        };
+        let main_battery = if flags & FLAG_MAIN_BATTERY == 0 {
+            None
+        } else {
+            Some(f32::from_be_bytes(read_array(bytes, 34)))
+        };
+        let fona_battery = if flags & FLAG_FONA_BATTERY == 0 {
+            None
+        } else {
+            Some(f32::from_be_bytes(read_array(bytes, 38)))
+        };
+        let launch = if flags & FLAG_LAUNCH == 0 {
+            None
+        } else {
+            Some((
+                f32::from_be_bytes(read_array(bytes, 42)),
+                f32::from_be_bytes(read_array(bytes, 46)),
+            ))
+        };
+
+        Ok(Self {
+            version: PROTOCOL_VERSION,
+            sequence,
+            timestamp,
+            state,
+            position,
+            main_battery,
+            fona_battery,
+            launch,
+        })
+    }
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[v{} #{}] {} {}",
+            self.version,
+            self.sequence,
+            self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f UTC"),
+            self.state.as_str()
+        )?;
+        if let Some(position) = self.position {
+            write!(f, " - {position}")?;
+        } else {
+            write!(f, " - no position")?;
+        }
+        if let Some(main_battery) = self.main_battery {
+            write!(f, " - Main bat: {:.0}%", main_battery * 100_f32)?;
+        }
+        if let Some(fona_battery) = self.fona_battery {
+            write!(f, " - GSM bat: {:.0}%", fona_battery * 100_f32)?;
+        }
+        if let Some((distance, bearing)) = self.launch {
+            write!(
+                f,
+                " - Launch: {:.1} km {:.0}\u{b0}",
+                distance / 1_000_f32,
+                bearing
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Position information in a telemetry packet.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Position {
+    /// Latitude of the probe, in *°* (degrees).
+    latitude: f32,
+    /// Longitude of the probe, in *°* (degrees).
+    longitude: f32,
+    /// Altitude of the probe from sea level, in *m*.
+    altitude: f32,
+    /// Number of GPS satellites connected.
+    satellites: u8,
+    /// Speed of the velocity vector, in *m/s*.
+    speed: f32,
+    /// Course of the velocity vector, in *°* (degrees).
+    course: f32,
+}
+
+impl Position {
+    /// Creates new position information.
+    #[must_use]
+    pub fn new(
+        latitude: f32,
+        longitude: f32,
+        altitude: f32,
+        satellites: u8,
+        speed: f32,
+        course: f32,
+    ) -> Self {
+        Self {
+            latitude,
+            longitude,
+            altitude,
+            satellites,
+            speed,
+            course,
+        }
+    }
+
+    /// Gets the latitude of the probe, in *°* (degrees).
+    #[must_use]
+    pub fn latitude(&self) -> f32 {
+        self.latitude
+    }
+
+    /// Gets the longitude of the probe, in *°* (degrees).
+    #[must_use]
+    pub fn longitude(&self) -> f32 {
+        self.longitude
+    }
+
+    /// Gets the altitude of the probe from sea level, in *m*.
+    #[must_use]
+    pub fn altitude(&self) -> f32 {
+        self.altitude
+    }
+
+    /// Gets the number of GPS satellites connected.
+    #[must_use]
+    pub fn satellites(&self) -> u8 {
+        self.satellites
+    }
+
+    /// Gets the speed of the velocity vector, in *m/s*.
+    #[must_use]
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Gets the course of the velocity vector, in *°* (degrees).
+    #[must_use]
+    pub fn course(&self) -> f32 {
+        self.course
+    }
+}
+
+#[cfg(feature = "gps")]
+impl From<Frame> for Position {
+    fn from(frame: Frame) -> Self {
+        Self {
+            latitude: frame.latitude(),
+            longitude: frame.longitude(),
+            altitude: frame.altitude(),
+            satellites: frame.satellites(),
+            speed: frame.speed(),
+            course: frame.course(),
+        }
+    }
+}
+
+/// GSM locations have no altitude, satellites nor velocity, so they are set to 0.
+#[cfg(all(feature = "gps", feature = "fona"))]
+impl From<Fix> for Position {
+    fn from(fix: Fix) -> Self {
+        match fix {
+            Fix::Gps(frame) => Self::from(frame),
+            Fix::Gsm(location) => Self {
+                latitude: location.latitude(),
+                longitude: location.longitude(),
+                altitude: 0_f32,
+                satellites: 0,
+                speed: 0_f32,
+                course: 0_f32,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Lat: {:.6} - Lon: {:.6} - Alt: {:.0} m - Sat: {} - Speed: {:.1} m/s - Course: {:.1}\u{b0}",
+            self.latitude, self.longitude, self.altitude, self.satellites, self.speed, self.course
+        )
+    }
+}
+
+/// Verifies that the CRC16-CCITT in the last two bytes of a record matches the rest of it.
+#[must_use]
+pub fn verify_crc(record: &[u8]) -> bool {
+    if record.len() < 2 {
+        return false;
+    }
+    let (payload, crc) = record.split_at(record.len() - 2);
+    crc16_ccitt(payload) == u16::from_be_bytes([crc[0], crc[1]])
+}
+
+/// Reads a fixed-size array from the given byte slice, starting at the given offset.
+///
+/// **Panics** if the slice is not long enough, so the length must be checked beforehand.
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    bytes[offset..offset + N]
+        .try_into()
+        .expect("the packet length was not checked")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{
+        verify_crc, Packet, Position, SequenceCounter, PACKET_LENGTH, PAYLOAD_LENGTH,
+        PROTOCOL_VERSION,
+    };
+    use crate::{crc::crc16_ccitt, error, logic::State};
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    use crate::{fona::Location, position::Fix};
+
+    /// Simple xorshift pseudo-random number generator, for property-style tests.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        fn next_u8(&mut self) -> u8 {
+            self.next_u64() as u8
+        }
+
+        fn next_bool(&mut self) -> bool {
+            self.next_u64() & 1 == 1
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        fn next_f32(&mut self, min: f32, max: f32) -> f32 {
+            min + (self.next_u64() % 1_000_000) as f32 / 1_000_000_f32 * (max - min)
+        }
+
+        fn next_option_f32(&mut self, min: f32, max: f32) -> Option<f32> {
+            if self.next_bool() {
+                Some(self.next_f32(min, max))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// All the states available in the current build.
+    const STATES: &[State] = &[
+        State::Init,
+        #[cfg(feature = "gps")]
+        State::AcquiringFix,
+        #[cfg(feature = "gps")]
+        State::FixAcquired,
+        #[cfg(feature = "gps")]
+        State::WaitingLaunch,
+        #[cfg(feature = "gps")]
+        State::GoingUp,
+        #[cfg(feature = "gps")]
+        State::GoingDown,
+        #[cfg(feature = "gps")]
+        State::Landed,
+        State::ShutDown,
+        State::SafeMode,
+        #[cfg(not(feature = "gps"))]
+        State::EternalLoop,
+    ];
+
+    /// Generates a random packet.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn random_packet(rng: &mut Rng) -> Packet {
+        let timestamp = Utc
+            .timestamp_millis_opt((rng.next_u64() % 4_102_444_800_000) as i64)
+            .unwrap();
+        let state = STATES[rng.next_u64() as usize % STATES.len()];
+        let position = if rng.next_bool() {
+            Some(Position::new(
+                rng.next_f32(-90_f32, 90_f32),
+                rng.next_f32(-180_f32, 180_f32),
+                rng.next_f32(-100_f32, 40_000_f32),
+                rng.next_u8(),
+                rng.next_f32(0_f32, 100_f32),
+                rng.next_f32(0_f32, 360_f32),
+            ))
+        } else {
+            None
+        };
+
+        let packet = Packet::new(
+            timestamp,
+            state,
+            position,
+            rng.next_option_f32(0_f32, 1_f32),
+            rng.next_option_f32(0_f32, 1_f32),
+        )
+        .with_sequence(rng.next_u64() as u16);
+        if rng.next_bool() {
+            packet.with_launch(
+                rng.next_f32(0_f32, 500_000_f32),
+                rng.next_f32(0_f32, 360_f32),
+            )
+        } else {
+            packet
+        }
+    }
+
+    /// Checks that decoding an encoded packet gives back the same packet.
+    #[test]
+    fn packet_round_trip() {
+        let mut rng = Rng(0x5EED_0F05_7A7A_0001);
+        for _ in 0..1_000 {
+            let packet = random_packet(&mut rng);
+            let bytes = packet.to_bytes();
+            assert_eq!(bytes.len(), PACKET_LENGTH);
+            assert_eq!(Packet::from_bytes(&bytes).unwrap(), packet);
+        }
+    }
+
+    /// Checks the exact binary layout of a packet.
+    #[test]
+    fn packet_to_bytes() {
+        let packet = Packet::new(
+            Utc.timestamp_millis_opt(1_490_443_906_123).unwrap(),
+            State::ShutDown,
+            Some(Position::new(1_f32, -2_f32, 1_500_f32, 7, 0.5, 90_f32)),
+            None,
+            Some(0.75),
+        )
+        .with_sequence(0x1234)
+        .with_launch(2_000_f32, 180_f32);
+
+        let expected = [
+            &[PROTOCOL_VERSION][..],
+            &[0x12, 0x34],                                     // Sequence
+            &[0x00, 0x00, 0x01, 0x5B, 0x05, 0x61, 0x2C, 0x4B], // Timestamp
+            &[7],                                              // State
+            &[0b1101],                                         // Flags
+            &[0x3F, 0x80, 0x00, 0x00],                         // Latitude
+            &[0xC0, 0x00, 0x00, 0x00],                         // Longitude
+            &[0x44, 0xBB, 0x80, 0x00],                         // Altitude
+            &[7],                                              // Satellites
+            &[0x3F, 0x00, 0x00, 0x00],                         // Speed
+            &[0x42, 0xB4, 0x00, 0x00],                         // Course
+            &[0x00, 0x00, 0x00, 0x00],                         // Main battery
+            &[0x3F, 0x40, 0x00, 0x00],                         // FONA battery
+            &[0x44, 0xFA, 0x00, 0x00],                         // Launch distance
+            &[0x43, 0x34, 0x00, 0x00],                         // Launch bearing
+            &[0x3C, 0x30],                                     // CRC
+        ]
+        .concat();
+
+        assert_eq!(packet.to_bytes(), expected);
+    }
+
+    /// Checks that invalid packets are rejected.
+    #[test]
+    fn packet_from_invalid_bytes() {
+        let packet = Packet::new(Utc::now(), State::Init, None, None, None);
+        let bytes = packet.to_bytes();
+
+        assert!(Packet::from_bytes(&[]).is_err());
+        assert!(Packet::from_bytes(&bytes[..PACKET_LENGTH - 1]).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = PROTOCOL_VERSION + 1;
+        update_crc(&mut wrong_version);
+        assert!(Packet::from_bytes(&wrong_version).is_err());
+
+        let mut wrong_state = bytes;
+        wrong_state[11] = 0xFF;
+        update_crc(&mut wrong_state);
+        assert!(Packet::from_bytes(&wrong_state).is_err());
+    }
+
+    /// Checks that corrupted and truncated records are detected by their CRC and length.
+    #[test]
+    fn packet_crc() {
+        let packet = random_packet(&mut Rng(0x0123_4567_89AB_CDEF));
+        let bytes = packet.to_bytes();
+        assert!(verify_crc(&bytes));
+
+        let mut corrupted = bytes.clone();
+        corrupted[20] ^= 0b0001_0000;
+        assert!(!verify_crc(&corrupted));
+        let error = Packet::from_bytes(&corrupted).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Telemetry>(),
+            Some(error::Telemetry::InvalidCrc { .. })
+        ));
+
+        let error = Packet::from_bytes(&bytes[..PACKET_LENGTH - 2]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Telemetry>(),
+            Some(error::Telemetry::InvalidLength {
+                expected: PACKET_LENGTH,
+                found: 50
+            })
+        ));
+    }
+
+    /// Checks that consecutive sequence numbers are incremented, wrapping at `u16::MAX`.
+    #[test]
+    fn sequence_wraps() {
+        let counter = SequenceCounter::new(u16::MAX - 2);
+        let sequences: Vec<_> = (0..5).map(|_| counter.next()).collect();
+        assert_eq!(sequences, vec![u16::MAX - 2, u16::MAX - 1, u16::MAX, 0, 1]);
+
+        let packet = Packet::new(Utc::now(), State::Init, None, None, None);
+        for sequence in sequences {
+            let bytes = packet.with_sequence(sequence).to_bytes();
+            assert_eq!(Packet::from_bytes(&bytes).unwrap().sequence(), sequence);
+        }
+    }
+
+    /// Checks that sequence numbers are not repeated when shared between threads.
+    #[test]
+    fn sequence_shared() {
+        use std::{sync::Arc, thread};
+
+        let counter = Arc::new(SequenceCounter::new(0));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || (0..1_000).map(|_| counter.next()).collect::<Vec<_>>())
+            })
+            .collect();
+
+        let mut sequences: Vec<_> = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+        sequences.sort_unstable();
+        assert_eq!(sequences, (0..4_000).collect::<Vec<_>>());
+    }
+
+    /// Recomputes the CRC of a manually modified record.
+    fn update_crc(bytes: &mut [u8]) {
+        let crc = crc16_ccitt(&bytes[..PAYLOAD_LENGTH]);
+        bytes[PAYLOAD_LENGTH..].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    /// Checks the human readable representation of a packet.
+    #[test]
+    fn packet_display() {
+        let packet = Packet::new(
+            Utc.timestamp_millis_opt(1_490_443_906_123).unwrap(),
+            State::Init,
+            Some(Position::new(3.25, -40.125, 256_f32, 7, 1.5, 180_f32)),
+            Some(0.92),
+            Some(0.93),
+        )
+        .with_launch(12_345_f32, 271.6);
+
+        assert_eq!(
+            packet.to_string(),
+            "[v4 #0] 2017-03-25 12:11:46.123 UTC INITIALIZING - Lat: 3.250000 - Lon: -40.125000 - \
+             Alt: 256 m - Sat: 7 - Speed: 1.5 m/s - Course: 180.0\u{b0} - Main bat: 92% - GSM \
+             bat: 93% - Launch: 12.3 km 272\u{b0}"
+        );
+    }
+
+    /// Checks that GSM locations are converted to packet positions without altitude.
+    #[test]
+    #[cfg(all(feature = "gps", feature = "fona"))]
+    fn position_from_gsm_fix() {
+        let position = Position::from(Fix::Gsm(Location::new(42.51, -2.74)));
+        assert!((position.latitude() - 42.51).abs() < f32::EPSILON);
+        assert!((position.longitude() + 2.74).abs() < f32::EPSILON);
+        assert!(position.altitude().abs() < f32::EPSILON);
+        assert_eq!(position.satellites(), 0);
+    }
+}
@@ -0,0 +1,409 @@
+//! Uplink command channel.
+//!
+//! The ground station can send commands to the probe through the telemetry link. Each command is
+//! a single ASCII line with the following format:
+//!
+//! ```text
+//! <HMAC> <counter> <COMMAND>[ <argument>]
+//! ```
+//!
+//! The `HMAC` is the hexadecimal HMAC-SHA256 of the rest of the line (everything after the first
+//! space), keyed with the `command_key` shared secret from the telemetry configuration. The
+//! `counter` must be strictly increasing, so that a captured command cannot be replayed. Note
+//! that the last accepted counter is not persisted, so the ground station should use a
+//! monotonically increasing value such as a UNIX timestamp.
+//!
+//! Accepted commands are:
+//!
+//! * `TAKE_PICTURE`: takes a picture with the camera.
+//! * `SEND_SMS <message>`: sends an SMS with the given message.
+//! * `STATUS`: sends a telemetry packet with the current status of the probe.
+//! * `SHUTDOWN`: powers the system off.
+//! * `ARM`: arms the probe, so that it can commit to the launch sequence (check the
+//!   [`arm`](../../arm/index.html) module).
+//!
+//! Commands are not acknowledged through the link, but every received command is logged.
+
+use std::{
+    fmt::{self, Write},
+    io::{self, BufRead},
+    str,
+};
+
+use anyhow::Error;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+
+use crate::error;
+
+/// HMAC type used to authenticate commands.
+type HmacSha256 = Hmac<Sha256>;
+
+/// Commands that can be received from the ground station.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Take a picture.
+    TakePicture,
+    /// Send an SMS with the given message.
+    SendSms(String),
+    /// Send the current status of the probe.
+    Status,
+    /// Power the system off.
+    ShutDown,
+    /// Arm the probe.
+    Arm,
+}
+
+impl Command {
+    /// Gets the name of the command, as sent in the link.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Command::TakePicture => "TAKE_PICTURE",
+            Command::SendSms(_) => "SEND_SMS",
+            Command::Status => "STATUS",
+            Command::ShutDown => "SHUTDOWN",
+            Command::Arm => "ARM",
+        }
+    }
+
+    /// Creates a command from its name and optional argument.
+    fn from_parts(name: &str, argument: Option<&str>) -> Result<Self, Error> {
+        match (name, argument) {
+            ("TAKE_PICTURE", None) => Ok(Command::TakePicture),
+            ("SEND_SMS", Some(message)) if !message.is_empty() => {
+                Ok(Command::SendSms(message.to_owned()))
+            }
+            ("STATUS", None) => Ok(Command::Status),
+            ("SHUTDOWN", None) => Ok(Command::ShutDown),
+            ("ARM", None) => Ok(Command::Arm),
+            ("TAKE_PICTURE" | "SEND_SMS" | "STATUS" | "SHUTDOWN" | "ARM", _) => {
+                Err(error::Command::InvalidArgument {
+                    command: name.to_owned(),
+                }
+                .into())
+            }
+            _ => Err(error::Command::Unknown {
+                command: name.to_owned(),
+            }
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::SendSms(message) => write!(f, "{} {}", self.name(), message),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// Handler for commands received from the ground station.
+pub trait CommandHandler {
+    /// Handles the given command.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command can't be carried out.
+    fn handle(&mut self, command: Command) -> Result<(), Error>;
+}
+
+/// Parser and authenticator of command lines.
+#[derive(Debug)]
+pub struct CommandParser {
+    /// Shared secret used to authenticate commands.
+    key: Vec<u8>,
+    /// Last accepted counter, to prevent replays.
+    last_counter: Option<u64>,
+}
+
+impl CommandParser {
+    /// Creates a new command parser with the given shared secret.
+    pub fn new<K>(key: K) -> Self
+    where
+        K: AsRef<[u8]>,
+    {
+        Self {
+            key: key.as_ref().to_vec(),
+            last_counter: None,
+        }
+    }
+
+    /// Parses and authenticates a command line.
+    ///
+    /// The counter of the command is only recorded if the command is valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the line is malformed, if its MAC is invalid, if the command is unknown
+    /// or if its counter is not newer than the last accepted one.
+    pub fn parse(&mut self, line: &str) -> Result<Command, Error> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (mac, message) = line.split_once(' ').ok_or(error::Command::Malformed)?;
+        let mac = decode_hex(mac).ok_or(error::Command::Malformed)?;
+
+        let mut hmac = keyed_hmac(&self.key);
+        hmac.update(message.as_bytes());
+        hmac.verify_slice(&mac)
+            .map_err(|_| error::Command::InvalidMac)?;
+
+        let mut parts = message.splitn(3, ' ');
+        let counter = parts
+            .next()
+            .and_then(|counter| counter.parse::<u64>().ok())
+            .ok_or(error::Command::Malformed)?;
+        let name = parts.next().ok_or(error::Command::Malformed)?;
+        let command = Command::from_parts(name, parts.next())?;
+
+        if let Some(last_counter) = self.last_counter {
+            if counter <= last_counter {
+                return Err(error::Command::Replayed {
+                    counter,
+                    last_counter,
+                }
+                .into());
+            }
+        }
+        self.last_counter = Some(counter);
+
+        Ok(command)
+    }
+}
+
+/// Generates an authenticated command line, as the ground station should send it.
+///
+/// The returned line does not include the line terminator.
+pub fn sign<K>(key: K, counter: u64, command: &Command) -> String
+where
+    K: AsRef<[u8]>,
+{
+    let message = format!("{counter} {command}");
+    let mut hmac = keyed_hmac(key.as_ref());
+    hmac.update(message.as_bytes());
+    let mac = hmac.finalize().into_bytes();
+
+    let mut line = String::with_capacity(mac.len() * 2 + 1 + message.len());
+    for byte in mac {
+        // Writing to a `String` never fails.
+        let _ = write!(line, "{byte:02x}");
+    }
+    line.push(' ');
+    line.push_str(&message);
+    line
+}
+
+/// Creates the HMAC of the command lines with the given shared secret.
+fn keyed_hmac(key: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size")
+}
+
+/// Reads command lines from the given reader until `EOF`, dispatching them to the handler.
+///
+/// Invalid commands are logged and ignored. Read timeouts are ignored too, so that this can be
+/// used with a serial connection: the part of the line read before the timeout is kept, and the
+/// rest of it is read in the next iteration.
+///
+/// # Errors
+///
+/// Returns an error if the reader fails, except on timeouts.
+pub fn read_commands<R, H>(
+    mut reader: R,
+    parser: &mut CommandParser,
+    handler: &mut H,
+) -> Result<(), io::Error>
+where
+    R: BufRead,
+    H: CommandHandler + ?Sized,
+{
+    let mut line = Vec::new();
+    loop {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e),
+        }
+
+        let text = String::from_utf8_lossy(&line);
+        if !text.trim().is_empty() {
+            match parser.parse(&text) {
+                Ok(command) => {
+                    info!("Received command: {}", command);
+                    if let Err(e) = handler.handle(command) {
+                        error!("Error handling command: {}", e);
+                    }
+                }
+                Err(e) => warn!("Rejected command `{}`: {}", text.trim_end(), e),
+            }
+        }
+        line.clear();
+    }
+}
+
+/// Decodes a hexadecimal string.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        io::{self, BufReader, Cursor, Read},
+    };
+
+    use anyhow::Error;
+
+    use super::{read_commands, sign, Command, CommandHandler, CommandParser};
+    use crate::error;
+
+    /// Shared secret used in tests.
+    const KEY: &[u8] = b"OpenStratos test key";
+
+    /// Command handler that stores the received commands.
+    #[derive(Default)]
+    struct Recorder(Vec<Command>);
+
+    impl CommandHandler for Recorder {
+        fn handle(&mut self, command: Command) -> Result<(), Error> {
+            self.0.push(command);
+            Ok(())
+        }
+    }
+
+    /// Reader that times out after returning each of its chunks, as a serial port would do.
+    struct TimingOut {
+        /// Chunks left to return.
+        chunks: VecDeque<Vec<u8>>,
+        /// Whether the next read should time out.
+        time_out: bool,
+    }
+
+    impl Read for TimingOut {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.time_out {
+                self.time_out = false;
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let Some(chunk) = self.chunks.pop_front() else {
+                return Ok(0);
+            };
+            self.time_out = true;
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    /// Checks that valid commands are parsed.
+    #[test]
+    fn parse_valid_commands() {
+        let mut parser = CommandParser::new(KEY);
+        let commands = [
+            Command::TakePicture,
+            Command::SendSms("Hello from the ground".to_owned()),
+            Command::Status,
+            Command::ShutDown,
+            Command::Arm,
+        ];
+
+        for (counter, command) in commands.iter().enumerate() {
+            let line = sign(KEY, counter as u64 + 1, command) + "\r\n";
+            assert_eq!(&parser.parse(&line).unwrap(), command);
+        }
+    }
+
+    /// Checks that commands with a bad HMAC are rejected.
+    #[test]
+    fn reject_bad_hmac() {
+        let mut parser = CommandParser::new(KEY);
+
+        let line = sign(b"wrong key", 1, &Command::ShutDown);
+        let error = parser.parse(&line).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Command>(),
+            Some(error::Command::InvalidMac)
+        ));
+
+        let tampered = sign(KEY, 1, &Command::Status).replace("STATUS", "SHUTDOWN");
+        let error = parser.parse(&tampered).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Command>(),
+            Some(error::Command::InvalidMac)
+        ));
+
+        assert!(parser.parse("SHUTDOWN").is_err());
+        assert!(parser.parse("zz 1 SHUTDOWN").is_err());
+    }
+
+    /// Checks that replayed, unknown and malformed commands are rejected.
+    #[test]
+    fn reject_invalid_commands() {
+        let mut parser = CommandParser::new(KEY);
+        let line = sign(KEY, 10, &Command::Status);
+        assert_eq!(parser.parse(&line).unwrap(), Command::Status);
+
+        let error = parser.parse(&line).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Command>(),
+            Some(error::Command::Replayed {
+                counter: 10,
+                last_counter: 10
+            })
+        ));
+
+        let unknown = sign(KEY, 11, &Command::Status).replace("STATUS", "LAUNCH");
+        assert!(parser.parse(&unknown).is_err());
+
+        let no_message = sign(KEY, 12, &Command::SendSms(String::new()));
+        let error = parser.parse(&no_message).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Command>(),
+            Some(error::Command::InvalidArgument { .. })
+        ));
+    }
+
+    /// Checks that only authenticated commands are dispatched to the handler.
+    #[test]
+    fn dispatch_commands() {
+        let input = [
+            sign(KEY, 1, &Command::TakePicture),
+            "garbage".to_owned(),
+            sign(b"wrong key", 2, &Command::ShutDown),
+            String::new(),
+            sign(KEY, 3, &Command::Status),
+        ]
+        .join("\n");
+
+        let mut parser = CommandParser::new(KEY);
+        let mut recorder = Recorder::default();
+        read_commands(Cursor::new(input), &mut parser, &mut recorder).unwrap();
+
+        assert_eq!(recorder.0, vec![Command::TakePicture, Command::Status]);
+    }
+
+    /// Checks that a command split by a read timeout is not lost.
+    #[test]
+    fn command_split_by_timeout() {
+        let line = sign(KEY, 1, &Command::Status) + "\n";
+        let (start, end) = line.as_bytes().split_at(20);
+        let reader = TimingOut {
+            chunks: VecDeque::from([start.to_vec(), end.to_vec()]),
+            time_out: false,
+        };
+
+        let mut parser = CommandParser::new(KEY);
+        let mut recorder = Recorder::default();
+        read_commands(BufReader::new(reader), &mut parser, &mut recorder).unwrap();
+
+        assert_eq!(recorder.0, vec![Command::Status]);
+    }
+}
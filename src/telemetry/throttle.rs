@@ -0,0 +1,154 @@
+//! Telemetry rate limiting.
+//!
+//! A fast GPS update rate could flood a low-baud telemetry link. The
+//! [`Throttle`](struct.Throttle.html) lets at most one item through per interval. Items offered
+//! before the interval has elapsed are not queued: only the latest one is kept, and it will be let
+//! through once the interval elapses, so that stale data is never sent.
+
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+/// Rate limiter that coalesces items to at most one per interval.
+#[derive(Debug)]
+pub struct Throttle<T> {
+    /// Minimum time between two items.
+    interval: Duration,
+    /// Time when the last item was let through.
+    last_sent: Option<Instant>,
+    /// Latest item waiting for the interval to elapse.
+    pending: Option<T>,
+    /// Number of items dropped because a newer one was offered.
+    dropped: u64,
+}
+
+impl<T> Throttle<T> {
+    /// Creates a new throttle with the given minimum interval between items.
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_sent: None,
+            pending: None,
+            dropped: 0,
+        }
+    }
+
+    /// Gets the minimum interval between items.
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Sets the minimum interval between items.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Gets the number of items dropped because a newer one was offered within the interval.
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Offers a new item at the given time.
+    ///
+    /// Returns the item if it can be sent right away. Otherwise, it's kept until the interval
+    /// elapses, replacing (and dropping) any item that was already waiting.
+    pub fn offer(&mut self, item: T, now: Instant) -> Option<T> {
+        if self.pending.replace(item).is_some() {
+            self.dropped += 1;
+            debug!(
+                "Dropped stale telemetry due to throttling ({} dropped so far).",
+                self.dropped
+            );
+        }
+        self.poll(now)
+    }
+
+    /// Returns the waiting item, if any, if the interval has elapsed at the given time.
+    pub fn poll(&mut self, now: Instant) -> Option<T> {
+        let ready = match self.last_sent {
+            Some(last_sent) => now.duration_since(last_sent) >= self.interval,
+            None => true,
+        };
+        if ready {
+            let item = self.pending.take();
+            if item.is_some() {
+                self.last_sent = Some(now);
+            }
+            item
+        } else {
+            None
+        }
+    }
+
+    /// Returns the waiting item, if any, even if the interval has not elapsed at the given time.
+    ///
+    /// It's used when no more items will be offered, so that the latest one is not lost.
+    pub fn flush(&mut self, now: Instant) -> Option<T> {
+        let item = self.pending.take();
+        if item.is_some() {
+            self.last_sent = Some(now);
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Throttle;
+
+    /// Checks that rapid updates are coalesced to one item per interval, always the latest one.
+    #[test]
+    fn rapid_updates() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(Duration::from_millis(1_000));
+
+        // 10 updates per second for 10 seconds.
+        let sent: Vec<_> = (0..100_u32)
+            .filter_map(|i| throttle.offer(i, start + Duration::from_millis(u64::from(i) * 100)))
+            .collect();
+
+        assert_eq!(sent, vec![0, 10, 20, 30, 40, 50, 60, 70, 80, 90]);
+        assert_eq!(throttle.dropped(), 89);
+
+        // The last update is not lost once the interval elapses.
+        assert_eq!(throttle.poll(start + Duration::from_millis(9_999)), None);
+        assert_eq!(
+            throttle.poll(start + Duration::from_millis(10_000)),
+            Some(99)
+        );
+        assert_eq!(throttle.poll(start + Duration::from_millis(20_000)), None);
+
+        // A flushed update is let through before the interval elapses.
+        assert_eq!(
+            throttle.offer(100, start + Duration::from_millis(20_000)),
+            Some(100)
+        );
+        assert_eq!(
+            throttle.offer(101, start + Duration::from_millis(20_100)),
+            None
+        );
+        assert_eq!(
+            throttle.flush(start + Duration::from_millis(20_200)),
+            Some(101)
+        );
+        assert_eq!(throttle.flush(start + Duration::from_millis(20_300)), None);
+    }
+
+    /// Checks that slow updates are never throttled.
+    #[test]
+    fn slow_updates() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(Duration::from_millis(500));
+
+        for i in 0..10_u32 {
+            let now = start + Duration::from_millis(u64::from(i) * 600);
+            assert_eq!(throttle.offer(i, now), Some(i));
+        }
+        assert_eq!(throttle.dropped(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+//! XBee API mode framing.
+//!
+//! In API mode, the XBee module expects each transmission to be wrapped in a frame, which lets
+//! OpenStratos address a specific ground unit and get delivery reports. Only the *transmit
+//! request* (`0x10`) frame is implemented, optionally with escaping of special bytes, as required
+//! by the `AP=2` API mode.
+
+/// Start delimiter of every API frame.
+pub const START_DELIMITER: u8 = 0x7E;
+/// Frame type of a transmit request.
+pub const TRANSMIT_REQUEST: u8 = 0x10;
+/// 64-bit broadcast address.
+pub const BROADCAST_ADDRESS: u64 = 0x0000_0000_0000_FFFF;
+
+/// Escape byte, in escaped API mode.
+const ESCAPE: u8 = 0x7D;
+/// Software flow control *XON* byte.
+const XON: u8 = 0x11;
+/// Software flow control *XOFF* byte.
+const XOFF: u8 = 0x13;
+/// Value XOR'ed with escaped bytes.
+const ESCAPE_XOR: u8 = 0x20;
+/// 16-bit address to use when the 64-bit address is used to address the destination.
+const UNKNOWN_16_BIT_ADDRESS: u16 = 0xFFFE;
+
+/// Wraps the given data in a transmit request API frame.
+///
+/// A `frame_id` of 0 will disable the transmit status response from the XBee module. If
+/// `escaped` is true, bytes that have a special meaning for the module will be escaped, as
+/// required in the `AP=2` API mode. Length and checksum are always computed over the unescaped
+/// data.
+///
+/// # Panics
+///
+/// Panics if the data is too long to fit in a frame.
+#[must_use]
+pub fn transmit_request(frame_id: u8, destination: u64, data: &[u8], escaped: bool) -> Vec<u8> {
+    let mut frame_data = Vec::with_capacity(14 + data.len());
+    frame_data.push(TRANSMIT_REQUEST);
+    frame_data.push(frame_id);
+    frame_data.extend_from_slice(&destination.to_be_bytes());
+    frame_data.extend_from_slice(&UNKNOWN_16_BIT_ADDRESS.to_be_bytes());
+    // Broadcast radius (maximum hops) and transmit options.
+    frame_data.extend_from_slice(&[0x00, 0x00]);
+    frame_data.extend_from_slice(data);
+
+    let length = u16::try_from(frame_data.len()).expect("data too long for an XBee API frame");
+    let checksum = checksum(&frame_data);
+
+    let mut frame = Vec::with_capacity(frame_data.len() + 4);
+    frame.push(START_DELIMITER);
+    for &byte in length
+        .to_be_bytes()
+        .iter()
+        .chain(&frame_data)
+        .chain(&[checksum])
+    {
+        if escaped && needs_escape(byte) {
+            frame.push(ESCAPE);
+            frame.push(byte ^ ESCAPE_XOR);
+        } else {
+            frame.push(byte);
+        }
+    }
+
+    frame
+}
+
+/// Computes the API frame checksum over the frame data.
+fn checksum(frame_data: &[u8]) -> u8 {
+    0xFF - frame_data
+        .iter()
+        .fold(0_u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Checks if the byte must be escaped in escaped API mode.
+fn needs_escape(byte: u8) -> bool {
+    matches!(byte, START_DELIMITER | ESCAPE | XON | XOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{transmit_request, BROADCAST_ADDRESS};
+
+    /// Checks the framing of a transmit request, using the example from the XBee documentation.
+    #[test]
+    fn transmit_request_frame() {
+        assert_eq!(
+            transmit_request(0x01, 0x0013_A200_400A_0127, b"TxData0A", false),
+            vec![
+                0x7E, 0x00, 0x16, 0x10, 0x01, 0x00, 0x13, 0xA2, 0x00, 0x40, 0x0A, 0x01, 0x27, 0xFF,
+                0xFE, 0x00, 0x00, 0x54, 0x78, 0x44, 0x61, 0x74, 0x61, 0x30, 0x41, 0x13,
+            ]
+        );
+    }
+
+    /// Checks the escaping of special bytes, including in the checksum.
+    #[test]
+    fn transmit_request_escaped() {
+        assert_eq!(
+            transmit_request(0x01, 0x0013_A200_400A_0127, b"TxData0A", true),
+            vec![
+                0x7E, 0x00, 0x16, 0x10, 0x01, 0x00, 0x7D, 0x33, 0xA2, 0x00, 0x40, 0x0A, 0x01, 0x27,
+                0xFF, 0xFE, 0x00, 0x00, 0x54, 0x78, 0x44, 0x61, 0x74, 0x61, 0x30, 0x41, 0x7D, 0x33,
+            ]
+        );
+
+        assert_eq!(
+            transmit_request(0x00, BROADCAST_ADDRESS, &[0x7E, 0x7D, 0x11], true),
+            vec![
+                0x7E, 0x00, 0x7D, 0x31, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF,
+                0xFF, 0xFE, 0x00, 0x00, 0x7D, 0x5E, 0x7D, 0x5D, 0x7D, 0x31, 0xE8,
+            ]
+        );
+    }
+}
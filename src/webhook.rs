@@ -0,0 +1,182 @@
+//! State transition webhook.
+//!
+//! For teams following the flight from a laptop, every state transition can be notified to a
+//! webhook, such as a Discord channel webhook. Each time the state machine changes state, a small
+//! JSON object is `POST`ed to the URL of the `[webhook]` configuration section, with a message
+//! such as:
+//!
+//! ```text
+//! Balloon launched at 13:42 UTC, 256 m.
+//! ```
+//!
+//! The message is in the `content` field of the object, as Discord expects, and the configured
+//! `username`, if any, is added to it.
+//!
+//! The request is sent with `curl` in a background thread, so it never blocks the flight logic.
+//! If there is no network connectivity, or the webhook fails, a warning is logged and the
+//! notification is dropped.
+
+use std::{process::Command, thread, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::{config::CONFIG, logic::State};
+
+/// Default timeout of the webhook requests.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Notifies the transition to the given state to the configured webhook, if any.
+///
+/// The altitude of the probe is added to the message if it's known. The request is sent in a
+/// background thread, and errors are only logged.
+pub fn notify(state: State, altitude: Option<f32>) {
+    let Some(config) = CONFIG.webhook() else {
+        return;
+    };
+
+    let body = payload(state, Utc::now(), altitude, config.username()).to_string();
+    let url = config.url().to_owned();
+    let timeout = config.timeout();
+
+    let spawned = thread::Builder::new()
+        .name("webhook".to_owned())
+        .spawn(move || {
+            let _ = deliver(&url, &body, timeout);
+        });
+    if let Err(e) = spawned {
+        warn!(error = %e, "Could not start the webhook thread.");
+    }
+}
+
+/// Sends the given JSON body to the webhook URL, returning wether it was accepted.
+///
+/// Failures are logged, never returned.
+pub fn deliver(url: &str, body: &str, timeout: Duration) -> bool {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time"])
+        .arg(timeout.as_secs().max(1).to_string())
+        .args(["--header", "Content-Type: application/json", "--data"])
+        .arg(body)
+        .arg(url)
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            debug!("Webhook notification sent.");
+            true
+        }
+        Ok(output) => {
+            warn!(
+                status = %output.status,
+                "Webhook notification failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            false
+        }
+        Err(e) => {
+            warn!(error = %e, "Could not run curl to send the webhook notification.");
+            false
+        }
+    }
+}
+
+/// Generates the JSON payload of the notification of the given state transition.
+#[must_use]
+pub fn payload(
+    state: State,
+    time: DateTime<Utc>,
+    altitude: Option<f32>,
+    username: Option<&str>,
+) -> Value {
+    let mut payload = json!({ "content": message(state, time, altitude) });
+    if let Some(username) = username {
+        payload["username"] = Value::from(username);
+    }
+    payload
+}
+
+/// Generates the human readable message of the transition to the given state.
+#[must_use]
+pub fn message(state: State, time: DateTime<Utc>, altitude: Option<f32>) -> String {
+    let event = match state {
+        State::Init => "Probe initializing",
+        #[cfg(feature = "gps")]
+        State::AcquiringFix => "Probe acquiring GPS fix",
+        #[cfg(feature = "gps")]
+        State::FixAcquired => "GPS fix acquired",
+        #[cfg(feature = "gps")]
+        State::WaitingLaunch => "Probe waiting for launch",
+        #[cfg(feature = "gps")]
+        State::GoingUp => "Balloon launched",
+        #[cfg(feature = "gps")]
+        State::GoingDown => "Balloon burst",
+        #[cfg(feature = "gps")]
+        State::Landed => "Balloon landed",
+        State::ShutDown => "Probe shutting down",
+        State::SafeMode => "Probe entered safe mode",
+        #[cfg(not(feature = "gps"))]
+        State::EternalLoop => "Probe recording",
+    };
+
+    let time = time.format("%H:%M");
+    match altitude {
+        Some(altitude) => format!("{event} at {time} UTC, {altitude:.0} m."),
+        None => format!("{event} at {time} UTC."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    // Only required for GPS
+    #[cfg(feature = "gps")]
+    use chrono::TimeZone;
+    // Only required for GPS
+    #[cfg(feature = "gps")]
+    use serde_json::json;
+
+    // Only required for GPS
+    #[cfg(feature = "gps")]
+    use super::message;
+    use super::{deliver, payload};
+    use crate::logic::State;
+
+    /// Checks the payload sent for a state transition.
+    #[test]
+    #[cfg(feature = "gps")]
+    fn transition_payload() {
+        let time = Utc.with_ymd_and_hms(2017, 5, 13, 13, 42, 7).unwrap();
+
+        assert_eq!(
+            payload(State::GoingUp, time, Some(256.3), None),
+            json!({ "content": "Balloon launched at 13:42 UTC, 256 m." })
+        );
+        assert_eq!(
+            payload(State::Landed, time, Some(1_203.7), Some("OpenStratos")),
+            json!({
+                "content": "Balloon landed at 13:42 UTC, 1204 m.",
+                "username": "OpenStratos",
+            })
+        );
+        assert_eq!(
+            message(State::SafeMode, time, None),
+            "Probe entered safe mode at 13:42 UTC."
+        );
+    }
+
+    /// Checks that a failing webhook is reported, without panicking.
+    #[test]
+    fn failing_webhook() {
+        let body = payload(State::ShutDown, Utc::now(), None, None).to_string();
+        // Nothing listens in the discard port.
+        assert!(!deliver(
+            "http://127.0.0.1:9/webhook",
+            &body,
+            Duration::from_secs(2)
+        ));
+    }
+}
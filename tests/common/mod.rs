@@ -0,0 +1,34 @@
+//! Helpers shared by the integration tests.
+//!
+//! The tests that write files in the `data` directory run the probe logic in a child process, that
+//! uses a temporary `data` directory created by the parent process.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use os_balloon::CONFIG;
+
+/// Creates an empty temporary `data` directory for the test with the given name.
+pub fn temp_data_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("os_balloon_{name}_{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Reloads the configuration so that it uses the given `data` directory.
+///
+/// The rest of the configuration is read from `config.toml`.
+pub fn use_data_dir(dir: &Path) {
+    let config = fs::read_to_string("config.toml").unwrap().replace(
+        "data_dir = \"data\"",
+        &format!("data_dir = {:?}", dir.display().to_string()),
+    );
+    let path = dir.join("config.toml");
+    fs::write(&path, config).unwrap();
+    CONFIG.reload_from(&path).unwrap();
+    assert_eq!(CONFIG.data_dir(), dir);
+}
@@ -0,0 +1,364 @@
+//! Integration tests of the whole flight logic, from the initialization to the shut down, and of
+//! the flight resumed after a restart.
+//!
+//! The state machine runs with fake devices: the GPS plays the synthetic flight profile in
+//! `tests/data/flight.csv`, and the GSM module and the camera only record their calls. Since the
+//! shut down state exits the process, the flight runs in a child process, that prints every
+//! recorded event to its standard output, and the test checks them once it exits. The child
+//! process writes its files in a temporary `data` directory.
+//!
+//! It needs the simulation feature, and can't use the real telemetry serial:
+//!
+//! ```text
+//! cargo test --no-default-features --features "sim fona raspicam" --test flight
+//! ```
+
+#![cfg(all(
+    feature = "sim",
+    feature = "fona",
+    feature = "raspicam",
+    not(feature = "telemetry")
+))]
+
+mod common;
+
+use std::{
+    cell::Cell,
+    env, fs, mem,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{bail, Error};
+use os_balloon::{
+    data::VIDEO_DIR,
+    devices::{CameraDevice, GsmDevice},
+    fona::{Delivery, Location},
+    logic::{self, MainLogic, State},
+    sim::{self, RecordedFlight},
+    CONFIG, STATE_FILE, STATS_FILE,
+};
+
+/// Synthetic flight profile.
+const FLIGHT_FILE: &str = "tests/data/flight.csv";
+/// Environment variable with the `data` directory, that makes the test run the flight, as the
+/// child process.
+const CHILD_VAR: &str = "OPENSTRATOS_FLIGHT_CHILD";
+/// Prefix of the event lines printed by the child process.
+///
+/// The test harness prints the name of the test in the same line as the first event, so it's not
+/// always at the start of the line.
+const EVENT_PREFIX: &str = "event: ";
+
+/// Recorder of the events of the flight, shared by the fake devices.
+#[derive(Debug, Default)]
+struct Recorder {
+    /// Last state printed.
+    state: Cell<Option<State>>,
+}
+
+impl Recorder {
+    /// Prints the given event, after the current state if it changed.
+    fn record(&self, event: &str) {
+        self.check_state();
+        println!("{EVENT_PREFIX}{event}");
+    }
+
+    /// Prints the current state if it changed since the last event.
+    fn check_state(&self) {
+        let state = logic::current_state();
+        if self.state.replace(Some(state)) != Some(state) {
+            println!("{EVENT_PREFIX}state {}", state.as_str());
+        }
+    }
+}
+
+/// Fake GSM module, that records the SMSs instead of sending them.
+#[derive(Debug)]
+struct RecordingGsm {
+    /// Event recorder.
+    recorder: Rc<Recorder>,
+}
+
+impl GsmDevice for RecordingGsm {
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.recorder.record("gsm on");
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<(), Error> {
+        self.recorder.record("gsm off");
+        Ok(())
+    }
+
+    fn send_sms(&mut self, message: &str) -> Result<(), Error> {
+        self.recorder
+            .record(&format!("sms {}", message.replace('\n', "|")));
+        Ok(())
+    }
+
+    fn send_sms_confirmed(&mut self, message: &str, _timeout: Duration) -> Result<Delivery, Error> {
+        self.send_sms(message)?;
+        Ok(Delivery::Delivered)
+    }
+
+    fn location(&mut self) -> Result<Location, Error> {
+        bail!("the fake GSM module has no location")
+    }
+
+    fn battery_percent(&mut self) -> Result<f32, Error> {
+        Ok(1_f32)
+    }
+
+    fn adc_voltage(&mut self) -> Result<f32, Error> {
+        Ok(CONFIG.battery().main_max())
+    }
+
+    fn has_connectivity(&mut self) -> Result<bool, Error> {
+        Ok(true)
+    }
+}
+
+/// Fake camera, that records its calls and creates empty video files.
+#[derive(Debug)]
+struct RecordingCamera {
+    /// Event recorder.
+    recorder: Rc<Recorder>,
+    /// Wether the camera is recording.
+    recording: bool,
+}
+
+impl CameraDevice for RecordingCamera {
+    fn record(&mut self, time: Option<Duration>, file_name: Option<&Path>) -> Result<(), Error> {
+        if self.is_recording() {
+            bail!("the camera is already recording");
+        }
+        if let Some(file_name) = file_name {
+            let _ = fs::File::create(CONFIG.data_dir().join(VIDEO_DIR).join(file_name))?;
+        }
+        self.recorder.record(&format!(
+            "record {} {}",
+            file_name.map_or_else(|| "default".to_owned(), |f| f.display().to_string()),
+            time.map_or_else(|| "forever".to_owned(), |t| format!("{}s", t.as_secs()))
+        ));
+        self.recording = time.is_none();
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<(), Error> {
+        self.recorder.record("stop");
+        self.recording = false;
+        Ok(())
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    fn take_picture(&mut self, _file_name: Option<&Path>) -> Result<(), Error> {
+        if self.is_recording() {
+            bail!("the camera is recording");
+        }
+        self.recorder.record("picture");
+        Ok(())
+    }
+
+    fn detect(&self) -> Result<bool, Error> {
+        self.recorder.record("detect");
+        Ok(true)
+    }
+}
+
+/// Creates a simulation context for the given flight, with the recording GSM module and camera.
+fn recording_context(flight: RecordedFlight) -> Result<logic::Context, Error> {
+    fs::create_dir_all(CONFIG.data_dir().join(VIDEO_DIR))?;
+
+    let recorder = Rc::new(Recorder::default());
+    let mut context = sim::context(flight, 0);
+    context.gsm = Box::new(RecordingGsm {
+        recorder: Rc::clone(&recorder),
+    });
+    context.camera = Box::new(RecordingCamera {
+        recorder: Rc::clone(&recorder),
+        recording: false,
+    });
+    let sleep = mem::replace(&mut context.sleep, Box::new(|_| {}));
+    let sleep_recorder = Rc::clone(&recorder);
+    context.sleep = Box::new(move |time| {
+        sleep_recorder.check_state();
+        sleep(time);
+    });
+    Ok(context)
+}
+
+/// Runs the whole flight with the fake devices, in the child process.
+///
+/// It only returns if the logic fails, since the shut down state exits the process.
+fn run_flight() -> Result<(), Error> {
+    let mut context = recording_context(RecordedFlight::from_file(FLIGHT_FILE)?)?;
+    logic::init()?.main_logic(&mut context)
+}
+
+/// Resumes the flight from the last state in the `data` directory, with the frames after the
+/// burst, in the child process.
+///
+/// It only returns if the logic fails, since the shut down state exits the process.
+fn run_resumed_descent() -> Result<(), Error> {
+    let csv = fs::read_to_string(FLIGHT_FILE)?;
+    let mut lines = csv.lines().filter(|line| !line.starts_with('#'));
+    let header = lines.next().unwrap_or_default();
+    let descent: Vec<_> = lines
+        .skip_while(|line| !line.contains(",12000.0,"))
+        .collect();
+    let flight = RecordedFlight::from_csv(&format!("{header}\n{}\n", descent.join("\n")))?;
+
+    let mut context = recording_context(flight)?;
+    let last_state = State::get_last()?.expect("no last state to resume from");
+    logic::resume(last_state, &mut context)
+}
+
+/// Runs the given test in a child process, with the given `data` directory, and returns the events
+/// it printed.
+fn run_child(test: &str, dir: &Path) -> Vec<String> {
+    let output = Command::new(env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture", "--test-threads", "1"])
+        .env(CHILD_VAR, dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "the flight failed:\n{stdout}\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    stdout
+        .lines()
+        .filter_map(|line| {
+            line.split_once(EVENT_PREFIX)
+                .map(|(_, event)| event.to_owned())
+        })
+        .collect()
+}
+
+/// Gets the `data` directory of the child process, if this is the child process.
+fn child_data_dir() -> Option<PathBuf> {
+    let dir = PathBuf::from(env::var_os(CHILD_VAR)?);
+    common::use_data_dir(&dir);
+    Some(dir)
+}
+
+/// Runs the flight from the initialization to the shut down, and checks the SMSs, the state
+/// transitions, the camera calls and the final state file.
+#[test]
+fn full_flight() {
+    if child_data_dir().is_some() {
+        run_flight().unwrap();
+        panic!("the flight logic returned without shutting down");
+    }
+
+    let dir = common::temp_data_dir("flight");
+    let events = run_child("full_flight", &dir);
+
+    let states: Vec<_> = events
+        .iter()
+        .filter_map(|event| event.strip_prefix("state "))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            "INITIALIZING",
+            "ACQUIRING_FIX",
+            "FIX_ACQUIRED",
+            "WAITING_LAUNCH",
+            "GOING_UP",
+            "GOING_DOWN",
+            "LANDED",
+            "SHUT_DOWN",
+        ]
+    );
+
+    let sms: Vec<_> = events
+        .iter()
+        .filter_map(|event| event.strip_prefix("sms "))
+        .collect();
+    let headers: Vec<_> = sms
+        .iter()
+        .map(|message| message.split('|').next().unwrap())
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "Init: OK.",
+            "Launched.",
+            "Launch OK.",
+            "Descending.",
+            "Descending.",
+            "Landed.",
+            "Landed 2nd.",
+        ]
+    );
+    assert!(sms.last().unwrap().ends_with("|Launch: 8.6 km 88\u{b0}"));
+
+    let camera: Vec<_> = events
+        .iter()
+        .filter(|event| {
+            ["record ", "stop", "picture"]
+                .iter()
+                .any(|call| event.starts_with(call))
+        })
+        .collect();
+    assert_eq!(*camera[0], "record test.h264 10s");
+    assert_eq!(*camera[1], "record default forever");
+    assert_eq!(**camera.last().unwrap(), "stop");
+    assert!(!dir.join(VIDEO_DIR).join("test.h264").exists());
+
+    let last_state = fs::read_to_string(dir.join(STATE_FILE)).unwrap();
+    assert_eq!(last_state, "SHUT_DOWN");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Resumes the flight in the descent after a restart, and checks that it lands with the saved
+/// flight statistics.
+#[test]
+fn resumed_descent() {
+    if child_data_dir().is_some() {
+        run_resumed_descent().unwrap();
+        panic!("the flight logic returned without shutting down");
+    }
+
+    let dir = common::temp_data_dir("resumed_descent");
+    fs::write(dir.join(STATE_FILE), "GOING_DOWN").unwrap();
+    fs::write(
+        dir.join(STATS_FILE),
+        "launch_altitude = 650.0\nmax_altitude = 12000.0\n",
+    )
+    .unwrap();
+    let events = run_child("resumed_descent", &dir);
+
+    let states: Vec<_> = events
+        .iter()
+        .filter_map(|event| event.strip_prefix("state "))
+        .collect();
+    assert_eq!(states, ["GOING_DOWN", "LANDED", "SHUT_DOWN"]);
+
+    let headers: Vec<_> = events
+        .iter()
+        .filter_map(|event| event.strip_prefix("sms "))
+        .map(|message| message.split('|').next().unwrap())
+        .collect();
+    assert_eq!(
+        headers,
+        ["Descending.", "Descending.", "Landed.", "Landed 2nd."]
+    );
+
+    let stats = fs::read_to_string(dir.join(STATS_FILE)).unwrap();
+    assert!(stats.contains("max_altitude = 12000.0"));
+    assert!(stats.contains("landing_time = "));
+
+    let last_state = fs::read_to_string(dir.join(STATE_FILE)).unwrap();
+    assert_eq!(last_state, "SHUT_DOWN");
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,53 @@
+//! Integration test of the panic recovery.
+//!
+//! The panic hook is process-wide, so the panic runs in a child process, where it can't interfere
+//! with other tests, and that saves its state in a temporary `data` directory.
+
+mod common;
+
+use std::{env, fs, panic, path::Path, process::Command, thread};
+
+use os_balloon::{
+    logic::State,
+    recovery::{cleanup, install_panic_hook},
+    STATE_FILE,
+};
+
+/// Environment variable with the `data` directory, that makes the test panic, as the child
+/// process.
+const CHILD_VAR: &str = "OPENSTRATOS_PANIC_CHILD";
+
+/// Checks that a panic runs the cleanup once, saving the current state.
+#[test]
+fn panic_saves_state() {
+    if let Some(dir) = env::var_os(CHILD_VAR) {
+        common::use_data_dir(Path::new(&dir));
+        install_panic_hook();
+        let result = thread::spawn(|| panic!("test panic")).join();
+        // Restores the default hook.
+        let _ = panic::take_hook();
+
+        assert!(result.is_err());
+        assert!(!cleanup());
+        return;
+    }
+
+    let dir = common::temp_data_dir("panic");
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["panic_saves_state", "--exact", "--nocapture"])
+        .env(CHILD_VAR, &dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "the panic test failed:\n{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_eq!(
+        fs::read_to_string(dir.join(STATE_FILE)).unwrap(),
+        State::Init.as_str()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}